@@ -0,0 +1,259 @@
+//! Typed capture annotations carried in `meta.annotations`.
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+pub use redproof_statements::witness::TimingTrace;
+
+/// Known annotation keys serialize in declaration order, followed by `extra`
+/// (sorted by key). Reading goes through [`CaptureAnnotations::from_map`], so
+/// older free-form annotation maps still load.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(from = "Map<String, Value>")]
+pub struct CaptureAnnotations {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_truncated: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_charset: Option<String>,
+    /// `omitted` when the ClientHello carried no SNI.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sni: Option<String>,
+    /// Browser whose request headers the prover imitated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub browser_profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// `close` or `keep-alive`: how the request treated the connection.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connection: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timing: Option<TimingAnnotation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_ips: Option<ResolvedIpsAnnotation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_skew: Option<ClockSkewAnnotation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_sequence: Option<RequestSequenceAnnotation>,
+    /// `--retry-on-status` re-captures made before the committed response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_retries: Option<usize>,
+    /// Status of every `--retry-on-status` attempt, in order; the last is
+    /// the committed response's.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_sequence: Option<Vec<u16>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub posture: Option<PostureSummary>,
+    /// Custom keys, and legacy keys whose value did not fit the typed slot.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Prover-attested time to first byte.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct TimingAnnotation {
+    pub cold_ttfb_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm_ttfb_ms: Option<u64>,
+    /// `cold` or `warm`: the figure timing statements were evaluated against.
+    pub statement_uses: String,
+    pub attested_by: String,
+    /// Phase breakdown of the measured connection, present when the prover
+    /// also committed it in the app-data transcript (`--commit-timing`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TimingTrace>,
+}
+
+/// Prover-attested DNS resolution.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct ResolvedIpsAnnotation {
+    pub addresses: Vec<IpAddr>,
+    pub attested_by: String,
+}
+
+/// The server's `Date` header against the prover's clock at capture time.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct ClockSkewAnnotation {
+    pub server_date: DateTime<Utc>,
+    /// `server_date` minus the local capture time; positive when the server
+    /// runs ahead. `Date` only has one-second resolution.
+    pub skew_ms: i64,
+    pub attested_by: String,
+}
+
+/// A `--request-script` capture: how many requests were sent in order, and
+/// which response (1-based) the artifact commits to.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct RequestSequenceAnnotation {
+    pub count: usize,
+    pub selected: usize,
+}
+
+/// Server software, intermediaries and protocol versions at a glance,
+/// derived from the committed capture so inventories need not parse raw
+/// headers.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct PostureSummary {
+    /// The `Server` header, if sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// `Via` entries (`1.1 varnish`), in the order listed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub via: Vec<String>,
+    pub http_version: String,
+    /// `none` for plaintext captures.
+    pub tls_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cipher: Option<String>,
+}
+
+/// Keys owned by the typed fields; custom annotations may not use them.
+pub const RESERVED_ANNOTATION_KEYS: &[&str] = &[
+    "request_method",
+    "status_code",
+    "body_truncated",
+    "header_count",
+    "http_version",
+    "body_charset",
+    "sni",
+    "browser_profile",
+    "origin",
+    "connection",
+    "timing",
+    "resolved_ips",
+    "clock_skew",
+    "request_sequence",
+    "status_retries",
+    "status_sequence",
+    "posture",
+];
+
+impl CaptureAnnotations {
+    /// Lift a free-form annotation map into typed fields. Unknown keys, and
+    /// known keys whose value has the wrong shape, are kept in `extra`.
+    pub fn from_map(mut map: Map<String, Value>) -> Self {
+        Self {
+            request_method: take(&mut map, "request_method"),
+            status_code: take(&mut map, "status_code"),
+            body_truncated: take(&mut map, "body_truncated"),
+            header_count: take(&mut map, "header_count"),
+            http_version: take(&mut map, "http_version"),
+            body_charset: take(&mut map, "body_charset"),
+            sni: take(&mut map, "sni"),
+            browser_profile: take(&mut map, "browser_profile"),
+            origin: take(&mut map, "origin"),
+            connection: take(&mut map, "connection"),
+            timing: take(&mut map, "timing"),
+            resolved_ips: take(&mut map, "resolved_ips"),
+            clock_skew: take(&mut map, "clock_skew"),
+            request_sequence: take(&mut map, "request_sequence"),
+            status_retries: take(&mut map, "status_retries"),
+            status_sequence: take(&mut map, "status_sequence"),
+            posture: take(&mut map, "posture"),
+            extra: map,
+        }
+    }
+
+    /// Add a custom annotation to `extra`, refusing reserved keys.
+    pub fn insert_custom(&mut self, key: &str, value: Value) -> Result<(), ReservedAnnotationKey> {
+        if RESERVED_ANNOTATION_KEYS.contains(&key) {
+            return Err(ReservedAnnotationKey(key.to_string()));
+        }
+        self.extra.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("annotation key '{0}' is reserved")]
+pub struct ReservedAnnotationKey(pub String);
+
+impl From<Map<String, Value>> for CaptureAnnotations {
+    fn from(map: Map<String, Value>) -> Self {
+        Self::from_map(map)
+    }
+}
+
+fn take<T: DeserializeOwned>(map: &mut Map<String, Value>, key: &str) -> Option<T> {
+    let parsed = serde_json::from_value(map.get(key)?.clone()).ok()?;
+    map.remove(key);
+    Some(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_known_keys_in_fixed_order() {
+        let mut annotations = CaptureAnnotations {
+            status_code: Some(200),
+            request_method: Some("GET".into()),
+            http_version: Some("HTTP/1.1".into()),
+            ..CaptureAnnotations::default()
+        };
+        annotations.extra.insert("aaa_custom".into(), json!(1));
+
+        let json = serde_json::to_string(&annotations).unwrap();
+        assert_eq!(
+            json,
+            r#"{"request_method":"GET","status_code":200,"http_version":"HTTP/1.1","aaa_custom":1}"#
+        );
+        let back: CaptureAnnotations = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, annotations);
+    }
+
+    #[test]
+    fn custom_annotations_cannot_shadow_typed_keys() {
+        let mut annotations = CaptureAnnotations::default();
+        annotations
+            .insert_custom("git_commit", json!("abc123"))
+            .unwrap();
+        assert_eq!(
+            annotations.insert_custom("status_code", json!(200)),
+            Err(ReservedAnnotationKey("status_code".into()))
+        );
+        assert_eq!(annotations.extra.len(), 1);
+        assert_eq!(annotations.status_code, None);
+    }
+
+    #[test]
+    fn reads_legacy_free_form_annotations() {
+        let legacy = json!({
+            "status_code": 404,
+            "body_truncated": "no",
+            "timing": {
+                "cold_ttfb_ms": 120,
+                "warm_ttfb_ms": null,
+                "statement_uses": "cold",
+                "attested_by": "prover"
+            },
+            "ticket": "SEC-42"
+        });
+        let annotations: CaptureAnnotations = serde_json::from_value(legacy).unwrap();
+
+        assert_eq!(annotations.status_code, Some(404));
+        assert_eq!(annotations.timing.unwrap().cold_ttfb_ms, 120);
+        assert_eq!(annotations.body_truncated, None);
+        assert_eq!(annotations.extra["body_truncated"], json!("no"));
+        assert_eq!(annotations.extra["ticket"], json!("SEC-42"));
+    }
+}
@@ -1,7 +1,6 @@
-use redproof_artifact::RedProofArtifact;
+use redproof_artifact::schema::artifact_schema;
 
 fn main() {
-    let schema = schemars::schema_for!(RedProofArtifact);
-    let json = serde_json::to_string_pretty(&schema).expect("schema json");
+    let json = serde_json::to_string_pretty(&artifact_schema()).expect("schema json");
     println!("{}", json);
 }
@@ -0,0 +1,121 @@
+//! Canonical CBOR (RFC 8949 §4.2.1 core deterministic encoding): definite
+//! lengths, shortest-form integers and floats, and map keys sorted by their
+//! encoded bytes. `serde_cbor::to_vec` writes struct fields in declaration
+//! order, so two encoders can disagree on the bytes of identical data.
+
+use serde::Serialize;
+use serde_cbor::Value;
+
+use crate::RedProofArtifact;
+
+/// Encode `value` as canonical CBOR.
+pub fn to_canonical_cbor<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, serde_cbor::Error> {
+    let value = serde_cbor::value::to_value(value)?;
+    let mut out = Vec::new();
+    encode(&value, &mut out)?;
+    Ok(out)
+}
+
+impl RedProofArtifact {
+    /// The artifact as canonical CBOR; byte-identical for equal artifacts.
+    pub fn to_canonical_cbor(&self) -> Result<Vec<u8>, serde_cbor::Error> {
+        to_canonical_cbor(self)
+    }
+}
+
+fn encode(value: &Value, out: &mut Vec<u8>) -> Result<(), serde_cbor::Error> {
+    match value {
+        Value::Array(items) => {
+            write_head(out, 4, items.len() as u64);
+            for item in items {
+                encode(item, out)?;
+            }
+        }
+        Value::Map(entries) => {
+            let mut encoded = entries
+                .iter()
+                .map(|(key, value)| {
+                    let mut key_bytes = Vec::new();
+                    encode(key, &mut key_bytes)?;
+                    Ok((key_bytes, value))
+                })
+                .collect::<Result<Vec<_>, serde_cbor::Error>>()?;
+            encoded.sort_by(|(a, _), (b, _)| a.cmp(b));
+            write_head(out, 5, encoded.len() as u64);
+            for (key, value) in encoded {
+                out.extend_from_slice(&key);
+                encode(value, out)?;
+            }
+        }
+        Value::Tag(tag, inner) => {
+            write_head(out, 6, *tag);
+            encode(inner, out)?;
+        }
+        // Scalars: serde_cbor already picks the shortest form.
+        scalar => out.extend_from_slice(&serde_cbor::to_vec(scalar)?),
+    }
+    Ok(())
+}
+
+fn write_head(out: &mut Vec<u8>, major: u8, len: u64) {
+    let major = major << 5;
+    match len {
+        0..=23 => out.push(major | len as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, len as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&len.to_be_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn sorts_keys_by_encoded_bytes() {
+        #[derive(Serialize)]
+        struct Unsorted {
+            zz: u8,
+            a: u8,
+            bb: u8,
+        }
+        let bytes = to_canonical_cbor(&Unsorted { zz: 1, a: 2, bb: 3 }).unwrap();
+        // Shorter keys first, then bytewise: a, bb, zz.
+        assert_eq!(
+            bytes,
+            [0xa3, 0x61, b'a', 0x02, 0x62, b'b', b'b', 0x03, 0x62, b'z', b'z', 0x01]
+        );
+        let map = BTreeMap::from([("bb", 3u8), ("zz", 1), ("a", 2)]);
+        assert_eq!(to_canonical_cbor(&map).unwrap(), bytes);
+    }
+
+    #[test]
+    fn writes_shortest_length_heads() {
+        let mut out = Vec::new();
+        for len in [23, 24, 256, 65_536] {
+            out.clear();
+            write_head(&mut out, 4, len);
+            assert_eq!(
+                out.len(),
+                match len {
+                    23 => 1,
+                    24 => 2,
+                    256 => 3,
+                    _ => 5,
+                }
+            );
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! Commitment hashers. Every algorithm is a [`Committer`] registered once
+//! under its tag; [`CommitmentAlgorithm`] only names a registered tag, so the
+//! prover, the verifier and the artifact schema pick up new algorithms
+//! without per-algorithm `match`es.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{OnceLock, RwLock};
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::EncodedBlob;
+
+/// A hash function usable for transcript and header commitments.
+pub trait Committer: Send + Sync {
+    /// Name recorded in `commitments.algorithm`, e.g. `blake3`. Lowercase
+    /// ASCII letters, digits and `-`.
+    fn algorithm_tag(&self) -> &str;
+
+    /// Raw digest of `data`.
+    fn hash(&self, data: &[u8]) -> Vec<u8>;
+
+    fn digest(&self, data: &[u8]) -> EncodedBlob {
+        EncodedBlob::from_bytes(&self.hash(data))
+    }
+}
+
+struct Blake3Committer;
+
+impl Committer for Blake3Committer {
+    fn algorithm_tag(&self) -> &str {
+        "blake3"
+    }
+
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        blake3::hash(data).as_bytes().to_vec()
+    }
+}
+
+struct Sha256Committer;
+
+impl Committer for Sha256Committer {
+    fn algorithm_tag(&self) -> &str {
+        "sha256"
+    }
+
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        Sha256::digest(data).to_vec()
+    }
+}
+
+type Registry = RwLock<BTreeMap<&'static str, &'static dyn Committer>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let builtins: [&'static dyn Committer; 2] = [&Blake3Committer, &Sha256Committer];
+        RwLock::new(
+            builtins
+                .into_iter()
+                .map(|committer| (committer.algorithm_tag(), committer))
+                .collect(),
+        )
+    })
+}
+
+/// Make `committer` available process-wide under its tag. Registered
+/// committers live for the rest of the process.
+pub fn register(
+    committer: Box<dyn Committer>,
+) -> Result<CommitmentAlgorithm, RegisterCommitterError> {
+    let tag = committer.algorithm_tag();
+    if tag.is_empty()
+        || !tag
+            .bytes()
+            .all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'-')
+    {
+        return Err(RegisterCommitterError::InvalidTag(tag.to_string()));
+    }
+    let mut registry = registry().write().unwrap_or_else(|err| err.into_inner());
+    if registry.contains_key(tag) {
+        return Err(RegisterCommitterError::Duplicate(tag.to_string()));
+    }
+    let committer: &'static dyn Committer = Box::leak(committer);
+    let tag = committer.algorithm_tag();
+    registry.insert(tag, committer);
+    Ok(CommitmentAlgorithm(tag))
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RegisterCommitterError {
+    #[error("commitment algorithm '{0}' is already registered")]
+    Duplicate(String),
+    #[error("invalid commitment algorithm tag '{0}' (use lowercase letters, digits and '-')")]
+    InvalidTag(String),
+}
+
+/// A registered commitment algorithm, serialized as its tag.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CommitmentAlgorithm(&'static str);
+
+// Named like the enum variants they replace so call sites read the same.
+#[allow(non_upper_case_globals)]
+impl CommitmentAlgorithm {
+    pub const Blake3: Self = Self("blake3");
+    pub const Sha256: Self = Self("sha256");
+}
+
+impl CommitmentAlgorithm {
+    /// Every registered algorithm, ordered by tag.
+    pub fn registered() -> Vec<Self> {
+        let registry = registry().read().unwrap_or_else(|err| err.into_inner());
+        registry.keys().copied().map(Self).collect()
+    }
+
+    pub fn tag(self) -> &'static str {
+        self.0
+    }
+
+    pub fn committer(self) -> &'static dyn Committer {
+        let registry = registry().read().unwrap_or_else(|err| err.into_inner());
+        // Only registered tags can be named, and registrations are never
+        // removed.
+        *registry
+            .get(self.0)
+            .expect("commitment algorithm is registered")
+    }
+
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        self.committer().hash(data)
+    }
+
+    /// Digest of `salt || data`; plain [`Self::digest`] for an empty salt.
+    pub fn digest_salted(&self, salt: &[u8], data: &[u8]) -> Vec<u8> {
+        if salt.is_empty() {
+            return self.digest(data);
+        }
+        let mut salted = Vec::with_capacity(salt.len() + data.len());
+        salted.extend_from_slice(salt);
+        salted.extend_from_slice(data);
+        self.digest(&salted)
+    }
+}
+
+impl fmt::Debug for CommitmentAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl fmt::Display for CommitmentAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl FromStr for CommitmentAlgorithm {
+    type Err = UnknownCommitmentAlgorithm;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let wanted = s.trim().to_ascii_lowercase();
+        let registry = registry().read().unwrap_or_else(|err| err.into_inner());
+        registry
+            .get_key_value(wanted.as_str())
+            .map(|(tag, _)| Self(tag))
+            .ok_or_else(|| UnknownCommitmentAlgorithm(s.to_string()))
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("unknown commitment algorithm '{0}'")]
+pub struct UnknownCommitmentAlgorithm(pub String);
+
+impl Serialize for CommitmentAlgorithm {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for CommitmentAlgorithm {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tag = String::deserialize(deserializer)?;
+        tag.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl JsonSchema for CommitmentAlgorithm {
+    fn schema_name() -> String {
+        "CommitmentAlgorithm".into()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Xor;
+
+    impl Committer for Xor {
+        fn algorithm_tag(&self) -> &str {
+            "test-xor"
+        }
+
+        fn hash(&self, data: &[u8]) -> Vec<u8> {
+            vec![data.iter().fold(0, |acc, byte| acc ^ byte)]
+        }
+    }
+
+    #[test]
+    fn builtins_keep_their_tags_and_digests() {
+        assert_eq!(
+            serde_json::to_string(&CommitmentAlgorithm::Sha256).unwrap(),
+            "\"sha256\""
+        );
+        assert_eq!(
+            "BLAKE3".parse::<CommitmentAlgorithm>(),
+            Ok(CommitmentAlgorithm::Blake3)
+        );
+        assert_eq!(
+            CommitmentAlgorithm::Blake3.digest(b"abc"),
+            blake3::hash(b"abc").as_bytes().to_vec()
+        );
+        assert!(serde_json::from_str::<CommitmentAlgorithm>("\"md5\"").is_err());
+    }
+
+    #[test]
+    fn registered_committer_round_trips_by_tag() {
+        let xor = register(Box::new(Xor)).unwrap();
+        assert_eq!(xor.tag(), "test-xor");
+        assert_eq!(xor.digest(&[1, 2, 4]), vec![7]);
+        assert_eq!(xor.committer().digest(&[1]), EncodedBlob::from_bytes(&[1]));
+        assert_eq!(
+            serde_json::from_str::<CommitmentAlgorithm>("\"test-xor\"").unwrap(),
+            xor
+        );
+        assert!(CommitmentAlgorithm::registered().contains(&xor));
+
+        assert_eq!(
+            register(Box::new(Xor)).unwrap_err(),
+            RegisterCommitterError::Duplicate("test-xor".into())
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_tags() {
+        struct Named(&'static str);
+        impl Committer for Named {
+            fn algorithm_tag(&self) -> &str {
+                self.0
+            }
+            fn hash(&self, data: &[u8]) -> Vec<u8> {
+                data.to_vec()
+            }
+        }
+        for tag in ["", "Upper", "with space"] {
+            assert_eq!(
+                register(Box::new(Named(tag))).unwrap_err(),
+                RegisterCommitterError::InvalidTag(tag.into())
+            );
+        }
+    }
+}
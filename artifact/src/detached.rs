@@ -0,0 +1,139 @@
+//! Detached Ed25519 signatures, kept in a sidecar file so a JSON artifact
+//! stays editable and inspectable. The signature covers the artifact's
+//! canonical CBOR encoding, so re-indenting the JSON or converting it to
+//! CBOR keeps the signature valid while any change to a field breaks it.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::signature::{Ed25519KeyPair, UnparsedPublicKey, ED25519};
+use thiserror::Error;
+
+use crate::RedProofArtifact;
+
+const PUBLIC_KEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+/// DER prefix of an Ed25519 SubjectPublicKeyInfo (`openssl pkey -pubout`).
+const SPKI_PREFIX: &[u8] = &[
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DetachedSignatureError {
+    #[error("public key is not a raw, base64 or PEM Ed25519 key")]
+    MalformedPublicKey,
+    #[error("signature is not {SIGNATURE_LEN} raw or base64-encoded bytes")]
+    MalformedSignature,
+    #[error("detached signature does not match the artifact")]
+    BadSignature,
+}
+
+impl RedProofArtifact {
+    /// Sign the canonical encoding; write the result to the sidecar as-is or
+    /// base64-encoded.
+    pub fn sign_detached(&self, key_pair: &Ed25519KeyPair) -> Vec<u8> {
+        key_pair.sign(&self.signed_bytes()).as_ref().to_vec()
+    }
+
+    /// Check a detached signature. `public_key` and `signature` are the
+    /// sidecar file contents: raw bytes, base64 text, or (for the key) a PEM
+    /// or DER SubjectPublicKeyInfo.
+    pub fn verify_detached(
+        &self,
+        public_key: &[u8],
+        signature: &[u8],
+    ) -> Result<(), DetachedSignatureError> {
+        let public_key =
+            decode_public_key(public_key).ok_or(DetachedSignatureError::MalformedPublicKey)?;
+        let signature = decode_sidecar(signature, SIGNATURE_LEN)
+            .ok_or(DetachedSignatureError::MalformedSignature)?;
+        UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(&self.signed_bytes(), &signature)
+            .map_err(|_| DetachedSignatureError::BadSignature)
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        self.to_canonical_cbor().expect("artifact serializes")
+    }
+}
+
+fn decode_public_key(data: &[u8]) -> Option<Vec<u8>> {
+    let der_len = SPKI_PREFIX.len() + PUBLIC_KEY_LEN;
+    let key = decode_sidecar(data, PUBLIC_KEY_LEN).or_else(|| decode_sidecar(data, der_len))?;
+    match key.strip_prefix(SPKI_PREFIX) {
+        Some(raw) if raw.len() == PUBLIC_KEY_LEN => Some(raw.to_vec()),
+        Some(_) => None,
+        None => (key.len() == PUBLIC_KEY_LEN).then_some(key),
+    }
+}
+
+/// `data` as exactly `len` raw bytes, or base64 text (PEM armour and
+/// whitespace ignored) decoding to `len` bytes.
+fn decode_sidecar(data: &[u8], len: usize) -> Option<Vec<u8>> {
+    if data.len() == len {
+        return Some(data.to_vec());
+    }
+    let text = std::str::from_utf8(data).ok()?;
+    let encoded = text
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .flat_map(|line| line.split_ascii_whitespace())
+        .collect::<String>();
+    STANDARD
+        .decode(encoded)
+        .ok()
+        .filter(|bytes| bytes.len() == len)
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::signature::KeyPair;
+
+    use super::*;
+    use crate::tests::sample_artifact;
+
+    fn key_pair() -> Ed25519KeyPair {
+        Ed25519KeyPair::from_seed_unchecked(&[9; 32]).unwrap()
+    }
+
+    #[test]
+    fn verifies_raw_and_base64_sidecars() {
+        let artifact = sample_artifact();
+        let key_pair = key_pair();
+        let signature = artifact.sign_detached(&key_pair);
+        let public_key = key_pair.public_key().as_ref();
+        assert_eq!(artifact.verify_detached(public_key, &signature), Ok(()));
+
+        let pem = format!(
+            "-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n",
+            STANDARD.encode([SPKI_PREFIX, public_key].concat())
+        );
+        let encoded = format!("{}\n", STANDARD.encode(&signature));
+        assert_eq!(
+            artifact.verify_detached(pem.as_bytes(), encoded.as_bytes()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_artifact_and_malformed_sidecars() {
+        let artifact = sample_artifact();
+        let key_pair = key_pair();
+        let signature = artifact.sign_detached(&key_pair);
+        let public_key = key_pair.public_key().as_ref();
+
+        let mut tampered = artifact.clone();
+        tampered.domain = "evil.example".into();
+        assert_eq!(
+            tampered.verify_detached(public_key, &signature),
+            Err(DetachedSignatureError::BadSignature)
+        );
+        assert_eq!(
+            artifact.verify_detached(b"not a key", &signature),
+            Err(DetachedSignatureError::MalformedPublicKey)
+        );
+        assert_eq!(
+            artifact.verify_detached(public_key, &signature[..32]),
+            Err(DetachedSignatureError::MalformedSignature)
+        );
+    }
+}
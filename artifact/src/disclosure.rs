@@ -0,0 +1,261 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{ArtifactValidationError, CommitmentAlgorithm, EncodedBlob};
+
+/// Bytes of the random nonce behind each header leaf.
+pub const HEADER_NONCE_BYTES: usize = 16;
+
+/// Lightweight selective disclosure for response headers: one leaf digest per
+/// header line, sorted, plus a digest over the concatenated leaves. Each leaf
+/// hashes a random nonce with the line, so the other leaf digests cannot be
+/// brute-forced; revealed pairs carry their nonce. Leaves and root are
+/// prefixed with the commitment salt like the transcripts.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct HeaderCommitment {
+    pub leaves: Vec<EncodedBlob>,
+    pub root: EncodedBlob,
+    /// Leaves and root include the commitment salt; false in older
+    /// artifacts, which hashed them unsalted.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub salted: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub revealed: Vec<DisclosedHeader>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct DisclosedHeader {
+    pub name: String,
+    pub value: String,
+    /// Nonce of the pair's leaf; absent in artifacts with unsalted leaves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<EncodedBlob>,
+}
+
+impl HeaderCommitment {
+    /// Commit to every `(name, value)` pair under `salt` and the matching
+    /// entry of `nonces`, and reveal those whose name is in `disclose`
+    /// (compared case-insensitively).
+    ///
+    /// # Panics
+    ///
+    /// When `nonces` and `headers` differ in length.
+    pub fn build(
+        algorithm: CommitmentAlgorithm,
+        salt: &[u8],
+        headers: &[(String, String)],
+        nonces: &[Vec<u8>],
+        disclose: &[String],
+    ) -> Self {
+        assert_eq!(headers.len(), nonces.len(), "one nonce per header");
+        let mut leaves = headers
+            .iter()
+            .zip(nonces)
+            .map(|((name, value), nonce)| header_leaf(algorithm, salt, nonce, name, value))
+            .collect::<Vec<_>>();
+        leaves.sort();
+        let root = EncodedBlob::from_bytes(&algorithm.digest_salted(salt, &leaves.concat()));
+        let revealed = headers
+            .iter()
+            .zip(nonces)
+            .filter(|((name, _), _)| disclose.iter().any(|d| d.eq_ignore_ascii_case(name)))
+            .map(|((name, value), nonce)| DisclosedHeader {
+                name: name.clone(),
+                value: value.clone(),
+                nonce: Some(EncodedBlob::from_bytes(nonce)),
+            })
+            .collect();
+        Self {
+            leaves: leaves
+                .iter()
+                .map(|leaf| EncodedBlob::from_bytes(leaf))
+                .collect(),
+            root,
+            salted: true,
+            revealed,
+        }
+    }
+
+    /// Check the leaf list against the root, then that every revealed pair
+    /// hashes to one of the committed leaves. `salt` is the commitment salt.
+    pub fn verify(
+        &self,
+        algorithm: CommitmentAlgorithm,
+        salt: &[u8],
+    ) -> Result<(), ArtifactValidationError> {
+        let salt = if self.salted { salt } else { &[] };
+        let leaves = self
+            .leaves
+            .iter()
+            .map(EncodedBlob::decode)
+            .collect::<Result<Vec<_>, _>>()?;
+        if !leaves.windows(2).all(|pair| pair[0] <= pair[1]) {
+            return Err(ArtifactValidationError::UnsortedHeaderLeaves);
+        }
+        if algorithm.digest_salted(salt, &leaves.concat()) != self.root.decode()? {
+            return Err(ArtifactValidationError::HeaderRootMismatch);
+        }
+        for header in &self.revealed {
+            let nonce = match &header.nonce {
+                Some(nonce) => nonce.decode()?,
+                None => Vec::new(),
+            };
+            if header.nonce.is_some() && nonce.len() != HEADER_NONCE_BYTES {
+                return Err(ArtifactValidationError::InvalidHeaderNonce(
+                    header.name.clone(),
+                ));
+            }
+            let leaf = header_leaf(algorithm, salt, &nonce, &header.name, &header.value);
+            if leaves.binary_search(&leaf).is_err() {
+                return Err(ArtifactValidationError::UncommittedHeader(
+                    header.name.clone(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `hash(salt || nonce || lowercase name || ":" || value)`; salt and nonce
+/// have fixed lengths and header names cannot contain `:`, so the encoding
+/// is unambiguous. Leaves of older artifacts have neither.
+pub fn header_leaf(
+    algorithm: CommitmentAlgorithm,
+    salt: &[u8],
+    nonce: &[u8],
+    name: &str,
+    value: &str,
+) -> Vec<u8> {
+    let line = format!("{}:{}", name.to_ascii_lowercase(), value);
+    algorithm.digest_salted(&[salt, nonce].concat(), line.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> Vec<(String, String)> {
+        vec![
+            ("server".into(), "nginx".into()),
+            ("set-cookie".into(), "session=secret".into()),
+            ("x-frame-options".into(), "DENY".into()),
+        ]
+    }
+
+    fn nonces() -> Vec<Vec<u8>> {
+        (0..3).map(|i| vec![i; HEADER_NONCE_BYTES]).collect()
+    }
+
+    fn build(algorithm: CommitmentAlgorithm, disclose: &[String]) -> HeaderCommitment {
+        HeaderCommitment::build(algorithm, &[], &headers(), &nonces(), disclose)
+    }
+
+    #[test]
+    fn reveals_single_header() {
+        let commitment = build(CommitmentAlgorithm::Sha256, &["Server".into()]);
+        assert_eq!(commitment.leaves.len(), 3);
+        assert_eq!(
+            commitment.revealed,
+            vec![DisclosedHeader {
+                name: "server".into(),
+                value: "nginx".into(),
+                nonce: Some(EncodedBlob::from_bytes(&nonces()[0])),
+            }]
+        );
+        commitment
+            .verify(CommitmentAlgorithm::Sha256, &[])
+            .expect("revealed header verifies");
+    }
+
+    #[test]
+    fn leaves_cannot_be_brute_forced_without_the_nonce() {
+        let commitment = build(CommitmentAlgorithm::Sha256, &[]);
+        let guess = EncodedBlob::from_bytes(&header_leaf(
+            CommitmentAlgorithm::Sha256,
+            &[],
+            &[],
+            "x-frame-options",
+            "DENY",
+        ));
+        assert!(!commitment.leaves.contains(&guess));
+    }
+
+    #[test]
+    fn salt_unlinks_identical_headers() {
+        let salted = |salt: &[u8]| {
+            HeaderCommitment::build(
+                CommitmentAlgorithm::Sha256,
+                salt,
+                &headers(),
+                &nonces(),
+                &["server".into()],
+            )
+        };
+        let first = salted(&[1; 32]);
+        let second = salted(&[2; 32]);
+        assert_ne!(first.root, second.root);
+        assert!(first
+            .leaves
+            .iter()
+            .all(|leaf| !second.leaves.contains(leaf)));
+        first
+            .verify(CommitmentAlgorithm::Sha256, &[1; 32])
+            .expect("verifies under its salt");
+        assert_eq!(
+            first.verify(CommitmentAlgorithm::Sha256, &[2; 32]),
+            Err(ArtifactValidationError::HeaderRootMismatch)
+        );
+    }
+
+    #[test]
+    fn unsalted_leaves_of_older_artifacts_still_verify() {
+        let mut leaves = headers()
+            .iter()
+            .map(|(name, value)| header_leaf(CommitmentAlgorithm::Sha256, &[], &[], name, value))
+            .collect::<Vec<_>>();
+        leaves.sort();
+        let legacy = HeaderCommitment {
+            root: EncodedBlob::from_bytes(&CommitmentAlgorithm::Sha256.digest(&leaves.concat())),
+            leaves: leaves
+                .iter()
+                .map(|leaf| EncodedBlob::from_bytes(leaf))
+                .collect(),
+            salted: false,
+            revealed: vec![DisclosedHeader {
+                name: "server".into(),
+                value: "nginx".into(),
+                nonce: None,
+            }],
+        };
+        legacy
+            .verify(CommitmentAlgorithm::Sha256, &[1; 32])
+            .expect("the transcript salt never reached older leaves");
+    }
+
+    #[test]
+    fn detects_forged_pair() {
+        let mut commitment = build(CommitmentAlgorithm::Blake3, &["server".into()]);
+        commitment.revealed[0].value = "Apache".into();
+        assert_eq!(
+            commitment.verify(CommitmentAlgorithm::Blake3, &[]),
+            Err(ArtifactValidationError::UncommittedHeader("server".into()))
+        );
+
+        let mut shifted = build(CommitmentAlgorithm::Blake3, &["server".into()]);
+        shifted.revealed[0].nonce = Some(EncodedBlob::from_bytes(&[0; HEADER_NONCE_BYTES - 1]));
+        assert_eq!(
+            shifted.verify(CommitmentAlgorithm::Blake3, &[]),
+            Err(ArtifactValidationError::InvalidHeaderNonce("server".into()))
+        );
+    }
+
+    #[test]
+    fn detects_tampered_leaf_list() {
+        let mut commitment = build(CommitmentAlgorithm::Blake3, &[]);
+        commitment.leaves.pop();
+        assert_eq!(
+            commitment.verify(CommitmentAlgorithm::Blake3, &[]),
+            Err(ArtifactValidationError::HeaderRootMismatch)
+        );
+    }
+}
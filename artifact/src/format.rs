@@ -0,0 +1,73 @@
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// Serialization formats a `.red` artifact can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArtifactFormat {
+    Json,
+    Cbor,
+}
+
+impl ArtifactFormat {
+    pub const ALL: [ArtifactFormat; 2] = [ArtifactFormat::Json, ArtifactFormat::Cbor];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ArtifactFormat::Json => "json",
+            ArtifactFormat::Cbor => "cbor",
+        }
+    }
+
+    pub fn to_mime(self) -> &'static str {
+        match self {
+            ArtifactFormat::Json => "application/json",
+            ArtifactFormat::Cbor => "application/cbor",
+        }
+    }
+}
+
+impl fmt::Display for ArtifactFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ArtifactFormat {
+    type Err = UnknownArtifactFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ArtifactFormat::ALL
+            .into_iter()
+            .find(|format| format.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| UnknownArtifactFormat(s.to_string()))
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("unknown artifact format '{0}' (expected json or cbor)")]
+pub struct UnknownArtifactFormat(pub String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_strings() {
+        for format in ArtifactFormat::ALL {
+            assert_eq!(format.to_string().parse::<ArtifactFormat>(), Ok(format));
+        }
+        assert_eq!("CBOR".parse::<ArtifactFormat>(), Ok(ArtifactFormat::Cbor));
+    }
+
+    #[test]
+    fn rejects_unknown_format_and_maps_mime() {
+        assert_eq!(
+            "yaml".parse::<ArtifactFormat>(),
+            Err(UnknownArtifactFormat("yaml".into()))
+        );
+        assert_eq!(ArtifactFormat::Json.to_mime(), "application/json");
+        assert_eq!(ArtifactFormat::Cbor.to_mime(), "application/cbor");
+    }
+}
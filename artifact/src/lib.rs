@@ -1,21 +1,67 @@
-use base64::engine::general_purpose::STANDARD;
+use std::collections::BTreeMap;
+
+use base64::alphabet;
+use base64::engine::general_purpose::{
+    GeneralPurpose, GeneralPurposeConfig, STANDARD, URL_SAFE_NO_PAD,
+};
+use base64::engine::DecodePaddingMode;
 use base64::Engine;
 use chrono::{DateTime, Utc};
 use redproof_statements::Statement;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
+pub mod annotations;
+pub mod cbor;
+pub mod committer;
+mod detached;
+pub mod disclosure;
+pub mod format;
+mod migrate;
+mod provenance;
+pub mod san;
+pub mod schema;
+pub mod sct;
+pub mod spki;
+pub mod store;
+mod witness;
+pub use annotations::{
+    CaptureAnnotations, ClockSkewAnnotation, PostureSummary, RequestSequenceAnnotation,
+    ReservedAnnotationKey, ResolvedIpsAnnotation, TimingAnnotation, TimingTrace,
+    RESERVED_ANNOTATION_KEYS,
+};
+pub use cbor::to_canonical_cbor;
+pub use committer::{
+    CommitmentAlgorithm, Committer, RegisterCommitterError, UnknownCommitmentAlgorithm,
+};
+pub use detached::DetachedSignatureError;
+pub use disclosure::{header_leaf, DisclosedHeader, HeaderCommitment, HEADER_NONCE_BYTES};
+pub use format::{ArtifactFormat, UnknownArtifactFormat};
+pub use migrate::{
+    check_verifier_version, RequiresNewerVerifier, UnsupportedArtifactVersion, ARTIFACT_VERSION,
+};
+pub use provenance::{ProvenanceEntry, ProvenanceError};
+pub use san::{covering_name, subject_alt_names};
+pub use sct::embedded_sct_count;
+pub use spki::{spki_pin, MalformedCertificate};
+pub use store::{ArtifactStore, FsStore, MemoryStore, StoreError, StoreFilter};
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct RedProofArtifact {
     pub version: String,
     pub domain: String,
     pub time_utc: DateTime<Utc>,
+    #[schemars(schema_with = "schema::tls_context")]
     pub tls: TlsProofContext,
     pub statement: Statement,
     pub commitments: CommitmentSet,
     pub proof: EncodedBlob,
+    /// Prover-declared end of the validity window; verifiers reject the
+    /// artifact afterwards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
     #[serde(default)]
     pub meta: ArtifactMeta,
 }
@@ -25,100 +71,631 @@ impl RedProofArtifact {
         if self.domain.trim().is_empty() {
             return Err(ArtifactValidationError::MissingDomain);
         }
+        if self
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= self.time_utc)
+        {
+            return Err(ArtifactValidationError::ExpiresBeforeCapture);
+        }
         self.tls.validate()?;
         self.commitments.validate()?;
+        self.commitments.verify_statement(&self.statement)?;
         self.proof.ensure_base64("proof")?;
         Ok(())
     }
+
+    /// [`validate`](Self::validate) plus commitment self-consistency: every
+    /// digest is recomputed from the witness (when present) and the header
+    /// commitment is checked.
+    pub fn validate_strict(&self) -> Result<(), ArtifactValidationError> {
+        self.validate()?;
+        for algorithm in self.commitments.algorithms() {
+            self.commitments.verify_witness(algorithm)?;
+        }
+        if let Some(headers) = &self.commitments.headers {
+            headers.verify(self.commitments.algorithm, &self.commitments.salt_bytes()?)?;
+        }
+        Ok(())
+    }
+
+    /// Re-encode the artifact's blobs in `encoding`. Provenance entries are
+    /// left alone, as they sign the artifact's earlier bytes.
+    pub fn reencode_blobs(
+        &mut self,
+        encoding: BlobEncoding,
+    ) -> Result<(), ArtifactValidationError> {
+        self.blobs_mut()
+            .try_for_each(|blob| blob.reencode(encoding))
+    }
+
+    /// Rewrite blobs that only decode leniently (missing or extra padding,
+    /// non-zero trailing bits) in canonical form, keeping their alphabet.
+    /// Returns how many were rewritten; blobs that are not base64 at all
+    /// are left for [`validate`](Self::validate) to report. Like
+    /// [`reencode_blobs`](Self::reencode_blobs) it leaves provenance alone.
+    pub fn canonicalize_base64(&mut self) -> usize {
+        let mut rewritten = 0;
+        for blob in self.blobs_mut() {
+            if blob.decode().is_ok() {
+                continue;
+            }
+            if let Ok(bytes) = blob.decode_lenient() {
+                *blob = EncodedBlob::from_bytes_as(&bytes, blob.encoding());
+                rewritten += 1;
+            }
+        }
+        rewritten
+    }
+
+    fn blobs_mut(&mut self) -> impl Iterator<Item = &mut EncodedBlob> {
+        let commitments = &mut self.commitments;
+        let headers = commitments.headers.iter_mut().flat_map(|headers| {
+            headers
+                .leaves
+                .iter_mut()
+                .chain(std::iter::once(&mut headers.root))
+                .chain(
+                    headers
+                        .revealed
+                        .iter_mut()
+                        .filter_map(|header| header.nonce.as_mut()),
+                )
+        });
+        let digests = commitments
+            .alternates
+            .values_mut()
+            .flat_map(|digests| [&mut digests.handshake, &mut digests.app_data]);
+        let witness = commitments.witness.iter_mut().flat_map(|witness| {
+            [&mut witness.handshake, &mut witness.app_data]
+                .into_iter()
+                .chain(witness.header_nonces.iter_mut())
+        });
+        std::iter::once(&mut self.proof)
+            .chain(self.tls.cert_chain.iter_mut())
+            .chain(commitments.salt.iter_mut())
+            .chain(commitments.statement.iter_mut())
+            .chain([&mut commitments.handshake, &mut commitments.app_data])
+            .chain(digests)
+            .chain(headers)
+            .chain(witness)
+    }
+
+    /// `sha256:<hex>` naming the attested fact: domain, statement, TLS
+    /// posture and the primary commitment. Unlike [`digest`](Self::digest) it
+    /// ignores `time_utc`, `expires_at`, `meta`, the proof, alternates and
+    /// the witness, so re-capturing an unchanged response under the same
+    /// statement yields the same ID. Anything committed still counts: a new
+    /// `Date` header or a commitment salt changes it. The blob alphabet does
+    /// not.
+    pub fn claim_id(&self) -> String {
+        #[derive(Serialize)]
+        struct Claim<'a> {
+            domain: &'a str,
+            statement: &'a Statement,
+            tls_version: &'a str,
+            cipher: &'a str,
+            alpn: Option<&'a str>,
+            cert_fingerprints: &'a [String],
+            spki_pins: &'a [String],
+            algorithm: CommitmentAlgorithm,
+            canonicalization: &'a str,
+            handshake: EncodedBlob,
+            app_data: EncodedBlob,
+        }
+        let standard = |blob: &EncodedBlob| {
+            let mut blob = blob.clone();
+            let _ = blob.reencode(BlobEncoding::Standard);
+            blob
+        };
+        let claim = Claim {
+            domain: &self.domain,
+            statement: &self.statement,
+            tls_version: &self.tls.version,
+            cipher: &self.tls.cipher,
+            alpn: self.tls.alpn.as_deref(),
+            cert_fingerprints: &self.tls.cert_fingerprints,
+            spki_pins: &self.tls.spki_pins,
+            algorithm: self.commitments.algorithm,
+            canonicalization: &self.commitments.canonicalization,
+            handshake: standard(&self.commitments.handshake),
+            app_data: standard(&self.commitments.app_data),
+        };
+        let bytes = serde_json::to_vec(&claim).expect("claim serializes");
+        format!("sha256:{:x}", Sha256::digest(bytes))
+    }
+
+    /// Commit to the statement under the primary algorithm, tied to the
+    /// app-data commitment, so a statement edited without re-binding
+    /// invalidates the artifact. The digest is unkeyed: it catches accidental
+    /// corruption and careless edits, not an attacker, who can swap the
+    /// statement and call this again. Only a detached signature or a
+    /// provenance entry, which sign the whole artifact, make a swap
+    /// detectable.
+    pub fn bind_statement(&mut self) -> Result<(), ArtifactValidationError> {
+        self.commitments.statement = Some(self.commitments.statement_digest(&self.statement)?);
+        Ok(())
+    }
+
+    /// Take the witness out so it can ship as a sidecar, recording its
+    /// digest so only that witness can be attached again.
+    pub fn detach_witness(&mut self) -> Result<Option<CommitmentWitness>, ArtifactValidationError> {
+        let Some(witness) = self.commitments.witness.take() else {
+            return Ok(None);
+        };
+        self.commitments.witness_digest = Some(witness.digest()?);
+        Ok(Some(witness))
+    }
+
+    /// Supply the transcript witness for a commitment-only artifact. The
+    /// witness must reproduce every committed digest and match the recorded
+    /// sidecar digest, if any.
+    pub fn attach_witness(
+        mut self,
+        witness: CommitmentWitness,
+    ) -> Result<Self, ArtifactValidationError> {
+        self.commitments.check_witness_digest(&witness)?;
+        self.commitments.witness = Some(witness);
+        for algorithm in self.commitments.algorithms() {
+            self.commitments.verify_witness(algorithm)?;
+        }
+        Ok(self)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct TlsProofContext {
     pub version: String,
     pub cipher: String,
+    #[schemars(schema_with = "schema::cert_fingerprints")]
     pub cert_fingerprints: Vec<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub alpn: Option<String>,
+    /// Optional DER certificate chain (leaf first) for re-validation against a
+    /// trust store.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cert_chain: Vec<EncodedBlob>,
+    /// `pin-sha256` of each certificate's SubjectPublicKeyInfo, leaf first;
+    /// recorded with `--record-spki`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spki_pins: Vec<String>,
+    /// DNS names and IP addresses in the leaf certificate's
+    /// SubjectAltName, wildcards as written.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subject_alt_names: Vec<String>,
+    /// Size of the chain the server sent. Prover-attested unless `cert_chain`
+    /// is embedded, in which case it must agree with it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_size: Option<CertChainSize>,
+}
+
+/// Certificates in a served chain and their combined DER length.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct CertChainSize {
+    pub count: usize,
+    pub total_bytes: usize,
+}
+
+impl CertChainSize {
+    pub fn of<C: AsRef<[u8]>>(chain: &[C]) -> Self {
+        Self {
+            count: chain.len(),
+            total_bytes: chain.iter().map(|cert| cert.as_ref().len()).sum(),
+        }
+    }
 }
 
 impl TlsProofContext {
+    /// `version` recorded for captures that fell back to plaintext HTTP.
+    pub const PLAINTEXT: &'static str = "none";
+
+    /// True when the response was captured without TLS, so nothing binds it
+    /// to the domain.
+    pub fn is_plaintext(&self) -> bool {
+        self.version == Self::PLAINTEXT
+    }
+
     pub fn validate(&self) -> Result<(), ArtifactValidationError> {
-        if self.cert_fingerprints.is_empty() {
+        if self.cert_fingerprints.is_empty() && !self.is_plaintext() {
             return Err(ArtifactValidationError::MissingCertFingerprint);
         }
+        for fingerprint in &self.cert_fingerprints {
+            validate_fingerprint(fingerprint)?;
+        }
+        for cert in &self.cert_chain {
+            cert.ensure_base64("embedded certificate")?;
+        }
+        if let Some(recorded) = self.chain_size.filter(|_| !self.cert_chain.is_empty()) {
+            let embedded = self
+                .cert_chain
+                .iter()
+                .map(EncodedBlob::decode)
+                .collect::<Result<Vec<_>, _>>()?;
+            if CertChainSize::of(&embedded) != recorded {
+                return Err(ArtifactValidationError::ChainSizeMismatch);
+            }
+        }
         Ok(())
     }
 }
 
+/// Accepts `sha256:<64 lowercase hex>` or `domain-only:<host>` entries.
+fn validate_fingerprint(fingerprint: &str) -> Result<(), ArtifactValidationError> {
+    let malformed = || ArtifactValidationError::MalformedCertFingerprint(fingerprint.to_string());
+    match fingerprint.split_once(':') {
+        Some(("sha256", digest)) => {
+            let well_formed = digest.len() == 64
+                && digest
+                    .bytes()
+                    .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+            well_formed.then_some(()).ok_or_else(malformed)
+        }
+        Some(("domain-only", host)) => {
+            if host.trim().is_empty() || host.contains(char::is_whitespace) {
+                Err(malformed())
+            } else {
+                Ok(())
+            }
+        }
+        Some((scheme, _)) => Err(ArtifactValidationError::UnknownFingerprintScheme(
+            scheme.to_string(),
+        )),
+        None => Err(malformed()),
+    }
+}
+
+/// Canonical JSON encoding of the handshake and response transcripts, as
+/// the first 1.x releases wrote it.
+pub const CANONICALIZATION_JSON_V1: &str = "json/v1";
+/// `json/v1` plus the status line's HTTP version and the optional fields of
+/// later releases (SPKI pins, subject alternative names, resolved addresses,
+/// early hints, body digest, timing trace and header `wire_name`). Repeated
+/// headers keep their wire order.
+pub const CANONICALIZATION_JSON_V2: &str = "json/v2";
+
+/// `source` committed in the `json/v2` handshake of an artifact minted from a
+/// response another tool captured; live captures carry no `source`.
+pub const SOURCE_IMPORTED: &str = "imported";
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct CommitmentSet {
     pub algorithm: CommitmentAlgorithm,
+    /// Scheme that turned the transcripts into the committed bytes. Artifacts
+    /// predating the field used `json/v1`.
+    #[serde(default = "default_canonicalization")]
+    pub canonicalization: String,
+    /// Random bytes prepended to each transcript and header leaf before
+    /// hashing, so equal responses do not yield linkable commitments (an
+    /// embedded witness still links them). Public by design.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<EncodedBlob>,
     pub handshake: EncodedBlob,
     pub app_data: EncodedBlob,
+    /// Commitments to the same transcript under further algorithms, so a
+    /// verifier can pick whichever one it supports.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub alternates: BTreeMap<CommitmentAlgorithm, CommitmentDigests>,
+    /// Per-header commitment for selective disclosure, under `algorithm`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HeaderCommitment>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub witness: Option<CommitmentWitness>,
+    /// Binds the artifact's statement to `app_data`; see
+    /// [`CommitmentSet::statement_digest`]. Absent in older artifacts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statement: Option<EncodedBlob>,
+    /// [`CommitmentWitness::digest`] of a witness shipped as a separate
+    /// sidecar; a witness supplied later must match it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub witness_digest: Option<String>,
+}
+
+fn default_canonicalization() -> String {
+    CANONICALIZATION_JSON_V1.to_string()
 }
 
 impl CommitmentSet {
     pub fn validate(&self) -> Result<(), ArtifactValidationError> {
+        if let Some(salt) = &self.salt {
+            salt.ensure_base64("commitment salt")?;
+        }
         self.handshake.ensure_base64("handshake commitment")?;
         self.app_data.ensure_base64("application-data commitment")?;
+        if self.alternates.contains_key(&self.algorithm) {
+            return Err(ArtifactValidationError::DuplicateCommitmentAlgorithm(
+                self.algorithm,
+            ));
+        }
+        for digests in self.alternates.values() {
+            digests.handshake.ensure_base64("handshake commitment")?;
+            digests
+                .app_data
+                .ensure_base64("application-data commitment")?;
+        }
+        if let Some(headers) = &self.headers {
+            headers.root.ensure_base64("header commitment root")?;
+            for leaf in &headers.leaves {
+                leaf.ensure_base64("header commitment leaf")?;
+            }
+            for nonce in headers.revealed.iter().filter_map(|h| h.nonce.as_ref()) {
+                nonce.ensure_base64("disclosed header nonce")?;
+            }
+        }
         if let Some(witness) = &self.witness {
             witness.handshake.ensure_base64("handshake witness")?;
             witness.app_data.ensure_base64("app-data witness")?;
+            for nonce in &witness.header_nonces {
+                nonce.ensure_base64("header nonce witness")?;
+            }
+            self.check_witness_digest(witness)?;
+        }
+        Ok(())
+    }
+
+    /// Primary-algorithm digest of the salt, the decoded `app_data`
+    /// commitment and `statement`'s canonical JSON, in that order, in the
+    /// alphabet of `app_data`.
+    pub fn statement_digest(
+        &self,
+        statement: &Statement,
+    ) -> Result<EncodedBlob, ArtifactValidationError> {
+        let mut bound = self.app_data.decode()?;
+        bound.extend_from_slice(&statement.canonical_json());
+        let digest = self.algorithm.digest_salted(&self.salt_bytes()?, &bound);
+        Ok(EncodedBlob::from_bytes_as(
+            &digest,
+            self.app_data.encoding(),
+        ))
+    }
+
+    /// Err when `statement` is not the one the statement commitment binds,
+    /// or when a `json/v2` set, written since the binding became mandatory,
+    /// has none. Older sets without one pass.
+    pub fn verify_statement(&self, statement: &Statement) -> Result<(), ArtifactValidationError> {
+        let Some(bound) = &self.statement else {
+            if self.canonicalization == CANONICALIZATION_JSON_V1 {
+                return Ok(());
+            }
+            return Err(ArtifactValidationError::MissingStatementCommitment);
+        };
+        if bound.decode()? != self.statement_digest(statement)?.decode()? {
+            return Err(ArtifactValidationError::StatementMismatch);
         }
         Ok(())
     }
+
+    /// Err when `witness` is not the sidecar recorded in `witness_digest`.
+    fn check_witness_digest(
+        &self,
+        witness: &CommitmentWitness,
+    ) -> Result<(), ArtifactValidationError> {
+        match &self.witness_digest {
+            Some(recorded) if *recorded != witness.digest()? => {
+                Err(ArtifactValidationError::WitnessDigestMismatch)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Every algorithm carried by the set, primary first.
+    pub fn algorithms(&self) -> Vec<CommitmentAlgorithm> {
+        std::iter::once(self.algorithm)
+            .chain(self.alternates.keys().copied())
+            .collect()
+    }
+
+    /// Recompute the `algorithm` digests from the witness and compare them to
+    /// the committed ones. Passes trivially when no witness is embedded. When
+    /// the digests were in fact made by another registered algorithm, reports
+    /// [`ArtifactValidationError::AlgorithmMismatch`] instead of a bare
+    /// digest mismatch.
+    pub fn verify_witness(
+        &self,
+        algorithm: CommitmentAlgorithm,
+    ) -> Result<(), ArtifactValidationError> {
+        let Some(witness) = &self.witness else {
+            return Ok(());
+        };
+        let digests = self
+            .digests_for(algorithm)
+            .ok_or(ArtifactValidationError::MissingCommitment(algorithm))?;
+        let salt = self.salt_bytes()?;
+        let handshake = witness.handshake.decode()?;
+        let app_data = witness.app_data.decode()?;
+        let committed = (digests.handshake.decode()?, digests.app_data.decode()?);
+        let matches = |candidate: CommitmentAlgorithm| {
+            (
+                candidate.digest_salted(&salt, &handshake) == committed.0,
+                candidate.digest_salted(&salt, &app_data) == committed.1,
+            )
+        };
+        let part = match matches(algorithm) {
+            (true, true) => return Ok(()),
+            (false, _) => "handshake",
+            (true, false) => "app-data",
+        };
+        let actual = CommitmentAlgorithm::registered()
+            .into_iter()
+            .filter(|candidate| *candidate != algorithm)
+            .find(|candidate| matches(*candidate) == (true, true));
+        Err(match actual {
+            Some(witness) => ArtifactValidationError::AlgorithmMismatch {
+                claimed: algorithm,
+                witness,
+            },
+            None => ArtifactValidationError::DigestMismatch(part),
+        })
+    }
+
+    /// Decoded `salt`; empty for unsalted commitments.
+    pub fn salt_bytes(&self) -> Result<Vec<u8>, ArtifactValidationError> {
+        self.salt
+            .as_ref()
+            .map(EncodedBlob::decode)
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Handshake and app-data digests committed under `algorithm`, if present.
+    pub fn digests_for(&self, algorithm: CommitmentAlgorithm) -> Option<CommitmentDigests> {
+        if algorithm == self.algorithm {
+            Some(CommitmentDigests {
+                handshake: self.handshake.clone(),
+                app_data: self.app_data.clone(),
+            })
+        } else {
+            self.alternates.get(&algorithm).cloned()
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
-#[serde(rename_all = "lowercase")]
-pub enum CommitmentAlgorithm {
-    Blake3,
-    Sha256,
+pub struct CommitmentDigests {
+    pub handshake: EncodedBlob,
+    pub app_data: EncodedBlob,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct CommitmentWitness {
     pub handshake: EncodedBlob,
     pub app_data: EncodedBlob,
+    /// Leaf nonces of [`CommitmentSet::headers`], one per canonical response
+    /// header in transcript order. Only the witness holds the nonces of
+    /// undisclosed headers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub header_nonces: Vec<EncodedBlob>,
+}
+
+impl CommitmentWitness {
+    /// `sha256:<hex>` over both transcripts and any header nonces, each
+    /// prefixed with its length, so the blob alphabet does not affect it.
+    pub fn digest(&self) -> Result<String, ArtifactValidationError> {
+        let mut hasher = Sha256::new();
+        for part in [&self.handshake, &self.app_data]
+            .into_iter()
+            .chain(&self.header_nonces)
+        {
+            let bytes = part.decode()?;
+            hasher.update((bytes.len() as u64).to_be_bytes());
+            hasher.update(bytes);
+        }
+        Ok(format!("sha256:{:x}", hasher.finalize()))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct ArtifactMeta {
     pub tool_version: String,
-    #[serde(default, skip_serializing_if = "Map::is_empty")]
-    pub annotations: Map<String, Value>,
+    /// Oldest verifier release that can decide the statement; newer
+    /// statement types raise it so older verifiers refuse the artifact.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_verifier_version: Option<String>,
+    #[serde(default, skip_serializing_if = "CaptureAnnotations::is_empty")]
+    pub annotations: CaptureAnnotations,
+    /// Signed enrichment steps applied after capture, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provenance: Vec<ProvenanceEntry>,
 }
 
 impl Default for ArtifactMeta {
     fn default() -> Self {
         Self {
             tool_version: "0.0.0".to_string(),
-            annotations: Map::new(),
+            min_verifier_version: None,
+            annotations: CaptureAnnotations::default(),
+            provenance: Vec::new(),
         }
     }
 }
 
+/// Base64 data. Standard, padded base64 is stored as-is; URL-safe base64
+/// without padding carries a `base64url:` prefix so each blob says which
+/// alphabet it uses.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct EncodedBlob(pub String);
 
+/// Alphabet of an [`EncodedBlob`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlobEncoding {
+    /// RFC 4648 base64 with padding.
+    #[default]
+    Standard,
+    /// RFC 4648 URL-safe base64 without padding, for artifacts embedded in
+    /// URLs or JWTs.
+    UrlSafeNoPad,
+}
+
+impl BlobEncoding {
+    const URL_SAFE_PREFIX: &'static str = "base64url:";
+}
+
+/// Decoding that tolerates any padding and non-zero trailing bits, for
+/// producers that do not emit canonical base64.
+const LENIENT: GeneralPurposeConfig = GeneralPurposeConfig::new()
+    .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+    .with_decode_allow_trailing_bits(true);
+const STANDARD_LENIENT: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, LENIENT);
+const URL_SAFE_LENIENT: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, LENIENT);
+
 impl EncodedBlob {
     fn ensure_base64(&self, field: &str) -> Result<(), ArtifactValidationError> {
-        STANDARD
-            .decode(self.0.as_bytes())
-            .map(|_| ())
-            .map_err(|_| ArtifactValidationError::InvalidBase64(field.to_string()))
+        match self.decode() {
+            Ok(_) => Ok(()),
+            Err(_) if self.decode_lenient().is_ok() => Err(
+                ArtifactValidationError::NonCanonicalBase64(field.to_string()),
+            ),
+            Err(_) => Err(ArtifactValidationError::InvalidBase64(field.to_string())),
+        }
     }
 
+    /// Decode with the alphabet the blob declares; data in the other
+    /// alphabet, and non-canonical encodings of it, are rejected.
     pub fn decode(&self) -> Result<Vec<u8>, ArtifactValidationError> {
-        STANDARD
-            .decode(self.0.as_bytes())
-            .map_err(|_| ArtifactValidationError::InvalidBase64("encoded blob".into()))
+        let decoded = match self.0.strip_prefix(BlobEncoding::URL_SAFE_PREFIX) {
+            Some(data) => URL_SAFE_NO_PAD.decode(data.as_bytes()),
+            None => STANDARD.decode(self.0.as_bytes()),
+        };
+        decoded.map_err(|_| ArtifactValidationError::InvalidBase64("encoded blob".into()))
+    }
+
+    /// Like [`decode`](Self::decode), but accepting any padding and
+    /// non-zero trailing bits.
+    pub fn decode_lenient(&self) -> Result<Vec<u8>, ArtifactValidationError> {
+        let decoded = match self.0.strip_prefix(BlobEncoding::URL_SAFE_PREFIX) {
+            Some(data) => URL_SAFE_LENIENT.decode(data.as_bytes()),
+            None => STANDARD_LENIENT.decode(self.0.as_bytes()),
+        };
+        decoded.map_err(|_| ArtifactValidationError::InvalidBase64("encoded blob".into()))
+    }
+
+    pub fn encoding(&self) -> BlobEncoding {
+        if self.0.starts_with(BlobEncoding::URL_SAFE_PREFIX) {
+            BlobEncoding::UrlSafeNoPad
+        } else {
+            BlobEncoding::Standard
+        }
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Self {
-        EncodedBlob(STANDARD.encode(bytes))
+        Self::from_bytes_as(bytes, BlobEncoding::Standard)
+    }
+
+    pub fn from_bytes_as(bytes: &[u8], encoding: BlobEncoding) -> Self {
+        match encoding {
+            BlobEncoding::Standard => EncodedBlob(STANDARD.encode(bytes)),
+            BlobEncoding::UrlSafeNoPad => EncodedBlob(format!(
+                "{}{}",
+                BlobEncoding::URL_SAFE_PREFIX,
+                URL_SAFE_NO_PAD.encode(bytes)
+            )),
+        }
+    }
+
+    /// Re-encode the same bytes in `encoding`.
+    pub fn reencode(&mut self, encoding: BlobEncoding) -> Result<(), ArtifactValidationError> {
+        if self.encoding() != encoding {
+            *self = Self::from_bytes_as(&self.decode()?, encoding);
+        }
+        Ok(())
     }
 }
 
@@ -126,21 +703,59 @@ impl EncodedBlob {
 pub enum ArtifactValidationError {
     #[error("artifact is missing domain")]
     MissingDomain,
+    #[error("artifact expires before its capture time")]
+    ExpiresBeforeCapture,
     #[error("no certificate fingerprints captured")]
     MissingCertFingerprint,
+    #[error("malformed certificate fingerprint '{0}'")]
+    MalformedCertFingerprint(String),
+    #[error("unknown certificate fingerprint scheme '{0}'")]
+    UnknownFingerprintScheme(String),
+    #[error("commitment algorithm {0} listed more than once")]
+    DuplicateCommitmentAlgorithm(CommitmentAlgorithm),
+    #[error("no commitment under {0}")]
+    MissingCommitment(CommitmentAlgorithm),
+    #[error("{0} digest mismatch")]
+    DigestMismatch(&'static str),
+    #[error("algorithm mismatch: claimed {claimed}, witness matches {witness}")]
+    AlgorithmMismatch {
+        claimed: CommitmentAlgorithm,
+        witness: CommitmentAlgorithm,
+    },
+    #[error("header commitment leaves do not match their root")]
+    HeaderRootMismatch,
+    #[error("header commitment leaves are not sorted")]
+    UnsortedHeaderLeaves,
+    #[error("revealed header '{0}' is not in the committed list")]
+    UncommittedHeader(String),
+    #[error("revealed header '{0}' has a malformed nonce")]
+    InvalidHeaderNonce(String),
+    #[error("recorded certificate chain size does not match the embedded chain")]
+    ChainSizeMismatch,
     #[error("{0} is not valid base64 data")]
     InvalidBase64(String),
+    #[error("{0} is not canonical base64 (padding or trailing bits)")]
+    NonCanonicalBase64(String),
+    #[error("witness does not match the recorded sidecar digest")]
+    WitnessDigestMismatch,
+    #[error("statement does not match the statement commitment")]
+    StatementMismatch,
+    #[error("artifact has no statement commitment")]
+    MissingStatementCommitment,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const FINGERPRINT: &str =
+        "sha256:455943cf819425761d1f950263ebf54755d8d684c25535943976f488bc79d23b";
+
     fn encoded(data: &str) -> EncodedBlob {
         EncodedBlob(STANDARD.encode(data))
     }
 
-    fn sample_artifact() -> RedProofArtifact {
+    pub(crate) fn sample_artifact() -> RedProofArtifact {
         let statement = Statement::HeaderAbsent {
             target: "Strict-Transport-Security".into(),
         };
@@ -151,30 +766,443 @@ mod tests {
             tls: TlsProofContext {
                 version: "TLS1.3".into(),
                 cipher: "TLS_AES_128_GCM_SHA256".into(),
-                cert_fingerprints: vec!["sha256:deadbeef".into()],
+                cert_fingerprints: vec![FINGERPRINT.into()],
                 alpn: Some("h2".into()),
+                cert_chain: vec![],
+                spki_pins: vec![],
+                subject_alt_names: vec![],
+                chain_size: None,
             },
             statement,
             commitments: CommitmentSet {
+                canonicalization: CANONICALIZATION_JSON_V1.into(),
+                salt: None,
                 algorithm: CommitmentAlgorithm::Blake3,
                 handshake: encoded("handshake"),
                 app_data: encoded("app"),
+                alternates: BTreeMap::new(),
+                headers: None,
                 witness: None,
+                statement: None,
+                witness_digest: None,
             },
             proof: encoded("proof"),
+            expires_at: None,
             meta: ArtifactMeta {
                 tool_version: "0.1.0".into(),
-                annotations: Map::new(),
+                min_verifier_version: None,
+                annotations: CaptureAnnotations::default(),
+                provenance: Vec::new(),
             },
         }
     }
 
+    fn enrich(artifact: &mut RedProofArtifact, key: &str, seed: u8) {
+        let prior = artifact.digest();
+        artifact
+            .meta
+            .annotations
+            .insert_custom(key, serde_json::json!(true))
+            .expect("custom key");
+        let key_pair = ring::signature::Ed25519KeyPair::from_seed_unchecked(&[seed; 32])
+            .expect("ed25519 seed");
+        artifact.sign_enrichment(prior, key, &key_pair, Utc::now());
+    }
+
     #[test]
     fn validates_sample() {
         let artifact = sample_artifact();
         artifact.validate().expect("valid artifact");
     }
 
+    #[test]
+    fn accepts_well_formed_fingerprints() {
+        let mut artifact = sample_artifact();
+        artifact.tls.cert_fingerprints = vec![FINGERPRINT.into(), "domain-only:example.com".into()];
+        artifact.validate().expect("valid fingerprints");
+    }
+
+    #[test]
+    fn rejects_truncated_fingerprint_digest() {
+        let mut artifact = sample_artifact();
+        artifact.tls.cert_fingerprints = vec!["sha256:deadbeef".into()];
+        assert_eq!(
+            artifact.validate(),
+            Err(ArtifactValidationError::MalformedCertFingerprint(
+                "sha256:deadbeef".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fingerprint_scheme() {
+        let mut artifact = sample_artifact();
+        artifact.tls.cert_fingerprints = vec!["md5:abcd".into()];
+        assert_eq!(
+            artifact.validate(),
+            Err(ArtifactValidationError::UnknownFingerprintScheme(
+                "md5".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn chain_size_must_agree_with_embedded_chain() {
+        let mut artifact = sample_artifact();
+        let chain = [vec![0u8; 1200], vec![1u8; 900], vec![2u8; 850]];
+        artifact.tls.cert_chain = chain
+            .iter()
+            .map(|der| EncodedBlob::from_bytes(der))
+            .collect();
+        artifact.tls.chain_size = Some(CertChainSize {
+            count: 3,
+            total_bytes: 2950,
+        });
+        assert_eq!(CertChainSize::of(&chain), artifact.tls.chain_size.unwrap());
+        assert_eq!(artifact.validate(), Ok(()));
+
+        artifact.tls.chain_size = Some(CertChainSize {
+            count: 2,
+            total_bytes: 2100,
+        });
+        assert_eq!(
+            artifact.validate(),
+            Err(ArtifactValidationError::ChainSizeMismatch)
+        );
+    }
+
+    #[test]
+    fn rejects_alternate_repeating_primary_algorithm() {
+        let mut artifact = sample_artifact();
+        artifact.commitments.alternates.insert(
+            CommitmentAlgorithm::Blake3,
+            CommitmentDigests {
+                handshake: encoded("handshake"),
+                app_data: encoded("app"),
+            },
+        );
+        assert_eq!(
+            artifact.validate(),
+            Err(ArtifactValidationError::DuplicateCommitmentAlgorithm(
+                CommitmentAlgorithm::Blake3
+            ))
+        );
+    }
+
+    #[test]
+    fn canonical_cbor_is_byte_stable_and_keeps_json_digest() {
+        let mut artifact = sample_artifact();
+        enrich(&mut artifact, "ticket", 7);
+        let canonical = artifact.to_canonical_cbor().expect("canonical cbor");
+        assert_eq!(artifact.to_canonical_cbor().unwrap(), canonical);
+
+        let json = serde_json::to_vec(&artifact).unwrap();
+        let from_json: RedProofArtifact = serde_json::from_slice(&json).unwrap();
+        assert_eq!(from_json.to_canonical_cbor().unwrap(), canonical);
+
+        let plain = serde_cbor::to_vec(&artifact).unwrap();
+        assert_ne!(plain, canonical, "field order differs from key order");
+        for bytes in [&plain, &canonical] {
+            let decoded: RedProofArtifact = serde_cbor::from_slice(bytes).expect("cbor decodes");
+            assert_eq!(decoded.digest(), artifact.digest());
+            assert_eq!(decoded.to_canonical_cbor().unwrap(), canonical);
+        }
+        artifact
+            .verify_provenance()
+            .expect("signed digests still match");
+    }
+
+    #[test]
+    fn claim_id_ignores_capture_time_but_not_the_response() {
+        let artifact = sample_artifact();
+        let mut recaptured = artifact.clone();
+        recaptured.time_utc = artifact.time_utc + chrono::Duration::hours(6);
+        recaptured.meta.tool_version = "0.2.0".into();
+        enrich(&mut recaptured, "ticket", 3);
+        assert_ne!(recaptured.digest(), artifact.digest());
+        assert_eq!(recaptured.claim_id(), artifact.claim_id());
+        assert!(artifact.claim_id().starts_with("sha256:"));
+
+        let commit = |server: &str| {
+            let app_data = format!(r#"{{"headers":[{{"name":"server","value":"{server}"}}]}}"#);
+            EncodedBlob::from_bytes(&CommitmentAlgorithm::Blake3.digest(app_data.as_bytes()))
+        };
+        let mut nginx = artifact.clone();
+        nginx.commitments.app_data = commit("nginx");
+        let mut apache = artifact.clone();
+        apache.commitments.app_data = commit("apache");
+        assert_ne!(nginx.claim_id(), apache.claim_id());
+        assert_ne!(nginx.claim_id(), artifact.claim_id());
+    }
+
+    fn witnessed_artifact() -> RedProofArtifact {
+        let mut artifact = sample_artifact();
+        let algorithm = artifact.commitments.algorithm;
+        artifact.commitments.handshake = EncodedBlob::from_bytes(&algorithm.digest(b"handshake"));
+        artifact.commitments.app_data = EncodedBlob::from_bytes(&algorithm.digest(b"app"));
+        artifact.commitments.witness = Some(CommitmentWitness {
+            handshake: encoded("handshake"),
+            app_data: encoded("app"),
+            header_nonces: Vec::new(),
+        });
+        artifact
+    }
+
+    #[test]
+    fn validate_strict_accepts_consistent_witness() {
+        let artifact = witnessed_artifact();
+        artifact.validate_strict().expect("consistent witness");
+        artifact.validate_strict().expect("idempotent");
+    }
+
+    #[test]
+    fn validate_strict_rejects_inconsistent_witness() {
+        let mut artifact = witnessed_artifact();
+        artifact.commitments.witness.as_mut().unwrap().app_data = encoded("tampered");
+        artifact.validate().expect("structurally valid");
+        assert_eq!(
+            artifact.validate_strict(),
+            Err(ArtifactValidationError::DigestMismatch("app-data"))
+        );
+    }
+
+    #[test]
+    fn validate_strict_names_the_algorithm_the_witness_matches() {
+        let mut artifact = witnessed_artifact();
+        let claimed = artifact.commitments.algorithm;
+        let actual = CommitmentAlgorithm::registered()
+            .into_iter()
+            .find(|algorithm| *algorithm != claimed)
+            .unwrap();
+        artifact.commitments.handshake = EncodedBlob::from_bytes(&actual.digest(b"handshake"));
+        artifact.commitments.app_data = EncodedBlob::from_bytes(&actual.digest(b"app"));
+        assert_eq!(
+            artifact.validate_strict(),
+            Err(ArtifactValidationError::AlgorithmMismatch {
+                claimed,
+                witness: actual,
+            })
+        );
+    }
+
+    #[test]
+    fn attach_witness_checks_commitments() {
+        let mut witnessless = witnessed_artifact();
+        let witness = witnessless.commitments.witness.take().unwrap();
+
+        let attached = witnessless
+            .clone()
+            .attach_witness(witness.clone())
+            .expect("matching witness");
+        assert_eq!(attached.commitments.witness, Some(witness));
+
+        let wrong = CommitmentWitness {
+            handshake: encoded("other"),
+            app_data: encoded("app"),
+            header_nonces: Vec::new(),
+        };
+        assert_eq!(
+            witnessless.attach_witness(wrong),
+            Err(ArtifactValidationError::DigestMismatch("handshake"))
+        );
+    }
+
+    #[test]
+    fn swapped_statement_breaks_the_binding() {
+        let mut artifact = witnessed_artifact();
+        artifact.bind_statement().unwrap();
+        artifact.validate().expect("bound statement");
+        let mut url_safe = artifact.clone();
+        url_safe.reencode_blobs(BlobEncoding::UrlSafeNoPad).unwrap();
+        url_safe.validate().expect("alphabet does not matter");
+
+        let mut swapped = artifact.clone();
+        swapped.statement = Statement::HeaderPresent {
+            target: "Strict-Transport-Security".into(),
+        };
+        assert_eq!(
+            swapped.validate(),
+            Err(ArtifactValidationError::StatementMismatch)
+        );
+
+        let mut moved = artifact;
+        moved.commitments.app_data = EncodedBlob::from_bytes(b"another capture");
+        assert_eq!(
+            moved.validate(),
+            Err(ArtifactValidationError::StatementMismatch)
+        );
+    }
+
+    #[test]
+    fn detached_witness_only_reattaches_itself() {
+        let mut artifact = witnessed_artifact();
+        let witness = artifact.detach_witness().unwrap().expect("witness");
+        assert_eq!(artifact.commitments.witness, None);
+        assert_eq!(
+            artifact.commitments.witness_digest,
+            Some(witness.digest().unwrap())
+        );
+
+        let mut other = witness.clone();
+        other
+            .handshake
+            .reencode(BlobEncoding::UrlSafeNoPad)
+            .unwrap();
+        other.app_data.reencode(BlobEncoding::UrlSafeNoPad).unwrap();
+        assert_eq!(other.digest(), witness.digest());
+        artifact
+            .clone()
+            .attach_witness(other)
+            .expect("same witness");
+
+        let swapped = CommitmentWitness {
+            handshake: witness.handshake.clone(),
+            app_data: encoded("other app"),
+            header_nonces: Vec::new(),
+        };
+        assert_eq!(
+            artifact.clone().attach_witness(swapped.clone()),
+            Err(ArtifactValidationError::WitnessDigestMismatch)
+        );
+        artifact.commitments.witness = Some(swapped);
+        assert_eq!(
+            artifact.validate(),
+            Err(ArtifactValidationError::WitnessDigestMismatch)
+        );
+    }
+
+    #[test]
+    fn plaintext_context_needs_no_fingerprint() {
+        let mut artifact = sample_artifact();
+        artifact.tls.cert_fingerprints.clear();
+        assert_eq!(
+            artifact.validate(),
+            Err(ArtifactValidationError::MissingCertFingerprint)
+        );
+        artifact.tls.version = TlsProofContext::PLAINTEXT.into();
+        artifact.tls.cipher = TlsProofContext::PLAINTEXT.into();
+        assert!(artifact.tls.is_plaintext());
+        artifact.validate().expect("plaintext capture");
+    }
+
+    #[test]
+    fn rejects_expiry_before_capture() {
+        let mut artifact = sample_artifact();
+        artifact.expires_at = Some(artifact.time_utc - chrono::Duration::seconds(1));
+        assert_eq!(
+            artifact.validate(),
+            Err(ArtifactValidationError::ExpiresBeforeCapture)
+        );
+    }
+
+    #[test]
+    fn provenance_chain_verifies() {
+        let mut artifact = sample_artifact();
+        artifact.verify_provenance().expect("empty chain");
+        enrich(&mut artifact, "triaged", 1);
+        enrich(&mut artifact, "reviewed", 2);
+        assert_eq!(artifact.provenance().len(), 2);
+        artifact.verify_provenance().expect("valid chain");
+
+        let json = serde_json::to_string(&artifact).unwrap();
+        let parsed: RedProofArtifact = serde_json::from_str(&json).unwrap();
+        parsed
+            .verify_provenance()
+            .expect("chain survives round trip");
+    }
+
+    #[test]
+    fn provenance_rejects_broken_link() {
+        let mut artifact = sample_artifact();
+        enrich(&mut artifact, "triaged", 1);
+        let key_pair = ring::signature::Ed25519KeyPair::from_seed_unchecked(&[2; 32]).unwrap();
+        artifact.sign_enrichment("sha256:00".into(), "reviewed", &key_pair, Utc::now());
+        assert_eq!(
+            artifact.verify_provenance(),
+            Err(ProvenanceError::BrokenLink(1))
+        );
+    }
+
+    #[test]
+    fn provenance_rejects_later_modification() {
+        let mut artifact = sample_artifact();
+        enrich(&mut artifact, "triaged", 1);
+        artifact.domain = "attacker.example".into();
+        assert_eq!(
+            artifact.verify_provenance(),
+            Err(ProvenanceError::ModifiedAfterLastEntry)
+        );
+
+        let mut artifact = sample_artifact();
+        enrich(&mut artifact, "triaged", 1);
+        artifact.meta.provenance[0].action = "forged".into();
+        assert_eq!(
+            artifact.verify_provenance(),
+            Err(ProvenanceError::BadSignature(0))
+        );
+    }
+
+    #[test]
+    fn blobs_round_trip_through_both_alphabets() {
+        // 0xfb 0xff encodes to `+/8=` in the standard alphabet.
+        let bytes = [0xfb, 0xff, 0x00, 0x3e];
+        let standard = EncodedBlob::from_bytes(&bytes);
+        let url_safe = EncodedBlob::from_bytes_as(&bytes, BlobEncoding::UrlSafeNoPad);
+        assert_eq!(url_safe.0, "base64url:-_8APg");
+        assert_eq!(url_safe.encoding(), BlobEncoding::UrlSafeNoPad);
+        assert_eq!(standard.decode().unwrap(), bytes);
+        assert_eq!(url_safe.decode().unwrap(), bytes);
+
+        let mut artifact = sample_artifact();
+        let claim_id = artifact.claim_id();
+        artifact.reencode_blobs(BlobEncoding::UrlSafeNoPad).unwrap();
+        assert!(artifact.commitments.handshake.0.starts_with("base64url:"));
+        artifact.validate().unwrap();
+        assert_eq!(artifact.claim_id(), claim_id);
+    }
+
+    #[test]
+    fn rejects_cross_alphabet_blobs() {
+        let url_safe_data = EncodedBlob("-_8APg".into());
+        assert!(url_safe_data.decode().is_err());
+        let standard_data = EncodedBlob("base64url:+/8APg==".into());
+        assert!(standard_data.decode().is_err());
+
+        let mut artifact = sample_artifact();
+        artifact.commitments.handshake = url_safe_data;
+        assert_eq!(
+            artifact.validate(),
+            Err(ArtifactValidationError::InvalidBase64(
+                "handshake commitment".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn non_canonical_blobs_fail_strict_decoding_and_canonicalize_leniently() {
+        // `hi` is `aGk=`; `aGk` drops the padding and `aGl=` sets trailing bits.
+        for sloppy in ["aGk", "aGl="] {
+            let blob = EncodedBlob(sloppy.into());
+            assert!(blob.decode().is_err());
+            assert_eq!(blob.decode_lenient().unwrap(), b"hi");
+        }
+        let url_safe = EncodedBlob("base64url:aGk=".into());
+        assert_eq!(url_safe.decode_lenient().unwrap(), b"hi");
+
+        let mut artifact = sample_artifact();
+        artifact.commitments.handshake = EncodedBlob("aGk".into());
+        assert_eq!(
+            artifact.validate(),
+            Err(ArtifactValidationError::NonCanonicalBase64(
+                "handshake commitment".into()
+            ))
+        );
+        assert_eq!(artifact.canonicalize_base64(), 1);
+        assert_eq!(artifact.commitments.handshake.0, "aGk=");
+        artifact.validate().unwrap();
+        assert_eq!(artifact.canonicalize_base64(), 0);
+    }
+
     #[test]
     fn schema_generation() {
         let schema = schemars::schema_for!(RedProofArtifact);
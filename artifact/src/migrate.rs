@@ -0,0 +1,165 @@
+//! Schema upgrades for artifacts written by older releases. These only touch
+//! fields outside the commitments; re-encoding the committed transcripts is
+//! the prover's job (`redproof-prover artifact fix`).
+
+use std::cmp::Ordering;
+
+use redproof_statements::version;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::{CaptureAnnotations, CertChainSize, RedProofArtifact, RESERVED_ANNOTATION_KEYS};
+
+/// Artifact spec version written by this release.
+pub const ARTIFACT_VERSION: &str = "1.0";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("unsupported artifact version '{0}' (this release reads 1.x)")]
+pub struct UnsupportedArtifactVersion(pub String);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("artifact requires newer verifier: its statement needs {required}, this is {running}")]
+pub struct RequiresNewerVerifier {
+    pub required: String,
+    pub running: String,
+}
+
+/// Refuse an artifact stamped with a `meta.min_verifier_version` above the
+/// `running` release. A requirement that is not a plain release number is
+/// refused too, since it cannot be shown to be met.
+pub fn check_verifier_version(required: &str, running: &str) -> Result<(), RequiresNewerVerifier> {
+    match version::compare(required, running) {
+        Some(Ordering::Less | Ordering::Equal) => Ok(()),
+        _ => Err(RequiresNewerVerifier {
+            required: required.to_string(),
+            running: running.to_string(),
+        }),
+    }
+}
+
+impl RedProofArtifact {
+    /// Bring schema-level fields up to [`ARTIFACT_VERSION`], returning one
+    /// line per change. Idempotent: a current artifact yields no changes.
+    pub fn migrate(&mut self) -> Result<Vec<String>, UnsupportedArtifactVersion> {
+        let mut changes = Vec::new();
+        let version = self.version.trim();
+        let major = version
+            .strip_prefix(['v', 'V'])
+            .unwrap_or(version)
+            .split('.')
+            .next()
+            .unwrap_or_default();
+        if major != "1" {
+            return Err(UnsupportedArtifactVersion(self.version.clone()));
+        }
+        if self.version != ARTIFACT_VERSION {
+            changes.push(format!("version: {} -> {ARTIFACT_VERSION}", self.version));
+            self.version = ARTIFACT_VERSION.to_string();
+        }
+        if self.tls.chain_size.is_none() && !self.tls.cert_chain.is_empty() {
+            let chain = self
+                .tls
+                .cert_chain
+                .iter()
+                .map(|cert| cert.decode())
+                .collect::<Result<Vec<_>, _>>();
+            if let Ok(chain) = chain {
+                changes.push("tls.chain_size: derived from the embedded chain".into());
+                self.tls.chain_size = Some(CertChainSize::of(&chain));
+            }
+        }
+        changes.extend(migrate_annotations(&mut self.meta.annotations));
+        Ok(changes)
+    }
+}
+
+/// Early releases wrote typed annotations as strings (`"status_code":
+/// "200"`), which [`CaptureAnnotations::from_map`] keeps in `extra`. Lift
+/// those whose text parses into the typed slot.
+fn migrate_annotations(annotations: &mut CaptureAnnotations) -> Vec<String> {
+    let mut lifted = Map::new();
+    let mut changes = Vec::new();
+    for key in RESERVED_ANNOTATION_KEYS {
+        let Some(Value::String(text)) = annotations.extra.get(*key) else {
+            continue;
+        };
+        let Ok(parsed) = serde_json::from_str::<Value>(text) else {
+            continue;
+        };
+        let single = Map::from_iter([(key.to_string(), parsed.clone())]);
+        if CaptureAnnotations::from_map(single).extra.is_empty() {
+            lifted.insert(key.to_string(), parsed);
+            changes.push(format!("annotations.{key}: converted legacy string value"));
+        }
+    }
+    if lifted.is_empty() {
+        return changes;
+    }
+    let Ok(Value::Object(mut map)) = serde_json::to_value(&*annotations) else {
+        unreachable!("annotations serialize to a JSON object");
+    };
+    map.extend(lifted);
+    *annotations = CaptureAnnotations::from_map(map);
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::tests::sample_artifact;
+
+    #[test]
+    fn upgrades_legacy_fields_once() {
+        let mut artifact = sample_artifact();
+        artifact.version = "1".into();
+        artifact.meta.annotations = CaptureAnnotations::from_map(
+            json!({"status_code": "200", "body_truncated": "false", "request_method": "GET"})
+                .as_object()
+                .unwrap()
+                .clone(),
+        );
+        assert_eq!(artifact.meta.annotations.status_code, None);
+
+        let changes = artifact.migrate().unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                "version: 1 -> 1.0",
+                "annotations.status_code: converted legacy string value",
+                "annotations.body_truncated: converted legacy string value",
+            ]
+        );
+        assert_eq!(artifact.meta.annotations.status_code, Some(200));
+        assert_eq!(artifact.meta.annotations.body_truncated, Some(false));
+        assert_eq!(
+            artifact.meta.annotations.request_method.as_deref(),
+            Some("GET")
+        );
+        assert!(artifact.meta.annotations.extra.is_empty());
+        assert_eq!(artifact.migrate().unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn refuses_other_major_versions() {
+        let mut artifact = sample_artifact();
+        artifact.version = "2.0".into();
+        assert_eq!(
+            artifact.migrate(),
+            Err(UnsupportedArtifactVersion("2.0".into()))
+        );
+    }
+
+    #[test]
+    fn verifier_version_must_reach_the_requirement() {
+        assert_eq!(check_verifier_version("0.0.1", "0.0.1"), Ok(()));
+        assert_eq!(check_verifier_version("0.1", "0.2.0"), Ok(()));
+        let err = check_verifier_version("0.3.0", "0.2.9").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "artifact requires newer verifier: its statement needs 0.3.0, this is 0.2.9"
+        );
+        assert!(check_verifier_version("next", "0.2.9").is_err());
+    }
+}
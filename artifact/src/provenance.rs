@@ -0,0 +1,121 @@
+//! Provenance chain for artifacts enriched after capture (witness attached,
+//! annotations added). Each enricher signs the digest of the version it
+//! started from and of the version it produced. Digests leave out the chain
+//! itself, so appending an entry does not change them.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{EncodedBlob, RedProofArtifact};
+
+/// Domain separator for provenance signatures.
+const SIGNING_CONTEXT: &str = "redproof-provenance-v1";
+
+/// One enrichment step, signed with Ed25519.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct ProvenanceEntry {
+    /// [`RedProofArtifact::digest`] of the version before this step.
+    pub prior_digest: String,
+    /// Digest of the version this step produced.
+    pub result_digest: String,
+    /// Short description of the change, e.g. `attach-witness`.
+    pub action: String,
+    pub signed_at: DateTime<Utc>,
+    /// Raw Ed25519 public key of the enricher.
+    pub public_key: EncodedBlob,
+    pub signature: EncodedBlob,
+}
+
+impl ProvenanceEntry {
+    fn message(&self) -> Vec<u8> {
+        format!(
+            "{SIGNING_CONTEXT}\n{}\n{}\n{}\n{}",
+            self.prior_digest,
+            self.result_digest,
+            self.action,
+            self.signed_at.to_rfc3339_opts(SecondsFormat::Micros, true)
+        )
+        .into_bytes()
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ProvenanceError {
+    #[error("provenance entry {0} has an invalid signature")]
+    BadSignature(usize),
+    #[error("provenance entry {0} does not start from the previous entry's result")]
+    BrokenLink(usize),
+    #[error("artifact was modified after the last provenance entry")]
+    ModifiedAfterLastEntry,
+    #[error("provenance entry {0} is not valid base64")]
+    Malformed(usize),
+}
+
+impl RedProofArtifact {
+    /// `sha256:<hex>` over the artifact's JSON encoding without
+    /// `meta.provenance`.
+    pub fn digest(&self) -> String {
+        let mut content = self.clone();
+        content.meta.provenance.clear();
+        let bytes = serde_json::to_vec(&content).expect("artifact serializes");
+        format!("sha256:{:x}", Sha256::digest(bytes))
+    }
+
+    pub fn provenance(&self) -> Vec<ProvenanceEntry> {
+        self.meta.provenance.clone()
+    }
+
+    /// Append a signed entry recording that `self` was derived from the
+    /// version whose digest is `prior_digest`. Call after making the change.
+    pub fn sign_enrichment(
+        &mut self,
+        prior_digest: String,
+        action: &str,
+        key_pair: &Ed25519KeyPair,
+        signed_at: DateTime<Utc>,
+    ) {
+        let mut entry = ProvenanceEntry {
+            prior_digest,
+            result_digest: self.digest(),
+            action: action.to_string(),
+            signed_at,
+            public_key: EncodedBlob::from_bytes(key_pair.public_key().as_ref()),
+            signature: EncodedBlob(String::new()),
+        };
+        entry.signature = EncodedBlob::from_bytes(key_pair.sign(&entry.message()).as_ref());
+        self.meta.provenance.push(entry);
+    }
+
+    /// Check every signature, that each entry starts where the previous one
+    /// ended, and that nothing changed after the last entry was signed.
+    pub fn verify_provenance(&self) -> Result<(), ProvenanceError> {
+        let chain = &self.meta.provenance;
+        for (idx, entry) in chain.iter().enumerate() {
+            let public_key = entry
+                .public_key
+                .decode()
+                .map_err(|_| ProvenanceError::Malformed(idx))?;
+            let signature = entry
+                .signature
+                .decode()
+                .map_err(|_| ProvenanceError::Malformed(idx))?;
+            UnparsedPublicKey::new(&ED25519, public_key)
+                .verify(&entry.message(), &signature)
+                .map_err(|_| ProvenanceError::BadSignature(idx))?;
+            if idx > 0 && entry.prior_digest != chain[idx - 1].result_digest {
+                return Err(ProvenanceError::BrokenLink(idx));
+            }
+        }
+        if chain
+            .last()
+            .is_some_and(|last| last.result_digest != self.digest())
+        {
+            return Err(ProvenanceError::ModifiedAfterLastEntry);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+//! Subject alternative names (RFC 5280 §4.2.1.6). Matching them against a
+//! host lives in [`redproof_statements::san`], which statements evaluate with.
+
+use std::net::IpAddr;
+
+pub use redproof_statements::san::{covering_name, name_covers};
+
+use crate::spki::{extension_value, read_any, read_element, MalformedCertificate, SEQUENCE};
+
+/// 2.5.29.17
+const SAN_OID: &[u8] = &[0x55, 0x1d, 0x11];
+/// `[2] IMPLICIT IA5String`
+const DNS_NAME: u8 = 0x82;
+/// `[7] IMPLICIT OCTET STRING`
+const IP_ADDRESS: u8 = 0x87;
+
+/// DNS names and IP addresses the DER certificate's SubjectAltName lists, in
+/// order; other name forms are skipped. Empty without the extension.
+pub fn subject_alt_names(cert_der: &[u8]) -> Result<Vec<String>, MalformedCertificate> {
+    let Some(value) = extension_value(cert_der, SAN_OID)? else {
+        return Ok(Vec::new());
+    };
+    let (names, _) = read_element(value, SEQUENCE).ok_or(MalformedCertificate)?;
+    let mut rest = names.content;
+    let mut found = Vec::new();
+    while !rest.is_empty() {
+        let (name, next) = read_any(rest).ok_or(MalformedCertificate)?;
+        rest = next;
+        match name.whole[0] {
+            DNS_NAME => found.push(
+                std::str::from_utf8(name.content)
+                    .map_err(|_| MalformedCertificate)?
+                    .to_ascii_lowercase(),
+            ),
+            IP_ADDRESS => found.push(match name.content.len() {
+                4 => IpAddr::from(<[u8; 4]>::try_from(name.content).unwrap()).to_string(),
+                16 => IpAddr::from(<[u8; 16]>::try_from(name.content).unwrap()).to_string(),
+                _ => return Err(MalformedCertificate),
+            }),
+            _ => {}
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEAF_DER: &[u8] = include_bytes!("../testdata/leaf.der");
+    const WILDCARD_DER: &[u8] = include_bytes!("../testdata/leaf-wildcard.der");
+
+    #[test]
+    fn reads_dns_and_ip_names() {
+        assert_eq!(
+            subject_alt_names(LEAF_DER).unwrap(),
+            vec!["example.test".to_string()]
+        );
+        assert_eq!(
+            subject_alt_names(WILDCARD_DER).unwrap(),
+            vec!["*.example.test", "example.test", "192.0.2.1"]
+        );
+        assert_eq!(
+            subject_alt_names(b"not a certificate"),
+            Err(MalformedCertificate)
+        );
+    }
+
+    #[test]
+    fn matches_exact_wildcard_and_uncovered_hosts() {
+        let names = subject_alt_names(WILDCARD_DER).unwrap();
+        assert_eq!(covering_name(&names, "example.test"), Some("example.test"));
+        assert_eq!(covering_name(&names, "EXAMPLE.test."), Some("example.test"));
+        assert_eq!(covering_name(&names, "192.0.2.1"), Some("192.0.2.1"));
+        assert_eq!(
+            covering_name(&names, "api.example.test"),
+            Some("*.example.test")
+        );
+        assert_eq!(covering_name(&names, "a.b.example.test"), None);
+        assert_eq!(covering_name(&names, "example.org"), None);
+        assert_eq!(covering_name(&names, "192.0.2.2"), None);
+    }
+
+    #[test]
+    fn wildcards_follow_rfc_6125() {
+        assert!(!name_covers("*.test", "example.test"));
+        assert!(!name_covers("*.example.test", ".example.test"));
+        assert!(!name_covers("a*.example.test", "ab.example.test"));
+        assert!(!name_covers("*.*.test", "a.b.test"));
+        assert!(!name_covers("*.0.2.1", "192.0.2.1"));
+    }
+}
@@ -0,0 +1,56 @@
+//! The published JSON Schema for `.red` artifacts, plus the field schemas
+//! that tighten what the derive emits to match [`RedProofArtifact::validate`].
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use serde_json::{json, Value};
+
+use crate::{RedProofArtifact, TlsProofContext};
+
+/// The schema `schema_dump` writes, as JSON.
+pub fn artifact_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(RedProofArtifact)).expect("schema serializes")
+}
+
+/// `tls`: at least one certificate fingerprint unless the capture fell back
+/// to plaintext.
+pub(crate) fn tls_context(gen: &mut SchemaGenerator) -> Schema {
+    let context = gen.subschema_for::<TlsProofContext>();
+    from_json(json!({
+        "allOf": [context],
+        "if": { "properties": { "version": { "const": TlsProofContext::PLAINTEXT } } },
+        "else": { "properties": { "cert_fingerprints": { "minItems": 1 } } }
+    }))
+}
+
+/// `cert_fingerprints`: `sha256:<64 lowercase hex>` or `domain-only:<host>`.
+pub(crate) fn cert_fingerprints(_: &mut SchemaGenerator) -> Schema {
+    from_json(json!({
+        "type": "array",
+        "items": {
+            "type": "string",
+            "pattern": "^(sha256:[0-9a-f]{64}|domain-only:\\S+)$"
+        }
+    }))
+}
+
+fn from_json(value: Value) -> Schema {
+    serde_json::from_value(value).expect("valid schema literal")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_in_schema_is_current() {
+        let checked_in: Value =
+            serde_json::from_str(include_str!("../schema/redproof.schema.json"))
+                .expect("checked-in schema is UTF-8 JSON");
+        assert!(
+            checked_in == artifact_schema(),
+            "artifact/schema/redproof.schema.json is stale; regenerate it with \
+             `cargo run -p redproof-artifact --bin schema_dump`"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Signed certificate timestamps (RFC 6962) embedded in a certificate's
+//! `SignedCertificateTimestampList` extension, the usual way servers prove
+//! Certificate Transparency logging.
+
+use crate::spki::{extension_value, read_element, MalformedCertificate, OCTET_STRING};
+
+/// 1.3.6.1.4.1.11129.2.4.2
+const SCT_LIST_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x04, 0x02];
+
+/// Number of SCTs embedded in the DER certificate; zero when it carries no
+/// SCT list.
+pub fn embedded_sct_count(cert_der: &[u8]) -> Result<usize, MalformedCertificate> {
+    let Some(value) = extension_value(cert_der, SCT_LIST_OID)? else {
+        return Ok(0);
+    };
+    let (list, _) = read_element(value, OCTET_STRING).ok_or(MalformedCertificate)?;
+    count_scts(list.content).ok_or(MalformedCertificate)
+}
+
+/// Entries in a `SignedCertificateTimestampList`: a u16 total length, then
+/// u16-length-prefixed SCTs.
+fn count_scts(list: &[u8]) -> Option<usize> {
+    let (total, mut rest) = split_u16(list)?;
+    if rest.len() != total {
+        return None;
+    }
+    let mut count = 0;
+    while !rest.is_empty() {
+        let (len, body) = split_u16(rest)?;
+        rest = body.get(len..)?;
+        count += 1;
+    }
+    Some(count)
+}
+
+fn split_u16(input: &[u8]) -> Option<(usize, &[u8])> {
+    let (len, rest) = input.split_first_chunk::<2>()?;
+    Some((u16::from_be_bytes(*len) as usize, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEAF_DER: &[u8] = include_bytes!("../testdata/leaf.der");
+    const LEAF_SCT_DER: &[u8] = include_bytes!("../testdata/leaf-sct.der");
+
+    #[test]
+    fn counts_embedded_scts() {
+        assert_eq!(embedded_sct_count(LEAF_SCT_DER), Ok(2));
+        assert_eq!(embedded_sct_count(LEAF_DER), Ok(0));
+        assert_eq!(
+            embedded_sct_count(b"not a certificate"),
+            Err(MalformedCertificate)
+        );
+    }
+}
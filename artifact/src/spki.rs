@@ -0,0 +1,145 @@
+//! SPKI pins (`pin-sha256`, RFC 7469): base64 SHA-256 over a certificate's
+//! DER-encoded SubjectPublicKeyInfo.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+pub(crate) const SEQUENCE: u8 = 0x30;
+pub(crate) const OCTET_STRING: u8 = 0x04;
+/// `[0] EXPLICIT Version`, absent in v1 certificates.
+const VERSION_TAG: u8 = 0xa0;
+/// `[3] EXPLICIT Extensions` in a v3 tbsCertificate.
+const EXTENSIONS_TAG: u8 = 0xa3;
+const OID: u8 = 0x06;
+const BOOLEAN: u8 = 0x01;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("certificate DER is malformed")]
+pub struct MalformedCertificate;
+
+/// `pin-sha256` of a DER certificate.
+pub fn spki_pin(cert_der: &[u8]) -> Result<String, MalformedCertificate> {
+    let spki = subject_public_key_info(cert_der).ok_or(MalformedCertificate)?;
+    Ok(STANDARD.encode(Sha256::digest(spki)))
+}
+
+/// The full SPKI element of `cert_der`.
+fn subject_public_key_info(cert_der: &[u8]) -> Option<&[u8]> {
+    let (spki, _) = read_element(tbs_from_spki(cert_der)?, SEQUENCE)?;
+    Some(spki.whole)
+}
+
+/// Walk `Certificate -> tbsCertificate` to the SPKI element and return the
+/// fields from there on: it follows serialNumber, signature, issuer,
+/// validity and subject.
+pub(crate) fn tbs_from_spki(cert_der: &[u8]) -> Option<&[u8]> {
+    let (cert, _) = read_element(cert_der, SEQUENCE)?;
+    let (tbs, _) = read_element(cert.content, SEQUENCE)?;
+    let mut rest = tbs.content;
+    if rest.first() == Some(&VERSION_TAG) {
+        rest = read_any(rest)?.1;
+    }
+    for _ in 0..5 {
+        rest = read_any(rest)?.1;
+    }
+    Some(rest)
+}
+
+/// The `extnValue` contents of the extension with DER-encoded `oid`;
+/// `None` when the certificate does not carry it.
+pub(crate) fn extension_value<'a>(
+    cert_der: &'a [u8],
+    oid: &[u8],
+) -> Result<Option<&'a [u8]>, MalformedCertificate> {
+    let mut rest = tbs_from_spki(cert_der).ok_or(MalformedCertificate)?;
+    while !rest.is_empty() {
+        let (field, next) = read_any(rest).ok_or(MalformedCertificate)?;
+        rest = next;
+        if field.whole[0] != EXTENSIONS_TAG {
+            continue;
+        }
+        let (extensions, _) = read_element(field.content, SEQUENCE).ok_or(MalformedCertificate)?;
+        let mut extensions = extensions.content;
+        while !extensions.is_empty() {
+            let (extension, next) =
+                read_element(extensions, SEQUENCE).ok_or(MalformedCertificate)?;
+            extensions = next;
+            let (id, rest) = read_element(extension.content, OID).ok_or(MalformedCertificate)?;
+            if id.content != oid {
+                continue;
+            }
+            let rest = match read_element(rest, BOOLEAN) {
+                Some((_, after_critical)) => after_critical,
+                None => rest,
+            };
+            let (value, _) = read_element(rest, OCTET_STRING).ok_or(MalformedCertificate)?;
+            return Ok(Some(value.content));
+        }
+    }
+    Ok(None)
+}
+
+pub(crate) struct Element<'a> {
+    pub(crate) whole: &'a [u8],
+    pub(crate) content: &'a [u8],
+}
+
+pub(crate) fn read_element(input: &[u8], tag: u8) -> Option<(Element<'_>, &[u8])> {
+    (input.first() == Some(&tag))
+        .then(|| read_any(input))
+        .flatten()
+}
+
+pub(crate) fn read_any(input: &[u8]) -> Option<(Element<'_>, &[u8])> {
+    let first = *input.get(1)?;
+    let (len, header) = if first < 0x80 {
+        (first as usize, 2)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let bytes = input.get(2..2 + count)?;
+        let len = bytes
+            .iter()
+            .fold(0usize, |acc, byte| (acc << 8) | *byte as usize);
+        (len, 2 + count)
+    };
+    let end = header.checked_add(len)?;
+    let whole = input.get(..end)?;
+    Some((
+        Element {
+            whole,
+            content: &whole[header..],
+        },
+        &input[end..],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEAF_DER: &[u8] = include_bytes!("../testdata/leaf.der");
+
+    #[test]
+    fn computes_pin_for_fixture_certificate() {
+        // openssl x509 -pubkey -noout | openssl pkey -pubin -outform der
+        //   | openssl dgst -sha256 -binary | base64
+        assert_eq!(
+            spki_pin(LEAF_DER).unwrap(),
+            "3brxwiwOeOoOswfHaD84AQrf+aMWL9q3wuuwpVUnBTM="
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_certificate() {
+        assert_eq!(
+            spki_pin(&LEAF_DER[..LEAF_DER.len() / 3]),
+            Err(MalformedCertificate)
+        );
+        assert_eq!(spki_pin(b"not a certificate"), Err(MalformedCertificate));
+    }
+}
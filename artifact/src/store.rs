@@ -0,0 +1,234 @@
+//! Storage for services that mint artifacts now and verify them later.
+//! Artifacts are keyed by their [`digest`](RedProofArtifact::digest), so
+//! storing the same artifact twice keeps one copy.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use crate::RedProofArtifact;
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("'{0}' is not an artifact id")]
+    InvalidId(String),
+    #[error("artifact store I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("stored artifact is not valid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Which artifacts [`ArtifactStore::list`] returns; the default matches all.
+#[derive(Debug, Clone, Default)]
+pub struct StoreFilter {
+    /// Case-insensitive domain match.
+    pub domain: Option<String>,
+    /// Captured at or after this time.
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl StoreFilter {
+    pub fn matches(&self, artifact: &RedProofArtifact) -> bool {
+        self.domain
+            .as_ref()
+            .is_none_or(|domain| artifact.domain.eq_ignore_ascii_case(domain))
+            && self.since.is_none_or(|since| artifact.time_utc >= since)
+    }
+}
+
+pub trait ArtifactStore {
+    /// Store `artifact`, returning its id.
+    fn put(&self, artifact: &RedProofArtifact) -> Result<String, StoreError>;
+
+    fn get(&self, id: &str) -> Result<Option<RedProofArtifact>, StoreError>;
+
+    /// Matching artifacts with their ids, oldest capture first.
+    fn list(&self, filter: &StoreFilter) -> Result<Vec<(String, RedProofArtifact)>, StoreError>;
+}
+
+/// Artifacts held in a map, for tests and short-lived services.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    artifacts: RwLock<BTreeMap<String, RedProofArtifact>>,
+}
+
+impl ArtifactStore for MemoryStore {
+    fn put(&self, artifact: &RedProofArtifact) -> Result<String, StoreError> {
+        let id = artifact.digest();
+        self.artifacts
+            .write()
+            .expect("store lock poisoned")
+            .insert(id.clone(), artifact.clone());
+        Ok(id)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<RedProofArtifact>, StoreError> {
+        Ok(self
+            .artifacts
+            .read()
+            .expect("store lock poisoned")
+            .get(id)
+            .cloned())
+    }
+
+    fn list(&self, filter: &StoreFilter) -> Result<Vec<(String, RedProofArtifact)>, StoreError> {
+        let artifacts = self.artifacts.read().expect("store lock poisoned");
+        Ok(sorted(
+            artifacts
+                .iter()
+                .filter(|(_, artifact)| filter.matches(artifact))
+                .map(|(id, artifact)| (id.clone(), artifact.clone()))
+                .collect(),
+        ))
+    }
+}
+
+/// One JSON `<hex>.red` file per artifact in a directory, named after the
+/// hex part of its id.
+#[derive(Debug, Clone)]
+pub struct FsStore {
+    dir: PathBuf,
+}
+
+impl FsStore {
+    /// Store under `dir`, creating it if needed.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, StoreError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf, StoreError> {
+        let hex = id
+            .strip_prefix("sha256:")
+            .filter(|hex| hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| StoreError::InvalidId(id.to_string()))?;
+        Ok(self.dir.join(format!("{}.red", hex.to_ascii_lowercase())))
+    }
+}
+
+impl ArtifactStore for FsStore {
+    fn put(&self, artifact: &RedProofArtifact) -> Result<String, StoreError> {
+        let id = artifact.digest();
+        let path = self.path(&id)?;
+        // Write then rename so readers never see a partial file.
+        let partial = path.with_extension("red.partial");
+        fs::write(&partial, serde_json::to_vec_pretty(artifact)?)?;
+        fs::rename(&partial, &path)?;
+        Ok(id)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<RedProofArtifact>, StoreError> {
+        match fs::read(self.path(id)?) {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn list(&self, filter: &StoreFilter) -> Result<Vec<(String, RedProofArtifact)>, StoreError> {
+        let mut found = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let Some(id) = stored_id(&path) else {
+                continue;
+            };
+            let artifact: RedProofArtifact = serde_json::from_slice(&fs::read(&path)?)?;
+            if filter.matches(&artifact) {
+                found.push((id, artifact));
+            }
+        }
+        Ok(sorted(found))
+    }
+}
+
+/// The id a `<hex>.red` file in the store was written under.
+fn stored_id(path: &Path) -> Option<String> {
+    if path.extension()? != "red" {
+        return None;
+    }
+    let hex = path.file_stem()?.to_str()?;
+    (hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit())).then(|| format!("sha256:{hex}"))
+}
+
+fn sorted(mut artifacts: Vec<(String, RedProofArtifact)>) -> Vec<(String, RedProofArtifact)> {
+    artifacts.sort_by(|(a_id, a), (b_id, b)| a.time_utc.cmp(&b.time_utc).then(a_id.cmp(b_id)));
+    artifacts
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::tests::sample_artifact;
+
+    fn artifacts() -> [RedProofArtifact; 3] {
+        let now = Utc::now();
+        let mut a = sample_artifact();
+        a.time_utc = now - Duration::hours(2);
+        let mut b = sample_artifact();
+        b.domain = "api.example.com".into();
+        b.time_utc = now - Duration::hours(1);
+        let mut c = sample_artifact();
+        c.time_utc = now;
+        [a, b, c]
+    }
+
+    fn exercise(store: &dyn ArtifactStore) {
+        let [a, b, c] = artifacts();
+        let ids = [&a, &b, &c].map(|artifact| store.put(artifact).unwrap());
+        assert_eq!(store.put(&a).unwrap(), ids[0], "same artifact, same id");
+        assert_eq!(store.get(&ids[1]).unwrap(), Some(b.clone()));
+        assert_eq!(
+            store.get(&format!("sha256:{}", "0".repeat(64))).unwrap(),
+            None
+        );
+
+        let all = store.list(&StoreFilter::default()).unwrap();
+        assert_eq!(all.len(), 3);
+        let by_domain = store
+            .list(&StoreFilter {
+                domain: Some("EXAMPLE.com".into()),
+                ..StoreFilter::default()
+            })
+            .unwrap();
+        assert_eq!(
+            by_domain,
+            vec![(ids[0].clone(), a), (ids[2].clone(), c.clone())],
+            "oldest first, other domains left out"
+        );
+        let recent = store
+            .list(&StoreFilter {
+                domain: Some("example.com".into()),
+                since: Some(c.time_utc),
+            })
+            .unwrap();
+        assert_eq!(recent, vec![(ids[2].clone(), c)]);
+    }
+
+    #[test]
+    fn memory_store_round_trips_and_filters() {
+        exercise(&MemoryStore::default());
+    }
+
+    #[test]
+    fn fs_store_round_trips_and_filters() {
+        let dir = std::env::temp_dir().join(format!("redproof-store-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = FsStore::open(&dir).unwrap();
+        exercise(&store);
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        assert_eq!(store.list(&StoreFilter::default()).unwrap().len(), 3);
+        assert!(matches!(
+            store.get("../../etc/passwd"),
+            Err(StoreError::InvalidId(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
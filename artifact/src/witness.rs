@@ -0,0 +1,47 @@
+//! The record a statement is re-evaluated against from an artifact's
+//! witness. `--recompute-all` and `artifact fix` both build it here, so
+//! they decide every statement alike.
+
+use chrono::{DateTime, Duration, Utc};
+use redproof_statements::record::EvalRecord;
+use redproof_statements::witness::{AppDataWitness, HandshakeWitness};
+use sha2::{Digest, Sha256};
+
+use crate::{embedded_sct_count, RedProofArtifact};
+
+impl RedProofArtifact {
+    /// [`EvalRecord::from_witness`] plus the facts the witness does not
+    /// commit, taken only from what vouches for them: the capture time the
+    /// `clock_skew` annotation implies, and the SCTs in the embedded leaf
+    /// certificate when its fingerprint is the committed one. Timing comes
+    /// from the committed trace alone.
+    pub fn witness_record<'a>(
+        &self,
+        handshake: &'a HandshakeWitness,
+        app: &AppDataWitness,
+        canonical_handshake: &'a [u8],
+        canonical_app_data: &'a [u8],
+    ) -> Result<EvalRecord<'a>, String> {
+        let mut record =
+            EvalRecord::from_witness(handshake, app, canonical_handshake, canonical_app_data)?;
+        record.captured_at = self.capture_time();
+        record.tls.embedded_scts = self.committed_leaf_scts(handshake);
+        Ok(record)
+    }
+
+    /// The prover's clock at capture, as the `clock_skew` annotation places
+    /// it relative to the `Date` header.
+    fn capture_time(&self) -> Option<DateTime<Utc>> {
+        let skew = self.meta.annotations.clock_skew.as_ref()?;
+        Some(skew.server_date - Duration::milliseconds(skew.skew_ms))
+    }
+
+    fn committed_leaf_scts(&self, handshake: &HandshakeWitness) -> Option<usize> {
+        let leaf = self.tls.cert_chain.first()?.decode().ok()?;
+        let fingerprint = format!("sha256:{:x}", Sha256::digest(&leaf));
+        if handshake.cert_fingerprints.first() != Some(&fingerprint) {
+            return None;
+        }
+        embedded_sct_count(&leaf).ok()
+    }
+}
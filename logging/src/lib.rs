@@ -0,0 +1,48 @@
+//! Logging setup shared by the prover and verifier binaries. Diagnostics go
+//! to stderr through `tracing`; results stay on stdout so scripts can keep
+//! parsing them.
+
+use clap::ValueEnum;
+use tracing_subscriber::filter::LevelFilter;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Warnings by default; each `-v` adds a level, each `-q` removes one.
+pub fn level_filter(verbose: u8, quiet: u8) -> LevelFilter {
+    match i16::from(verbose) - i16::from(quiet) {
+        i16::MIN..=-2 => LevelFilter::OFF,
+        -1 => LevelFilter::ERROR,
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+pub fn init(format: LogFormat, verbose: u8, quiet: u8) {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level_filter(verbose, quiet))
+        .with_writer(std::io::stderr);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbosity_flags_shift_the_level() {
+        assert_eq!(level_filter(0, 0), LevelFilter::WARN);
+        assert_eq!(level_filter(2, 0), LevelFilter::DEBUG);
+        assert_eq!(level_filter(9, 0), LevelFilter::TRACE);
+        assert_eq!(level_filter(0, 1), LevelFilter::ERROR);
+        assert_eq!(level_filter(1, 3), LevelFilter::OFF);
+    }
+}
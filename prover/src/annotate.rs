@@ -0,0 +1,301 @@
+//! Deployment-supplied annotations (git commit, CI job, environment) merged
+//! into `meta.annotations` so artifacts trace back to what produced them.
+
+use anyhow::{bail, Result};
+use redproof_artifact::{CaptureAnnotations, ReservedAnnotationKey, RESERVED_ANNOTATION_KEYS};
+use redproof_prover::capture::HttpResponse;
+use serde_json::Value;
+
+/// Environment variables with this prefix become annotations:
+/// `REDPROOF_ANNOTATE_CI_JOB=42` yields `ci_job: 42`.
+pub const ENV_PREFIX: &str = "REDPROOF_ANNOTATE_";
+
+/// Source of custom annotations. Providers are applied in order; a later
+/// provider overrides an earlier one's key.
+pub trait AnnotationProvider {
+    fn annotations(&self) -> Vec<(String, Value)>;
+}
+
+/// Fixed `key=value` pairs, e.g. from `--annotate`.
+pub struct StaticAnnotations(pub Vec<(String, Value)>);
+
+impl AnnotationProvider for StaticAnnotations {
+    fn annotations(&self) -> Vec<(String, Value)> {
+        self.0.clone()
+    }
+}
+
+/// `REDPROOF_ANNOTATE_*` variables, keys lower-cased.
+pub struct EnvAnnotations {
+    vars: Vec<(String, String)>,
+}
+
+impl EnvAnnotations {
+    pub fn from_env() -> Self {
+        Self::from_vars(std::env::vars())
+    }
+
+    pub fn from_vars(vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            vars: vars.into_iter().collect(),
+        }
+    }
+}
+
+impl AnnotationProvider for EnvAnnotations {
+    fn annotations(&self) -> Vec<(String, Value)> {
+        let mut pairs = self
+            .vars
+            .iter()
+            .filter_map(|(name, value)| {
+                let key = name.strip_prefix(ENV_PREFIX)?.to_ascii_lowercase();
+                (!key.is_empty()).then(|| (key, typed_value(value)))
+            })
+            .collect::<Vec<_>>();
+        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        pairs
+    }
+}
+
+/// Parse `key=value` for `--annotate`.
+pub fn parse_annotation(raw: &str) -> Result<(String, Value), String> {
+    let (key, value) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{raw}'"))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("empty annotation key in '{raw}'"));
+    }
+    Ok((key.to_string(), typed_value(value)))
+}
+
+/// Parse `name=pointer` for `--extract`. The pointer is a JSON pointer
+/// (`/build/version`) or the equivalent `$.build.version` path, with
+/// `[n]` for array indices.
+pub fn parse_extract(raw: &str) -> Result<(String, String), String> {
+    let (name, path) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=POINTER, got '{raw}'"))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("empty annotation key in '{raw}'"));
+    }
+    if RESERVED_ANNOTATION_KEYS.contains(&name) {
+        return Err(ReservedAnnotationKey(name.to_string()).to_string());
+    }
+    let path = path.trim();
+    let pointer = match path.strip_prefix('$') {
+        Some(rest) => dotted_to_pointer(rest).ok_or_else(|| format!("invalid path '{path}'"))?,
+        None if path.is_empty() || path.starts_with('/') => path.to_string(),
+        None => return Err(format!("'{path}' is neither a JSON pointer nor a $. path")),
+    };
+    Ok((name.to_string(), pointer))
+}
+
+/// `.a.b[0]` as `/a/b/0`, escaping `~` and `/` in keys.
+fn dotted_to_pointer(mut path: &str) -> Option<String> {
+    let mut pointer = String::new();
+    while !path.is_empty() {
+        let segment;
+        if let Some(rest) = path.strip_prefix('[') {
+            let (index, rest) = rest.split_once(']')?;
+            index.parse::<usize>().ok()?;
+            (segment, path) = (index, rest);
+        } else {
+            let rest = path.strip_prefix('.')?;
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            (segment, path) = rest.split_at(end);
+            if segment.is_empty() {
+                return None;
+            }
+        }
+        pointer.push('/');
+        pointer.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    }
+    Some(pointer)
+}
+
+/// `--extract` values resolved against a captured response body. Anything
+/// that does not resolve is recorded as `null`, with a warning saying why.
+pub struct BodyExtracts(Vec<(String, Value)>);
+
+impl BodyExtracts {
+    pub fn resolve(extracts: &[(String, String)], response: &HttpResponse) -> Self {
+        if extracts.is_empty() {
+            return Self(Vec::new());
+        }
+        let document = if response.body_digest.is_some() {
+            Err("body was streamed into a digest; bytes unavailable".to_string())
+        } else if response.body_truncated {
+            Err("response body truncated".to_string())
+        } else {
+            serde_json::from_slice::<Value>(&response.body)
+                .map_err(|err| format!("body is not JSON: {err}"))
+        };
+        let pairs = extracts
+            .iter()
+            .map(|(name, pointer)| {
+                let value = match &document {
+                    Ok(document) => document.pointer(pointer).cloned().unwrap_or_else(|| {
+                        tracing::warn!(name, pointer, "--extract path not found; recording null");
+                        Value::Null
+                    }),
+                    Err(reason) => {
+                        tracing::warn!(name, reason, "--extract not resolved; recording null");
+                        Value::Null
+                    }
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        Self(pairs)
+    }
+}
+
+impl AnnotationProvider for BodyExtracts {
+    fn annotations(&self) -> Vec<(String, Value)> {
+        self.0.clone()
+    }
+}
+
+/// Numbers, booleans and `null` keep their JSON type; anything else is a
+/// string.
+fn typed_value(raw: &str) -> Value {
+    match serde_json::from_str::<Value>(raw.trim()) {
+        Ok(value @ (Value::Number(_) | Value::Bool(_) | Value::Null)) => value,
+        _ => Value::String(raw.to_string()),
+    }
+}
+
+/// Merge every provider's annotations into `annotations`, rejecting keys
+/// reserved for capture fields.
+pub fn apply(
+    providers: &[&dyn AnnotationProvider],
+    annotations: &mut CaptureAnnotations,
+) -> Result<()> {
+    for provider in providers {
+        for (key, value) in provider.annotations() {
+            if let Err(err) = annotations.insert_custom(&key, value) {
+                bail!("{err}");
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn json_response(body: &str) -> HttpResponse {
+        HttpResponse {
+            http_version: "HTTP/1.1".into(),
+            status_code: 200,
+            reason: "OK".into(),
+            headers: Vec::new(),
+            early_hints: Vec::new(),
+            body: body.as_bytes().to_vec(),
+            body_truncated: false,
+            body_digest: None,
+        }
+    }
+
+    #[test]
+    fn cli_annotations_override_environment() {
+        let env = EnvAnnotations::from_vars([
+            ("REDPROOF_ANNOTATE_CI_JOB".to_string(), "42".to_string()),
+            ("REDPROOF_ANNOTATE_ENV".to_string(), "staging".to_string()),
+            ("HOME".to_string(), "/root".to_string()),
+        ]);
+        let cli = StaticAnnotations(vec![
+            parse_annotation("env=prod").unwrap(),
+            parse_annotation("signed=true").unwrap(),
+        ]);
+        let mut annotations = CaptureAnnotations::default();
+        apply(&[&env, &cli], &mut annotations).expect("annotations");
+
+        assert_eq!(annotations.extra.len(), 3);
+        assert_eq!(annotations.extra["ci_job"], json!(42));
+        assert_eq!(annotations.extra["env"], json!("prod"));
+        assert_eq!(annotations.extra["signed"], json!(true));
+    }
+
+    #[test]
+    fn rejects_reserved_keys() {
+        let cli = StaticAnnotations(vec![parse_annotation("status_code=200").unwrap()]);
+        let mut annotations = CaptureAnnotations::default();
+        let err = apply(&[&cli], &mut annotations).unwrap_err();
+        assert_eq!(err.to_string(), "annotation key 'status_code' is reserved");
+    }
+
+    #[test]
+    fn parse_annotation_requires_key() {
+        assert!(parse_annotation("no-separator").is_err());
+        assert!(parse_annotation("=value").is_err());
+        assert_eq!(
+            parse_annotation("git_commit=0a1b2c").unwrap(),
+            ("git_commit".to_string(), json!("0a1b2c"))
+        );
+    }
+
+    #[test]
+    fn parse_extract_accepts_pointers_and_dollar_paths() {
+        assert_eq!(
+            parse_extract("version=/build/version").unwrap(),
+            ("version".to_string(), "/build/version".to_string())
+        );
+        assert_eq!(
+            parse_extract("region=$.deploys[1].region").unwrap(),
+            ("region".to_string(), "/deploys/1/region".to_string())
+        );
+        assert_eq!(
+            parse_extract("odd=$.a/b.c~d").unwrap().1,
+            "/a~1b/c~0d".to_string()
+        );
+        assert!(parse_extract("version").is_err());
+        assert_eq!(
+            parse_extract("status_code=/status"),
+            Err("annotation key 'status_code' is reserved".to_string())
+        );
+        assert!(parse_extract("version=build.version").is_err());
+        assert!(parse_extract("version=$..build").is_err());
+        assert!(parse_extract("version=$.deploys[x]").is_err());
+    }
+
+    #[test]
+    fn extracts_nested_fields_into_annotations() {
+        let response = json_response(
+            r#"{"build": {"version": "1.4.2", "number": 812}, "deploys": [{"region": "eu"}]}"#,
+        );
+        let extracts = [
+            parse_extract("build_version=$.build.version").unwrap(),
+            parse_extract("build_number=/build/number").unwrap(),
+            parse_extract("region=$.deploys[0].region").unwrap(),
+            parse_extract("missing=$.build.commit").unwrap(),
+        ];
+        let mut annotations = CaptureAnnotations::default();
+        apply(
+            &[&BodyExtracts::resolve(&extracts, &response)],
+            &mut annotations,
+        )
+        .expect("annotations");
+
+        assert_eq!(annotations.extra["build_version"], json!("1.4.2"));
+        assert_eq!(annotations.extra["build_number"], json!(812));
+        assert_eq!(annotations.extra["region"], json!("eu"));
+        assert_eq!(annotations.extra["missing"], Value::Null);
+    }
+
+    #[test]
+    fn non_json_bodies_extract_null() {
+        let extracts = [parse_extract("version=/version").unwrap()];
+        let mut annotations = CaptureAnnotations::default();
+        apply(
+            &[&BodyExtracts::resolve(&extracts, &json_response("<html>"))],
+            &mut annotations,
+        )
+        .expect("annotations");
+        assert_eq!(annotations.extra["version"], Value::Null);
+    }
+}
@@ -0,0 +1,254 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use redproof_artifact::{ArtifactFormat, BlobEncoding, CommitmentAlgorithm, UnknownArtifactFormat};
+use rustls::SupportedCipherSuite;
+
+use redproof_prover::capture::{cipher_suite, BrowserProfile};
+use redproof_prover::evaluate::EvalMode;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum MethodArg {
+    Get,
+    Head,
+    /// CORS preflight when combined with `--origin`.
+    Options,
+    /// Sent as-is; the server may act on it.
+    Delete,
+}
+
+impl From<MethodArg> for http::Method {
+    fn from(value: MethodArg) -> Self {
+        match value {
+            MethodArg::Get => http::Method::GET,
+            MethodArg::Head => http::Method::HEAD,
+            MethodArg::Options => http::Method::OPTIONS,
+            MethodArg::Delete => http::Method::DELETE,
+        }
+    }
+}
+
+impl TryFrom<&http::Method> for MethodArg {
+    type Error = String;
+
+    fn try_from(value: &http::Method) -> Result<Self, Self::Error> {
+        match *value {
+            http::Method::GET => Ok(MethodArg::Get),
+            http::Method::HEAD => Ok(MethodArg::Head),
+            http::Method::OPTIONS => Ok(MethodArg::Options),
+            http::Method::DELETE => Ok(MethodArg::Delete),
+            ref other => Err(format!("unsupported method {}", other)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum EvalModeArg {
+    /// Evaluate every composite member and report each leaf.
+    All,
+    /// Stop at the first member that decides a composite.
+    #[default]
+    ShortCircuit,
+}
+
+impl From<EvalModeArg> for EvalMode {
+    fn from(value: EvalModeArg) -> Self {
+        match value {
+            EvalModeArg::All => EvalMode::All,
+            EvalModeArg::ShortCircuit => EvalMode::ShortCircuit,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum BrowserProfileArg {
+    Chrome,
+    Firefox,
+}
+
+impl From<BrowserProfileArg> for BrowserProfile {
+    fn from(value: BrowserProfileArg) -> Self {
+        match value {
+            BrowserProfileArg::Chrome => BrowserProfile::Chrome,
+            BrowserProfileArg::Firefox => BrowserProfile::Firefox,
+        }
+    }
+}
+
+/// `--format`: `auto` (infer from the `--out` extension) or any
+/// [`ArtifactFormat`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Auto,
+    Explicit(ArtifactFormat),
+}
+
+impl OutputFormat {
+    /// The format to write `path` in: `.cbor` and `.json` pick theirs, and
+    /// anything else (`.red`, no extension, `-` for stdout) is JSON.
+    pub fn for_path(self, path: &Path) -> ArtifactFormat {
+        match self {
+            OutputFormat::Explicit(format) => format,
+            OutputFormat::Auto => path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .and_then(|extension| extension.parse().ok())
+                .unwrap_or(ArtifactFormat::Json),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = UnknownArtifactFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("auto") {
+            Ok(OutputFormat::Auto)
+        } else {
+            s.parse().map(OutputFormat::Explicit)
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Auto => f.write_str("auto"),
+            OutputFormat::Explicit(format) => format.fmt(f),
+        }
+    }
+}
+
+/// Base64 alphabet for the artifact's blobs.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum BlobEncodingArg {
+    /// Padded standard base64.
+    #[default]
+    Standard,
+    /// Unpadded URL-safe base64, each blob prefixed `base64url:`.
+    UrlSafe,
+}
+
+impl From<BlobEncodingArg> for BlobEncoding {
+    fn from(value: BlobEncodingArg) -> Self {
+        match value {
+            BlobEncodingArg::Standard => BlobEncoding::Standard,
+            BlobEncodingArg::UrlSafe => BlobEncoding::UrlSafeNoPad,
+        }
+    }
+}
+
+/// Serialization of the `--dry-run` preview; the artifact format is separate.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PreviewFormat {
+    /// Pretty-printed JSON.
+    #[default]
+    Json,
+    /// Single-line JSON.
+    JsonCompact,
+    Yaml,
+}
+
+/// Parse a `--hash-alg` entry against the registered commitment algorithms.
+pub fn parse_hash_alg(value: &str) -> Result<CommitmentAlgorithm, String> {
+    value.parse().map_err(|err| {
+        let registered = CommitmentAlgorithm::registered()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        format!("{err} (registered: {})", registered.join(", "))
+    })
+}
+
+/// Parse a `--tls-cipher` entry into a suite the crypto provider supports.
+pub fn parse_tls_cipher(value: &str) -> Result<SupportedCipherSuite, String> {
+    cipher_suite(value).ok_or_else(|| format!("unsupported cipher suite '{value}'"))
+}
+
+/// Parse a validity window such as `90m`, `12h`, `30d` or `2w`.
+pub fn parse_validity(value: &str) -> Result<chrono::Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in '{value}' (use s, m, h, d or w)"))?;
+    let (amount, unit) = value.split_at(split);
+    let amount = amount
+        .parse::<i64>()
+        .map_err(|_| format!("invalid amount in '{value}'"))?;
+    let duration = match unit {
+        "s" => chrono::Duration::try_seconds(amount),
+        "m" => chrono::Duration::try_minutes(amount),
+        "h" => chrono::Duration::try_hours(amount),
+        "d" => chrono::Duration::try_days(amount),
+        "w" => chrono::Duration::try_weeks(amount),
+        other => return Err(format!("unknown unit '{other}' (use s, m, h, d or w)")),
+    };
+    match duration {
+        Some(duration) if amount > 0 => Ok(duration),
+        _ => Err(format!("validity '{value}' out of range")),
+    }
+}
+
+impl fmt::Display for MethodArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MethodArg::Get => "get",
+            MethodArg::Head => "head",
+            MethodArg::Options => "options",
+            MethodArg::Delete => "delete",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn method_arg_converts_both_ways() {
+        for arg in MethodArg::value_variants() {
+            let method = http::Method::from(*arg);
+            assert_eq!(MethodArg::try_from(&method), Ok(*arg));
+            assert_eq!(MethodArg::from_str(&arg.to_string(), false), Ok(*arg));
+        }
+        assert!(MethodArg::try_from(&http::Method::POST).is_err());
+    }
+
+    #[test]
+    fn auto_output_format_follows_the_extension() {
+        let auto = |path: &str| OutputFormat::Auto.for_path(Path::new(path));
+        assert_eq!(auto("out/proof.cbor"), ArtifactFormat::Cbor);
+        assert_eq!(auto("proof.JSON"), ArtifactFormat::Json);
+        assert_eq!(auto("proof.red"), ArtifactFormat::Json);
+        assert_eq!(auto("-"), ArtifactFormat::Json);
+        let explicit = "cbor".parse::<OutputFormat>().unwrap();
+        assert_eq!(
+            explicit.for_path(Path::new("proof.json")),
+            ArtifactFormat::Cbor
+        );
+        assert_eq!("Auto".parse(), Ok(OutputFormat::Auto));
+    }
+
+    #[test]
+    fn parse_validity_accepts_units() {
+        assert_eq!(parse_validity("30d"), Ok(chrono::Duration::days(30)));
+        assert_eq!(parse_validity("90m"), Ok(chrono::Duration::minutes(90)));
+        assert!(parse_validity("30").is_err());
+        assert!(parse_validity("0d").is_err());
+        assert!(parse_validity("3y").is_err());
+    }
+
+    #[test]
+    fn parse_hash_alg_accepts_registered_tags() {
+        for algorithm in CommitmentAlgorithm::registered() {
+            assert_eq!(parse_hash_alg(&algorithm.to_string()), Ok(algorithm));
+        }
+        assert_eq!(
+            parse_hash_alg("md5"),
+            Err("unknown commitment algorithm 'md5' (registered: blake3, sha256)".into())
+        );
+    }
+}
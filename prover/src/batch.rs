@@ -0,0 +1,180 @@
+//! Batch evaluation: NDJSON targets in, one JSON line per finished capture
+//! out, written as soon as it completes.
+
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use anyhow::{Context, Result};
+use redproof_prover::evaluate::EvalReason;
+use serde::{Deserialize, Serialize};
+
+/// One input line: `{"url": "...", "prove": "<statement expression>"}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchTarget {
+    pub url: String,
+    pub prove: String,
+}
+
+/// What a successful capture of a target produced.
+#[derive(Debug, Clone)]
+pub struct BatchOutcome {
+    pub domain: String,
+    pub statement: String,
+    pub satisfied: bool,
+    pub details: Option<String>,
+    pub reason: Option<EvalReason>,
+}
+
+/// One output line. `index` is the target's input position; lines arrive in
+/// completion order.
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    pub index: usize,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub statement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub satisfied: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<EvalReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BatchResult {
+    fn new(index: usize, target: &BatchTarget, outcome: Result<BatchOutcome>) -> Self {
+        let mut result = BatchResult {
+            index,
+            url: target.url.clone(),
+            domain: None,
+            statement: None,
+            satisfied: None,
+            details: None,
+            reason: None,
+            error: None,
+        };
+        match outcome {
+            Ok(outcome) => {
+                result.domain = Some(outcome.domain);
+                result.statement = Some(outcome.statement);
+                result.satisfied = Some(outcome.satisfied);
+                result.details = outcome.details;
+                result.reason = outcome.reason;
+            }
+            Err(err) => result.error = Some(format!("{err:#}")),
+        }
+        result
+    }
+}
+
+/// Parse NDJSON targets, skipping blank lines.
+pub fn read_targets(text: &str) -> Result<Vec<BatchTarget>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            serde_json::from_str(line).with_context(|| format!("batch line {}", idx + 1))
+        })
+        .collect()
+}
+
+/// Run `probe` over `targets` on `jobs` workers, writing and flushing one
+/// JSON line per target as results come in. Workers block once `jobs`
+/// results are waiting to be written. Returns the number of error lines.
+pub fn run<F>(targets: &[BatchTarget], jobs: usize, probe: F, out: &mut impl Write) -> Result<usize>
+where
+    F: Fn(&BatchTarget) -> Result<BatchOutcome> + Sync,
+{
+    let jobs = jobs.clamp(1, targets.len().max(1));
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::sync_channel::<BatchResult>(jobs);
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let tx = tx.clone();
+            let (next, probe) = (&next, &probe);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(target) = targets.get(index) else {
+                    break;
+                };
+                if tx
+                    .send(BatchResult::new(index, target, probe(target)))
+                    .is_err()
+                {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        let mut failures = 0;
+        for result in rx {
+            failures += usize::from(result.error.is_some());
+            serde_json::to_writer(&mut *out, &result)?;
+            out.write_all(b"\n")?;
+            out.flush()?;
+        }
+        Ok(failures)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::bail;
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn emits_one_line_per_target_with_error_lines() {
+        let targets = read_targets(
+            r#"{"url": "https://a.example", "prove": "header:present:Server"}
+
+{"url": "https://down.example", "prove": "header:present:Server"}
+{"url": "https://c.example", "prove": "header:absent:Server"}
+"#,
+        )
+        .expect("targets");
+        let probe = |target: &BatchTarget| {
+            if target.url.contains("down") {
+                bail!("connection refused");
+            }
+            Ok(BatchOutcome {
+                domain: target.url.trim_start_matches("https://").into(),
+                statement: target.prove.clone(),
+                satisfied: target.prove.contains("present"),
+                details: None,
+                reason: None,
+            })
+        };
+
+        let mut out = Vec::new();
+        let failures = run(&targets, 2, probe, &mut out).expect("batch");
+        assert_eq!(failures, 1);
+
+        let mut lines = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        lines.sort_by_key(|line| line["index"].as_u64());
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["satisfied"], true);
+        assert_eq!(lines[1]["error"], "connection refused");
+        assert!(lines[1].get("satisfied").is_none());
+        assert_eq!(lines[2]["domain"], "c.example");
+        assert_eq!(lines[2]["satisfied"], false);
+    }
+
+    #[test]
+    fn read_targets_reports_bad_line() {
+        let err = read_targets("{\"url\": \"https://a.example\"}").unwrap_err();
+        assert!(err.to_string().contains("batch line 1"));
+    }
+}
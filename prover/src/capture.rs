@@ -1,7 +1,8 @@
-use std::io::{Read, Write};
-use std::net::TcpStream;
+use std::borrow::Cow;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
 use std::sync::{Arc, Once};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, bail, Context, Result};
 use base64::engine::general_purpose::STANDARD as B64;
@@ -9,36 +10,297 @@ use base64::Engine;
 use chrono::{DateTime, Utc};
 use http::Method;
 use rustls::client::ClientConnection;
+use rustls::crypto::ring as ring_provider;
 use rustls::pki_types::ServerName;
-use rustls::{ClientConfig, ProtocolVersion, RootCertStore, StreamOwned};
+use rustls::{ClientConfig, ProtocolVersion, RootCertStore, StreamOwned, SupportedCipherSuite};
 use rustls_native_certs::load_native_certs;
 use serde::Serialize;
 use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::{debug, info_span, warn};
 use url::Url;
 
+use redproof_artifact::{
+    embedded_sct_count, spki_pin, subject_alt_names, CertChainSize, PostureSummary,
+    RequestSequenceAnnotation, TimingTrace, TlsProofContext, SOURCE_IMPORTED,
+};
+pub use redproof_statements::record::{BodyDigest, HeaderEntry, HttpResponse};
+use redproof_statements::record::{EvalRecord, TlsFacts};
+use redproof_statements::witness::{AppDataWitness, HandshakeWitness};
+use redproof_statements::{split_list, HashAlgorithm};
+
 use crate::commit::Transcript;
-use crate::evaluate::HeaderMap;
+use crate::evaluate::{BodyHasher, HeaderMap};
+use crate::resolver::Resolver;
+use crate::script::{CookieJar, RequestScript, ScriptedRequest};
 
 const USER_AGENT: &str = concat!("RedProof/", env!("CARGO_PKG_VERSION"));
 const DEFAULT_TIMEOUT_SECS: u64 = 20;
+const NOT_HTTP_PREVIEW_BYTES: usize = 32;
+pub const DEFAULT_MAX_HEADERS: usize = 256;
+/// Bytes accepted before the final response head ends, interim heads
+/// included.
+const MAX_HEAD_BYTES: usize = 1024 * 1024;
+/// Absolute cap on the retained body, in MiB, whatever `max_body_kb` says.
+pub const DEFAULT_BODY_CEILING_MB: usize = 64;
+
+/// Body bytes to retain for a `max_body_kb` setting, where `0` means
+/// unlimited. Either way the result never exceeds `ceiling_mb`.
+pub fn body_limit(max_body_kb: usize, ceiling_mb: usize) -> Result<usize> {
+    let ceiling = ceiling_mb
+        .checked_mul(1024 * 1024)
+        .ok_or_else(|| anyhow!("max-body-ceiling-mb overflow"))?;
+    if max_body_kb == 0 {
+        return Ok(ceiling);
+    }
+    let requested = max_body_kb
+        .checked_mul(1024)
+        .ok_or_else(|| anyhow!("max-body-kb overflow"))?;
+    Ok(requested.min(ceiling))
+}
+
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error(
+        "server did not answer with HTTP (first bytes: \"{preview}\"); is this the right port?"
+    )]
+    NotHttp { preview: String },
+    #[error("TLS handshake failed")]
+    Handshake(#[source] std::io::Error),
+    /// The handshake failed on a TLS-level error (alert, certificate or
+    /// version mismatch) rather than on the transport.
+    #[error("TLS handshake failed: {alert}")]
+    TlsHandshake { alert: String },
+    /// The peer does not speak TLS, or no TLS version is shared: the only
+    /// handshake failures the plaintext fallback retries.
+    #[error("TLS handshake failed: {alert}")]
+    TlsUnavailable { alert: String },
+    #[error("capture exceeded its {phase} time budget")]
+    Timeout { phase: TimeoutPhase },
+    #[error("{name} header value must not contain CR or LF")]
+    InvalidHeaderValue { name: &'static str },
+    #[error("response has more than {limit} headers")]
+    TooManyHeaders { limit: usize },
+    #[error("response head exceeds {limit} bytes")]
+    HeadTooLarge { limit: usize },
+}
+
+/// Browser whose navigation request headers are imitated, for servers that
+/// gate on `Sec-Fetch-*` or a browser User-Agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserProfile {
+    Chrome,
+    Firefox,
+}
+
+impl BrowserProfile {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BrowserProfile::Chrome => "chrome",
+            BrowserProfile::Firefox => "firefox",
+        }
+    }
+
+    /// Request headers in the order the browser sends them; `Host` and
+    /// `Connection` are added separately.
+    fn headers(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            BrowserProfile::Chrome => &[
+                ("sec-ch-ua", "\"Chromium\";v=\"124\", \"Google Chrome\";v=\"124\", \"Not-A.Brand\";v=\"99\""),
+                ("sec-ch-ua-mobile", "?0"),
+                ("sec-ch-ua-platform", "\"Windows\""),
+                ("Upgrade-Insecure-Requests", "1"),
+                ("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36"),
+                ("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8"),
+                ("Sec-Fetch-Site", "none"),
+                ("Sec-Fetch-Mode", "navigate"),
+                ("Sec-Fetch-User", "?1"),
+                ("Sec-Fetch-Dest", "document"),
+                ("Accept-Language", "en-US,en;q=0.9"),
+            ],
+            BrowserProfile::Firefox => &[
+                ("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0"),
+                ("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8"),
+                ("Accept-Language", "en-US,en;q=0.5"),
+                ("Upgrade-Insecure-Requests", "1"),
+                ("Sec-Fetch-Dest", "document"),
+                ("Sec-Fetch-Mode", "navigate"),
+                ("Sec-Fetch-Site", "none"),
+                ("Sec-Fetch-User", "?1"),
+            ],
+        }
+    }
+}
+
+/// Whether the request asks the server to close the connection after its
+/// response, which is also how the read knows the response has ended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConnectionMode {
+    /// `Connection: close`; the response ends when the server closes.
+    #[default]
+    Close,
+    /// No `Connection` header, so HTTP/1.1 keep-alive applies; the response
+    /// ends at its `Content-Length` or last chunk.
+    KeepAlive,
+}
+
+impl ConnectionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionMode::Close => "close",
+            ConnectionMode::KeepAlive => "keep-alive",
+        }
+    }
+}
+
+/// Which budget a [`CaptureError::Timeout`] ran out of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutPhase {
+    /// The wall-clock budget over every connection the capture makes.
+    Total,
+    /// A single read waited longer than the per-read timeout.
+    Read,
+}
+
+impl std::fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeoutPhase::Total => f.write_str("total"),
+            TimeoutPhase::Read => f.write_str("read"),
+        }
+    }
+}
+
+/// GET, HEAD and OPTIONS: the only methods the prover sends more than once
+/// for a capture.
+fn is_safe_to_resend(method: &Method) -> bool {
+    [Method::GET, Method::HEAD, Method::OPTIONS].contains(method)
+}
 
 pub struct CaptureOptions {
     pub url: Url,
     pub method: Method,
     pub max_body_bytes: usize,
+    /// Header lines accepted per response head before reading gives up.
+    pub max_headers: usize,
     pub timeout: Option<Duration>,
+    /// Wall-clock budget for the whole capture, warm-up included. Callers
+    /// that re-capture pass the same deadline to every attempt.
+    pub deadline: Deadline,
+    pub record_dns: bool,
+    /// Record and commit the SPKI pin of each peer certificate.
+    pub record_spki: bool,
+    /// Hash the body while reading instead of retaining it; only the digest
+    /// is kept and committed.
+    pub stream_body_hash: Option<HashAlgorithm>,
+    /// Send a throwaway request first so the measured one runs against warmed
+    /// server and TLS session caches.
+    pub warm: bool,
+    /// Leave the SNI extension out of the ClientHello.
+    pub no_sni: bool,
+    /// Cipher suites offered, in preference order; empty offers every suite
+    /// the crypto provider supports.
+    pub cipher_suites: Vec<SupportedCipherSuite>,
+    /// Send a browser's header set instead of the RedProof defaults.
+    pub browser_profile: Option<BrowserProfile>,
+    /// Value for an `Origin` request header.
+    pub origin: Option<String>,
+    pub connection: ConnectionMode,
+    /// When the server does not speak TLS or shares no TLS version, retry
+    /// over plaintext HTTP and record `tls.version = "none"`. Certificate
+    /// and other handshake errors are never retried.
+    pub allow_plaintext_fallback: bool,
+    /// On a read timeout after the response head arrived, keep the partial
+    /// response (marked truncated) instead of failing.
+    pub salvage_on_timeout: bool,
+    /// Include the measured connection's [`TimingTrace`] in the canonical
+    /// app data, so it is covered by the commitment.
+    pub commit_timing: bool,
+    /// Record each header name as sent on the wire (`wire_name`) next to
+    /// the lowercased name, and commit to it.
+    pub preserve_header_case: bool,
+    /// Send these requests in order instead of one `method` request to
+    /// `url`, committing to the selected response.
+    pub request_script: Option<RequestScript>,
+    pub resolver: Arc<dyn Resolver>,
+}
+
+impl CaptureOptions {
+    /// Method of every request the capture sends, script steps included.
+    pub fn methods(&self) -> impl Iterator<Item = &Method> {
+        let scripted = self.request_script.as_ref().map(|script| {
+            script
+                .requests
+                .iter()
+                .map(|step| &step.method)
+                .collect::<Vec<_>>()
+        });
+        scripted.unwrap_or_else(|| vec![&self.method]).into_iter()
+    }
+
+    /// Refuse `flag` when it would send a request whose method is not safe
+    /// to repeat.
+    pub fn refuse_resending(&self, flag: &str) -> Result<()> {
+        match self.methods().find(|method| !is_safe_to_resend(method)) {
+            Some(method) => bail!(
+                "{flag} would send {method} more than once; only GET, HEAD and OPTIONS \
+                 requests are re-sent"
+            ),
+            None => Ok(()),
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct CaptureRecord {
     pub requested_url: Url,
     pub domain: String,
     pub method: Method,
-    pub captured_at: DateTime<Utc>,
+    /// The prover's clock when the response arrived; `None` when the
+    /// response was not captured by RedProof.
+    pub captured_at: Option<DateTime<Utc>>,
     pub tls: TlsMetadata,
     pub response: HttpResponse,
     pub canonical_handshake: Vec<u8>,
     pub canonical_app_data: Vec<u8>,
     pub headers: HeaderMap,
+    /// Addresses the host resolved to, when DNS recording was requested.
+    /// Prover-attested only: the verifier cannot re-check a past resolution.
+    pub resolved_ips: Vec<IpAddr>,
+    /// `None` when the response was not captured by RedProof: nothing was
+    /// timed.
+    pub timing: Option<CaptureTiming>,
+    /// Whether the ClientHello carried SNI; never true for IP-literal hosts.
+    pub sni_sent: bool,
+    pub browser_profile: Option<BrowserProfile>,
+    pub origin: Option<String>,
+    /// How the request treated the connection; `None` when the response was
+    /// not captured by RedProof.
+    pub connection: Option<ConnectionMode>,
+    /// Set when a request script sent several requests in the capture.
+    pub request_sequence: Option<RequestSequenceAnnotation>,
+}
+
+/// Time from writing the request (after the TLS handshake) to the first
+/// response byte. Prover-attested; only `trace` can be committed.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureTiming {
+    pub cold_ttfb_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warm_ttfb_ms: Option<u64>,
+    /// Phase breakdown of the measured (warm, if any) connection.
+    #[serde(skip)]
+    pub trace: TimingTrace,
+}
+
+impl CaptureTiming {
+    /// The figure timing statements are evaluated against, with its label.
+    pub fn measured(&self) -> (&'static str, u64) {
+        match self.warm_ttfb_ms {
+            Some(warm) => ("warm", warm),
+            None => ("cold", self.cold_ttfb_ms),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -48,38 +310,123 @@ pub struct TlsMetadata {
     pub cert_fingerprints: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub alpn: Option<String>,
-}
-
-#[derive(Debug, Clone, Serialize)]
-pub struct HttpResponse {
-    pub http_version: String,
-    pub status_code: u16,
-    pub reason: String,
-    pub headers: Vec<HeaderEntry>,
+    /// DER-encoded peer certificates, leaf first.
+    #[serde(skip)]
+    pub cert_chain: Vec<Vec<u8>>,
+    /// `pin-sha256` per peer certificate, when requested.
     #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub body: Vec<u8>,
-    pub body_truncated: bool,
+    pub spki_pins: Vec<String>,
+    /// DNS names and IP addresses in the leaf certificate's SubjectAltName.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub subject_alt_names: Vec<String>,
+    /// Certificates the server sent and their combined DER size; zero
+    /// without TLS.
+    pub chain_size: CertChainSize,
+    /// Signed certificate timestamps embedded in the leaf certificate.
+    /// rustls does not expose SCTs delivered in the TLS extension or a
+    /// stapled OCSP response, so only embedded ones are seen.
+    pub embedded_scts: usize,
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct HeaderEntry {
-    pub name: String,
-    pub value: String,
+impl TlsMetadata {
+    /// Placeholder for responses read without TLS.
+    fn plaintext() -> Self {
+        TlsMetadata {
+            version: TlsProofContext::PLAINTEXT.into(),
+            cipher: TlsProofContext::PLAINTEXT.into(),
+            cert_fingerprints: Vec::new(),
+            alpn: None,
+            cert_chain: Vec::new(),
+            spki_pins: Vec::new(),
+            subject_alt_names: Vec::new(),
+            chain_size: CertChainSize::default(),
+            embedded_scts: 0,
+        }
+    }
 }
 
 impl CaptureRecord {
+    /// The capture as statements see it; the prover vouches for every fact
+    /// it recorded.
+    pub fn eval_record(&self) -> EvalRecord<'_> {
+        EvalRecord {
+            response: Cow::Borrowed(&self.response),
+            headers: Cow::Borrowed(&self.headers),
+            canonical_handshake: &self.canonical_handshake,
+            canonical_app_data: &self.canonical_app_data,
+            tls: TlsFacts {
+                spki_pins: &self.tls.spki_pins,
+                subject_alt_names: &self.tls.subject_alt_names,
+                served_certificates: self.tls.cert_chain.len(),
+                chain_bytes: Some(self.tls.chain_size.total_bytes),
+                embedded_scts: Some(self.tls.embedded_scts),
+            },
+            resolved_ips: &self.resolved_ips,
+            captured_at: self.captured_at,
+            ttfb: self.timing.as_ref().map(CaptureTiming::measured),
+        }
+    }
+
     pub fn transcript(&self) -> Transcript {
         Transcript {
             handshake: self.canonical_handshake.clone(),
             app_data: self.canonical_app_data.clone(),
         }
     }
+
+    /// Server software, `Via` intermediaries and protocol versions.
+    pub fn posture(&self) -> PostureSummary {
+        let values = |name: &str| self.headers.get(name).into_iter().flatten();
+        let cipher = &self.tls.cipher;
+        PostureSummary {
+            server: values("server").next().cloned(),
+            via: values("via").flat_map(|value| split_list(value)).collect(),
+            http_version: self.response.http_version.clone(),
+            tls_version: self.tls.version.clone(),
+            cipher: (!cipher.is_empty() && cipher != TlsProofContext::PLAINTEXT)
+                .then(|| cipher.clone()),
+        }
+    }
+
+    /// BLAKE3 hex digest of the canonical response with the `ignore`d
+    /// headers and any committed timing left out; with neither it equals the
+    /// BLAKE3 app-data commitment.
+    pub fn change_digest(&self, ignore: &[String]) -> Result<String> {
+        let headers = self
+            .response
+            .headers
+            .iter()
+            .filter(|header| {
+                !ignore
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(&header.name))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let canonical = canonicalize_app_data(&self.response, &headers, None)?;
+        Ok(blake3::hash(&canonical).to_hex().to_string())
+    }
 }
 
 pub fn capture(options: &CaptureOptions) -> Result<CaptureRecord> {
     install_crypto_provider();
-    if options.url.scheme() != "https" {
-        bail!("only https:// URLs are supported (got {})", options.url);
+    let _span = info_span!("capture", url = %options.url).entered();
+    if options.warm {
+        options.refuse_resending("--warm")?;
+    }
+    if options.allow_plaintext_fallback {
+        options.refuse_resending("--allow-plaintext-fallback")?;
+    }
+    let plaintext = match options.url.scheme() {
+        "https" => false,
+        "http" => true,
+        _ => bail!(
+            "only https:// and http:// URLs are supported (got {})",
+            options.url
+        ),
+    };
+    if plaintext {
+        warn!(url = %options.url, "capturing over plaintext HTTP; nothing will bind the artifact to the domain");
     }
     let domain = options
         .url
@@ -102,100 +449,1046 @@ pub fn capture(options: &CaptureOptions) -> Result<CaptureRecord> {
     let timeout = options
         .timeout
         .unwrap_or_else(|| Duration::from_secs(DEFAULT_TIMEOUT_SECS));
-    let tcp =
-        TcpStream::connect(&addr).with_context(|| format!("failed to connect to {}", addr))?;
-    tcp.set_read_timeout(Some(timeout))?;
-    tcp.set_write_timeout(Some(timeout))?;
+    let deadline = options.deadline;
+    let socket_addrs = options.resolver.resolve(&domain, port)?;
+    let overridden = options.resolver.is_override(&domain, port);
+    if options.record_dns && overridden {
+        warn!(%domain, "addresses come from --resolve, not DNS; recording no resolved_ips");
+    }
+    let resolved_ips = if options.record_dns && !overridden {
+        unique_ips(&socket_addrs)
+    } else {
+        Vec::new()
+    };
 
-    let config = build_tls_config()?;
+    let config = Arc::new(build_tls_config(&TlsConfigOptions {
+        enable_sni: !options.no_sni,
+        cipher_suites: options.cipher_suites.clone(),
+        ..TlsConfigOptions::default()
+    })?);
     let server_name =
         ServerName::try_from(domain.clone()).map_err(|_| anyhow!("invalid DNS name"))?;
-    let connection =
-        ClientConnection::new(Arc::new(config), server_name).context("failed to negotiate TLS")?;
-    let mut stream = StreamOwned::new(connection, tcp);
+    let sni_sent = !plaintext && !options.no_sni && matches!(server_name, ServerName::DnsName(_));
+    let (steps, selected) = match &options.request_script {
+        Some(script) => (script.requests.clone(), script.select),
+        None => (
+            vec![ScriptedRequest {
+                method: options.method.clone(),
+                target,
+            }],
+            0,
+        ),
+    };
+    let open = || -> Result<Connected> {
+        deadline.check()?;
+        let started = Instant::now();
+        let tcp = info_span!("connect", %addr)
+            .in_scope(|| connect_tcp(&socket_addrs, &deadline, timeout))
+            .with_context(|| format!("failed to connect to {}", addr))?;
+        tcp.set_write_timeout(Some(deadline.clamp(timeout)?))?;
+        Ok(Connected {
+            tcp,
+            started,
+            connect: started.elapsed(),
+        })
+    };
+    let open_session = || -> Result<Session> {
+        if plaintext {
+            return Ok(Session::plain(open()?));
+        }
+        match handshake(
+            config.clone(),
+            server_name.clone(),
+            open()?,
+            &deadline,
+            timeout,
+        ) {
+            Err(err) if options.allow_plaintext_fallback && is_tls_unavailable(&err) => {
+                warn!(%addr, error = %err, "server does not speak TLS; retrying as plaintext HTTP");
+                Ok(Session::plain(open()?))
+            }
+            Err(err) => Err(explain_handshake_failure(err, sni_sent, port)),
+            ok => ok,
+        }
+    };
+    let request = |step: &ScriptedRequest, connection, cookie: Option<&str>| {
+        build_request(
+            &step.method,
+            &domain,
+            &step.target,
+            options.browser_profile,
+            options.origin.as_deref(),
+            connection,
+            cookie,
+        )
+    };
+    let buffer = |request: &str, connection, stream_hash: Option<&HashAlgorithm>| {
+        let buffer = ResponseBuffer::new(
+            stream_hash,
+            options.max_body_bytes,
+            options.max_headers,
+            options.salvage_on_timeout,
+        );
+        match connection {
+            ConnectionMode::Close => buffer,
+            ConnectionMode::KeepAlive => buffer.until_framed_end(request.starts_with("HEAD ")),
+        }
+    };
+
+    // The warm-up shares the client config, so the measured connection can
+    // resume its TLS session.
+    let cold_ttfb = if options.warm {
+        let warm_up = request(&steps[selected], options.connection, None)?;
+        let exchange = open_session()?.send(
+            &warm_up,
+            buffer(&warm_up, options.connection, None),
+            &deadline,
+            timeout,
+        )?;
+        Some(exchange.trace.ttfb_ms)
+    } else {
+        None
+    };
+    // Script steps share one connection while the server keeps it open, and
+    // carry the cookies earlier responses set; only the selected response is
+    // kept. The last request follows `options.connection`.
+    let mut jar = CookieJar::default();
+    let mut session: Option<Session> = None;
+    let mut measured = None;
+    for (idx, step) in steps.iter().enumerate() {
+        let connection = if idx + 1 < steps.len() {
+            ConnectionMode::KeepAlive
+        } else {
+            options.connection
+        };
+        let request = request(step, connection, jar.header().as_deref())?;
+        let stream_hash = options
+            .stream_body_hash
+            .as_ref()
+            .filter(|_| idx == selected);
+        let mut current = match session.take() {
+            Some(session) => session,
+            None => open_session()?,
+        };
+        let exchange = current.send(
+            &request,
+            buffer(&request, connection, stream_hash),
+            &deadline,
+            timeout,
+        );
+        let exchange = if idx == selected {
+            exchange?
+        } else {
+            exchange.with_context(|| {
+                format!(
+                    "request {} of {} in the script failed",
+                    idx + 1,
+                    steps.len()
+                )
+            })?
+        };
+        let head = exchange.buffer.head();
+        if let Some(head) = &head {
+            jar.store(head);
+        }
+        let reusable = connection == ConnectionMode::KeepAlive
+            && exchange.buffer.is_complete()
+            && head.as_deref().is_some_and(keeps_connection_open);
+        if idx == selected {
+            measured = Some((exchange, current.tls_metadata(&domain)));
+        }
+        if reusable {
+            session = Some(current);
+        }
+    }
+    let (Exchange { buffer, trace }, mut tls) =
+        measured.expect("the selected request is in the script");
+    let timing = match cold_ttfb {
+        Some(cold) => CaptureTiming {
+            cold_ttfb_ms: cold,
+            warm_ttfb_ms: Some(trace.ttfb_ms),
+            trace,
+        },
+        None => CaptureTiming {
+            cold_ttfb_ms: trace.ttfb_ms,
+            warm_ttfb_ms: None,
+            trace,
+        },
+    };
+    if options.record_spki {
+        tls.spki_pins = tls
+            .cert_chain
+            .iter()
+            .map(|der| spki_pin(der))
+            .collect::<Result<_, _>>()?;
+    }
+
+    let salvaged = buffer.salvaged;
+    let (raw, body_digest) = buffer.finish();
+    let (mut response, headers, header_map) = parse_http_response(
+        &raw,
+        options.max_body_bytes,
+        options.max_headers,
+        options.preserve_header_case,
+    )?;
+    if salvaged {
+        // A digest over part of the body would prove nothing.
+        response.body_truncated = true;
+    } else {
+        response.body_digest = body_digest;
+    }
+    let canonical_handshake = canonicalize_handshake(&tls, &domain, &resolved_ips, None)?;
+    let committed_timing = options.commit_timing.then_some(&timing.trace);
+    let canonical_app_data = canonicalize_app_data(&response, &headers, committed_timing)?;
+
+    let step = &steps[selected];
+    let requested_url = match &options.request_script {
+        Some(_) if step.target != "*" => options.url.join(&step.target)?,
+        _ => options.url.clone(),
+    };
+    Ok(CaptureRecord {
+        requested_url,
+        domain,
+        method: step.method.clone(),
+        captured_at: Some(Utc::now()),
+        tls,
+        response,
+        canonical_handshake,
+        canonical_app_data,
+        headers: header_map,
+        resolved_ips,
+        timing: Some(timing),
+        sni_sent,
+        browser_profile: options.browser_profile,
+        origin: options.origin.clone(),
+        connection: Some(options.connection),
+        request_sequence: options
+            .request_script
+            .as_ref()
+            .map(|script| RequestSequenceAnnotation {
+                count: script.requests.len(),
+                selected: script.select + 1,
+            }),
+    })
+}
 
-    let request = build_request(&options.method, &domain, &target);
+/// Build a record from a raw HTTP/1.x response read by another tool (a
+/// pcap, a recorded fixture). Nothing is measured, so the record has no
+/// timing and no capture time; the body is kept whole. `sni_sent` says
+/// whether the ClientHello carried SNI. The handshake commits
+/// `source: imported`, so the artifact cannot pass for a live capture.
+pub fn record_from_response(
+    domain: &str,
+    tls: TlsMetadata,
+    sni_sent: bool,
+    raw: &[u8],
+) -> Result<CaptureRecord> {
+    let (response, headers, header_map) =
+        parse_http_response(raw, usize::MAX, DEFAULT_MAX_HEADERS, false)?;
+    let canonical_handshake = canonicalize_handshake(&tls, domain, &[], Some(SOURCE_IMPORTED))?;
+    let canonical_app_data = canonicalize_app_data(&response, &headers, None)?;
+    Ok(CaptureRecord {
+        requested_url: Url::parse(&format!("https://{domain}/")).context("invalid domain")?,
+        domain: domain.to_string(),
+        method: Method::GET,
+        captured_at: None,
+        tls,
+        response,
+        canonical_handshake,
+        canonical_app_data,
+        headers: header_map,
+        resolved_ips: Vec::new(),
+        timing: None,
+        sni_sent,
+        browser_profile: None,
+        origin: None,
+        connection: None,
+        request_sequence: None,
+    })
+}
+
+/// A fresh TCP connection and how long establishing it took.
+struct Connected {
+    tcp: TcpStream,
+    started: Instant,
+    connect: Duration,
+}
+
+/// A response read over a [`Session`] and the timing of its request.
+struct Exchange {
+    buffer: ResponseBuffer,
+    trace: TimingTrace,
+}
+
+/// An open connection, TLS or plaintext, that requests are sent over one
+/// after another.
+struct Session {
+    stream: SessionStream,
+    /// When the connection was opened and how long its connect and
+    /// handshake took, charged to the first request's trace only.
+    setup: Option<(Instant, Duration, Duration)>,
+}
+
+enum SessionStream {
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+    Plain(TcpStream),
+}
+
+impl Session {
+    /// A session over plaintext HTTP.
+    fn plain(connected: Connected) -> Self {
+        Self {
+            setup: Some((connected.started, connected.connect, Duration::ZERO)),
+            stream: SessionStream::Plain(connected.tcp),
+        }
+    }
+
+    /// Send `request` and read its response into `buffer`.
+    fn send(
+        &mut self,
+        request: &str,
+        buffer: ResponseBuffer,
+        deadline: &Deadline,
+        timeout: Duration,
+    ) -> Result<Exchange> {
+        let (started, connect, handshake) = self
+            .setup
+            .take()
+            .unwrap_or_else(|| (Instant::now(), Duration::ZERO, Duration::ZERO));
+        let (buffer, ttfb) = match &mut self.stream {
+            SessionStream::Tls(stream) => {
+                let _read = info_span!("read").entered();
+                send_and_read(
+                    stream.as_mut(),
+                    |stream| &stream.sock,
+                    request,
+                    buffer,
+                    deadline,
+                    timeout,
+                )?
+            }
+            SessionStream::Plain(tcp) => {
+                let _read = info_span!("read", tls = false).entered();
+                send_and_read(tcp, |tcp| tcp, request, buffer, deadline, timeout)?
+            }
+        };
+        Ok(Exchange {
+            buffer,
+            trace: TimingTrace {
+                connect_ms: duration_ms(connect),
+                handshake_ms: duration_ms(handshake),
+                ttfb_ms: duration_ms(ttfb),
+                total_ms: duration_ms(started.elapsed()),
+            },
+        })
+    }
+
+    fn tls_metadata(&self, domain: &str) -> TlsMetadata {
+        match &self.stream {
+            SessionStream::Tls(stream) => extract_tls_metadata(&stream.conn, domain),
+            SessionStream::Plain(_) => TlsMetadata::plaintext(),
+        }
+    }
+}
+
+/// Wall-clock budget shared by every connection a capture makes, and by
+/// every attempt when the capture is retried.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Deadline(Option<Instant>);
+
+impl Deadline {
+    /// A budget too large to represent as an instant is unbounded.
+    pub fn new(budget: Option<Duration>) -> Self {
+        Deadline(budget.and_then(|budget| Instant::now().checked_add(budget)))
+    }
+
+    pub fn check(&self) -> Result<(), CaptureError> {
+        self.clamp(Duration::MAX).map(|_| ())
+    }
+
+    /// Shrink a per-operation timeout to what is left of the budget.
+    pub fn clamp(&self, timeout: Duration) -> Result<Duration, CaptureError> {
+        let Some(at) = self.0 else {
+            return Ok(timeout);
+        };
+        let remaining = at.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(CaptureError::Timeout {
+                phase: TimeoutPhase::Total,
+            });
+        }
+        Ok(remaining.min(timeout))
+    }
+}
+
+/// Connect to the first address that answers, each attempt bounded by
+/// `timeout` and the remaining budget.
+fn connect_tcp(addrs: &[SocketAddr], deadline: &Deadline, timeout: Duration) -> Result<TcpStream> {
+    let mut last = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(addr, deadline.clamp(timeout)?) {
+            Ok(tcp) => return Ok(tcp),
+            Err(err) => last = Some(err),
+        }
+    }
+    deadline.check()?;
+    Err(match last {
+        Some(err) => err.into(),
+        None => anyhow!("no addresses to connect to"),
+    })
+}
+
+/// Complete a TLS handshake over a fresh connection. Every read is bounded
+/// by `timeout` and the remaining budget.
+fn handshake(
+    config: Arc<ClientConfig>,
+    server_name: ServerName<'static>,
+    connected: Connected,
+    deadline: &Deadline,
+    timeout: Duration,
+) -> Result<Session> {
+    let Connected {
+        mut tcp,
+        started,
+        connect,
+    } = connected;
+    let handshake_started = Instant::now();
+    let _handshake = info_span!("handshake", sni = config.enable_sni).entered();
+    let mut conn = ClientConnection::new(config, server_name).context("failed to negotiate TLS")?;
+    while conn.is_handshaking() {
+        tcp.set_read_timeout(Some(deadline.clamp(timeout)?))?;
+        if let Err(err) = conn.complete_io(&mut tcp) {
+            deadline.check()?;
+            return Err(handshake_error(err).into());
+        }
+    }
+    debug!(version = ?conn.protocol_version(), "handshake complete");
+    Ok(Session {
+        setup: Some((started, connect, handshake_started.elapsed())),
+        stream: SessionStream::Tls(Box::new(StreamOwned::new(conn, tcp))),
+    })
+}
+
+/// Write `request` and read until the server closes the connection (or,
+/// for keep-alive, until the response's framing ends), returning the buffered response and the time to its first byte. A read
+/// timeout fails the exchange unless the buffer salvages it.
+fn send_and_read<S: Read + Write>(
+    stream: &mut S,
+    socket: fn(&S) -> &TcpStream,
+    request: &str,
+    mut buffer: ResponseBuffer,
+    deadline: &Deadline,
+    timeout: Duration,
+) -> Result<(ResponseBuffer, Duration)> {
+    let started = Instant::now();
     stream.write_all(request.as_bytes())?;
     stream.flush()?;
 
-    let mut raw = Vec::new();
+    let mut ttfb = None;
     let mut buf = [0u8; 8192];
     loop {
+        socket(stream).set_read_timeout(Some(deadline.clamp(timeout)?))?;
         match stream.read(&mut buf) {
             Ok(0) => break,
-            Ok(n) => raw.extend_from_slice(&buf[..n]),
-            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
-            Err(e) => return Err(e.into()),
+            Ok(n) => {
+                ttfb.get_or_insert_with(|| started.elapsed());
+                buffer.push(&buf[..n])?;
+                if buffer.is_full() {
+                    debug!(bytes = buffer.raw.len(), "body cap reached; stopping read");
+                    break;
+                }
+                if buffer.is_complete() {
+                    debug!("response framing complete; leaving the connection open");
+                    break;
+                }
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                deadline.check()?;
+                if buffer.salvage_on_timeout && buffer.head_received() {
+                    warn!(
+                        bytes = buffer.raw.len(),
+                        "read timed out mid-body; keeping the partial response"
+                    );
+                    buffer.salvaged = true;
+                    break;
+                }
+                return Err(CaptureError::Timeout {
+                    phase: TimeoutPhase::Read,
+                }
+                .into());
+            }
+            Err(e) => {
+                deadline.check()?;
+                return Err(e.into());
+            }
+        }
+    }
+    debug!(ttfb = ?ttfb, "response read");
+    Ok((buffer, ttfb.unwrap_or_else(|| started.elapsed())))
+}
+
+fn is_handshake_failure(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<CaptureError>(),
+        Some(
+            CaptureError::Handshake(_)
+                | CaptureError::TlsHandshake { .. }
+                | CaptureError::TlsUnavailable { .. }
+        )
+    )
+}
+
+fn is_tls_unavailable(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<CaptureError>(),
+        Some(CaptureError::TlsUnavailable { .. })
+    )
+}
+
+/// Keep the TLS-level reason rustls wraps in the I/O error, if any.
+fn handshake_error(err: std::io::Error) -> CaptureError {
+    match err
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<rustls::Error>())
+    {
+        Some(
+            tls @ (rustls::Error::AlertReceived(rustls::AlertDescription::ProtocolVersion)
+            | rustls::Error::InvalidMessage(rustls::InvalidMessage::InvalidContentType)),
+        ) => CaptureError::TlsUnavailable {
+            alert: describe_tls_error(tls),
+        },
+        Some(tls) => CaptureError::TlsHandshake {
+            alert: describe_tls_error(tls),
+        },
+        None => CaptureError::Handshake(err),
+    }
+}
+
+fn describe_tls_error(err: &rustls::Error) -> String {
+    use rustls::{AlertDescription, CertificateError};
+    match err {
+        rustls::Error::InvalidCertificate(
+            CertificateError::Expired | CertificateError::ExpiredContext { .. },
+        ) => "server certificate expired".into(),
+        rustls::Error::InvalidCertificate(
+            CertificateError::NotValidYet | CertificateError::NotValidYetContext { .. },
+        ) => "server certificate not yet valid".into(),
+        rustls::Error::InvalidCertificate(CertificateError::UnknownIssuer) => {
+            "server certificate issued by an untrusted root".into()
+        }
+        rustls::Error::InvalidCertificate(
+            CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. },
+        ) => "server certificate does not cover this host name".into(),
+        rustls::Error::AlertReceived(AlertDescription::ProtocolVersion) => {
+            "server sent protocol_version alert (no TLS version in common)".into()
+        }
+        rustls::Error::AlertReceived(AlertDescription::HandshakeFailure) => {
+            "server sent handshake_failure alert (no cipher suite or parameters in common)".into()
         }
+        rustls::Error::AlertReceived(alert) => format!("server sent {alert:?} alert"),
+        other => other.to_string(),
     }
+}
 
-    let StreamOwned { conn, .. } = stream;
-    let tls = extract_tls_metadata(&conn, &domain);
+/// Ports that conventionally serve plaintext HTTP.
+const PLAINTEXT_PORTS: &[u16] = &[80, 8000, 8008, 8080];
 
-    let (response, headers, header_map) = parse_http_response(&raw, options.max_body_bytes)?;
-    let canonical_handshake = canonicalize_handshake(&tls, &domain)?;
-    let canonical_app_data = canonicalize_app_data(&response, &headers)?;
+/// Add likely causes to a handshake failure.
+fn explain_handshake_failure(err: anyhow::Error, sni_sent: bool, port: u16) -> anyhow::Error {
+    if !is_handshake_failure(&err) {
+        return err;
+    }
+    let err = if sni_sent {
+        err
+    } else {
+        err.context("connection made without SNI; the server may require it")
+    };
+    if PLAINTEXT_PORTS.contains(&port) {
+        err.context(format!(
+            "port {port} usually serves plaintext HTTP; use an http:// URL to capture it anyway"
+        ))
+    } else {
+        err
+    }
+}
 
-    Ok(CaptureRecord {
-        requested_url: options.url.clone(),
-        domain,
-        method: options.method.clone(),
-        captured_at: Utc::now(),
-        tls,
-        response,
-        canonical_handshake,
-        canonical_app_data,
-        headers: header_map,
+fn duration_ms(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+fn unique_ips(addrs: &[SocketAddr]) -> Vec<IpAddr> {
+    let mut ips = addrs.iter().map(SocketAddr::ip).collect::<Vec<_>>();
+    ips.sort();
+    ips.dedup();
+    ips
+}
+
+/// Accumulates response bytes. With a streaming hasher, everything after the
+/// final response head is hashed and dropped instead of buffered.
+struct ResponseBuffer {
+    raw: Vec<u8>,
+    hasher: Option<(HashAlgorithm, BodyHasher)>,
+    /// Start of the head being read, or of the final head once read.
+    head_start: usize,
+    /// Start of the first head line not yet complete.
+    line_start: usize,
+    /// Header lines seen so far in the head being read.
+    header_lines: usize,
+    /// Whether the head being read has a 1xx status line.
+    interim_head: bool,
+    /// Offset just past the final head, once it has been read.
+    head_end: Option<usize>,
+    /// Header lines accepted per head before reading gives up.
+    max_headers: usize,
+    /// Body bytes worth retaining; reading stops once more are buffered.
+    body_cap: usize,
+    /// Keep what was read when a read times out after the final head.
+    salvage_on_timeout: bool,
+    /// Set when the read stopped on a timeout rather than end of stream.
+    salvaged: bool,
+    message_end: MessageEnd,
+}
+
+/// How the read recognizes the end of the response.
+enum MessageEnd {
+    /// The server closes the connection.
+    Close,
+    /// The response's own framing ends; `framing` is known once the final
+    /// head has been read.
+    Framed {
+        head_request: bool,
+        framing: Option<Framing>,
+    },
+}
+
+/// Body framing of a keep-alive response.
+enum Framing {
+    /// Body bytes still to come.
+    Length(usize),
+    Chunked(ChunkState),
+    /// No length given: the body runs until the server closes.
+    UntilClose,
+}
+
+/// Position in a `Transfer-Encoding: chunked` body.
+enum ChunkState {
+    /// Reading a chunk-size line.
+    Size(Vec<u8>),
+    /// Bytes of chunk data still to come.
+    Data(usize),
+    /// Bytes of the CRLF after a chunk's data still to come.
+    DataEnd(usize),
+    /// Reading trailer lines after the last chunk.
+    Trailer(Vec<u8>),
+    Done,
+    /// Unparseable chunk size; fall back to reading until close.
+    Malformed,
+}
+
+impl ResponseBuffer {
+    fn new(
+        stream_hash: Option<&HashAlgorithm>,
+        body_cap: usize,
+        max_headers: usize,
+        salvage_on_timeout: bool,
+    ) -> Self {
+        Self {
+            raw: Vec::new(),
+            hasher: stream_hash.map(|algo| (algo.clone(), BodyHasher::new(algo))),
+            head_start: 0,
+            line_start: 0,
+            header_lines: 0,
+            interim_head: false,
+            head_end: None,
+            max_headers,
+            body_cap,
+            salvage_on_timeout,
+            salvaged: false,
+            message_end: MessageEnd::Close,
+        }
+    }
+
+    /// Stop reading once the response's framing says it is complete, for
+    /// connections the server keeps open.
+    fn until_framed_end(mut self, head_request: bool) -> Self {
+        self.message_end = MessageEnd::Framed {
+            head_request,
+            framing: None,
+        };
+        self
+    }
+
+    /// Whether a keep-alive response has been read to the end of its framing.
+    fn is_complete(&self) -> bool {
+        match &self.message_end {
+            MessageEnd::Framed {
+                framing: Some(framing),
+                ..
+            } => framing.is_done(),
+            _ => false,
+        }
+    }
+
+    /// The final (non-1xx) response head, once read.
+    fn head(&self) -> Option<String> {
+        self.head_end
+            .map(|end| String::from_utf8_lossy(&self.raw[self.head_start..end]).into_owned())
+    }
+
+    /// Whether the final (non-1xx) response head has been read.
+    fn head_received(&self) -> bool {
+        self.head_end.is_some()
+    }
+
+    /// Whether the retained body already exceeds the cap, so reading on
+    /// would only buffer bytes that get truncated. Hashed bodies are not
+    /// retained and never fill up.
+    fn is_full(&self) -> bool {
+        self.hasher.is_none()
+            && self
+                .head_end
+                .is_some_and(|end| self.raw.len() - end > self.body_cap)
+    }
+
+    /// Buffer `chunk`. Head lines are counted as they arrive, so a server
+    /// that never ends its head is cut off at `max_headers` lines or
+    /// `MAX_HEAD_BYTES`, whichever comes first.
+    fn push(&mut self, chunk: &[u8]) -> Result<(), CaptureError> {
+        if self.head_end.is_some() {
+            if let MessageEnd::Framed {
+                framing: Some(framing),
+                ..
+            } = &mut self.message_end
+            {
+                framing.advance(chunk);
+            }
+            match self.hasher.as_mut() {
+                Some((_, hasher)) => hasher.update(chunk),
+                None => self.raw.extend_from_slice(chunk),
+            }
+            return Ok(());
+        }
+        // A CR ending the previous chunk may pair with an LF starting this one.
+        let mut search = self.raw.len().saturating_sub(1).max(self.line_start);
+        self.raw.extend_from_slice(chunk);
+        let (start, end) = loop {
+            let head = &self.raw[self.head_start..];
+            if !head.starts_with(&b"HTTP/"[..head.len().min(5)]) {
+                return Err(CaptureError::NotHttp {
+                    preview: escape_preview(head),
+                });
+            }
+            let Some(offset) = find_line_end(&self.raw[search..]) else {
+                if self.raw.len() > MAX_HEAD_BYTES {
+                    return Err(CaptureError::HeadTooLarge {
+                        limit: MAX_HEAD_BYTES,
+                    });
+                }
+                return Ok(());
+            };
+            let line = &self.raw[self.line_start..search + offset];
+            if self.line_start == self.head_start {
+                // An unparseable status line ends the read like a final one;
+                // parsing reports it.
+                self.interim_head = parse_status_line(&String::from_utf8_lossy(line))
+                    .is_ok_and(|(_, status_code, _)| is_interim_status(status_code));
+            } else if line.is_empty() {
+                let start = self.head_start;
+                self.head_start = search + offset + 2;
+                self.header_lines = 0;
+                if !self.interim_head {
+                    break (start, self.head_start);
+                }
+            } else if line.contains(&b':') {
+                self.header_lines += 1;
+                if self.header_lines > self.max_headers {
+                    return Err(CaptureError::TooManyHeaders {
+                        limit: self.max_headers,
+                    });
+                }
+            }
+            self.line_start = search + offset + 2;
+            search = self.line_start;
+        };
+        self.head_start = start;
+        self.head_end = Some(end);
+        if let MessageEnd::Framed {
+            head_request,
+            framing,
+        } = &mut self.message_end
+        {
+            let head = String::from_utf8_lossy(&self.raw[start..end]);
+            let mut body_framing = Framing::of(&head, *head_request);
+            body_framing.advance(&self.raw[end..]);
+            *framing = Some(body_framing);
+        }
+        if let Some((_, hasher)) = self.hasher.as_mut() {
+            hasher.update(&self.raw[end..]);
+            self.raw.truncate(end);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> (Vec<u8>, Option<BodyDigest>) {
+        let digest = self.hasher.map(|(algorithm, hasher)| BodyDigest {
+            algorithm,
+            hex: hasher.finalize_hex(),
+        });
+        (self.raw, digest)
+    }
+}
+
+/// Whether a response `head` leaves the connection open for another
+/// request, i.e. it does not carry `Connection: close`.
+fn keeps_connection_open(head: &str) -> bool {
+    !head.split("\r\n").skip(1).any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("connection")
+                && value
+                    .split(',')
+                    .any(|option| option.trim().eq_ignore_ascii_case("close"))
+        })
     })
 }
 
-fn build_tls_config() -> Result<ClientConfig> {
-    let mut root_store = RootCertStore::empty();
-    for cert in load_native_certs().context("failed to load system certificates")? {
-        root_store
-            .add(cert)
-            .map_err(|_| anyhow!("unable to add root certificate"))?;
+impl Framing {
+    /// Framing declared by a final response `head`. Responses to HEAD, and
+    /// 204/304 responses, have no body; `chunked` wins over a length.
+    fn of(head: &str, head_request: bool) -> Self {
+        let mut lines = head.split("\r\n");
+        let status = lines
+            .next()
+            .and_then(|line| parse_status_line(line).ok())
+            .map(|(_, status_code, _)| status_code);
+        if head_request || matches!(status, Some(204 | 304)) {
+            return Framing::Length(0);
+        }
+        let mut length = None;
+        for line in lines {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let name = name.trim();
+            if name.eq_ignore_ascii_case("transfer-encoding")
+                && value.to_ascii_lowercase().contains("chunked")
+            {
+                return Framing::Chunked(ChunkState::Size(Vec::new()));
+            }
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().ok();
+            }
+        }
+        length.map_or(Framing::UntilClose, Framing::Length)
+    }
+
+    /// Account for body bytes read.
+    fn advance(&mut self, bytes: &[u8]) {
+        match self {
+            Framing::Length(remaining) => *remaining = remaining.saturating_sub(bytes.len()),
+            Framing::Chunked(state) => state.advance(bytes),
+            Framing::UntilClose => {}
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        matches!(
+            self,
+            Framing::Length(0) | Framing::Chunked(ChunkState::Done)
+        )
+    }
+}
+
+impl ChunkState {
+    fn advance(&mut self, mut bytes: &[u8]) {
+        while let Some(&byte) = bytes.first() {
+            match self {
+                ChunkState::Data(remaining) | ChunkState::DataEnd(remaining) => {
+                    let taken = (*remaining).min(bytes.len());
+                    *remaining -= taken;
+                    bytes = &bytes[taken..];
+                    if *remaining == 0 {
+                        *self = match self {
+                            ChunkState::Data(_) => ChunkState::DataEnd(2),
+                            _ => ChunkState::Size(Vec::new()),
+                        };
+                    }
+                }
+                ChunkState::Size(line) | ChunkState::Trailer(line) => {
+                    line.push(byte);
+                    bytes = &bytes[1..];
+                    let Some(text) = line.strip_suffix(b"\r\n") else {
+                        continue;
+                    };
+                    let text = String::from_utf8_lossy(text).into_owned();
+                    *self = match self {
+                        ChunkState::Trailer(_) if text.is_empty() => ChunkState::Done,
+                        ChunkState::Trailer(_) => ChunkState::Trailer(Vec::new()),
+                        _ => {
+                            let size = text.split(';').next().unwrap_or_default().trim();
+                            match usize::from_str_radix(size, 16) {
+                                Ok(0) => ChunkState::Trailer(Vec::new()),
+                                Ok(size) => ChunkState::Data(size),
+                                Err(_) => ChunkState::Malformed,
+                            }
+                        }
+                    };
+                }
+                ChunkState::Done | ChunkState::Malformed => return,
+            }
+        }
+    }
+}
+
+/// Client side of the handshake. The default is what a capture uses:
+/// system roots, SNI, every provider suite and no ALPN.
+#[derive(Debug, Clone)]
+pub struct TlsConfigOptions {
+    pub enable_sni: bool,
+    /// Trust anchors; `None` loads the system store.
+    pub roots: Option<RootCertStore>,
+    /// Cipher suites offered, in preference order; empty offers every suite
+    /// the crypto provider supports.
+    pub cipher_suites: Vec<SupportedCipherSuite>,
+    /// Protocols offered via ALPN. Captures speak HTTP/1.1 only, so offering
+    /// anything else breaks them.
+    pub alpn_protocols: Vec<Vec<u8>>,
+}
+
+impl Default for TlsConfigOptions {
+    fn default() -> Self {
+        Self {
+            enable_sni: true,
+            roots: None,
+            cipher_suites: Vec::new(),
+            alpn_protocols: Vec::new(),
+        }
     }
-    let config = ClientConfig::builder()
-        .with_root_certificates(root_store)
+}
+
+/// The cipher suite recorded as `name` in `tls.cipher`, e.g.
+/// `TLS13_AES_128_GCM_SHA256` (case-insensitive).
+pub fn cipher_suite(name: &str) -> Option<SupportedCipherSuite> {
+    ring_provider::DEFAULT_CIPHER_SUITES
+        .iter()
+        .find(|suite| format!("{:?}", suite.suite()).eq_ignore_ascii_case(name.trim()))
+        .copied()
+}
+
+pub fn build_tls_config(options: &TlsConfigOptions) -> Result<ClientConfig> {
+    let roots = match &options.roots {
+        Some(roots) => roots.clone(),
+        None => {
+            let mut root_store = RootCertStore::empty();
+            for cert in load_native_certs().context("failed to load system certificates")? {
+                root_store
+                    .add(cert)
+                    .map_err(|_| anyhow!("unable to add root certificate"))?;
+            }
+            root_store
+        }
+    };
+    let mut provider = ring_provider::default_provider();
+    if !options.cipher_suites.is_empty() {
+        provider.cipher_suites = options.cipher_suites.clone();
+    }
+    let mut config = ClientConfig::builder_with_provider(Arc::new(provider))
+        .with_safe_default_protocol_versions()
+        .context("no protocol version supports the requested cipher suites")?
+        .with_root_certificates(roots)
         .with_no_client_auth();
+    config.enable_sni = options.enable_sni;
+    config.alpn_protocols = options.alpn_protocols.clone();
     Ok(config)
 }
 
-fn build_request(method: &Method, host: &str, target: &str) -> String {
-    format!(
-        "{method} {target} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: {ua}\r\nAccept: */*\r\nConnection: close\r\n\r\n",
+/// Method announced in `Access-Control-Request-Method` on OPTIONS requests.
+const PREFLIGHT_METHOD: &str = "GET";
+
+fn build_request(
+    method: &Method,
+    host: &str,
+    target: &str,
+    profile: Option<BrowserProfile>,
+    origin: Option<&str>,
+    connection: ConnectionMode,
+    cookie: Option<&str>,
+) -> Result<String, CaptureError> {
+    let mut request = format!(
+        "{method} {target} HTTP/1.1\r\nHost: {host}\r\n",
         method = method.as_str(),
-        target = target,
-        host = host,
-        ua = USER_AGENT
-    )
+    );
+    let defaults: &[(&str, &str)] = &[("User-Agent", USER_AGENT), ("Accept", "*/*")];
+    let headers = profile.map_or(defaults, |profile| profile.headers());
+    for (name, value) in headers {
+        request.push_str(&format!("{name}: {value}\r\n"));
+    }
+    if let Some(origin) = origin {
+        if origin.contains(['\r', '\n']) {
+            return Err(CaptureError::InvalidHeaderValue { name: "Origin" });
+        }
+        request.push_str(&format!("Origin: {origin}\r\n"));
+        if *method == Method::OPTIONS {
+            // Make OPTIONS a CORS preflight for a simple GET.
+            request.push_str(&format!(
+                "Access-Control-Request-Method: {PREFLIGHT_METHOD}\r\n"
+            ));
+        }
+    }
+    if let Some(cookie) = cookie {
+        if cookie.contains(['\r', '\n']) {
+            return Err(CaptureError::InvalidHeaderValue { name: "Cookie" });
+        }
+        request.push_str(&format!("Cookie: {cookie}\r\n"));
+    }
+    if connection == ConnectionMode::Close {
+        request.push_str("Connection: close\r\n");
+    }
+    request.push_str("\r\n");
+    Ok(request)
 }
 
 fn parse_http_response(
     raw: &[u8],
     max_body_bytes: usize,
+    max_headers: usize,
+    preserve_case: bool,
 ) -> Result<(HttpResponse, Vec<HeaderEntry>, HeaderMap)> {
-    let split = find_header_split(raw).context("malformed HTTP response")?;
-    let (header_bytes, body_bytes) = raw.split_at(split);
-    let body = &body_bytes[4..];
-    let header_text = String::from_utf8_lossy(header_bytes);
-    let mut lines = header_text.split("\r\n");
-    let status_line = lines.next().ok_or_else(|| anyhow!("missing status line"))?;
-    let (http_version, status_code, reason) = parse_status_line(status_line)?;
-
-    let mut header_entries = Vec::new();
-    for line in lines {
-        if line.trim().is_empty() {
-            continue;
+    let mut rest = raw;
+    let mut early_hints = Vec::new();
+    let (http_version, status_code, reason, mut header_entries, body) = loop {
+        if !rest.starts_with(b"HTTP/") {
+            return Err(CaptureError::NotHttp {
+                preview: escape_preview(rest),
+            }
+            .into());
         }
-        if let Some((name, value)) = line.split_once(':') {
-            header_entries.push(HeaderEntry {
-                name: name.trim().to_ascii_lowercase(),
-                value: value.trim().to_string(),
-            });
+        let split = find_header_split(rest).context("malformed HTTP response")?;
+        let (header_bytes, body_bytes) = rest.split_at(split);
+        let header_text = String::from_utf8_lossy(header_bytes);
+        let mut lines = header_text.split("\r\n");
+        let status_line = lines.next().ok_or_else(|| anyhow!("missing status line"))?;
+        let (http_version, status_code, reason) = parse_status_line(status_line)?;
+        let entries = parse_header_lines(lines, max_headers, preserve_case)?;
+        rest = &body_bytes[4..];
+
+        // Interim responses carry no body; skip to the next status line. 101 is
+        // final since the connection switches protocols after it.
+        if is_interim_status(status_code) {
+            if status_code == 103 {
+                early_hints.extend(entries);
+            }
+            continue;
         }
-    }
+        break (
+            http_version.to_string(),
+            status_code,
+            reason.to_string(),
+            entries,
+            rest,
+        );
+    };
+    // Stable: repeated headers keep their wire order, which is significant
+    // for some (`Set-Cookie`, `Via`).
     header_entries.sort_by(|a, b| a.name.cmp(&b.name));
 
     let mut header_map = HeaderMap::default();
@@ -214,17 +1507,61 @@ fn parse_http_response(
     }
 
     let response = HttpResponse {
-        http_version: http_version.to_string(),
+        http_version,
         status_code,
-        reason: reason.to_string(),
+        reason,
         headers: header_entries.clone(),
+        early_hints,
         body: body_vec,
         body_truncated: truncated,
+        body_digest: None,
     };
 
     Ok((response, header_entries, header_map))
 }
 
+fn escape_preview(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .take(NOT_HTTP_PREVIEW_BYTES)
+        .flat_map(|b| std::ascii::escape_default(*b))
+        .map(char::from)
+        .collect()
+}
+
+fn parse_header_lines<'a>(
+    lines: impl Iterator<Item = &'a str>,
+    max_headers: usize,
+    preserve_case: bool,
+) -> Result<Vec<HeaderEntry>, CaptureError> {
+    let mut entries = Vec::new();
+    for line in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if entries.len() == max_headers {
+                return Err(CaptureError::TooManyHeaders { limit: max_headers });
+            }
+            let name = name.trim();
+            entries.push(HeaderEntry {
+                name: name.to_ascii_lowercase(),
+                value: value.trim().to_string(),
+                wire_name: preserve_case.then(|| name.to_string()),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+fn is_interim_status(status_code: u16) -> bool {
+    (100..200).contains(&status_code) && status_code != 101
+}
+
+fn find_line_end(raw: &[u8]) -> Option<usize> {
+    raw.windows(2).position(|window| window == b"\r\n")
+}
+
 fn find_header_split(raw: &[u8]) -> Option<usize> {
     raw.windows(4).position(|window| window == b"\r\n\r\n")
 }
@@ -241,43 +1578,46 @@ fn parse_status_line(line: &str) -> Result<(&str, u16, &str)> {
     Ok((version, code, reason))
 }
 
-fn canonicalize_handshake(tls: &TlsMetadata, domain: &str) -> Result<Vec<u8>> {
-    #[derive(Serialize)]
-    struct CanonicalHandshake<'a> {
-        domain: &'a str,
-        version: &'a str,
-        cipher: &'a str,
-        alpn: Option<&'a String>,
-        cert_fingerprints: &'a [String],
+/// `json/v2` handshake encoding; any change needs a new canonicalization tag.
+pub(crate) fn canonicalize_handshake(
+    tls: &TlsMetadata,
+    domain: &str,
+    resolved_ips: &[IpAddr],
+    source: Option<&str>,
+) -> Result<Vec<u8>> {
+    HandshakeWitness {
+        domain: domain.to_string(),
+        version: tls.version.clone(),
+        cipher: tls.cipher.clone(),
+        alpn: tls.alpn.clone(),
+        cert_fingerprints: tls.cert_fingerprints.clone(),
+        spki_pins: tls.spki_pins.clone(),
+        subject_alt_names: tls.subject_alt_names.clone(),
+        resolved_ips: resolved_ips.to_vec(),
+        source: source.map(str::to_string),
     }
-
-    serde_json::to_vec(&CanonicalHandshake {
-        domain,
-        version: &tls.version,
-        cipher: &tls.cipher,
-        alpn: tls.alpn.as_ref(),
-        cert_fingerprints: &tls.cert_fingerprints,
-    })
+    .encode()
     .context("failed to canonicalize handshake")
 }
 
-fn canonicalize_app_data(response: &HttpResponse, headers: &[HeaderEntry]) -> Result<Vec<u8>> {
-    #[derive(Serialize)]
-    struct CanonicalAppData<'a> {
-        status_code: u16,
-        reason: &'a str,
-        headers: &'a [HeaderEntry],
-        body_base64: String,
-        body_truncated: bool,
-    }
-
-    serde_json::to_vec(&CanonicalAppData {
+/// `json/v2` response encoding; any change needs a new canonicalization tag.
+pub(crate) fn canonicalize_app_data(
+    response: &HttpResponse,
+    headers: &[HeaderEntry],
+    timing: Option<&TimingTrace>,
+) -> Result<Vec<u8>> {
+    AppDataWitness {
+        http_version: Some(response.http_version.clone()),
         status_code: response.status_code,
-        reason: &response.reason,
-        headers,
+        reason: response.reason.clone(),
+        headers: headers.to_vec(),
+        early_hints: response.early_hints.clone(),
         body_base64: B64.encode(&response.body),
         body_truncated: response.body_truncated,
-    })
+        body_digest: response.body_digest.clone(),
+        timing: timing.cloned(),
+    }
+    .encode()
     .context("failed to canonicalize response")
 }
 
@@ -313,25 +1653,64 @@ fn extract_tls_metadata(conn: &ClientConnection, domain: &str) -> TlsMetadata {
         })
         .unwrap_or_else(|| vec![format!("domain-only:{}", domain)]);
 
+    let cert_chain: Vec<Vec<u8>> = conn
+        .peer_certificates()
+        .map(|certs| certs.iter().map(|cert| cert.as_ref().to_vec()).collect())
+        .unwrap_or_default();
+
+    let embedded_scts = cert_chain
+        .first()
+        .and_then(|leaf| embedded_sct_count(leaf).ok())
+        .unwrap_or_default();
+    let subject_alt_names = cert_chain
+        .first()
+        .and_then(|leaf| subject_alt_names(leaf).ok())
+        .unwrap_or_default();
+
     TlsMetadata {
         version,
         cipher,
         cert_fingerprints: fingerprints,
         alpn,
+        chain_size: CertChainSize::of(&cert_chain),
+        cert_chain,
+        spki_pins: Vec::new(),
+        subject_alt_names,
+        embedded_scts,
     }
 }
+fn install_crypto_provider() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        rustls::crypto::ring::default_provider()
+            .install_default()
+            .expect("install ring crypto provider");
+    });
+}
+
 #[cfg(test)]
 mod tests {
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
     use super::*;
     use http::Method;
+    use rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer};
+    use rustls::{ServerConfig, ServerConnection};
     use serde_json::Value;
+    use tracing::span::{Attributes, Id};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context as LayerContext, Layer, SubscriberExt};
+
+    use crate::resolver::{StaticResolver, SystemResolver};
 
     #[test]
     fn parse_http_response_normalizes_headers_and_body() {
         let raw =
             b"HTTP/1.1 200 OK\r\nServer: Example\r\nX-Test: One\r\nX-Test: Two\r\n\r\nHello body"
                 .to_vec();
-        let (response, headers, map) = parse_http_response(&raw, 1024).expect("parse http");
+        let (response, headers, map) =
+            parse_http_response(&raw, 1024, DEFAULT_MAX_HEADERS, false).expect("parse http");
 
         assert_eq!(response.status_code, 200);
         assert_eq!(response.reason, "OK");
@@ -346,11 +1725,162 @@ mod tests {
     #[test]
     fn parse_http_response_truncates_body_when_needed() {
         let raw = b"HTTP/1.1 200 OK\r\nServer: Example\r\n\r\nHello body".to_vec();
-        let (response, _, _) = parse_http_response(&raw, 4).expect("parse http");
+        let (response, _, _) =
+            parse_http_response(&raw, 4, DEFAULT_MAX_HEADERS, false).expect("parse http");
         assert_eq!(response.body, b"Hell");
         assert!(response.body_truncated);
     }
 
+    #[test]
+    fn parse_http_response_skips_early_hints() {
+        let raw = b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\nHTTP/1.1 200 OK\r\nServer: Example\r\n\r\nHello".to_vec();
+        let (response, headers, map) =
+            parse_http_response(&raw, 1024, DEFAULT_MAX_HEADERS, false).expect("parse http");
+
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.reason, "OK");
+        assert_eq!(headers.len(), 1);
+        assert!(!map.contains_key("link"));
+        assert_eq!(response.early_hints[0].name, "link");
+        assert_eq!(response.body, b"Hello");
+    }
+
+    #[test]
+    fn parse_http_response_caps_header_count() {
+        let mut raw = b"HTTP/1.1 200 OK\r\n".to_vec();
+        for idx in 0..5 {
+            raw.extend_from_slice(format!("X-Flood-{idx}: {idx}\r\n").as_bytes());
+        }
+        raw.extend_from_slice(b"\r\nbody");
+
+        let (response, _, _) = parse_http_response(&raw, 1024, 5, false).expect("at the cap");
+        assert_eq!(response.headers.len(), 5);
+        let err = parse_http_response(&raw, 1024, 4, false).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CaptureError>(),
+            Some(CaptureError::TooManyHeaders { limit: 4 })
+        ));
+    }
+
+    #[test]
+    fn response_buffer_streams_body_into_hasher() {
+        let body = vec![b'x'; 100_000];
+        let mut raw =
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nServer: Example\r\n\r\n".to_vec();
+        let head_len = raw.len();
+        raw.extend_from_slice(&body);
+
+        let mut buffer =
+            ResponseBuffer::new(Some(&HashAlgorithm::Sha256), 16, DEFAULT_MAX_HEADERS, false);
+        for chunk in raw.chunks(7) {
+            buffer.push(chunk).expect("push");
+        }
+        let (head, digest) = buffer.finish();
+        assert_eq!(head.len(), head_len);
+        assert_eq!(
+            digest.expect("digest").hex,
+            format!("{:x}", Sha256::digest(&body))
+        );
+    }
+
+    fn buffered(raw: &[u8], body_cap: usize) -> ResponseBuffer {
+        let mut buffer = ResponseBuffer::new(None, body_cap, DEFAULT_MAX_HEADERS, false);
+        for chunk in raw.chunks(8192) {
+            if buffer.is_full() {
+                break;
+            }
+            buffer.push(chunk).expect("push");
+        }
+        buffer
+    }
+
+    #[test]
+    fn response_buffer_cuts_off_heads_that_never_end() {
+        let mut buffer = ResponseBuffer::new(None, 1024, 4, false);
+        buffer
+            .push(b"HTTP/1.1 103 Early Hints\r\nLink: </a>\r\n\r\nHTTP/1.1 200 OK\r")
+            .expect("interim head");
+        let mut pushed = 0;
+        let err = loop {
+            // Split mid-line so header lines straddle chunks.
+            match buffer.push(b"\nX-Flood: 1\r") {
+                Ok(()) => pushed += 1,
+                Err(err) => break err,
+            }
+        };
+        assert!(matches!(err, CaptureError::TooManyHeaders { limit: 4 }));
+        // The first push only ends the status line; the sixth ends a fifth header.
+        assert_eq!(pushed, 5);
+
+        let mut buffer = ResponseBuffer::new(None, 1024, 4, false);
+        buffer
+            .push(b"HTTP/1.1 200 OK\r\nX-Endless: ")
+            .expect("status line");
+        let chunk = [b'a'; 8192];
+        let err = loop {
+            if let Err(err) = buffer.push(&chunk) {
+                break err;
+            }
+        };
+        assert!(matches!(
+            err,
+            CaptureError::HeadTooLarge {
+                limit: MAX_HEAD_BYTES
+            }
+        ));
+        assert!(buffer.raw.len() <= MAX_HEAD_BYTES + chunk.len());
+    }
+
+    #[test]
+    fn zero_max_body_kb_keeps_bodies_past_the_old_default() {
+        let limit = body_limit(0, DEFAULT_BODY_CEILING_MB).unwrap();
+        assert_eq!(limit, 64 * 1024 * 1024);
+        let body = vec![b'x'; 300 * 1024];
+        let raw = [b"HTTP/1.1 200 OK\r\n\r\n".as_slice(), &body].concat();
+
+        let buffer = buffered(&raw, limit);
+        assert!(!buffer.is_full());
+        let (raw, _) = buffer.finish();
+        let (response, _, _) =
+            parse_http_response(&raw, limit, DEFAULT_MAX_HEADERS, false).expect("parse http");
+        assert_eq!(response.body.len(), body.len());
+        assert!(!response.body_truncated);
+    }
+
+    #[test]
+    fn body_ceiling_bounds_unlimited_and_explicit_limits() {
+        assert_eq!(body_limit(0, 1).unwrap(), 1024 * 1024);
+        assert_eq!(body_limit(4096, 1).unwrap(), 1024 * 1024);
+        assert_eq!(body_limit(4, 1).unwrap(), 4096);
+        assert!(body_limit(usize::MAX, 1).is_err());
+
+        let limit = body_limit(0, 1).unwrap();
+        let head = b"HTTP/1.1 200 OK\r\n\r\n";
+        let raw = [head.as_slice(), &vec![b'x'; 3 * 1024 * 1024]].concat();
+        let buffer = buffered(&raw, limit);
+        assert!(buffer.is_full());
+        let (kept, _) = buffer.finish();
+        assert!(kept.len() <= head.len() + limit + 8192);
+        let (response, _, _) =
+            parse_http_response(&kept, limit, DEFAULT_MAX_HEADERS, false).expect("parse http");
+        assert_eq!(response.body.len(), limit);
+        assert!(response.body_truncated);
+    }
+
+    #[test]
+    fn parse_http_response_flags_non_http_services() {
+        let mut raw = b"SSH-2.0-OpenSSH_9.6\r\n\x00\x01".to_vec();
+        raw.extend_from_slice(&[0xff; 64]);
+        let err = parse_http_response(&raw, 1024, DEFAULT_MAX_HEADERS, false).unwrap_err();
+        match err.downcast_ref::<CaptureError>() {
+            Some(CaptureError::NotHttp { preview }) => {
+                assert!(preview.starts_with("SSH-2.0-OpenSSH_9.6\\r\\n\\x00\\x01"));
+                assert!(preview.len() < 4 * NOT_HTTP_PREVIEW_BYTES + 1);
+            }
+            other => panic!("expected NotHttp, got {other:?}"),
+        }
+    }
+
     #[test]
     fn canonicalize_handshake_outputs_expected_json() {
         let tls = TlsMetadata {
@@ -358,13 +1888,797 @@ mod tests {
             cipher: "TLS_AES_128_GCM_SHA256".into(),
             cert_fingerprints: vec!["sha256:deadbeef".into()],
             alpn: Some("h2".into()),
+            cert_chain: vec![],
+            spki_pins: vec![],
+            subject_alt_names: vec![],
+            chain_size: CertChainSize::default(),
+            embedded_scts: 0,
         };
-        let bytes = canonicalize_handshake(&tls, "example.com").expect("handshake");
+        let bytes = canonicalize_handshake(&tls, "example.com", &[], None).expect("handshake");
         let json: Value = serde_json::from_slice(&bytes).expect("json");
         assert_eq!(json["domain"], "example.com");
         assert_eq!(json["version"], "TLS1.3");
         assert_eq!(json["cipher"], "TLS_AES_128_GCM_SHA256");
         assert_eq!(json["alpn"], "h2");
+        assert!(json.get("resolved_ips").is_none());
+        assert!(json.get("spki_pins").is_none());
+
+        let pinned = TlsMetadata {
+            spki_pins: vec!["pin".into()],
+            subject_alt_names: vec![],
+            ..tls
+        };
+        let bytes = canonicalize_handshake(&pinned, "example.com", &[], None).expect("handshake");
+        let json: Value = serde_json::from_slice(&bytes).expect("json");
+        assert_eq!(json["spki_pins"], serde_json::json!(["pin"]));
+    }
+
+    #[test]
+    fn posture_summarizes_server_via_and_protocols() {
+        let tls = TlsMetadata {
+            version: "TLS1.3".into(),
+            cipher: "TLS13_AES_128_GCM_SHA256".into(),
+            cert_fingerprints: vec![format!("sha256:{}", "ab".repeat(32))],
+            alpn: None,
+            cert_chain: vec![],
+            spki_pins: vec![],
+            subject_alt_names: vec![],
+            chain_size: CertChainSize::default(),
+            embedded_scts: 0,
+        };
+        let raw = b"HTTP/1.1 200 OK\r\nServer: nginx/1.25.3\r\nVia: 1.1 varnish, 1.1 cdn\r\nVia: 2 edge\r\nContent-Length: 2\r\n\r\nok";
+        let record = record_from_response("example.com", tls, true, raw).unwrap();
+        assert_eq!(
+            record.posture(),
+            PostureSummary {
+                server: Some("nginx/1.25.3".into()),
+                via: vec!["1.1 varnish".into(), "1.1 cdn".into(), "2 edge".into()],
+                http_version: "HTTP/1.1".into(),
+                tls_version: "TLS1.3".into(),
+                cipher: Some("TLS13_AES_128_GCM_SHA256".into()),
+            }
+        );
+
+        let plain = record_from_response(
+            "example.com",
+            TlsMetadata::plaintext(),
+            false,
+            b"HTTP/1.1 204 No Content\r\n\r\n",
+        )
+        .unwrap()
+        .posture();
+        assert_eq!(plain.server, None);
+        assert!(plain.via.is_empty());
+        assert_eq!(plain.tls_version, "none");
+        assert_eq!(plain.cipher, None);
+    }
+
+    #[test]
+    fn resolve_records_local_addresses() {
+        let addrs = SystemResolver
+            .resolve("localhost", 443)
+            .expect("resolve localhost");
+        let ips = unique_ips(&addrs);
+        assert!(!ips.is_empty());
+        assert!(ips.iter().all(IpAddr::is_loopback));
+        assert!(ips.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    /// Records the name of every span opened while installed.
+    struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+
+    impl<S: Subscriber> Layer<S> for SpanNames {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _: &Id, _: LayerContext<'_, S>) {
+            self.0.lock().unwrap().push(attrs.metadata().name());
+        }
+    }
+
+    #[test]
+    fn handshake_opens_its_span() {
+        install_crypto_provider();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Accept and hang up so the handshake fails fast.
+        let server = std::thread::spawn(move || drop(listener.accept()));
+
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(SpanNames(names.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let connected = Connected {
+                tcp: TcpStream::connect(addr).unwrap(),
+                started: Instant::now(),
+                connect: Duration::ZERO,
+            };
+            let result = super::handshake(
+                Arc::new(build_tls_config(&offline_tls()).unwrap()),
+                ServerName::try_from("localhost").unwrap(),
+                connected,
+                &Deadline::new(None),
+                Duration::from_secs(5),
+            );
+            assert!(result.is_err());
+        });
+        server.join().unwrap();
+
+        assert!(names.lock().unwrap().contains(&"handshake"));
+    }
+
+    #[test]
+    fn build_request_applies_browser_profile_and_origin() {
+        let request = build_request(
+            &Method::GET,
+            "example.com",
+            "/",
+            Some(BrowserProfile::Chrome),
+            Some("https://app.example.com"),
+            ConnectionMode::Close,
+            None,
+        )
+        .unwrap();
+        assert!(request.starts_with("GET / HTTP/1.1\r\nHost: example.com\r\n"));
+        assert!(request.contains("\r\nSec-Fetch-Mode: navigate\r\n"));
+        assert!(request.contains("\r\nSec-Fetch-Dest: document\r\n"));
+        assert!(request.contains("Chrome/124"));
+        assert!(!request.contains(USER_AGENT));
+        assert!(request.ends_with("Origin: https://app.example.com\r\nConnection: close\r\n\r\n"));
+
+        let plain = build_request(
+            &Method::HEAD,
+            "example.com",
+            "/x",
+            None,
+            None,
+            ConnectionMode::Close,
+            None,
+        )
+        .unwrap();
+        assert!(plain.contains(USER_AGENT));
+        assert!(!plain.contains("Sec-Fetch"));
+
+        let err = build_request(
+            &Method::GET,
+            "h",
+            "/",
+            None,
+            Some("a\r\nX-Evil: 1"),
+            ConnectionMode::Close,
+            None,
+        );
+        assert!(matches!(
+            err,
+            Err(CaptureError::InvalidHeaderValue { name: "Origin" })
+        ));
+    }
+
+    #[test]
+    fn repeated_headers_keep_their_wire_order() {
+        let canonical = |raw: &[u8]| {
+            let (response, headers, _) =
+                parse_http_response(raw, 1024, DEFAULT_MAX_HEADERS, false).expect("response");
+            canonicalize_app_data(&response, &headers, None).expect("canonical")
+        };
+        let interleaved = canonical(
+            b"HTTP/1.1 200 OK\r\nSet-Cookie: b=2\r\nVary: Origin\r\nSet-Cookie: a=1\r\n\r\n",
+        );
+        let grouped = canonical(
+            b"HTTP/1.1 200 OK\r\nVary: Origin\r\nSet-Cookie: b=2\r\nSet-Cookie: a=1\r\n\r\n",
+        );
+        assert_eq!(interleaved, grouped);
+
+        let reordered = canonical(
+            b"HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\nVary: Origin\r\nSet-Cookie: b=2\r\n\r\n",
+        );
+        assert_ne!(interleaved, reordered);
+        let value: serde_json::Value = serde_json::from_slice(&interleaved).expect("json");
+        let cookies: Vec<_> = value["headers"]
+            .as_array()
+            .expect("headers")
+            .iter()
+            .filter(|header| header["name"] == "set-cookie")
+            .map(|header| header["value"].as_str().expect("value"))
+            .collect();
+        assert_eq!(cookies, ["b=2", "a=1"]);
+    }
+
+    #[test]
+    fn handshake_errors_keep_the_tls_reason() {
+        let wrap = |err: rustls::Error| {
+            handshake_error(std::io::Error::new(ErrorKind::InvalidData, err)).to_string()
+        };
+        assert_eq!(
+            wrap(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::Expired
+            )),
+            "TLS handshake failed: server certificate expired"
+        );
+        assert_eq!(
+            wrap(rustls::Error::AlertReceived(
+                rustls::AlertDescription::ProtocolVersion
+            )),
+            "TLS handshake failed: server sent protocol_version alert (no TLS version in common)"
+        );
+
+        let reset = handshake_error(std::io::Error::from(ErrorKind::ConnectionReset));
+        assert!(matches!(reset, CaptureError::Handshake(_)));
+    }
+
+    #[test]
+    fn options_with_origin_is_a_preflight() {
+        let request = build_request(
+            &Method::OPTIONS,
+            "api.example.com",
+            "/items",
+            None,
+            Some("https://app.example.com"),
+            ConnectionMode::Close,
+            None,
+        )
+        .unwrap();
+        assert!(request.starts_with("OPTIONS /items HTTP/1.1\r\n"));
+        assert!(request.ends_with(
+            "Origin: https://app.example.com\r\nAccess-Control-Request-Method: GET\r\nConnection: close\r\n\r\n"
+        ));
+
+        let bare = build_request(
+            &Method::OPTIONS,
+            "api.example.com",
+            "*",
+            None,
+            None,
+            ConnectionMode::Close,
+            None,
+        )
+        .unwrap();
+        assert!(!bare.contains("Access-Control-Request-Method"));
+        let delete = build_request(
+            &Method::DELETE,
+            "api.example.com",
+            "/items/1",
+            None,
+            None,
+            ConnectionMode::Close,
+            None,
+        );
+        assert!(delete.unwrap().starts_with("DELETE /items/1 HTTP/1.1\r\n"));
+    }
+
+    /// Answers `connections` connections with a plaintext HTTP response,
+    /// whatever the client sends. The URL is `http://`.
+    fn plaintext_server(connections: usize) -> (Url, std::thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = http_url(&listener);
+        let handle = std::thread::spawn(move || {
+            for _ in 0..connections {
+                let (mut conn, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = conn.read(&mut buf);
+                let _ = conn.write_all(b"HTTP/1.1 200 OK\r\nServer: plain\r\n\r\nok");
+            }
+        });
+        (url, handle)
+    }
+
+    fn http_url(listener: &TcpListener) -> Url {
+        Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap()
+    }
+
+    fn local_options(url: Url) -> CaptureOptions {
+        CaptureOptions {
+            url,
+            method: Method::GET,
+            max_body_bytes: 1024,
+            max_headers: DEFAULT_MAX_HEADERS,
+            timeout: Some(Duration::from_secs(5)),
+            deadline: Deadline::default(),
+            record_dns: false,
+            record_spki: false,
+            stream_body_hash: None,
+            warm: false,
+            no_sni: false,
+            cipher_suites: Vec::new(),
+            browser_profile: None,
+            origin: None,
+            connection: ConnectionMode::Close,
+            allow_plaintext_fallback: false,
+            salvage_on_timeout: false,
+            commit_timing: false,
+            preserve_header_case: false,
+            request_script: None,
+            resolver: Arc::new(SystemResolver),
+        }
+    }
+
+    #[test]
+    fn salvages_headers_when_body_read_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = http_url(&listener);
+        // The body stalls after a few bytes.
+        let server = std::thread::spawn(move || {
+            let (mut conn, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = conn.read(&mut buf);
+            let _ = conn
+                .write_all(b"HTTP/1.1 200 OK\r\nServer: slow\r\nContent-Length: 100\r\n\r\npart");
+            std::thread::sleep(Duration::from_millis(800));
+        });
+        let options = CaptureOptions {
+            timeout: Some(Duration::from_millis(200)),
+            salvage_on_timeout: true,
+            ..local_options(url)
+        };
+
+        let record = capture(&options).expect("salvaged capture");
+        assert!(record.response.body_truncated);
+        assert_eq!(record.response.body, b"part");
+        assert!(record.headers.contains_key("server"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn keep_alive_omits_close_and_stops_at_content_length() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = http_url(&listener);
+        // The server never closes the connection, so the read must end on
+        // Content-Length.
+        let server = std::thread::spawn(move || {
+            let (mut conn, _) = listener.accept().unwrap();
+            conn.set_read_timeout(Some(Duration::from_secs(10)))
+                .unwrap();
+            let mut buf = [0u8; 4096];
+            let read = conn.read(&mut buf).unwrap_or(0);
+            let request = String::from_utf8_lossy(&buf[..read]).into_owned();
+            let _ =
+                conn.write_all(b"HTTP/1.1 200 OK\r\nServer: plain\r\nContent-Length: 2\r\n\r\nok");
+            // Hold the connection until the client hangs up.
+            while matches!(conn.read(&mut buf), Ok(n) if n > 0) {}
+            request
+        });
+        let options = CaptureOptions {
+            connection: ConnectionMode::KeepAlive,
+            ..local_options(url)
+        };
+
+        let record = capture(&options).expect("keep-alive capture");
+        assert_eq!(record.response.body, b"ok");
+        assert_eq!(record.connection, Some(ConnectionMode::KeepAlive));
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET / HTTP/1.1\r\n"), "{request}");
+        assert!(!request.contains("Connection: close"), "{request}");
+    }
+
+    #[test]
+    fn chunked_framing_ends_after_trailers() {
+        let head = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Length: 99";
+        let mut framing = Framing::of(head, false);
+        for part in [&b"4;ext=1\r\nwi"[..], b"ki\r\n0\r\n", b"X-Trailer: 1\r\n"] {
+            framing.advance(part);
+            assert!(!framing.is_done());
+        }
+        framing.advance(b"\r\n");
+        assert!(framing.is_done());
+        assert!(Framing::of("HTTP/1.1 200 OK\r\nContent-Length: 5", true).is_done());
+        assert!(Framing::of("HTTP/1.1 304 Not Modified", false).is_done());
+    }
+
+    #[test]
+    fn request_script_commits_to_selected_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = http_url(&listener);
+        // One connection for the whole script. The login response sets a
+        // session cookie; the account response echoes the request line and
+        // the cookie it was sent.
+        let server = std::thread::spawn(move || {
+            let (mut conn, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let read = conn.read(&mut buf).unwrap_or(0);
+            let login = String::from_utf8_lossy(&buf[..read]).into_owned();
+            let _ = conn.write_all(
+                b"HTTP/1.1 200 OK\r\nSet-Cookie: session=abc; HttpOnly\r\nContent-Length: 2\r\n\r\nok",
+            );
+            let read = conn.read(&mut buf).unwrap_or(0);
+            let account = String::from_utf8_lossy(&buf[..read]).into_owned();
+            let mut lines = account.split("\r\n");
+            let line = lines.next().unwrap_or_default();
+            let cookie = lines
+                .find(|line| line.starts_with("Cookie:"))
+                .unwrap_or("no cookie");
+            let _ = conn.write_all(
+                format!("HTTP/1.1 200 OK\r\nServer: plain\r\n\r\n{line}\n{cookie}").as_bytes(),
+            );
+            login
+        });
+        let script = RequestScript::parse("GET /login\nHEAD /account?tab=1\n", Some(2)).unwrap();
+        let options = CaptureOptions {
+            request_script: Some(script),
+            ..local_options(url.clone())
+        };
+
+        let record = capture(&options).expect("scripted capture");
+        assert_eq!(
+            record.response.body,
+            b"HEAD /account?tab=1 HTTP/1.1\nCookie: session=abc"
+        );
+        assert_eq!(record.method, Method::HEAD);
+        assert_eq!(record.requested_url, url.join("/account?tab=1").unwrap());
+        assert_eq!(
+            record.request_sequence,
+            Some(RequestSequenceAnnotation {
+                count: 2,
+                selected: 2,
+            })
+        );
+        let login = server.join().unwrap();
+        assert!(!login.contains("Connection: close"), "{login}");
+    }
+
+    #[test]
+    fn static_resolver_redirects_hostname_to_local_server() {
+        let (url, server) = plaintext_server(1);
+        let port = url.port().unwrap();
+        let mut resolver = StaticResolver::new(Box::new(SystemResolver));
+        resolver.insert("proof.test", port, "127.0.0.1".parse().unwrap());
+        let options = CaptureOptions {
+            record_dns: true,
+            record_spki: false,
+            resolver: Arc::new(resolver),
+            ..local_options(Url::parse(&format!("http://proof.test:{port}/")).unwrap())
+        };
+
+        let record = capture(&options).expect("capture via static resolver");
+        server.join().unwrap();
+        assert_eq!(record.domain, "proof.test");
+        // The override is not what the name resolved to, so it is not
+        // recorded as a resolution.
+        assert!(record.resolved_ips.is_empty());
+        let handshake: Value = serde_json::from_slice(&record.canonical_handshake).unwrap();
+        assert!(handshake.get("resolved_ips").is_none());
+        assert_eq!(record.response.body, b"ok");
+    }
+
+    #[test]
+    fn unsafe_methods_are_never_sent_twice() {
+        let delete = |warm, allow_plaintext_fallback| CaptureOptions {
+            method: Method::DELETE,
+            warm,
+            allow_plaintext_fallback,
+            // Nothing listens here; a refused capture never connects.
+            ..local_options(Url::parse("http://127.0.0.1:9/items/1").unwrap())
+        };
+        for (options, flag) in [
+            (delete(true, false), "--warm"),
+            (delete(false, true), "--allow-plaintext-fallback"),
+        ] {
+            let Err(err) = capture(&options) else {
+                panic!("{flag} captured a DELETE");
+            };
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "{flag} would send DELETE more than once; only GET, HEAD and OPTIONS \
+                     requests are re-sent"
+                )
+            );
+        }
+        assert!(local_options(Url::parse("http://127.0.0.1:9/").unwrap())
+            .refuse_resending("--warm")
+            .is_ok());
+    }
+
+    #[test]
+    fn plaintext_fallback_is_off_by_default() {
+        let (mut url, server) = plaintext_server(1);
+        url.set_scheme("https").unwrap();
+        let Err(err) = capture(&local_options(url)) else {
+            panic!("TLS capture of a plaintext server succeeded");
+        };
+        server.join().unwrap();
+        assert!(is_tls_unavailable(&err), "{err:#}");
+    }
+
+    #[test]
+    fn plaintext_fallback_retries_without_tls() {
+        let (mut url, server) = plaintext_server(2);
+        url.set_scheme("https").unwrap();
+        let options = CaptureOptions {
+            allow_plaintext_fallback: true,
+            ..local_options(url)
+        };
+        let record = capture(&options).expect("plaintext capture");
+        server.join().unwrap();
+
+        assert_eq!(record.tls.version, TlsProofContext::PLAINTEXT);
+        assert!(record.tls.cert_fingerprints.is_empty());
+        assert_eq!(record.response.status_code, 200);
+        assert_eq!(record.response.body, b"ok");
+    }
+
+    #[test]
+    fn preserved_header_casing_is_committed() {
+        let raw = b"HTTP/1.1 200 OK\r\nX-Powered-By: PHP\r\ncontent-TYPE: text/plain\r\n\r\nok";
+        let (response, _, map) =
+            parse_http_response(raw, 1024, DEFAULT_MAX_HEADERS, true).expect("parse http");
+        let spellings = response
+            .headers
+            .iter()
+            .map(|h| (h.name.as_str(), h.wire_name.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spellings,
+            [
+                ("content-type", Some("content-TYPE")),
+                ("x-powered-by", Some("X-Powered-By")),
+            ]
+        );
+        assert!(map.contains_key("x-powered-by"));
+
+        let (url, server) = plaintext_server(1);
+        let options = CaptureOptions {
+            preserve_header_case: true,
+            ..local_options(url)
+        };
+        let record = capture(&options).expect("plaintext capture");
+        server.join().unwrap();
+        let app: Value = serde_json::from_slice(&record.canonical_app_data).unwrap();
+        assert_eq!(
+            app["headers"][0],
+            serde_json::json!({"name": "server", "value": "plain", "wire_name": "Server"})
+        );
+    }
+
+    #[test]
+    fn commit_timing_adds_trace_to_app_data() {
+        let (url, server) = plaintext_server(2);
+        let canonical_timing = |commit_timing| {
+            let options = CaptureOptions {
+                commit_timing,
+                ..local_options(url.clone())
+            };
+            let record = capture(&options).expect("plaintext capture");
+            let app: Value = serde_json::from_slice(&record.canonical_app_data).unwrap();
+            (app.get("timing").cloned(), record.timing.unwrap().trace)
+        };
+        let (committed, trace) = canonical_timing(true);
+        let (uncommitted, _) = canonical_timing(false);
+        server.join().unwrap();
+
+        assert_eq!(committed, Some(serde_json::to_value(&trace).unwrap()));
+        assert_eq!(trace.handshake_ms, 0);
+        assert!(trace.total_ms >= trace.ttfb_ms);
+        assert_eq!(uncommitted, None);
+    }
+
+    #[test]
+    fn deadline_clamps_and_expires() {
+        let unbounded = Deadline::new(None);
+        assert_eq!(
+            unbounded.clamp(Duration::from_secs(5)).unwrap(),
+            Duration::from_secs(5)
+        );
+
+        let generous = Deadline::new(Some(Duration::from_secs(3600)));
+        assert_eq!(
+            generous.clamp(Duration::from_secs(5)).unwrap(),
+            Duration::from_secs(5)
+        );
+        assert!(generous.clamp(Duration::MAX).unwrap() <= Duration::from_secs(3600));
+
+        let spent = Deadline::new(Some(Duration::ZERO));
+        assert!(matches!(
+            spent.check(),
+            Err(CaptureError::Timeout {
+                phase: TimeoutPhase::Total
+            })
+        ));
+    }
+
+    #[test]
+    fn huge_budget_is_unbounded() {
+        let huge = Deadline::new(Some(Duration::MAX));
+        assert_eq!(
+            huge.clamp(Duration::from_secs(5)).unwrap(),
+            Duration::from_secs(5)
+        );
+    }
+
+    #[test]
+    fn retry_sharing_an_expired_deadline_gives_up() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("https://{}/", listener.local_addr().unwrap())).unwrap();
+        // Accepts and then says nothing, so the first attempt spends the
+        // whole budget waiting for the handshake.
+        let server = std::thread::spawn(move || {
+            let (conn, _) = listener.accept().unwrap();
+            std::thread::sleep(Duration::from_millis(600));
+            drop(conn);
+        });
+        let options = CaptureOptions {
+            deadline: Deadline::new(Some(Duration::from_millis(300))),
+            ..local_options(url)
+        };
+
+        let total = |result: Result<CaptureRecord>| {
+            let Err(err) = result else {
+                panic!("capture outlived its deadline");
+            };
+            matches!(
+                err.downcast_ref::<CaptureError>(),
+                Some(CaptureError::Timeout {
+                    phase: TimeoutPhase::Total
+                })
+            )
+        };
+        assert!(total(capture(&options)));
+        // The retry gets no budget of its own and never connects.
+        let started = Instant::now();
+        assert!(total(capture(&options)));
+        assert!(started.elapsed() < Duration::from_millis(100));
+        server.join().unwrap();
+    }
+
+    const ROOT_DER: &[u8] = include_bytes!("../testdata/root.der");
+    const LOCALHOST_DER: &[u8] = include_bytes!("../testdata/localhost.der");
+    const LOCALHOST_KEY_DER: &[u8] = include_bytes!("../testdata/localhost.key.der");
+    /// `localhost` leaf under its own root whose SCT list extension carries
+    /// two (unsigned) timestamps.
+    const SCT_ROOT_DER: &[u8] = include_bytes!("../testdata/sct-root.der");
+    const LOCALHOST_SCT_DER: &[u8] = include_bytes!("../testdata/localhost-sct.der");
+    const LOCALHOST_SCT_KEY_DER: &[u8] = include_bytes!("../testdata/localhost-sct.key.der");
+
+    fn offline_tls() -> TlsConfigOptions {
+        TlsConfigOptions {
+            roots: Some(RootCertStore::empty()),
+            ..TlsConfigOptions::default()
+        }
+    }
+
+    /// Handshake `config` against an in-memory `localhost` server that
+    /// accepts every suite and offers `http/1.1`.
+    fn handshake(config: ClientConfig) -> ClientConnection {
+        handshake_serving(config, LOCALHOST_DER, LOCALHOST_KEY_DER)
+    }
+
+    /// [`handshake`] with the server presenting `cert` (signed by `key`).
+    fn handshake_serving(config: ClientConfig, cert: &[u8], key: &[u8]) -> ClientConnection {
+        install_crypto_provider();
+        let key = PrivatePkcs8KeyDer::from(key.to_vec());
+        let mut server_config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![CertificateDer::from(cert.to_vec())], key.into())
+            .unwrap();
+        server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        let mut server = ServerConnection::new(Arc::new(server_config)).unwrap();
+        let mut client =
+            ClientConnection::new(Arc::new(config), ServerName::try_from("localhost").unwrap())
+                .unwrap();
+        while client.is_handshaking() || server.is_handshaking() {
+            let mut flight = Vec::new();
+            client.write_tls(&mut flight).unwrap();
+            server.read_tls(&mut flight.as_slice()).unwrap();
+            server.process_new_packets().unwrap();
+            flight.clear();
+            server.write_tls(&mut flight).unwrap();
+            client.read_tls(&mut flight.as_slice()).unwrap();
+            client.process_new_packets().unwrap();
+        }
+        client
+    }
+
+    #[test]
+    fn plaintext_fallback_never_retries_certificate_errors() {
+        install_crypto_provider();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // A real TLS server whose certificate no system root trusts, as a
+        // man in the middle would present.
+        let server = std::thread::spawn(move || {
+            let key = PrivatePkcs8KeyDer::from(LOCALHOST_KEY_DER.to_vec());
+            let config = ServerConfig::builder()
+                .with_no_client_auth()
+                .with_single_cert(vec![CertificateDer::from(LOCALHOST_DER)], key.into())
+                .unwrap();
+            let (mut tcp, _) = listener.accept().unwrap();
+            let mut conn = ServerConnection::new(Arc::new(config)).unwrap();
+            while conn.is_handshaking() && conn.complete_io(&mut tcp).is_ok() {}
+            // A fallback would open a second connection.
+            listener.set_nonblocking(true).unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+            listener.accept().is_ok()
+        });
+        let options = CaptureOptions {
+            allow_plaintext_fallback: true,
+            ..local_options(Url::parse(&format!("https://localhost:{port}/")).unwrap())
+        };
+
+        let Err(err) = capture(&options) else {
+            panic!("capture accepted an untrusted certificate");
+        };
+        assert!(
+            matches!(
+                err.downcast_ref::<CaptureError>(),
+                Some(CaptureError::TlsHandshake { .. })
+            ),
+            "{err:#}"
+        );
+        assert!(!server.join().unwrap(), "fell back to plaintext");
+    }
+
+    #[test]
+    fn records_scts_embedded_in_the_served_leaf() {
+        let mut roots = RootCertStore::empty();
+        roots.add(CertificateDer::from(SCT_ROOT_DER)).unwrap();
+        let options = TlsConfigOptions {
+            roots: Some(roots),
+            ..TlsConfigOptions::default()
+        };
+        let client = handshake_serving(
+            build_tls_config(&options).unwrap(),
+            LOCALHOST_SCT_DER,
+            LOCALHOST_SCT_KEY_DER,
+        );
+        assert_eq!(extract_tls_metadata(&client, "localhost").embedded_scts, 2);
+
+        let mut roots = RootCertStore::empty();
+        roots.add(CertificateDer::from(ROOT_DER)).unwrap();
+        let options = TlsConfigOptions {
+            roots: Some(roots),
+            ..TlsConfigOptions::default()
+        };
+        let client = handshake(build_tls_config(&options).unwrap());
+        assert_eq!(extract_tls_metadata(&client, "localhost").embedded_scts, 0);
+    }
+
+    #[test]
+    fn records_the_leaf_subject_alt_names() {
+        let mut roots = RootCertStore::empty();
+        roots.add(CertificateDer::from(ROOT_DER)).unwrap();
+        let options = TlsConfigOptions {
+            roots: Some(roots),
+            ..TlsConfigOptions::default()
+        };
+        let client = handshake(build_tls_config(&options).unwrap());
+        let tls = extract_tls_metadata(&client, "localhost");
+        assert_eq!(tls.subject_alt_names, vec!["localhost".to_string()]);
+        let handshake = canonicalize_handshake(&tls, "localhost", &[], None).unwrap();
+        let handshake: serde_json::Value = serde_json::from_slice(&handshake).unwrap();
+        assert_eq!(
+            handshake["subject_alt_names"],
+            serde_json::json!(["localhost"])
+        );
+    }
+
+    #[test]
+    fn tls_config_can_omit_sni() {
+        install_crypto_provider();
+        assert!(build_tls_config(&offline_tls()).unwrap().enable_sni);
+        let no_sni = TlsConfigOptions {
+            enable_sni: false,
+            ..offline_tls()
+        };
+        assert!(!build_tls_config(&no_sni).unwrap().enable_sni);
+    }
+
+    #[test]
+    fn restricted_cipher_list_fixes_negotiated_suite() {
+        let mut roots = RootCertStore::empty();
+        roots.add(CertificateDer::from(ROOT_DER)).unwrap();
+        for (name, version) in [
+            ("TLS13_CHACHA20_POLY1305_SHA256", "TLS1.3"),
+            ("TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256", "TLS1.2"),
+        ] {
+            let options = TlsConfigOptions {
+                roots: Some(roots.clone()),
+                cipher_suites: vec![cipher_suite(name).unwrap()],
+                alpn_protocols: vec![b"http/1.1".to_vec()],
+                ..TlsConfigOptions::default()
+            };
+            let client = handshake(build_tls_config(&options).unwrap());
+            let tls = extract_tls_metadata(&client, "localhost");
+            assert_eq!(tls.cipher, name);
+            assert_eq!(tls.version, version);
+            assert_eq!(tls.alpn.as_deref(), Some("http/1.1"));
+        }
+        assert!(cipher_suite("tls13_aes_128_gcm_sha256").is_some());
+        assert!(cipher_suite("TLS_RSA_WITH_RC4_128_MD5").is_none());
     }
 
     #[test]
@@ -373,24 +2687,38 @@ mod tests {
             requested_url: Url::parse("https://example.com").unwrap(),
             domain: "example.com".into(),
             method: Method::GET,
-            captured_at: Utc::now(),
+            captured_at: Some(Utc::now()),
             tls: TlsMetadata {
                 version: String::new(),
                 cipher: String::new(),
                 cert_fingerprints: vec![],
                 alpn: None,
+                cert_chain: vec![],
+                spki_pins: vec![],
+                subject_alt_names: vec![],
+                chain_size: CertChainSize::default(),
+                embedded_scts: 0,
             },
             response: HttpResponse {
                 http_version: "HTTP/1.1".into(),
                 status_code: 200,
                 reason: "OK".into(),
                 headers: vec![],
+                early_hints: vec![],
                 body: vec![],
                 body_truncated: false,
+                body_digest: None,
             },
             canonical_handshake: b"handshake".to_vec(),
             canonical_app_data: b"app".to_vec(),
             headers: HeaderMap::new(),
+            resolved_ips: vec![],
+            timing: None,
+            sni_sent: true,
+            browser_profile: None,
+            origin: None,
+            connection: None,
+            request_sequence: None,
         };
 
         let transcript = record.transcript();
@@ -398,12 +2726,3 @@ mod tests {
         assert_eq!(transcript.app_data, b"app");
     }
 }
-
-fn install_crypto_provider() {
-    static INIT: Once = Once::new();
-    INIT.call_once(|| {
-        rustls::crypto::ring::default_provider()
-            .install_default()
-            .expect("install ring crypto provider");
-    });
-}
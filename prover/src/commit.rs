@@ -1,22 +1,77 @@
-use redproof_artifact::{CommitmentAlgorithm, CommitmentSet, CommitmentWitness, EncodedBlob};
-use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::thread;
+
+use anyhow::{anyhow, Result};
+use redproof_artifact::{
+    CommitmentAlgorithm, CommitmentDigests, CommitmentSet, CommitmentWitness, EncodedBlob,
+    HeaderCommitment, CANONICALIZATION_JSON_V2, HEADER_NONCE_BYTES,
+};
+use ring::rand::{SecureRandom, SystemRandom};
 
 pub struct Transcript {
     pub handshake: Vec<u8>,
     pub app_data: Vec<u8>,
 }
 
+/// Length of the random salt drawn by [`random_salt`].
+pub const SALT_BYTES: usize = 32;
+
+/// Transcripts at least this large (handshake and app data together) are
+/// hashed on one thread per digest; below it spawning costs more than it
+/// saves.
+pub const PARALLEL_HASH_THRESHOLD: usize = 1 << 20;
+
+/// Commit to the transcript under every requested algorithm. The first entry
+/// becomes the primary commitment; the rest are recorded as alternates. A
+/// non-empty `salt` is prepended to both transcripts before hashing.
 pub fn build_commitments(
     transcript: &Transcript,
-    algorithm: CommitmentAlgorithm,
+    algorithms: &[CommitmentAlgorithm],
+    include_witness: bool,
+    salt: &[u8],
+) -> CommitmentSet {
+    let parallel =
+        transcript.handshake.len() + transcript.app_data.len() >= PARALLEL_HASH_THRESHOLD;
+    commit(transcript, algorithms, include_witness, salt, parallel)
+}
+
+fn commit(
+    transcript: &Transcript,
+    algorithms: &[CommitmentAlgorithm],
     include_witness: bool,
+    salt: &[u8],
+    parallel: bool,
 ) -> CommitmentSet {
-    let handshake = hash_bytes(&algorithm, &transcript.handshake);
-    let app_data = hash_bytes(&algorithm, &transcript.app_data);
+    let algorithm = algorithms
+        .first()
+        .copied()
+        .unwrap_or(CommitmentAlgorithm::Blake3);
+    let mut order = vec![algorithm];
+    for alt in algorithms {
+        if !order.contains(alt) {
+            order.push(*alt);
+        }
+    }
+    let mut digests = digest_all(&order, transcript, salt, parallel).into_iter();
+    let (handshake, app_data) = digests.next().expect("primary digests");
+    let alternates = order[1..]
+        .iter()
+        .zip(digests)
+        .map(|(alt, (handshake, app_data))| {
+            (
+                *alt,
+                CommitmentDigests {
+                    handshake,
+                    app_data,
+                },
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
     let witness = if include_witness {
         Some(CommitmentWitness {
             handshake: EncodedBlob::from_bytes(&transcript.handshake),
             app_data: EncodedBlob::from_bytes(&transcript.app_data),
+            header_nonces: Vec::new(),
         })
     } else {
         None
@@ -24,37 +79,230 @@ pub fn build_commitments(
 
     CommitmentSet {
         algorithm,
+        canonicalization: CANONICALIZATION_JSON_V2.to_string(),
+        salt: (!salt.is_empty()).then(|| EncodedBlob::from_bytes(salt)),
         handshake,
         app_data,
+        alternates,
+        headers: None,
         witness,
+        statement: None,
+        witness_digest: None,
     }
 }
 
-fn hash_bytes(algo: &CommitmentAlgorithm, data: &[u8]) -> EncodedBlob {
-    match algo {
-        CommitmentAlgorithm::Blake3 => {
-            let digest = blake3::hash(data);
-            EncodedBlob::from_bytes(digest.as_bytes())
-        }
-        CommitmentAlgorithm::Sha256 => {
-            let digest = Sha256::digest(data);
-            EncodedBlob::from_bytes(&digest)
-        }
+/// Handshake and app-data digests under each of `algorithms`, in order.
+/// With `parallel`, every digest gets its own scoped thread.
+fn digest_all(
+    algorithms: &[CommitmentAlgorithm],
+    transcript: &Transcript,
+    salt: &[u8],
+    parallel: bool,
+) -> Vec<(EncodedBlob, EncodedBlob)> {
+    let hash_bytes = |algo: CommitmentAlgorithm, data: &[u8]| {
+        EncodedBlob::from_bytes(&algo.digest_salted(salt, data))
+    };
+    if !parallel {
+        return algorithms
+            .iter()
+            .map(|algo| {
+                (
+                    hash_bytes(*algo, &transcript.handshake),
+                    hash_bytes(*algo, &transcript.app_data),
+                )
+            })
+            .collect();
     }
+    thread::scope(|scope| {
+        let handles = algorithms
+            .iter()
+            .map(|algo| {
+                (
+                    scope.spawn(move || hash_bytes(*algo, &transcript.handshake)),
+                    scope.spawn(move || hash_bytes(*algo, &transcript.app_data)),
+                )
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|(handshake, app_data)| {
+                (
+                    handshake.join().expect("handshake digest thread"),
+                    app_data.join().expect("app-data digest thread"),
+                )
+            })
+            .collect()
+    })
+}
+
+pub fn random_salt() -> Result<Vec<u8>> {
+    random_bytes(SALT_BYTES)
+}
+
+/// Commit to the response `headers` for selective disclosure, revealing
+/// those named in `disclose`. Every leaf gets a fresh random nonce; the
+/// witness, when embedded, keeps the nonces of the undisclosed ones.
+pub fn commit_headers(
+    commitments: &mut CommitmentSet,
+    headers: &[(String, String)],
+    disclose: &[String],
+) -> Result<()> {
+    let nonces = headers
+        .iter()
+        .map(|_| random_bytes(HEADER_NONCE_BYTES))
+        .collect::<Result<Vec<_>>>()?;
+    commitments.headers = Some(HeaderCommitment::build(
+        commitments.algorithm,
+        &commitments.salt_bytes()?,
+        headers,
+        &nonces,
+        disclose,
+    ));
+    if let Some(witness) = &mut commitments.witness {
+        witness.header_nonces = nonces
+            .iter()
+            .map(|nonce| EncodedBlob::from_bytes(nonce))
+            .collect();
+    }
+    Ok(())
+}
+
+fn random_bytes(len: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| anyhow!("system random number generator failed"))?;
+    Ok(bytes)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn hash_bytes(algo: &CommitmentAlgorithm, data: &[u8]) -> EncodedBlob {
+        EncodedBlob::from_bytes(&algo.digest(data))
+    }
+
     #[test]
     fn blake3_commitments_differ() {
         let transcript = Transcript {
             handshake: b"handshake".to_vec(),
             app_data: b"app".to_vec(),
         };
-        let commitments = build_commitments(&transcript, CommitmentAlgorithm::Blake3, true);
+        let commitments = build_commitments(&transcript, &[CommitmentAlgorithm::Blake3], true, &[]);
         assert_ne!(commitments.handshake.0, commitments.app_data.0);
         assert!(commitments.witness.is_some());
     }
+
+    #[test]
+    fn extra_algorithms_become_alternates() {
+        let transcript = Transcript {
+            handshake: b"handshake".to_vec(),
+            app_data: b"app".to_vec(),
+        };
+        let commitments = build_commitments(
+            &transcript,
+            &[CommitmentAlgorithm::Blake3, CommitmentAlgorithm::Sha256],
+            false,
+            &[],
+        );
+        assert_eq!(commitments.algorithm, CommitmentAlgorithm::Blake3);
+        let sha = commitments
+            .digests_for(CommitmentAlgorithm::Sha256)
+            .expect("sha256 alternate");
+        assert_eq!(
+            sha.handshake,
+            hash_bytes(&CommitmentAlgorithm::Sha256, b"handshake")
+        );
+    }
+
+    #[test]
+    fn parallel_and_sequential_commitments_match() {
+        let body = (0..PARALLEL_HASH_THRESHOLD as u32 * 3)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let transcript = Transcript {
+            handshake: b"handshake".to_vec(),
+            app_data: body,
+        };
+        let algorithms = [CommitmentAlgorithm::Sha256, CommitmentAlgorithm::Blake3];
+        let salt = random_salt().unwrap();
+        let parallel = commit(&transcript, &algorithms, false, &salt, true);
+        let sequential = commit(&transcript, &algorithms, false, &salt, false);
+        assert_eq!(parallel, sequential);
+        assert_eq!(
+            build_commitments(&transcript, &algorithms, false, &salt),
+            sequential
+        );
+    }
+
+    #[test]
+    fn salted_commitments_of_identical_data_differ_and_verify() {
+        let transcript = Transcript {
+            handshake: b"handshake".to_vec(),
+            app_data: b"app".to_vec(),
+        };
+        let algorithms = [CommitmentAlgorithm::Blake3, CommitmentAlgorithm::Sha256];
+        let first = build_commitments(&transcript, &algorithms, true, &random_salt().unwrap());
+        let second = build_commitments(&transcript, &algorithms, true, &random_salt().unwrap());
+        assert_ne!(first.salt, second.salt);
+        assert_ne!(first.handshake, second.handshake);
+        assert_ne!(first.app_data, second.app_data);
+        for commitments in [&first, &second] {
+            commitments.validate().expect("valid set");
+            for algorithm in algorithms {
+                commitments
+                    .verify_witness(algorithm)
+                    .expect("salted witness");
+            }
+        }
+
+        let unsalted = build_commitments(&transcript, &algorithms, true, &[]);
+        assert!(unsalted.salt.is_none());
+        let mut stripped = first.clone();
+        stripped.salt = None;
+        assert!(stripped
+            .verify_witness(CommitmentAlgorithm::Blake3)
+            .is_err());
+    }
+
+    #[test]
+    fn registered_committer_is_usable_end_to_end() {
+        struct Reversed;
+        impl redproof_artifact::Committer for Reversed {
+            fn algorithm_tag(&self) -> &str {
+                "reversed-sha256"
+            }
+            fn hash(&self, data: &[u8]) -> Vec<u8> {
+                let mut digest = CommitmentAlgorithm::Sha256.digest(data);
+                digest.reverse();
+                digest
+            }
+        }
+
+        let reversed = redproof_artifact::committer::register(Box::new(Reversed)).unwrap();
+        let transcript = Transcript {
+            handshake: b"handshake".to_vec(),
+            app_data: b"app".to_vec(),
+        };
+        let commitments = build_commitments(
+            &transcript,
+            &[reversed, CommitmentAlgorithm::Blake3],
+            true,
+            &random_salt().unwrap(),
+        );
+        let json = serde_json::to_value(&commitments).unwrap();
+        assert_eq!(json["algorithm"], "reversed-sha256");
+
+        let parsed: CommitmentSet = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, commitments);
+        parsed.validate().expect("valid set");
+        assert!(CommitmentAlgorithm::registered().contains(&reversed));
+        for algorithm in parsed.algorithms() {
+            parsed.verify_witness(algorithm).expect("witness verifies");
+        }
+        let mut tampered = parsed;
+        tampered.app_data = hash_bytes(&CommitmentAlgorithm::Sha256, b"app");
+        assert!(tampered.verify_witness(reversed).is_err());
+    }
 }
@@ -0,0 +1,384 @@
+//! Upgrading artifacts written by older releases: schema migration plus
+//! re-encoding the witness transcripts in the current `json/v2` form, which
+//! recomputes the commitments when the bytes or the tag change, and binding
+//! the statement to them.
+
+use std::net::IpAddr;
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use redproof_artifact::{
+    CertChainSize, RedProofArtifact, TimingTrace, CANONICALIZATION_JSON_V1,
+    CANONICALIZATION_JSON_V2,
+};
+use redproof_statements::evaluate::{evaluate, EvalReason};
+use redproof_statements::witness::{AppDataWitness, HandshakeWitness};
+use serde::Deserialize;
+
+use crate::capture::{
+    canonicalize_app_data, canonicalize_handshake, BodyDigest, HeaderEntry, HttpResponse,
+    TlsMetadata,
+};
+use crate::commit::{build_commitments, commit_headers, Transcript};
+
+/// Handshake transcript as any 1.x release wrote it.
+#[derive(Deserialize)]
+struct LegacyHandshake {
+    domain: String,
+    version: String,
+    cipher: String,
+    alpn: Option<String>,
+    cert_fingerprints: Vec<String>,
+    #[serde(default)]
+    spki_pins: Vec<String>,
+    #[serde(default)]
+    subject_alt_names: Vec<String>,
+    #[serde(default)]
+    resolved_ips: Vec<IpAddr>,
+    source: Option<String>,
+}
+
+/// Response transcript as any 1.x release wrote it.
+#[derive(Deserialize)]
+struct LegacyAppData {
+    /// Only committed since `json/v2`; the unsigned annotation of older
+    /// artifacts is not carried into the commitments.
+    #[serde(default)]
+    http_version: String,
+    status_code: u16,
+    #[serde(default)]
+    reason: String,
+    headers: Vec<HeaderEntry>,
+    #[serde(default)]
+    early_hints: Vec<HeaderEntry>,
+    #[serde(default)]
+    body_base64: String,
+    #[serde(default)]
+    body_truncated: bool,
+    body_digest: Option<BodyDigest>,
+    timing: Option<TimingTrace>,
+}
+
+/// Migrate `artifact` and re-canonicalize it from its witness. Returns the
+/// upgraded artifact and one line per change; no lines means it was already
+/// current. Refuses commitment-only artifacts, which have nothing to
+/// re-encode from, witnesses that do not reproduce the existing commitments,
+/// and statements the re-canonicalized response no longer satisfies.
+pub fn fix_artifact(mut artifact: RedProofArtifact) -> Result<(RedProofArtifact, Vec<String>)> {
+    let Some(witness) = artifact.commitments.witness.clone() else {
+        bail!("artifact has no witness to re-canonicalize from; re-capture it instead");
+    };
+    let canonicalization = artifact.commitments.canonicalization.as_str();
+    if canonicalization != CANONICALIZATION_JSON_V1 && canonicalization != CANONICALIZATION_JSON_V2
+    {
+        bail!(
+            "unsupported canonicalization '{}'",
+            artifact.commitments.canonicalization
+        );
+    }
+    // New commitments must not launder an edited witness or statement.
+    for algorithm in artifact.commitments.algorithms() {
+        artifact
+            .commitments
+            .verify_witness(algorithm)
+            .context("witness does not match the artifact's commitments")?;
+    }
+    artifact.commitments.verify_statement(&artifact.statement)?;
+    let mut changes = artifact.migrate()?;
+
+    let handshake_bytes = witness.handshake.decode()?;
+    let handshake: LegacyHandshake = serde_json::from_slice(&handshake_bytes)
+        .context("witness handshake is not canonical JSON")?;
+    if !handshake.domain.eq_ignore_ascii_case(&artifact.domain) {
+        bail!(
+            "witness handshake is for '{}', not '{}'",
+            handshake.domain,
+            artifact.domain
+        );
+    }
+    let tls = TlsMetadata {
+        version: handshake.version,
+        cipher: handshake.cipher,
+        cert_fingerprints: handshake.cert_fingerprints,
+        alpn: handshake.alpn,
+        cert_chain: Vec::new(),
+        spki_pins: handshake.spki_pins,
+        subject_alt_names: handshake.subject_alt_names,
+        chain_size: CertChainSize::default(),
+        embedded_scts: 0,
+    };
+    let canonical_handshake = canonicalize_handshake(
+        &tls,
+        &handshake.domain,
+        &handshake.resolved_ips,
+        handshake.source.as_deref(),
+    )?;
+
+    let app_data_bytes = witness.app_data.decode()?;
+    let app_data: LegacyAppData = serde_json::from_slice(&app_data_bytes)
+        .context("witness app data is not canonical JSON")?;
+    let normalize = |headers: Vec<HeaderEntry>| {
+        headers
+            .into_iter()
+            .map(|header| HeaderEntry {
+                name: header.name.trim().to_ascii_lowercase(),
+                value: header.value.trim().to_string(),
+                wire_name: header.wire_name,
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut headers = normalize(app_data.headers);
+    headers.sort_by(|a, b| a.name.cmp(&b.name));
+    let response = HttpResponse {
+        http_version: app_data.http_version,
+        status_code: app_data.status_code,
+        reason: app_data.reason,
+        headers,
+        early_hints: normalize(app_data.early_hints),
+        body: B64
+            .decode(&app_data.body_base64)
+            .context("witness body is not base64")?,
+        body_truncated: app_data.body_truncated,
+        body_digest: app_data.body_digest,
+    };
+    let canonical_app_data =
+        canonicalize_app_data(&response, &response.headers, app_data.timing.as_ref())?;
+    let evaluation = {
+        let witnessed_handshake: HandshakeWitness = serde_json::from_slice(&canonical_handshake)?;
+        let witnessed_app: AppDataWitness = serde_json::from_slice(&canonical_app_data)?;
+        // The record `--recompute-all` builds, so a fixed artifact is one
+        // it accepts.
+        let record = artifact
+            .witness_record(
+                &witnessed_handshake,
+                &witnessed_app,
+                &canonical_handshake,
+                &canonical_app_data,
+            )
+            .map_err(anyhow::Error::msg)?;
+        evaluate(&artifact.statement, &record)
+    };
+    if evaluation.reason == Some(EvalReason::NotCommitted) {
+        bail!(
+            "statement cannot be re-evaluated from the witness: {}",
+            evaluation.details.unwrap_or_default()
+        );
+    }
+    if !evaluation.satisfied {
+        match evaluation.details {
+            Some(details) => bail!("witness does not satisfy the statement: {details}"),
+            None => bail!("witness does not satisfy the statement"),
+        }
+    }
+
+    if canonical_handshake != handshake_bytes
+        || canonical_app_data != app_data_bytes
+        || artifact.commitments.canonicalization != CANONICALIZATION_JSON_V2
+    {
+        if !artifact.meta.provenance.is_empty() {
+            bail!("re-canonicalizing would invalidate the signed provenance entries");
+        }
+        let salt = artifact.commitments.salt_bytes()?;
+        let transcript = Transcript {
+            handshake: canonical_handshake,
+            app_data: canonical_app_data,
+        };
+        let mut commitments =
+            build_commitments(&transcript, &artifact.commitments.algorithms(), true, &salt);
+        if let Some(headers) = &artifact.commitments.headers {
+            let disclosed = headers
+                .revealed
+                .iter()
+                .map(|header| header.name.clone())
+                .collect::<Vec<_>>();
+            let pairs = response
+                .headers
+                .iter()
+                .map(|header| (header.name.clone(), header.value.clone()))
+                .collect::<Vec<_>>();
+            commit_headers(&mut commitments, &pairs, &disclosed)?;
+        }
+        artifact.commitments = commitments;
+        changes.push("commitments: re-canonicalized the witness and recomputed digests".into());
+    }
+    // Binding after signed provenance would invalidate it.
+    if artifact.commitments.statement.is_none() && artifact.meta.provenance.is_empty() {
+        artifact.bind_statement()?;
+        changes.push("commitments.statement: bound the statement to the app-data digest".into());
+    }
+
+    let annotations = &mut artifact.meta.annotations;
+    if annotations.status_code.is_none() {
+        annotations.status_code = Some(response.status_code);
+        changes.push("annotations.status_code: filled from the witness".into());
+    }
+    if annotations.header_count.is_none() {
+        annotations.header_count = Some(response.headers.len());
+        changes.push("annotations.header_count: filled from the witness".into());
+    }
+    if annotations.body_truncated.is_none() {
+        annotations.body_truncated = Some(response.body_truncated);
+        changes.push("annotations.body_truncated: filled from the witness".into());
+    }
+    artifact.validate_strict()?;
+    Ok((artifact, changes))
+}
+
+#[cfg(test)]
+mod tests {
+    use redproof_artifact::{CommitmentAlgorithm, CommitmentSet, EncodedBlob, TimingAnnotation};
+    use redproof_statements::Statement;
+    use serde_json::json;
+
+    use super::*;
+    use crate::prove_from_response;
+
+    const RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nServer: nginx\r\nContent-Length: 2\r\n\r\nok";
+
+    fn current() -> RedProofArtifact {
+        let tls = TlsMetadata {
+            version: "TLS1.3".into(),
+            cipher: "TLS13_AES_128_GCM_SHA256".into(),
+            cert_fingerprints: vec![format!("sha256:{}", "ab".repeat(32))],
+            alpn: None,
+            cert_chain: vec![],
+            spki_pins: vec![],
+            subject_alt_names: vec![],
+            chain_size: CertChainSize::default(),
+            embedded_scts: 0,
+        };
+        let statement = Statement::HeaderAbsent {
+            target: "X-Powered-By".into(),
+        };
+        prove_from_response(
+            "example.com",
+            tls,
+            true,
+            RESPONSE,
+            &statement,
+            CommitmentAlgorithm::Sha256,
+        )
+        .unwrap()
+    }
+
+    /// `current` as an early 1.x release wrote it: bare version, header
+    /// names as sent, pretty-printed transcripts and no typed annotations.
+    fn legacy() -> RedProofArtifact {
+        let mut artifact = current();
+        artifact.version = "1".into();
+        artifact.meta.annotations = Default::default();
+        artifact.commitments.canonicalization = CANONICALIZATION_JSON_V1.into();
+        let app_data = json!({
+            "status_code": 200,
+            "reason": "OK",
+            "headers": [
+                {"name": "Server", "value": "nginx"},
+                {"name": "Content-Length", "value": "2"}
+            ],
+            "body_base64": "b2s=",
+            "body_truncated": false
+        });
+        let witness = artifact.commitments.witness.as_mut().unwrap();
+        witness.app_data = EncodedBlob::from_bytes(&serde_json::to_vec_pretty(&app_data).unwrap());
+        let transcript = Transcript {
+            handshake: witness.handshake.decode().unwrap(),
+            app_data: witness.app_data.decode().unwrap(),
+        };
+        artifact.commitments =
+            build_commitments(&transcript, &[CommitmentAlgorithm::Sha256], true, &[]);
+        artifact.commitments.canonicalization = CANONICALIZATION_JSON_V1.into();
+        artifact
+    }
+
+    #[test]
+    fn re_canonicalizes_legacy_witness() {
+        let (fixed, changes) = fix_artifact(legacy()).unwrap();
+        assert_eq!(changes[0], "version: 1 -> 1.0");
+        assert!(changes
+            .iter()
+            .any(|change| change.starts_with("commitments:")));
+        // The legacy witness never committed the HTTP version, so the
+        // annotation is not laundered into the new commitments.
+        let current = current().commitments;
+        assert_eq!(fixed.commitments.handshake, current.handshake);
+        let app_data = |commitments: &CommitmentSet| {
+            let bytes = commitments
+                .witness
+                .as_ref()
+                .unwrap()
+                .app_data
+                .decode()
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+        };
+        let mut expected = app_data(&current);
+        expected.as_object_mut().unwrap().remove("http_version");
+        assert_eq!(app_data(&fixed.commitments), expected);
+        assert_eq!(fixed.commitments.canonicalization, CANONICALIZATION_JSON_V2);
+        assert_eq!(fixed.meta.annotations.status_code, Some(200));
+        assert_eq!(fixed.meta.annotations.header_count, Some(2));
+
+        let (again, changes) = fix_artifact(fixed.clone()).unwrap();
+        assert_eq!(again, fixed);
+        assert!(changes.is_empty(), "{changes:?}");
+    }
+
+    #[test]
+    fn refuses_tampered_witness() {
+        let mut artifact = legacy();
+        let witness = artifact.commitments.witness.as_mut().unwrap();
+        let mut app_data: serde_json::Value =
+            serde_json::from_slice(&witness.app_data.decode().unwrap()).unwrap();
+        app_data["status_code"] = 500.into();
+        witness.app_data = EncodedBlob::from_bytes(&serde_json::to_vec(&app_data).unwrap());
+        let err = fix_artifact(artifact).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "witness does not match the artifact's commitments"
+        );
+    }
+
+    #[test]
+    fn refuses_witness_that_no_longer_satisfies_the_statement() {
+        let mut artifact = legacy();
+        artifact.statement = Statement::HeaderPresent {
+            target: "X-Powered-By".into(),
+        };
+        let err = fix_artifact(artifact).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("witness does not satisfy the statement"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn decides_statements_on_the_record_recompute_builds() {
+        // An uncommitted timing annotation vouches for nothing, here or
+        // under `--recompute-all`.
+        let mut artifact = legacy();
+        artifact.statement = Statement::ResponseTimeUnder { max_ms: 1000 };
+        artifact.meta.annotations.timing = Some(TimingAnnotation {
+            cold_ttfb_ms: 10,
+            warm_ttfb_ms: None,
+            statement_uses: "cold".into(),
+            attested_by: "prover".into(),
+            trace: None,
+        });
+        let err = fix_artifact(artifact).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("statement cannot be re-evaluated from the witness"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn refuses_artifact_without_witness() {
+        let mut artifact = legacy();
+        artifact.commitments.witness = None;
+        let err = fix_artifact(artifact).unwrap_err();
+        assert!(err.to_string().contains("no witness"), "{err}");
+    }
+}
@@ -0,0 +1,13 @@
+//! Capture, statement evaluation and commitment building behind the
+//! `redproof-prover` CLI, usable on their own: [`prove_from_response`] mints
+//! an artifact from a response captured by another tool.
+
+pub mod capture;
+pub mod commit;
+pub mod evaluate;
+pub mod fix;
+pub mod mint;
+pub mod resolver;
+pub mod script;
+
+pub use mint::{build_artifact, prove_from_response, ArtifactOptions};
@@ -1,179 +1,725 @@
-mod capture;
-mod commit;
-mod evaluate;
+mod annotate;
+mod args;
+mod batch;
+mod retry;
+mod watch;
 
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{bail, Context, Result};
 use base64::engine::general_purpose::STANDARD as B64;
 use base64::Engine;
 use chrono::{DateTime, Utc};
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand};
 use redproof_artifact::{
-    ArtifactMeta, CommitmentAlgorithm, CommitmentSet, EncodedBlob, RedProofArtifact,
-    TlsProofContext,
+    ArtifactFormat, CaptureAnnotations, CommitmentAlgorithm, PostureSummary, RedProofArtifact,
 };
+use redproof_logging::LogFormat;
 use redproof_statements::{parse_statement, Statement};
+use rustls::SupportedCipherSuite;
 use serde::Serialize;
-use serde_json::{json, Map, Value};
+use serde_json::{Map, Value};
 use url::Url;
 
-use crate::capture::{capture, CaptureOptions, CaptureRecord};
-use crate::commit::build_commitments;
-use crate::evaluate::{evaluate, StatementEvaluation};
+use crate::annotate::{
+    parse_annotation, parse_extract, BodyExtracts, EnvAnnotations, StaticAnnotations,
+};
+use crate::args::{
+    parse_hash_alg, parse_tls_cipher, parse_validity, BlobEncodingArg, BrowserProfileArg,
+    EvalModeArg, MethodArg, OutputFormat, PreviewFormat,
+};
+use crate::batch::{BatchOutcome, BatchTarget};
+use crate::retry::{RetryPolicy, StatusRetries};
+use crate::watch::WatchOptions;
+use redproof_prover::capture::{
+    self, body_limit, capture, CaptureOptions, CaptureRecord, ConnectionMode, Deadline,
+    DEFAULT_BODY_CEILING_MB, DEFAULT_MAX_HEADERS,
+};
+use redproof_prover::commit::{build_commitments, commit_headers, random_salt};
+use redproof_prover::evaluate::{evaluate_with, StatementEvaluation};
+use redproof_prover::fix::fix_artifact;
+use redproof_prover::resolver::{parse_resolve_entry, Resolver, StaticResolver, SystemResolver};
+use redproof_prover::script::RequestScript;
+use redproof_prover::{build_artifact, ArtifactOptions};
 
 #[derive(Parser, Debug)]
 #[command(
     name = "redproof-prover",
-    about = "Capture HTTPS responses and emit RedProof artifacts."
+    about = "Capture HTTPS responses and emit RedProof artifacts.",
+    subcommand_negates_reqs = true
 )]
 struct Cli {
-    #[arg(long)]
-    url: String,
+    #[arg(long, required_unless_present = "batch")]
+    url: Option<String>,
 
-    #[arg(long)]
-    prove: String,
+    #[arg(long, required_unless_present = "batch")]
+    prove: Option<String>,
+
+    /// JSON Schema for a bare `--prove json:schema` (also applied to batch
+    /// targets proving a bare `json:schema`).
+    #[arg(long, value_name = "FILE")]
+    schema_file: Option<PathBuf>,
 
+    /// Artifact path; `-` writes to stdout.
     #[arg(long, default_value = "proof.red")]
     out: PathBuf,
 
     #[arg(long, default_value_t = MethodArg::Get)]
     method: MethodArg,
 
-    #[arg(long, default_value_t = HashAlgArg::Blake3)]
-    hash_alg: HashAlgArg,
+    /// Capture even when the statement cannot hold for the method, e.g. a
+    /// body statement against a `HEAD` request, which has no body.
+    #[arg(long)]
+    allow_incompatible: bool,
 
-    #[arg(long, default_value_t = ArtifactFormat::Json)]
-    format: ArtifactFormat,
+    /// Commitment algorithms, comma-separated; the first one is primary.
+    #[arg(long, value_delimiter = ',', default_value = "blake3", value_parser = parse_hash_alg)]
+    hash_alg: Vec<CommitmentAlgorithm>,
+
+    /// Artifact encoding: `json`, `cbor`, or `auto` to follow the `--out`
+    /// extension (`.cbor` is CBOR, anything else JSON).
+    #[arg(long, visible_alias = "out-format", default_value_t = OutputFormat::Auto)]
+    format: OutputFormat,
 
+    /// Body bytes kept, in KiB; `0` keeps the whole body. Either way
+    /// `--max-body-ceiling-mb` caps it.
     #[arg(long, default_value_t = 256)]
     max_body_kb: usize,
 
+    /// Absolute cap on the retained body, in MiB. Reading stops once the
+    /// body passes it, and the artifact records `body_truncated`.
+    #[arg(long, default_value_t = DEFAULT_BODY_CEILING_MB)]
+    max_body_ceiling_mb: usize,
+
+    /// Reject responses with more header lines than this; the read stops as
+    /// soon as the cap is passed. Heads over 1 MiB are rejected as well.
+    #[arg(long, default_value_t = DEFAULT_MAX_HEADERS)]
+    max_headers: usize,
+
     #[arg(long)]
     timeout_secs: Option<u64>,
 
+    /// Wall-clock budget in seconds for the whole capture, including the
+    /// `--warm` request; per-read timeouts are shortened to fit it.
+    #[arg(long = "capture-timeout-total", value_name = "SECS")]
+    capture_timeout_total: Option<u64>,
+
     #[arg(long)]
     dry_run: bool,
-}
 
-#[derive(Copy, Clone, Debug, ValueEnum)]
-enum MethodArg {
-    Get,
-    Head,
-}
+    /// Whether `all`/`any` composites stop at the first deciding member or
+    /// evaluate every leaf and list each result in the details.
+    #[arg(long, value_enum, default_value_t)]
+    eval_mode: EvalModeArg,
 
-impl MethodArg {
-    fn to_http(self) -> http::Method {
-        match self {
-            MethodArg::Get => http::Method::GET,
-            MethodArg::Head => http::Method::HEAD,
-        }
-    }
-}
+    /// Show the first N body bytes in the `--dry-run` preview (UTF-8 text,
+    /// or hex for binary). The full `body_base64` needs `-v`.
+    #[arg(long, value_name = "N", requires = "dry_run")]
+    sample_body_bytes: Option<usize>,
+
+    /// Serialization of the `--dry-run` preview.
+    #[arg(long, value_enum, default_value_t, requires = "dry_run")]
+    preview_format: PreviewFormat,
+
+    /// Omit per-run fields (capture time, timing data, status retries) so
+    /// identical server behavior yields byte-identical artifacts.
+    #[arg(long)]
+    deterministic: bool,
+
+    /// RFC 3339 timestamp recorded as `time_utc` in deterministic mode
+    /// (defaults to the Unix epoch).
+    #[arg(long, requires = "deterministic")]
+    source_date: Option<DateTime<Utc>>,
+
+    /// Declare how long the proof stays meaningful (e.g. `30d`); verifiers
+    /// reject the artifact afterwards.
+    #[arg(long, value_parser = parse_validity, conflicts_with = "deterministic")]
+    expires_in: Option<chrono::Duration>,
+
+    /// Prepend a random per-artifact salt to the transcripts and header leaves
+    /// before hashing, so two artifacts of the same response cannot be linked
+    /// by their commitments; pair with `--no-witness` or
+    /// `--output-witness-separate`, as an embedded witness links them anyway.
+    /// The salt is stored in the artifact.
+    #[arg(long, conflicts_with = "deterministic")]
+    salt_commitments: bool,
+
+    /// Embed the server's DER certificate chain so verifiers can re-validate
+    /// it against a trust store.
+    #[arg(long)]
+    embed_certs: bool,
+
+    /// Base64 alphabet for digests, witness and other blobs; `url-safe`
+    /// suits artifacts embedded in URLs or JWTs.
+    #[arg(long, value_enum, default_value_t = BlobEncodingArg::Standard)]
+    blob_encoding: BlobEncodingArg,
+
+    /// Record and commit the addresses the host resolved to (prover-attested).
+    #[arg(long)]
+    record_dns: bool,
+
+    /// Record and commit the SPKI SHA-256 pin (`pin-sha256`) of each server
+    /// certificate.
+    #[arg(long)]
+    record_spki: bool,
+
+    /// Leave the transcript witness out of the artifact. For a GET proving
+    /// `hash:eq`, the body is then hashed while streaming instead of buffered.
+    #[arg(long)]
+    no_witness: bool,
+
+    /// Write the witness to a `.witness` sidecar next to `--out` instead of
+    /// embedding it; the artifact records the sidecar's digest. Verify with
+    /// `redproof-verify --witness`.
+    #[arg(long, conflicts_with = "no_witness")]
+    output_witness_separate: bool,
+
+    /// Commit to each response header separately and reveal the named one
+    /// (repeatable); pair with `--no-witness` to disclose it without the body.
+    /// Each header is committed under a random nonce, so it conflicts with
+    /// `--deterministic`.
+    #[arg(
+        long = "disclose-header",
+        value_name = "NAME",
+        conflicts_with = "deterministic"
+    )]
+    disclose_headers: Vec<String>,
+
+    /// Send a throwaway request first and record both cold and warm TTFB;
+    /// timing statements use the warm figure. GET, HEAD and OPTIONS only.
+    #[arg(long)]
+    warm: bool,
+
+    /// Send the requests in FILE (`METHOD /path?query` per line) in order
+    /// against the `--url` origin, over one connection and carrying cookies
+    /// between steps, and commit to one response; `--method` is ignored.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["warm", "batch"])]
+    request_script: Option<PathBuf>,
+
+    /// 1-based position in `--request-script` of the committed response;
+    /// defaults to the last.
+    #[arg(long, value_name = "N", requires = "request_script")]
+    script_select: Option<usize>,
+
+    /// Connect without sending SNI, for servers that route differently
+    /// without it. IP-literal hosts never send SNI.
+    #[arg(long)]
+    no_sni: bool,
+
+    /// Offer only these cipher suites, in preference order (repeatable or
+    /// comma-separated), named as recorded in `tls.cipher`, e.g.
+    /// `TLS13_AES_128_GCM_SHA256`. Pins the negotiated suite for
+    /// reproducible captures.
+    #[arg(long = "tls-cipher", value_name = "SUITE", value_delimiter = ',', value_parser = parse_tls_cipher)]
+    tls_ciphers: Vec<SupportedCipherSuite>,
+
+    /// Send a browser's navigation headers (`Sec-Fetch-*`, User-Agent, ...)
+    /// for servers that gate on them.
+    #[arg(long, value_enum)]
+    browser_profile: Option<BrowserProfileArg>,
+
+    /// Header consulted by `cache:hit` statements that name none
+    /// (repeatable, in order); replaces the default `X-Cache`,
+    /// `CF-Cache-Status`, `Age`.
+    #[arg(long = "cache-header", value_name = "NAME")]
+    cache_headers: Vec<String>,
+
+    /// Send this `Origin` request header.
+    #[arg(long)]
+    origin: Option<String>,
+
+    /// Omit `Connection: close` and stop reading at the end of the response's
+    /// `Content-Length` or chunked framing, for servers that misbehave when
+    /// asked to close. The connection mode is recorded in the artifact.
+    #[arg(long)]
+    keep_alive: bool,
 
-#[derive(Copy, Clone, Debug, ValueEnum)]
-enum HashAlgArg {
-    Blake3,
-    Sha256,
+    /// If the server does not speak TLS (or shares no TLS version), retry
+    /// over plaintext HTTP; certificate errors are never retried. The artifact
+    /// then records `tls.version = "none"` and proves nothing about the domain.
+    #[arg(long)]
+    allow_plaintext_fallback: bool,
+
+    /// If a read times out after the response headers arrived, keep what was
+    /// read (marked truncated) instead of failing, so header statements can
+    /// still be proven against a stalling body.
+    #[arg(long)]
+    salvage_on_timeout: bool,
+
+    /// Record and commit each response header name as the server spelled
+    /// it (`wire_name`), for `header:casing` statements. Evaluation still
+    /// matches names case-insensitively.
+    #[arg(long)]
+    header_case_preserve: bool,
+
+    /// Re-capture while the response status is one of these (comma-separated,
+    /// e.g. `502,503,504`), up to three times and within
+    /// `--capture-timeout-total`. The statuses seen are recorded as
+    /// `status_retries` and `status_sequence` annotations, except under
+    /// `--deterministic`. A failed statement
+    /// is never a reason to retry. Only GET, HEAD and OPTIONS are re-sent.
+    #[arg(long, value_name = "STATUS", value_delimiter = ',')]
+    retry_on_status: Vec<u16>,
+
+    /// Commit the connect/handshake/TTFB/total timing trace along with the
+    /// response, so edits to the recorded timing break the commitment.
+    #[arg(long, conflicts_with = "deterministic")]
+    commit_timing: bool,
+
+    /// Connect to ADDR for HOST:PORT instead of resolving it (repeatable),
+    /// e.g. `example.com:443:203.0.113.5`.
+    #[arg(long, value_name = "HOST:PORT:ADDR", value_parser = parse_resolve_entry)]
+    resolve: Vec<(String, u16, IpAddr)>,
+
+    /// Attach a custom `KEY=VALUE` annotation (repeatable). Numbers and
+    /// booleans keep their type; `REDPROOF_ANNOTATE_<KEY>` variables are read
+    /// too, and this flag wins on conflict.
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_annotation)]
+    annotate: Vec<(String, Value)>,
+
+    /// Record a value from a JSON response body as annotation NAME
+    /// (repeatable). POINTER is a JSON pointer (`/build/version`) or a
+    /// `$.build.version` path; values that do not resolve are recorded as
+    /// `null` with a warning.
+    #[arg(
+        long,
+        visible_alias = "json-pointer-extract",
+        value_name = "NAME=POINTER",
+        value_parser = parse_extract
+    )]
+    extract: Vec<(String, String)>,
+
+    /// Evaluate each `{"url": ..., "prove": ...}` line of this NDJSON file and
+    /// print one JSON result line per capture as it completes. No artifacts
+    /// are written.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["url", "prove", "dry_run"])]
+    batch: Option<PathBuf>,
+
+    /// Concurrent captures in `--batch` mode.
+    #[arg(long, default_value_t = 4)]
+    jobs: usize,
+
+    /// Format of diagnostic logs on stderr.
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Log more (repeatable: -v info, -vv debug, -vvv trace).
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log less (repeatable: -q errors only, -qq silent).
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "verbose")]
+    quiet: u8,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-impl From<HashAlgArg> for CommitmentAlgorithm {
-    fn from(value: HashAlgArg) -> Self {
-        match value {
-            HashAlgArg::Blake3 => CommitmentAlgorithm::Blake3,
-            HashAlgArg::Sha256 => CommitmentAlgorithm::Sha256,
-        }
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Re-capture `--url` every interval and write a timestamped artifact
+    /// next to `--out` only when the response changed. Prints one JSON line
+    /// per change or failed capture.
+    Watch {
+        /// Time between captures (e.g. `30s`, `5m`, `1h`).
+        #[arg(long, default_value = "5m", value_parser = parse_validity)]
+        interval: chrono::Duration,
+
+        /// Stop after this many captures instead of running forever.
+        #[arg(long)]
+        count: Option<usize>,
+
+        /// Header left out of change detection (repeatable); volatile
+        /// headers would otherwise make every capture a change.
+        #[arg(long = "ignore-header", value_name = "NAME", default_value = "date")]
+        ignore_headers: Vec<String>,
+    },
+    /// Work on existing artifacts.
+    Artifact {
+        #[command(subcommand)]
+        command: ArtifactCommand,
+    },
 }
 
-#[derive(Copy, Clone, Debug, ValueEnum)]
-enum ArtifactFormat {
-    Json,
-    Cbor,
+#[derive(Subcommand, Debug)]
+enum ArtifactCommand {
+    /// Upgrade an artifact written by an older release: migrate its schema
+    /// and re-canonicalize the witness, recomputing commitments if the
+    /// encoding changed. Refuses artifacts without a witness.
+    Fix {
+        /// Artifact to upgrade, JSON or CBOR.
+        input: PathBuf,
+
+        /// Where to write the upgraded artifact; defaults to `input`.
+        #[arg(long)]
+        out: Option<PathBuf>,
+
+        /// Output encoding; defaults to the input's.
+        #[arg(long)]
+        format: Option<ArtifactFormat>,
+    },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
-    let url = Url::parse(&cli.url).context("invalid URL")?;
-    let statement = parse_statement(&cli.prove).context("invalid statement expression")?;
-    let method = cli.method.to_http();
-    let max_body_bytes = cli
-        .max_body_kb
-        .checked_mul(1024)
-        .ok_or_else(|| anyhow!("max-body-kb overflow"))?;
-    let timeout = cli.timeout_secs.map(Duration::from_secs);
-
-    let capture = capture(&CaptureOptions {
-        url,
-        method,
-        max_body_bytes,
-        timeout,
-    })?;
-    let evaluation = evaluate(&statement, &capture);
+    redproof_logging::init(cli.log_format, cli.verbose, cli.quiet);
+    if let Some(path) = &cli.batch {
+        return run_batch(&cli, path);
+    }
+    if let Some(Command::Artifact {
+        command: ArtifactCommand::Fix { input, out, format },
+    }) = &cli.command
+    {
+        return run_fix(input, out.as_ref().unwrap_or(input), *format);
+    }
+    let (Some(url), Some(prove)) = (&cli.url, &cli.prove) else {
+        bail!("--url and --prove are required");
+    };
+    let url = Url::parse(url).context("invalid URL")?;
+    let schema = schema_file(&cli)?;
+    if schema.is_some() && !is_bare_json_schema(prove) {
+        bail!("--schema-file requires --prove json:schema");
+    }
+    let statement = parse_prove(prove, schema.as_deref(), &cli.cache_headers)?;
+    let custom_annotations = custom_annotations(&cli)?;
+    if let Some(Command::Watch {
+        interval,
+        count,
+        ignore_headers,
+    }) = &cli.command
+    {
+        let options = WatchOptions {
+            interval: interval.to_std()?,
+            max_iterations: *count,
+        };
+        return run_watch(
+            &cli,
+            &url,
+            &statement,
+            &custom_annotations,
+            &options,
+            ignore_headers,
+        );
+    }
+    let (capture, retries) = capture_with_retries(&cli, &url, &statement)?;
+    let evaluation = tracing::info_span!("evaluate")
+        .in_scope(|| evaluate_with(&statement, &capture, cli.eval_mode.into()));
+    tracing::info!(
+        satisfied = evaluation.satisfied,
+        details = evaluation.details.as_deref(),
+        reason = ?evaluation.reason,
+        "statement evaluated"
+    );
 
     if cli.dry_run {
-        let preview = CapturePreview::new(&capture, &statement, &evaluation, &cli.prove);
-        println!("{}", serde_json::to_string_pretty(&preview)?);
+        let preview = CapturePreview::new(
+            &capture,
+            &statement,
+            &evaluation,
+            prove,
+            cli.sample_body_bytes,
+            cli.verbose > 0,
+        );
+        println!("{}", render_preview(&preview, cli.preview_format)?);
         return Ok(());
     }
 
-    let commitments = build_commitments(&capture.transcript(), cli.hash_alg.into(), true);
-    let artifact = build_artifact(&capture, &statement, commitments)?;
-    write_artifact(&artifact, cli.format, &cli.out)?;
-    println!(
+    let artifact = artifact_for(&cli, &capture, &retries, &statement, &custom_annotations)?;
+    write_outputs(&cli, artifact, &cli.out)?;
+    let done = format!(
         "[ok] {} {} -> {} (statement={})",
         capture.method.as_str(),
         capture.requested_url,
         cli.out.display(),
         evaluation.satisfied
     );
+    // Keep stdout to the artifact itself when it is written there.
+    if cli.out.as_os_str() == "-" {
+        eprintln!("{done}");
+    } else {
+        println!("{done}");
+    }
     Ok(())
 }
 
-fn build_artifact(
+/// `REDPROOF_ANNOTATE_*` variables overlaid with `--annotate` values.
+fn custom_annotations(cli: &Cli) -> Result<Map<String, Value>> {
+    let mut annotations = CaptureAnnotations::default();
+    annotate::apply(
+        &[
+            &EnvAnnotations::from_env(),
+            &StaticAnnotations(cli.annotate.clone()),
+        ],
+        &mut annotations,
+    )?;
+    Ok(annotations.extra)
+}
+
+/// Commit to `capture` and assemble the artifact as the CLI flags ask.
+fn artifact_for(
+    cli: &Cli,
     capture: &CaptureRecord,
+    retries: &StatusRetries,
     statement: &Statement,
-    commitments: CommitmentSet,
+    custom_annotations: &Map<String, Value>,
 ) -> Result<RedProofArtifact> {
-    let tls = TlsProofContext {
-        version: capture.tls.version.clone(),
-        cipher: capture.tls.cipher.clone(),
-        cert_fingerprints: capture.tls.cert_fingerprints.clone(),
-        alpn: capture.tls.alpn.clone(),
+    let algorithms = cli.hash_alg.clone();
+    let salt = if cli.salt_commitments {
+        random_salt()?
+    } else {
+        Vec::new()
     };
+    let mut commitments =
+        build_commitments(&capture.transcript(), &algorithms, !cli.no_witness, &salt);
+    if !cli.disclose_headers.is_empty() {
+        let pairs = capture
+            .response
+            .headers
+            .iter()
+            .map(|h| (h.name.clone(), h.value.clone()))
+            .collect::<Vec<_>>();
+        commit_headers(&mut commitments, &pairs, &cli.disclose_headers)?;
+    }
+    let mut annotations = CaptureAnnotations {
+        extra: custom_annotations.clone(),
+        ..CaptureAnnotations::default()
+    };
+    annotate::apply(
+        &[&BodyExtracts::resolve(&cli.extract, &capture.response)],
+        &mut annotations,
+    )?;
+    let options = ArtifactOptions {
+        source_date: cli
+            .deterministic
+            .then(|| cli.source_date.unwrap_or(DateTime::UNIX_EPOCH)),
+        embed_certs: cli.embed_certs,
+        expires_in: cli.expires_in,
+        custom_annotations: annotations.extra,
+        commit_timing: cli.commit_timing,
+        blob_encoding: cli.blob_encoding.into(),
+        status_sequence: (!cli.retry_on_status.is_empty()).then(|| retries.statuses.clone()),
+    };
+    build_artifact(capture, statement, commitments, &options)
+}
 
-    let mut annotations = Map::new();
-    annotations.insert(
-        "request_method".into(),
-        Value::String(capture.method.as_str().to_string()),
-    );
-    annotations.insert("status_code".into(), json!(capture.response.status_code));
-    annotations.insert(
-        "body_truncated".into(),
-        Value::Bool(capture.response.body_truncated),
-    );
-    annotations.insert(
-        "http_version".into(),
-        Value::String(capture.response.http_version.clone()),
-    );
+fn run_watch(
+    cli: &Cli,
+    url: &Url,
+    statement: &Statement,
+    custom_annotations: &Map<String, Value>,
+    options: &WatchOptions,
+    ignore_headers: &[String],
+) -> Result<()> {
+    let probe = || capture_with_retries(cli, url, statement);
+    let emit = |(record, retries): &(CaptureRecord, StatusRetries)| {
+        let artifact = artifact_for(cli, record, retries, statement, custom_annotations)?;
+        let captured_at = record.captured_at.unwrap_or_else(Utc::now);
+        let path = watch::artifact_path(&cli.out, captured_at);
+        write_outputs(cli, artifact, &path)?;
+        Ok(path)
+    };
+    let digest =
+        |(record, _): &(CaptureRecord, StatusRetries)| record.change_digest(ignore_headers);
+    let changes = watch::run(options, probe, digest, emit, &mut std::io::stdout().lock())?;
+    tracing::info!(changes, "watch finished");
+    Ok(())
+}
 
-    Ok(RedProofArtifact {
-        version: "1.0".into(),
-        domain: capture.domain.clone(),
-        time_utc: capture.captured_at,
-        tls,
-        statement: statement.clone(),
-        commitments,
-        proof: EncodedBlob::from_bytes(b"phase2-naive-proof"),
-        meta: ArtifactMeta {
-            tool_version: env!("CARGO_PKG_VERSION").into(),
-            annotations,
+/// Capture `url`, re-capturing per `--retry-on-status`.
+fn capture_with_retries(
+    cli: &Cli,
+    url: &Url,
+    statement: &Statement,
+) -> Result<(CaptureRecord, StatusRetries)> {
+    let options = capture_options(cli, url.clone(), statement)?;
+    if !cli.retry_on_status.is_empty() {
+        options.refuse_resending("--retry-on-status")?;
+    }
+    if matches!(cli.command, Some(Command::Watch { .. })) {
+        options.refuse_resending("watch")?;
+    }
+    let policy = RetryPolicy::on_statuses(cli.retry_on_status.clone());
+    retry::run(
+        &policy,
+        &options.deadline,
+        || capture(&options),
+        |record| record.response.status_code,
+    )
+}
+
+fn capture_options(cli: &Cli, url: Url, statement: &Statement) -> Result<CaptureOptions> {
+    let request_script = cli
+        .request_script
+        .as_ref()
+        .map(|path| {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            RequestScript::parse(&text, cli.script_select)
+        })
+        .transpose()?;
+    let method = match &request_script {
+        Some(script) => script.selected().method.clone(),
+        None => http::Method::from(cli.method),
+    };
+    if method == http::Method::HEAD && statement.reads_body() && !cli.allow_incompatible {
+        bail!(
+            "'{}' checks the response body, but HEAD responses have none; use --method get \
+             (or --allow-incompatible to capture anyway)",
+            statement.summary()
+        );
+    }
+    let max_body_bytes = body_limit(cli.max_body_kb, cli.max_body_ceiling_mb)?;
+    let stream_body_hash = match statement {
+        Statement::HashEquals { algorithm, .. }
+            if cli.no_witness && method == http::Method::GET =>
+        {
+            Some(algorithm.clone())
+        }
+        _ => None,
+    };
+    Ok(CaptureOptions {
+        url,
+        method,
+        max_body_bytes,
+        max_headers: cli.max_headers,
+        timeout: cli.timeout_secs.map(Duration::from_secs),
+        deadline: Deadline::new(cli.capture_timeout_total.map(Duration::from_secs)),
+        record_dns: cli.record_dns,
+        record_spki: cli.record_spki,
+        stream_body_hash,
+        warm: cli.warm,
+        no_sni: cli.no_sni,
+        cipher_suites: cli.tls_ciphers.clone(),
+        browser_profile: cli.browser_profile.map(Into::into),
+        origin: cli.origin.clone(),
+        connection: if cli.keep_alive {
+            ConnectionMode::KeepAlive
+        } else {
+            ConnectionMode::Close
         },
+        allow_plaintext_fallback: cli.allow_plaintext_fallback,
+        salvage_on_timeout: cli.salvage_on_timeout,
+        commit_timing: cli.commit_timing,
+        preserve_header_case: cli.header_case_preserve,
+        request_script,
+        resolver: resolver(&cli.resolve),
     })
 }
 
+/// Contents of `--schema-file`, if given.
+fn schema_file(cli: &Cli) -> Result<Option<String>> {
+    cli.schema_file
+        .as_ref()
+        .map(|path| {
+            fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))
+        })
+        .transpose()
+}
+
+fn is_bare_json_schema(expression: &str) -> bool {
+    expression.trim().eq_ignore_ascii_case("json:schema")
+}
+
+/// Parse and normalize a `--prove` expression; `schema` fills in a bare
+/// `json:schema` and `cache_headers` the indicators of `cache:hit`.
+fn parse_prove(
+    expression: &str,
+    schema: Option<&str>,
+    cache_headers: &[String],
+) -> Result<Statement> {
+    let expression = match schema {
+        Some(schema) if is_bare_json_schema(expression) => format!("json:schema:{schema}"),
+        _ => expression.to_string(),
+    };
+    Ok(parse_statement(&expression)
+        .context("invalid statement expression")?
+        .with_cache_headers(cache_headers)
+        .normalize()?)
+}
+
+fn resolver(overrides: &[(String, u16, IpAddr)]) -> Arc<dyn Resolver> {
+    if overrides.is_empty() {
+        return Arc::new(SystemResolver);
+    }
+    let mut resolver = StaticResolver::new(Box::new(SystemResolver));
+    for (host, port, ip) in overrides {
+        resolver.insert(host, *port, *ip);
+    }
+    Arc::new(resolver)
+}
+
+fn run_batch(cli: &Cli, path: &Path) -> Result<()> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let targets = batch::read_targets(&text)?;
+    let schema = schema_file(cli)?;
+    let probe = |target: &BatchTarget| {
+        let url = Url::parse(&target.url).context("invalid URL")?;
+        let statement = parse_prove(&target.prove, schema.as_deref(), &cli.cache_headers)?;
+        let (capture, _) = capture_with_retries(cli, &url, &statement)?;
+        let evaluation = evaluate_with(&statement, &capture, cli.eval_mode.into());
+        Ok(BatchOutcome {
+            domain: capture.domain,
+            statement: statement.summary(),
+            satisfied: evaluation.satisfied,
+            details: evaluation.details,
+            reason: evaluation.reason,
+        })
+    };
+    let failures = batch::run(&targets, cli.jobs, probe, &mut std::io::stdout().lock())?;
+    if failures > 0 {
+        tracing::warn!(failures, total = targets.len(), "batch targets failed");
+    }
+    Ok(())
+}
+
+fn run_fix(input: &PathBuf, out: &PathBuf, format: Option<ArtifactFormat>) -> Result<()> {
+    let data = fs::read(input).with_context(|| format!("failed to read {}", input.display()))?;
+    let input_format = if data.trim_ascii_start().starts_with(b"{") {
+        ArtifactFormat::Json
+    } else {
+        ArtifactFormat::Cbor
+    };
+    let artifact: RedProofArtifact = match input_format {
+        ArtifactFormat::Json => {
+            serde_json::from_slice(&data).context("unable to parse artifact as JSON")?
+        }
+        ArtifactFormat::Cbor => {
+            serde_cbor::from_slice(&data).context("unable to parse artifact as CBOR")?
+        }
+    };
+    let (artifact, changes) = fix_artifact(artifact)?;
+    if changes.is_empty() {
+        println!("{}: already current", input.display());
+    }
+    for change in &changes {
+        println!("{change}");
+    }
+    write_artifact(&artifact, format.unwrap_or(input_format), out)
+}
+
+/// Write `artifact` to `path`, first moving its witness to the sidecar when
+/// `--output-witness-separate` asks for it.
+fn write_outputs(cli: &Cli, mut artifact: RedProofArtifact, path: &PathBuf) -> Result<()> {
+    if cli.output_witness_separate {
+        if path.as_os_str() == "-" {
+            bail!("--output-witness-separate needs an --out file to place the sidecar next to");
+        }
+        let witness = artifact
+            .detach_witness()?
+            .context("the artifact has no witness to separate")?;
+        let sidecar = path.with_extension("witness");
+        fs::write(&sidecar, serde_json::to_vec_pretty(&witness)?)
+            .with_context(|| format!("failed to write {}", sidecar.display()))?;
+    }
+    write_artifact(&artifact, cli.format.for_path(path), path)
+}
+
 fn write_artifact(
     artifact: &RedProofArtifact,
     format: ArtifactFormat,
@@ -181,15 +727,27 @@ fn write_artifact(
 ) -> Result<()> {
     let bytes = match format {
         ArtifactFormat::Json => serde_json::to_vec_pretty(artifact)?,
-        ArtifactFormat::Cbor => serde_cbor::to_vec(artifact)?,
+        ArtifactFormat::Cbor => artifact.to_canonical_cbor()?,
     };
+    if path.as_os_str() == "-" {
+        return Ok(std::io::stdout().write_all(&bytes)?);
+    }
     fs::write(path, bytes).with_context(|| format!("failed to write {}", path.display()))
 }
 
+fn render_preview(preview: &CapturePreview, format: PreviewFormat) -> Result<String> {
+    Ok(match format {
+        PreviewFormat::Json => serde_json::to_string_pretty(preview)?,
+        PreviewFormat::JsonCompact => serde_json::to_string(preview)?,
+        PreviewFormat::Yaml => serde_yaml::to_string(preview)?.trim_end().to_string(),
+    })
+}
+
 #[derive(Serialize)]
 struct CapturePreview<'a> {
     request: RequestPreview<'a>,
     tls: &'a capture::TlsMetadata,
+    posture: PostureSummary,
     response: ResponsePreview<'a>,
     statement: StatementPreview<'a>,
 }
@@ -198,7 +756,8 @@ struct CapturePreview<'a> {
 struct RequestPreview<'a> {
     method: &'a str,
     url: &'a Url,
-    captured_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    captured_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Serialize)]
@@ -206,8 +765,53 @@ struct ResponsePreview<'a> {
     status_code: u16,
     reason: &'a str,
     headers: &'a [capture::HeaderEntry],
-    body_base64: String,
+    #[serde(skip_serializing_if = "<[capture::HeaderEntry]>::is_empty")]
+    early_hints: &'a [capture::HeaderEntry],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body_preview: Option<BodyPreview>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
     body_truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body_digest: Option<&'a capture::BodyDigest>,
+}
+
+/// Leading body bytes for a quick look at the response.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct BodyPreview {
+    /// `utf8` or `hex`.
+    encoding: &'static str,
+    data: String,
+    /// Bytes shown; an incomplete trailing UTF-8 sequence is dropped.
+    sampled_bytes: usize,
+    body_bytes: usize,
+}
+
+impl BodyPreview {
+    fn new(body: &[u8], limit: usize) -> Self {
+        let sample = &body[..body.len().min(limit)];
+        let text = match std::str::from_utf8(sample) {
+            Ok(text) => Some(text),
+            Err(err) if err.error_len().is_none() => {
+                std::str::from_utf8(&sample[..err.valid_up_to()]).ok()
+            }
+            Err(_) => None,
+        };
+        let (encoding, data, sampled_bytes) = match text {
+            Some(text) => ("utf8", text.to_string(), text.len()),
+            None => (
+                "hex",
+                sample.iter().map(|byte| format!("{byte:02x}")).collect(),
+                sample.len(),
+            ),
+        };
+        Self {
+            encoding,
+            data,
+            sampled_bytes,
+            body_bytes: body.len(),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -224,6 +828,8 @@ impl<'a> CapturePreview<'a> {
         statement: &'a Statement,
         evaluation: &'a StatementEvaluation,
         expression: &'a str,
+        sample_body_bytes: Option<usize>,
+        full_body: bool,
     ) -> Self {
         Self {
             request: RequestPreview {
@@ -232,12 +838,17 @@ impl<'a> CapturePreview<'a> {
                 captured_at: capture.captured_at,
             },
             tls: &capture.tls,
+            posture: capture.posture(),
             response: ResponsePreview {
                 status_code: capture.response.status_code,
                 reason: &capture.response.reason,
                 headers: &capture.response.headers,
-                body_base64: B64.encode(&capture.response.body),
+                early_hints: &capture.response.early_hints,
+                body_preview: sample_body_bytes
+                    .map(|limit| BodyPreview::new(&capture.response.body, limit)),
+                body_base64: full_body.then(|| B64.encode(&capture.response.body)),
                 body_truncated: capture.response.body_truncated,
+                body_digest: capture.response.body_digest.as_ref(),
             },
             statement: StatementPreview {
                 expression,
@@ -248,29 +859,353 @@ impl<'a> CapturePreview<'a> {
         }
     }
 }
-impl std::fmt::Display for MethodArg {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self {
-            MethodArg::Get => "get",
-            MethodArg::Head => "head",
-        })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redproof_artifact::{CertChainSize, TimingTrace};
+    use redproof_prover::capture::{CaptureTiming, HttpResponse, TlsMetadata};
+    use redproof_prover::evaluate::{evaluate, HeaderMap};
+
+    fn stub_record(captured_at: DateTime<Utc>) -> CaptureRecord {
+        CaptureRecord {
+            requested_url: Url::parse("https://example.com").unwrap(),
+            domain: "example.com".into(),
+            method: http::Method::GET,
+            captured_at: Some(captured_at),
+            tls: TlsMetadata {
+                version: "TLS1.3".into(),
+                cipher: "TLS13_AES_128_GCM_SHA256".into(),
+                cert_fingerprints: vec![
+                    "sha256:455943cf819425761d1f950263ebf54755d8d684c25535943976f488bc79d23b"
+                        .into(),
+                ],
+                alpn: None,
+                cert_chain: vec![],
+                spki_pins: vec![],
+                subject_alt_names: vec![],
+                chain_size: CertChainSize::default(),
+                embedded_scts: 0,
+            },
+            response: HttpResponse {
+                http_version: "HTTP/1.1".into(),
+                status_code: 200,
+                reason: "OK".into(),
+                headers: vec![],
+                early_hints: vec![],
+                body: b"stub".to_vec(),
+                body_truncated: false,
+                body_digest: None,
+            },
+            canonical_handshake: b"handshake".to_vec(),
+            canonical_app_data: b"app".to_vec(),
+            headers: HeaderMap::new(),
+            resolved_ips: vec![],
+            timing: Some(CaptureTiming {
+                cold_ttfb_ms: captured_at.timestamp_subsec_millis().into(),
+                warm_ttfb_ms: Some(12),
+                trace: TimingTrace::default(),
+            }),
+            sni_sent: true,
+            browser_profile: None,
+            origin: None,
+            connection: None,
+            request_sequence: None,
+        }
     }
-}
 
-impl std::fmt::Display for HashAlgArg {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self {
-            HashAlgArg::Blake3 => "blake3",
-            HashAlgArg::Sha256 => "sha256",
-        })
+    #[test]
+    fn warm_capture_records_both_timings() {
+        let record = stub_record(Utc::now());
+        let statement = Statement::ResponseTimeUnder { max_ms: 50 };
+        let commitments = build_commitments(
+            &record.transcript(),
+            &[CommitmentAlgorithm::Blake3],
+            true,
+            &[],
+        );
+        let artifact = build_artifact(
+            &record,
+            &statement,
+            commitments,
+            &ArtifactOptions::default(),
+        )
+        .expect("artifact");
+        let timing = artifact.meta.annotations.timing.expect("timing");
+        assert_eq!(
+            timing.cold_ttfb_ms,
+            record.timing.as_ref().unwrap().cold_ttfb_ms
+        );
+        assert_eq!(timing.warm_ttfb_ms, Some(12));
+        assert_eq!(timing.statement_uses, "warm");
     }
-}
 
-impl std::fmt::Display for ArtifactFormat {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self {
-            ArtifactFormat::Json => "json",
-            ArtifactFormat::Cbor => "cbor",
-        })
+    #[test]
+    fn records_certificate_chain_size() {
+        let mut record = stub_record(Utc::now());
+        record.tls.cert_chain = vec![vec![0; 1400], vec![1; 1100], vec![2; 900]];
+        record.tls.chain_size = CertChainSize::of(&record.tls.cert_chain);
+        let statement = Statement::CertChainLengthAtMost { n: 3 };
+        let commitments = build_commitments(
+            &record.transcript(),
+            &[CommitmentAlgorithm::Blake3],
+            true,
+            &[],
+        );
+        let options = ArtifactOptions {
+            embed_certs: true,
+            ..ArtifactOptions::default()
+        };
+        let artifact =
+            build_artifact(&record, &statement, commitments, &options).expect("artifact");
+        assert_eq!(
+            artifact.tls.chain_size,
+            Some(CertChainSize {
+                count: 3,
+                total_bytes: 3400
+            })
+        );
+        assert_eq!(artifact.tls.validate(), Ok(()));
+    }
+
+    fn head_capture_options(prove: &str, extra: &[&str]) -> Result<()> {
+        let cli = Cli::parse_from(
+            [
+                "redproof-prover",
+                "--url",
+                "https://example.com",
+                "--prove",
+                prove,
+                "--method",
+                "head",
+            ]
+            .iter()
+            .chain(extra),
+        );
+        let statement = parse_prove(prove, None, &[])?;
+        capture_options(&cli, Url::parse("https://example.com").unwrap(), &statement).map(drop)
+    }
+
+    /// Answers each connection with the next of `statuses`, then stops.
+    fn status_server(statuses: &'static [u16]) -> (String, std::thread::JoinHandle<()>) {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            for status in statuses {
+                let (mut conn, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = conn.read(&mut buf);
+                let _ = conn.write_all(
+                    format!(
+                        "HTTP/1.1 {status} Status\r\n\
+                         Date: Thu, 01 Jan 2026 00:00:00 GMT\r\n\r\n"
+                    )
+                    .as_bytes(),
+                );
+            }
+        });
+        (url, server)
+    }
+
+    fn retrying_cli(url: &str, extra: &[&str]) -> Cli {
+        Cli::parse_from(
+            [
+                "redproof-prover",
+                "--url",
+                url,
+                "--prove",
+                "header:absent:Server",
+                "--retry-on-status",
+                "503",
+            ]
+            .iter()
+            .chain(extra),
+        )
+    }
+
+    #[test]
+    fn retry_on_status_recaptures_until_the_stub_recovers() {
+        let (url, server) = status_server(&[503, 200]);
+        let cli = retrying_cli(&url, &[]);
+        let statement = parse_prove("header:absent:Server", None, &[]).unwrap();
+        let (record, retries) =
+            capture_with_retries(&cli, &Url::parse(&url).unwrap(), &statement).unwrap();
+        server.join().unwrap();
+        assert_eq!(record.response.status_code, 200);
+        assert_eq!(retries.statuses, [503, 200]);
+
+        let artifact = artifact_for(&cli, &record, &retries, &statement, &Map::new()).unwrap();
+        let annotations = &artifact.meta.annotations;
+        assert_eq!(annotations.status_retries, Some(1));
+        assert_eq!(annotations.status_sequence, Some(vec![503, 200]));
+        assert!(annotations.extra.is_empty());
+    }
+
+    #[test]
+    fn retry_and_watch_refuse_unsafe_methods() {
+        let cli = retrying_cli("http://127.0.0.1:9/", &["--method", "delete"]);
+        let statement = parse_prove("header:absent:Server", None, &[]).unwrap();
+        let url = Url::parse("http://127.0.0.1:9/").unwrap();
+        let Err(err) = capture_with_retries(&cli, &url, &statement) else {
+            panic!("DELETE was retried");
+        };
+        assert!(
+            err.to_string()
+                .starts_with("--retry-on-status would send DELETE more than once"),
+            "{err}"
+        );
+
+        let cli = Cli::parse_from([
+            "redproof-prover",
+            "--url",
+            "http://127.0.0.1:9/",
+            "--prove",
+            "header:absent:Server",
+            "--method",
+            "delete",
+            "watch",
+            "--count",
+            "1",
+        ]);
+        let Err(err) = capture_with_retries(&cli, &url, &statement) else {
+            panic!("watch re-sent DELETE");
+        };
+        assert!(
+            err.to_string().starts_with("watch would send DELETE"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn head_requests_reject_body_statements() {
+        let hash = format!("hash:eq:sha256:{}", "0".repeat(64));
+        let err = head_capture_options(&hash, &[]).unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "checks the response body, but HEAD responses have none; use --method get"
+            ),
+            "{err}"
+        );
+        assert!(head_capture_options(&hash, &["--allow-incompatible"]).is_ok());
+        assert!(head_capture_options("header:present:Strict-Transport-Security", &[]).is_ok());
+        assert!(head_capture_options("regex:headers:nginx", &[]).is_ok());
+    }
+
+    #[test]
+    fn schema_file_fills_bare_json_schema_only() {
+        let schema = r#"{"type": "array"}"#;
+        assert_eq!(
+            parse_prove(" JSON:schema ", Some(schema), &[]).unwrap(),
+            Statement::JsonSchemaValid {
+                schema: serde_json::json!({"type": "array"}),
+            }
+        );
+        assert_eq!(
+            parse_prove("header:present:ETag", Some(schema), &[]).unwrap(),
+            Statement::HeaderPresent {
+                target: "ETag".into(),
+            }
+        );
+        assert!(parse_prove("json:schema", None, &[]).is_err());
+    }
+
+    #[test]
+    fn body_preview_stops_at_sample_size() {
+        let text = BodyPreview::new(b"<!doctype html><html>", 9);
+        assert_eq!(text.encoding, "utf8");
+        assert_eq!(text.data, "<!doctype");
+        assert_eq!(text.sampled_bytes, 9);
+        assert_eq!(text.body_bytes, 21);
+
+        // The cut lands inside the two-byte "é".
+        let split = BodyPreview::new("café".as_bytes(), 4);
+        assert_eq!(split.data, "caf");
+        assert_eq!(split.sampled_bytes, 3);
+
+        let binary = BodyPreview::new(b"\x89PNG\r\n\x1a\n", 4);
+        assert_eq!(binary.encoding, "hex");
+        assert_eq!(binary.data, "89504e47");
+    }
+
+    #[test]
+    fn yaml_preview_round_trips_to_json_preview() {
+        let record = stub_record(Utc::now());
+        let statement = Statement::ResponseTimeUnder { max_ms: 50 };
+        let evaluation = evaluate(&statement, &record);
+        let preview = CapturePreview::new(
+            &record,
+            &statement,
+            &evaluation,
+            "timing:ttfb-under:50",
+            Some(8),
+            true,
+        );
+        let yaml = render_preview(&preview, PreviewFormat::Yaml).expect("yaml");
+        let parsed: Value = serde_yaml::from_str(&yaml).expect("yaml parses");
+        assert_eq!(parsed, serde_json::to_value(&preview).unwrap());
+
+        let compact = render_preview(&preview, PreviewFormat::JsonCompact).expect("json");
+        assert!(!compact.contains('\n'));
+        assert_eq!(serde_json::from_str::<Value>(&compact).unwrap(), parsed);
+    }
+
+    #[test]
+    fn custom_annotations_land_in_meta() {
+        let record = stub_record(Utc::now());
+        let statement = Statement::ResponseTimeUnder { max_ms: 50 };
+        let commitments = build_commitments(
+            &record.transcript(),
+            &[CommitmentAlgorithm::Blake3],
+            true,
+            &[],
+        );
+        let mut custom_annotations = Map::new();
+        custom_annotations.insert("git_commit".into(), Value::from("0a1b2c"));
+        let options = ArtifactOptions {
+            custom_annotations,
+            ..ArtifactOptions::default()
+        };
+        let artifact =
+            build_artifact(&record, &statement, commitments, &options).expect("artifact");
+        let annotations = &artifact.meta.annotations;
+        assert_eq!(annotations.extra["git_commit"], "0a1b2c");
+        assert_eq!(annotations.status_code, Some(record.response.status_code));
+    }
+
+    /// Captures `url` once with `args` and serializes the resulting artifact.
+    fn captured_artifact_bytes(url: &str, args: &[&str]) -> Vec<u8> {
+        let cli = Cli::parse_from(
+            [
+                "redproof-prover",
+                "--url",
+                url,
+                "--prove",
+                "header:absent:Server",
+            ]
+            .iter()
+            .chain(args),
+        );
+        let statement = parse_prove("header:absent:Server", None, &[]).unwrap();
+        let (record, retries) =
+            capture_with_retries(&cli, &Url::parse(url).unwrap(), &statement).unwrap();
+        let artifact = artifact_for(&cli, &record, &retries, &statement, &Map::new()).unwrap();
+        serde_json::to_vec_pretty(&artifact).expect("json")
+    }
+
+    #[test]
+    fn deterministic_artifacts_are_byte_identical() {
+        let (url, server) = status_server(&[200, 200]);
+        let first = captured_artifact_bytes(&url, &["--deterministic"]);
+        let second = captured_artifact_bytes(&url, &["--deterministic"]);
+        server.join().unwrap();
+        assert_eq!(first, second);
+
+        // The first run succeeds at once, the second needs a retry.
+        let (url, server) = status_server(&[200, 503, 200]);
+        let args = ["--deterministic", "--retry-on-status", "503"];
+        let direct = captured_artifact_bytes(&url, &args);
+        let retried = captured_artifact_bytes(&url, &args);
+        server.join().unwrap();
+        assert_eq!(direct, retried);
     }
 }
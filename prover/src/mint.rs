@@ -0,0 +1,314 @@
+//! Turning a capture into an artifact, whether RedProof made the capture or
+//! another tool did.
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use redproof_artifact::{
+    ArtifactMeta, BlobEncoding, CaptureAnnotations, ClockSkewAnnotation, CommitmentAlgorithm,
+    CommitmentSet, EncodedBlob, RedProofArtifact, ResolvedIpsAnnotation, TimingAnnotation,
+    TlsProofContext,
+};
+use redproof_statements::Statement;
+use serde_json::{Map, Value};
+
+use crate::capture::{record_from_response, CaptureRecord, TlsMetadata};
+use crate::commit::build_commitments;
+use crate::evaluate::{clock_skew, detect_charset, evaluate};
+
+#[derive(Default)]
+pub struct ArtifactOptions {
+    /// Selects deterministic mode: replaces the capture time and leaves out
+    /// volatile annotations.
+    pub source_date: Option<DateTime<Utc>>,
+    pub embed_certs: bool,
+    pub expires_in: Option<chrono::Duration>,
+    /// Deployment annotations, already checked against reserved keys.
+    pub custom_annotations: Map<String, Value>,
+    /// The timing trace was committed; record it in the timing annotation.
+    pub commit_timing: bool,
+    pub blob_encoding: BlobEncoding,
+    /// Statuses of the `--retry-on-status` attempts, the committed one last.
+    pub status_sequence: Option<Vec<u16>>,
+}
+
+/// Assemble the artifact for `capture`, asserting `statement` under
+/// `commitments`.
+pub fn build_artifact(
+    capture: &CaptureRecord,
+    statement: &Statement,
+    commitments: CommitmentSet,
+    options: &ArtifactOptions,
+) -> Result<RedProofArtifact> {
+    let cert_chain = if options.embed_certs {
+        capture
+            .tls
+            .cert_chain
+            .iter()
+            .map(|der| EncodedBlob::from_bytes(der))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let tls = TlsProofContext {
+        version: capture.tls.version.clone(),
+        cipher: capture.tls.cipher.clone(),
+        cert_fingerprints: capture.tls.cert_fingerprints.clone(),
+        alpn: capture.tls.alpn.clone(),
+        cert_chain,
+        spki_pins: capture.tls.spki_pins.clone(),
+        subject_alt_names: capture.tls.subject_alt_names.clone(),
+        chain_size: (capture.tls.chain_size.count > 0).then_some(capture.tls.chain_size),
+    };
+
+    let annotations = CaptureAnnotations {
+        request_method: Some(capture.method.as_str().to_string()),
+        status_code: Some(capture.response.status_code),
+        body_truncated: Some(capture.response.body_truncated),
+        header_count: Some(capture.response.headers.len()),
+        http_version: Some(capture.response.http_version.clone()),
+        body_charset: Some(detect_charset(&capture.response).name().to_string()),
+        sni: (!capture.sni_sent).then(|| "omitted".to_string()),
+        browser_profile: capture
+            .browser_profile
+            .map(|profile| profile.as_str().to_string()),
+        origin: capture.origin.clone(),
+        connection: capture.connection.map(|mode| mode.as_str().to_string()),
+        timing: capture
+            .timing
+            .as_ref()
+            .filter(|_| options.source_date.is_none())
+            .map(|timing| TimingAnnotation {
+                cold_ttfb_ms: timing.cold_ttfb_ms,
+                warm_ttfb_ms: timing.warm_ttfb_ms,
+                statement_uses: timing.measured().0.to_string(),
+                attested_by: "prover".into(),
+                trace: options.commit_timing.then(|| timing.trace.clone()),
+            }),
+        resolved_ips: (!capture.resolved_ips.is_empty()).then(|| ResolvedIpsAnnotation {
+            addresses: capture.resolved_ips.clone(),
+            attested_by: "prover".into(),
+        }),
+        clock_skew: capture
+            .captured_at
+            .filter(|_| options.source_date.is_none())
+            .and_then(|captured_at| clock_skew(&capture.headers, captured_at).ok())
+            .map(|(server_date, skew_ms)| ClockSkewAnnotation {
+                server_date,
+                skew_ms,
+                attested_by: "prover".into(),
+            }),
+        request_sequence: capture.request_sequence.clone(),
+        status_retries: options
+            .status_sequence
+            .as_ref()
+            .filter(|_| options.source_date.is_none())
+            .map(|statuses| statuses.len().saturating_sub(1)),
+        status_sequence: options
+            .status_sequence
+            .clone()
+            .filter(|_| options.source_date.is_none()),
+        posture: Some(capture.posture()),
+        extra: options.custom_annotations.clone(),
+    };
+
+    // Imported responses were not captured here; date them by the import.
+    let captured_at = capture.captured_at.unwrap_or_else(Utc::now);
+    let mut artifact = RedProofArtifact {
+        version: "1.0".into(),
+        domain: capture.domain.clone(),
+        time_utc: options.source_date.unwrap_or(captured_at),
+        tls,
+        statement: statement.clone(),
+        commitments,
+        proof: EncodedBlob::from_bytes(b"phase2-naive-proof"),
+        expires_at: options.expires_in.map(|window| captured_at + window),
+        meta: ArtifactMeta {
+            tool_version: env!("CARGO_PKG_VERSION").into(),
+            min_verifier_version: Some(statement.min_verifier_version().into()),
+            annotations,
+            provenance: Vec::new(),
+        },
+    };
+    artifact.bind_statement()?;
+    artifact.reencode_blobs(options.blob_encoding)?;
+    Ok(artifact)
+}
+
+/// Mint an artifact from a raw HTTP/1.x response captured elsewhere, e.g.
+/// extracted from a pcap. `tls` describes the connection it arrived on.
+/// The response is canonicalized and committed under `algorithm` with the
+/// witness included, exactly as a live capture would be. Fails when the
+/// response does not satisfy `statement`.
+///
+/// Nothing was measured, so the artifact carries no timing, clock skew or
+/// request method annotation, and timing statements (`timing:ttfb-under`,
+/// `timing:clock-skew-under`) are refused; `time_utc` is the time of the
+/// call. `sni_sent` says whether the ClientHello carried SNI. The committed
+/// handshake is marked `source: imported`.
+pub fn prove_from_response(
+    domain: &str,
+    tls: TlsMetadata,
+    sni_sent: bool,
+    response: &[u8],
+    statement: &Statement,
+    algorithm: CommitmentAlgorithm,
+) -> Result<RedProofArtifact> {
+    let record = record_from_response(domain, tls, sni_sent, response)?;
+    let evaluation = evaluate(statement, &record);
+    if !evaluation.satisfied {
+        match evaluation.details {
+            Some(details) => bail!("response does not satisfy the statement: {details}"),
+            None => bail!("response does not satisfy the statement"),
+        }
+    }
+    let commitments = build_commitments(&record.transcript(), &[algorithm], true, &[]);
+    let mut artifact =
+        build_artifact(&record, statement, commitments, &ArtifactOptions::default())?;
+    artifact.meta.annotations.request_method = None;
+    Ok(artifact)
+}
+
+#[cfg(test)]
+mod tests {
+    use redproof_artifact::CertChainSize;
+
+    use super::*;
+
+    const RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\n\
+Server: nginx\r\n\
+Content-Type: text/html\r\n\
+Content-Length: 5\r\n\
+\r\n\
+hello";
+
+    fn tls() -> TlsMetadata {
+        TlsMetadata {
+            version: "TLS1.3".into(),
+            cipher: "TLS13_AES_128_GCM_SHA256".into(),
+            cert_fingerprints: vec![
+                "sha256:455943cf819425761d1f950263ebf54755d8d684c25535943976f488bc79d23b".into(),
+            ],
+            alpn: None,
+            cert_chain: vec![],
+            spki_pins: vec![],
+            subject_alt_names: vec![],
+            chain_size: CertChainSize::default(),
+            embedded_scts: 0,
+        }
+    }
+
+    #[test]
+    fn offline_artifact_round_trips_and_verifies() {
+        let statement = Statement::HeaderAbsent {
+            target: "Strict-Transport-Security".into(),
+        };
+        let artifact = prove_from_response(
+            "example.com",
+            tls(),
+            true,
+            RESPONSE,
+            &statement,
+            CommitmentAlgorithm::Sha256,
+        )
+        .expect("artifact");
+        assert_eq!(artifact.domain, "example.com");
+        assert_eq!(artifact.commitments.algorithm, CommitmentAlgorithm::Sha256);
+        assert_eq!(artifact.meta.annotations.status_code, Some(200));
+        assert!(artifact.meta.annotations.timing.is_none());
+        artifact
+            .validate_strict()
+            .expect("commitments match the witness");
+
+        let json = serde_json::to_vec(&artifact).unwrap();
+        let decoded: RedProofArtifact = serde_json::from_slice(&json).unwrap();
+        assert_eq!(decoded, artifact);
+
+        let witness = artifact.commitments.witness.as_ref().expect("witness");
+        let app_data: Value = serde_json::from_slice(&witness.app_data.decode().unwrap()).unwrap();
+        assert_eq!(app_data["status_code"], 200);
+        assert_eq!(app_data["body_base64"], "aGVsbG8=");
+        let handshake: Value =
+            serde_json::from_slice(&witness.handshake.decode().unwrap()).unwrap();
+        assert_eq!(handshake["source"], "imported");
+    }
+
+    #[test]
+    fn refuses_statement_the_response_does_not_satisfy() {
+        let statement = Statement::HeaderAbsent {
+            target: "Server".into(),
+        };
+        let err = prove_from_response(
+            "example.com",
+            tls(),
+            true,
+            RESPONSE,
+            &statement,
+            CommitmentAlgorithm::Blake3,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "response does not satisfy the statement");
+
+        let garbage = prove_from_response(
+            "example.com",
+            tls(),
+            true,
+            b"SSH-2.0-OpenSSH_9.6\r\n",
+            &statement,
+            CommitmentAlgorithm::Blake3,
+        );
+        assert!(garbage.is_err());
+    }
+
+    #[test]
+    fn refuses_timing_statements_nobody_measured() {
+        let dated = b"HTTP/1.1 200 OK\r\n\
+Date: Fri, 01 Mar 2024 12:00:00 GMT\r\n\
+Content-Length: 2\r\n\
+\r\n\
+ok";
+        for (statement, details) in [
+            (
+                Statement::ResponseTimeUnder { max_ms: 1 },
+                "timing trace not committed",
+            ),
+            (
+                Statement::ClockSkewUnder { millis: u64::MAX },
+                "capture time not committed; skew cannot be measured",
+            ),
+        ] {
+            let err = prove_from_response(
+                "example.com",
+                tls(),
+                true,
+                dated,
+                &statement,
+                CommitmentAlgorithm::Blake3,
+            )
+            .unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("response does not satisfy the statement: {details}")
+            );
+        }
+    }
+
+    #[test]
+    fn records_whether_sni_was_sent() {
+        let statement = Statement::HeaderPresent {
+            target: "Server".into(),
+        };
+        let mint = |sni_sent| {
+            prove_from_response(
+                "example.com",
+                tls(),
+                sni_sent,
+                RESPONSE,
+                &statement,
+                CommitmentAlgorithm::Blake3,
+            )
+            .unwrap()
+        };
+        assert_eq!(mint(true).meta.annotations.sni, None);
+        assert_eq!(mint(false).meta.annotations.sni.as_deref(), Some("omitted"));
+    }
+}
@@ -0,0 +1,122 @@
+//! Host name resolution for captures, replaceable for overrides and tests.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+
+use anyhow::{bail, Context, Result};
+
+/// Turns the target host and port into the addresses to connect to.
+pub trait Resolver: Send + Sync {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>>;
+
+    /// Whether the addresses for `host:port` were supplied by the operator
+    /// rather than looked up, so they say nothing about what the name
+    /// resolves to.
+    fn is_override(&self, _host: &str, _port: u16) -> bool {
+        false
+    }
+}
+
+/// The operating system's resolver.
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        let addrs = (host, port)
+            .to_socket_addrs()
+            .with_context(|| format!("failed to resolve {}", host))?
+            .collect::<Vec<_>>();
+        if addrs.is_empty() {
+            bail!("{} did not resolve to any address", host);
+        }
+        Ok(addrs)
+    }
+}
+
+/// Fixed `host:port` -> address entries, like curl's `--resolve`. Anything
+/// without an entry goes to the fallback resolver.
+pub struct StaticResolver {
+    entries: HashMap<(String, u16), Vec<IpAddr>>,
+    fallback: Box<dyn Resolver>,
+}
+
+impl StaticResolver {
+    pub fn new(fallback: Box<dyn Resolver>) -> Self {
+        Self {
+            entries: HashMap::new(),
+            fallback,
+        }
+    }
+
+    pub fn insert(&mut self, host: &str, port: u16, ip: IpAddr) {
+        self.entries
+            .entry((host.to_ascii_lowercase(), port))
+            .or_default()
+            .push(ip);
+    }
+}
+
+impl Resolver for StaticResolver {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        match self.entries.get(&(host.to_ascii_lowercase(), port)) {
+            Some(ips) => Ok(ips.iter().map(|ip| SocketAddr::new(*ip, port)).collect()),
+            None => self.fallback.resolve(host, port),
+        }
+    }
+
+    fn is_override(&self, host: &str, port: u16) -> bool {
+        self.entries
+            .contains_key(&(host.to_ascii_lowercase(), port))
+            || self.fallback.is_override(host, port)
+    }
+}
+
+/// Parse a `--resolve` entry of the form `<host>:<port>:<addr>`; IPv6
+/// addresses may be bracketed.
+pub fn parse_resolve_entry(value: &str) -> Result<(String, u16, IpAddr), String> {
+    let mut parts = value.splitn(3, ':');
+    let (Some(host), Some(port), Some(addr)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(format!("expected <host>:<port>:<addr>, got '{value}'"));
+    };
+    if host.is_empty() {
+        return Err(format!("missing host in '{value}'"));
+    }
+    let port = port
+        .parse::<u16>()
+        .map_err(|_| format!("invalid port in '{value}'"))?;
+    let addr = addr
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .map_err(|_| format!("invalid address in '{value}'"))?;
+    Ok((host.to_string(), port, addr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_entries_override_and_fall_back() {
+        let mut resolver = StaticResolver::new(Box::new(SystemResolver));
+        resolver.insert("Proof.Test", 443, "192.0.2.7".parse().unwrap());
+
+        let addrs = resolver.resolve("proof.test", 443).unwrap();
+        assert_eq!(addrs, vec!["192.0.2.7:443".parse().unwrap()]);
+        let local = resolver.resolve("localhost", 443).unwrap();
+        assert!(local.iter().all(|addr| addr.ip().is_loopback()));
+        assert!(resolver.is_override("proof.test", 443));
+        assert!(!resolver.is_override("proof.test", 80));
+        assert!(!resolver.is_override("localhost", 443));
+    }
+
+    #[test]
+    fn parses_resolve_entries() {
+        assert_eq!(
+            parse_resolve_entry("example.com:443:[2001:db8::1]").unwrap(),
+            ("example.com".into(), 443, "2001:db8::1".parse().unwrap())
+        );
+        assert!(parse_resolve_entry("example.com:443").is_err());
+        assert!(parse_resolve_entry("example.com:https:127.0.0.1").is_err());
+    }
+}
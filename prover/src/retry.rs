@@ -0,0 +1,176 @@
+//! `--retry-on-status`: re-capture while an upstream answers with a
+//! transient status (502/503 while it warms up), so the proof is of the
+//! stable response. Only the status decides; capture errors and statement
+//! failures are never retried. Attempts and the waits between them share
+//! the capture's `--capture-timeout-total` budget.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use redproof_prover::capture::Deadline;
+
+/// Re-captures allowed after the first attempt.
+pub const MAX_RETRIES: usize = 3;
+/// Wait before the first re-capture.
+pub const BACKOFF: Duration = Duration::from_millis(500);
+
+pub struct RetryPolicy {
+    /// Statuses that trigger a re-capture; empty disables retrying.
+    pub statuses: Vec<u16>,
+    /// Re-captures allowed after the first attempt.
+    pub max_retries: usize,
+    /// Wait before the first re-capture, doubled before each later one.
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Retry `statuses` with the default limits.
+    pub fn on_statuses(statuses: Vec<u16>) -> Self {
+        Self {
+            statuses,
+            max_retries: MAX_RETRIES,
+            backoff: BACKOFF,
+        }
+    }
+}
+
+/// What the retry loop saw, recorded in the `status_retries` and
+/// `status_sequence` annotations when `--retry-on-status` is set.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct StatusRetries {
+    /// Status of every attempt, in order; the last one is the committed
+    /// response.
+    pub statuses: Vec<u16>,
+}
+
+impl StatusRetries {
+    pub fn retries(&self) -> usize {
+        self.statuses.len().saturating_sub(1)
+    }
+}
+
+/// Call `probe` until `status` of its result is outside the policy's set,
+/// the retries run out or waiting would overrun `deadline`, and return the
+/// last result. An error from `probe` ends the loop immediately.
+pub fn run<T>(
+    policy: &RetryPolicy,
+    deadline: &Deadline,
+    mut probe: impl FnMut() -> Result<T>,
+    status: impl Fn(&T) -> u16,
+) -> Result<(T, StatusRetries)> {
+    let mut seen = StatusRetries::default();
+    let mut backoff = policy.backoff;
+    loop {
+        let result = probe()?;
+        let code = status(&result);
+        seen.statuses.push(code);
+        if !policy.statuses.contains(&code) {
+            return Ok((result, seen));
+        }
+        if seen.retries() >= policy.max_retries {
+            tracing::warn!(status = code, retries = seen.retries(), "retries exhausted");
+            return Ok((result, seen));
+        }
+        if !deadline.clamp(backoff).is_ok_and(|wait| wait >= backoff) {
+            tracing::warn!(
+                status = code,
+                retries = seen.retries(),
+                "capture time budget spent; keeping the last response"
+            );
+            return Ok((result, seen));
+        }
+        tracing::info!(
+            status = code,
+            wait_ms = backoff.as_millis() as u64,
+            "transient status; retrying"
+        );
+        std::thread::sleep(backoff);
+        backoff = backoff.saturating_mul(2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    fn policy(max_retries: usize) -> RetryPolicy {
+        RetryPolicy {
+            statuses: vec![502, 503, 504],
+            max_retries,
+            backoff: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn retries_503_until_200() {
+        // Stub upstream: unavailable once, then healthy.
+        let mut responses = [503, 200].into_iter();
+        let (status, seen) = run(
+            &policy(3),
+            &Deadline::default(),
+            || Ok(responses.next().unwrap()),
+            |s| *s,
+        )
+        .unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(seen.statuses, [503, 200]);
+        assert_eq!(seen.retries(), 1);
+    }
+
+    #[test]
+    fn stops_after_max_retries() {
+        let mut calls = 0;
+        let (status, seen) = run(
+            &policy(2),
+            &Deadline::default(),
+            || {
+                calls += 1;
+                Ok(503)
+            },
+            |s| *s,
+        )
+        .unwrap();
+        assert_eq!((status, calls), (503, 3));
+        assert_eq!(seen.retries(), 2);
+    }
+
+    #[test]
+    fn capture_errors_and_other_statuses_are_not_retried() {
+        let mut calls = 0;
+        let err = run(
+            &policy(3),
+            &Deadline::default(),
+            || -> Result<u16> {
+                calls += 1;
+                Err(anyhow!("connection reset"))
+            },
+            |s| *s,
+        )
+        .unwrap_err();
+        assert_eq!((err.to_string().as_str(), calls), ("connection reset", 1));
+
+        let (_, seen) = run(&policy(3), &Deadline::default(), || Ok(404), |s| *s).unwrap();
+        assert_eq!(seen.statuses, [404]);
+
+        let disabled = RetryPolicy {
+            statuses: Vec::new(),
+            ..policy(3)
+        };
+        let (_, seen) = run(&disabled, &Deadline::default(), || Ok(503), |s| *s).unwrap();
+        assert_eq!(seen.statuses, [503]);
+    }
+
+    #[test]
+    fn backoff_never_overruns_the_capture_budget() {
+        let slow = RetryPolicy {
+            backoff: Duration::from_secs(10),
+            ..policy(3)
+        };
+        let deadline = Deadline::new(Some(Duration::from_millis(100)));
+        let started = std::time::Instant::now();
+        let (status, seen) = run(&slow, &deadline, || Ok(503), |s| *s).unwrap();
+        assert_eq!((status, seen.retries()), (503, 0));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
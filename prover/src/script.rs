@@ -0,0 +1,188 @@
+//! `--request-script`: several requests sent in order, with the artifact
+//! about one chosen response, e.g. the one after a warm-up or
+//! session-establishing request.
+
+use anyhow::{bail, Result};
+use http::Method;
+
+/// Methods a script may use; requests carry no body.
+const SCRIPT_METHODS: [Method; 4] = [Method::GET, Method::HEAD, Method::OPTIONS, Method::DELETE];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptedRequest {
+    pub method: Method,
+    /// Origin-form request target (`/path?query`), or `*` for OPTIONS.
+    pub target: String,
+}
+
+/// Requests sent one after another over one connection while the server
+/// keeps it open, of which the capture commits to the `select`ed response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestScript {
+    pub requests: Vec<ScriptedRequest>,
+    /// Index into `requests` of the committed response.
+    pub select: usize,
+}
+
+impl RequestScript {
+    /// Parse a script of `METHOD /path?query` lines; blank lines and lines
+    /// starting with `#` are skipped. `select` is the 1-based position of
+    /// the committed response, the last request when `None`.
+    pub fn parse(text: &str, select: Option<usize>) -> Result<Self> {
+        let mut requests = Vec::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (method, target) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [method, target] => (method, target),
+                _ => bail!("request script line {}: expected `METHOD /path`", idx + 1),
+            };
+            let Some(method) = SCRIPT_METHODS
+                .into_iter()
+                .find(|known| known.as_str().eq_ignore_ascii_case(method))
+            else {
+                bail!(
+                    "request script line {}: unsupported method '{method}'",
+                    idx + 1
+                );
+            };
+            let asterisk = target == "*" && method == Method::OPTIONS;
+            if !target.starts_with('/') && !asterisk {
+                bail!(
+                    "request script line {}: target '{target}' must start with '/'",
+                    idx + 1
+                );
+            }
+            requests.push(ScriptedRequest {
+                method,
+                target: target.to_string(),
+            });
+        }
+        if requests.is_empty() {
+            bail!("request script has no requests");
+        }
+        let select = match select {
+            None => requests.len() - 1,
+            Some(position @ 1..) if position <= requests.len() => position - 1,
+            Some(position) => bail!(
+                "cannot select response {position}: the script sends {} request(s)",
+                requests.len()
+            ),
+        };
+        Ok(Self { requests, select })
+    }
+
+    pub fn selected(&self) -> &ScriptedRequest {
+        &self.requests[self.select]
+    }
+}
+
+/// Cookies set by earlier script responses, sent with later requests. Every
+/// step targets the same origin, so only names and values are kept.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: Vec<(String, String)>,
+}
+
+impl CookieJar {
+    /// Take the `Set-Cookie` lines of a response head. A cookie set again
+    /// replaces the old value; `Max-Age=0` removes it.
+    pub fn store(&mut self, head: &str) {
+        for line in head.split("\r\n").skip(1) {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            if !name.trim().eq_ignore_ascii_case("set-cookie") {
+                continue;
+            }
+            let mut attributes = value.split(';');
+            let Some((name, value)) = attributes.next().and_then(|pair| pair.split_once('='))
+            else {
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            if name.is_empty() {
+                continue;
+            }
+            self.cookies.retain(|(known, _)| known != name);
+            let expired = attributes.any(|attribute| {
+                attribute.split_once('=').is_some_and(|(key, value)| {
+                    key.trim().eq_ignore_ascii_case("max-age") && value.trim() == "0"
+                })
+            });
+            if !expired {
+                self.cookies.push((name.to_string(), value.to_string()));
+            }
+        }
+    }
+
+    /// `Cookie` request header value, if any cookie is set.
+    pub fn header(&self) -> Option<String> {
+        (!self.cookies.is_empty()).then(|| {
+            self.cookies
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_requests_and_selection() {
+        let text = "# establish a session\nget /login\n\nHEAD /account?tab=1\n";
+        let script = RequestScript::parse(text, None).unwrap();
+        assert_eq!(script.requests.len(), 2);
+        assert_eq!(script.requests[0].method, Method::GET);
+        assert_eq!(
+            script.selected(),
+            &ScriptedRequest {
+                method: Method::HEAD,
+                target: "/account?tab=1".into(),
+            }
+        );
+        assert_eq!(RequestScript::parse(text, Some(1)).unwrap().select, 0);
+    }
+
+    #[test]
+    fn rejects_malformed_scripts() {
+        let err = |text: &str, select| RequestScript::parse(text, select).unwrap_err().to_string();
+        assert_eq!(
+            err("GET /\nPOST /x", None),
+            "request script line 2: unsupported method 'POST'"
+        );
+        assert_eq!(
+            err("GET", None),
+            "request script line 1: expected `METHOD /path`"
+        );
+        assert_eq!(
+            err("GET login", None),
+            "request script line 1: target 'login' must start with '/'"
+        );
+        assert_eq!(err("# nothing\n", None), "request script has no requests");
+        assert_eq!(
+            err("GET /", Some(2)),
+            "cannot select response 2: the script sends 1 request(s)"
+        );
+        assert!(RequestScript::parse("OPTIONS *", Some(1)).is_ok());
+    }
+
+    #[test]
+    fn cookie_jar_replaces_and_expires_cookies() {
+        let mut jar = CookieJar::default();
+        assert_eq!(jar.header(), None);
+        jar.store(
+            "HTTP/1.1 200 OK\r\nSet-Cookie: session=abc; Path=/; HttpOnly\r\n\
+             set-cookie: theme=dark\r\nX-Other: a=b",
+        );
+        assert_eq!(jar.header().as_deref(), Some("session=abc; theme=dark"));
+        jar.store("HTTP/1.1 200 OK\r\nSet-Cookie: theme=light\r\nSet-Cookie: session=; Max-Age=0");
+        assert_eq!(jar.header().as_deref(), Some("theme=light"));
+    }
+}
@@ -0,0 +1,151 @@
+//! Watch mode: re-capture on an interval and emit an artifact only when the
+//! response changed since the last successful capture.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// One JSON line on stdout. Unchanged captures print nothing.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum WatchEvent {
+    Changed {
+        iteration: usize,
+        digest: String,
+        artifact: PathBuf,
+    },
+    /// A failed capture; it does not count as a change.
+    Error { iteration: usize, error: String },
+}
+
+pub struct WatchOptions {
+    pub interval: Duration,
+    /// Stop after this many capture attempts; runs forever when `None`.
+    pub max_iterations: Option<usize>,
+}
+
+/// Call `probe` every `interval`. Whenever `digest` of a successful result
+/// differs from the previous successful one, `emit` writes an artifact and a
+/// `changed` event is printed. Returns the number of changes.
+pub fn run<T>(
+    options: &WatchOptions,
+    mut probe: impl FnMut() -> Result<T>,
+    digest: impl Fn(&T) -> Result<String>,
+    mut emit: impl FnMut(&T) -> Result<PathBuf>,
+    out: &mut impl Write,
+) -> Result<usize> {
+    let mut last = None;
+    let mut changes = 0;
+    let mut iteration = 0;
+    loop {
+        if iteration > 0 {
+            std::thread::sleep(options.interval);
+        }
+        let event = match probe().and_then(|snapshot| Ok((digest(&snapshot)?, snapshot))) {
+            Ok((current, _)) if last.as_ref() == Some(&current) => None,
+            Ok((current, snapshot)) => match emit(&snapshot) {
+                Ok(artifact) => {
+                    changes += 1;
+                    last = Some(current.clone());
+                    Some(WatchEvent::Changed {
+                        iteration,
+                        digest: current,
+                        artifact,
+                    })
+                }
+                Err(err) => Some(WatchEvent::Error {
+                    iteration,
+                    error: format!("{err:#}"),
+                }),
+            },
+            Err(err) => Some(WatchEvent::Error {
+                iteration,
+                error: format!("{err:#}"),
+            }),
+        };
+        if let Some(event) = event {
+            serde_json::to_writer(&mut *out, &event)?;
+            out.write_all(b"\n")?;
+            out.flush()?;
+        }
+        iteration += 1;
+        if options.max_iterations.is_some_and(|max| iteration >= max) {
+            return Ok(changes);
+        }
+    }
+}
+
+/// `proof.red` becomes `proof-20250101T120000.000Z.red` next to it.
+pub fn artifact_path(out: &Path, captured_at: DateTime<Utc>) -> PathBuf {
+    let stem = out
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "proof".into());
+    let stamp = captured_at.format("%Y%m%dT%H%M%S%.3fZ");
+    let name = match out.extension() {
+        Some(ext) => format!("{stem}-{stamp}.{}", ext.to_string_lossy()),
+        None => format!("{stem}-{stamp}"),
+    };
+    out.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use chrono::TimeZone;
+
+    #[test]
+    fn emits_only_on_change_and_skips_errors() {
+        // Stub server: same body twice, an outage, then a new body.
+        let mut responses = vec![
+            Ok("v1"),
+            Ok("v1"),
+            Err(anyhow!("connection reset")),
+            Ok("v1"),
+            Ok("v2"),
+            Ok("v2"),
+        ]
+        .into_iter();
+        let mut written = Vec::new();
+        let mut out = Vec::new();
+        let options = WatchOptions {
+            interval: Duration::ZERO,
+            max_iterations: Some(6),
+        };
+
+        let changes = run(
+            &options,
+            || responses.next().unwrap(),
+            |body: &&str| Ok(format!("digest-{body}")),
+            |body| {
+                written.push(body.to_string());
+                Ok(PathBuf::from(format!("proof-{}.red", written.len())))
+            },
+            &mut out,
+        )
+        .expect("watch");
+
+        assert_eq!(changes, 2);
+        assert_eq!(written, ["v1", "v2"]);
+        let events = String::from_utf8(out).unwrap();
+        let lines = events.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains(r#""event":"changed","iteration":0"#));
+        assert!(lines[1].contains(r#""event":"error","iteration":2"#));
+        assert!(lines[2].contains(r#""digest":"digest-v2""#));
+    }
+
+    #[test]
+    fn artifact_path_adds_capture_time() {
+        let at = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+        assert_eq!(
+            artifact_path(Path::new("out/proof.red"), at),
+            PathBuf::from("out/proof-20250102T030405.000Z.red")
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! `cache:hit`: whether a response was served from a cache, judged from the
+//! headers caches and CDNs add. The conventions vary, so the verdict is a
+//! heuristic and the header set can be chosen per statement.
+
+use crate::Statement;
+
+/// Indicator headers consulted when a statement names none, in order.
+pub const DEFAULT_HEADERS: [&str; 3] = ["x-cache", "cf-cache-status", "age"];
+
+/// Status words meaning the cache answered, besides those ending in `HIT`
+/// (`HIT`, `TCP_HIT`, `Hit from cloudfront`).
+const SERVED_FROM_CACHE: [&str; 3] = ["STALE", "UPDATING", "REVALIDATED"];
+
+/// The header that decided a [`detect`] verdict.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheVerdict {
+    pub hit: bool,
+    pub header: String,
+    pub value: String,
+}
+
+/// Judge the response by the first header of `indicators` (or
+/// [`DEFAULT_HEADERS`] when empty) it carries in a recognizable form.
+/// `lookup` returns a header's first value, matching names
+/// case-insensitively. An `Age` above zero is a hit; other headers are hits
+/// when their value reads `HIT`, `TCP_HIT`, `STALE` and the like. `None`
+/// when no indicator is present.
+pub fn detect<'a>(
+    indicators: &[String],
+    lookup: impl Fn(&str) -> Option<&'a str>,
+) -> Option<CacheVerdict> {
+    let defaults = DEFAULT_HEADERS.map(String::from);
+    let indicators = if indicators.is_empty() {
+        &defaults[..]
+    } else {
+        indicators
+    };
+    indicators.iter().find_map(|header| {
+        let value = lookup(header)?.trim();
+        let hit = if header.eq_ignore_ascii_case("age") {
+            value.parse::<u64>().ok()? > 0
+        } else {
+            value
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .map(str::to_ascii_uppercase)
+                .any(|word| word.ends_with("HIT") || SERVED_FROM_CACHE.contains(&word.as_str()))
+        };
+        Some(CacheVerdict {
+            hit,
+            header: header.to_ascii_lowercase(),
+            value: value.to_string(),
+        })
+    })
+}
+
+impl Statement {
+    /// Give every `cache:hit` member that names no indicator headers the
+    /// set `headers`, e.g. from the prover's `--cache-header`.
+    pub fn with_cache_headers(self, headers: &[String]) -> Statement {
+        if headers.is_empty() {
+            return self;
+        }
+        match self {
+            Statement::CacheHit {
+                expected,
+                headers: own,
+            } if own.is_empty() => Statement::CacheHit {
+                expected,
+                headers: headers.to_vec(),
+            },
+            Statement::All { statements } => Statement::All {
+                statements: set_all(statements, headers),
+            },
+            Statement::Any { statements } => Statement::Any {
+                statements: set_all(statements, headers),
+            },
+            Statement::RegexReplace {
+                pattern,
+                replacement,
+                statement,
+            } => Statement::RegexReplace {
+                pattern,
+                replacement,
+                statement: Box::new(statement.with_cache_headers(headers)),
+            },
+            other => other,
+        }
+    }
+}
+
+fn set_all(statements: Vec<Statement>, headers: &[String]) -> Vec<Statement> {
+    statements
+        .into_iter()
+        .map(|statement| statement.with_cache_headers(headers))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verdict(headers: &[(&str, &str)], indicators: &[&str]) -> Option<CacheVerdict> {
+        let indicators = indicators
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        detect(&indicators, |name| {
+            headers
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                .map(|(_, value)| *value)
+        })
+    }
+
+    #[test]
+    fn reads_common_cache_indicators() {
+        let hit = verdict(&[("X-Cache", "Hit from cloudfront")], &[]).unwrap();
+        assert!(hit.hit);
+        assert_eq!(hit.header, "x-cache");
+        assert!(!verdict(&[("X-Cache", "MISS")], &[]).unwrap().hit);
+        assert!(
+            verdict(&[("CF-Cache-Status", "REVALIDATED")], &[])
+                .unwrap()
+                .hit
+        );
+        assert!(!verdict(&[("CF-Cache-Status", "DYNAMIC")], &[]).unwrap().hit);
+        assert!(verdict(&[("Age", "60")], &[]).unwrap().hit);
+        assert!(!verdict(&[("Age", "0")], &[]).unwrap().hit);
+    }
+
+    #[test]
+    fn first_configured_indicator_decides() {
+        let headers = [("X-Cache", "MISS"), ("Age", "60"), ("X-Proxy-Cache", "HIT")];
+        assert!(!verdict(&headers, &[]).unwrap().hit);
+        let custom = verdict(&headers, &["x-proxy-cache"]).unwrap();
+        assert!(custom.hit);
+        assert_eq!(custom.header, "x-proxy-cache");
+        assert_eq!(verdict(&[("Server", "nginx"), ("Age", "soon")], &[]), None);
+    }
+}
@@ -0,0 +1,113 @@
+//! Builders for composing statements in code: `a.and(b)`, `a | b`,
+//! `Statement::all([...])`. Members of the same kind are spliced in rather
+//! than nested, so chains stay one level deep. Duplicates are kept; see
+//! [`Statement::normalize`] for deduplication.
+
+use std::ops::{BitAnd, BitOr};
+
+use crate::Statement;
+
+impl Statement {
+    /// Conjunction of `statements`, splicing in the members of nested `all`s.
+    pub fn all(statements: impl IntoIterator<Item = Statement>) -> Statement {
+        Statement::All {
+            statements: flatten(statements, true),
+        }
+    }
+
+    /// Disjunction of `statements`, splicing in the members of nested `any`s.
+    pub fn any(statements: impl IntoIterator<Item = Statement>) -> Statement {
+        Statement::Any {
+            statements: flatten(statements, false),
+        }
+    }
+
+    /// Both `self` and `other` hold.
+    pub fn and(self, other: Statement) -> Statement {
+        Statement::all([self, other])
+    }
+
+    /// `self`, `other`, or both hold.
+    pub fn or(self, other: Statement) -> Statement {
+        Statement::any([self, other])
+    }
+}
+
+fn flatten(statements: impl IntoIterator<Item = Statement>, conjunction: bool) -> Vec<Statement> {
+    let mut members = Vec::new();
+    for statement in statements {
+        match statement {
+            Statement::All { statements } if conjunction => members.extend(statements),
+            Statement::Any { statements } if !conjunction => members.extend(statements),
+            other => members.push(other),
+        }
+    }
+    members
+}
+
+impl BitAnd for Statement {
+    type Output = Statement;
+
+    fn bitand(self, other: Statement) -> Statement {
+        self.and(other)
+    }
+}
+
+impl BitOr for Statement {
+    type Output = Statement;
+
+    fn bitor(self, other: Statement) -> Statement {
+        self.or(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn present(target: &str) -> Statement {
+        Statement::HeaderPresent {
+            target: target.into(),
+        }
+    }
+
+    #[test]
+    fn chains_of_one_kind_stay_flat() {
+        let chained = present("A").and(present("B")).and(present("C"));
+        assert_eq!(
+            chained,
+            Statement::All {
+                statements: vec![present("A"), present("B"), present("C")],
+            }
+        );
+        assert_eq!(present("A") & present("B") & present("C"), chained);
+        assert_eq!(
+            Statement::any([present("A") | present("B"), present("C")]),
+            Statement::Any {
+                statements: vec![present("A"), present("B"), present("C")],
+            }
+        );
+    }
+
+    #[test]
+    fn mixed_kinds_nest() {
+        let mixed = (present("A") | present("B")) & present("C");
+        assert_eq!(
+            mixed,
+            Statement::All {
+                statements: vec![
+                    Statement::Any {
+                        statements: vec![present("A"), present("B")],
+                    },
+                    present("C"),
+                ],
+            }
+        );
+        assert_eq!(
+            Statement::all([present("A"), present("A")]),
+            Statement::All {
+                statements: vec![present("A"), present("A")],
+            }
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! `body:content-digest`: the body hashes to what the server's own
+//! `Content-Digest` (RFC 9530, `sha-256=:<base64>:`) or legacy `Digest`
+//! (RFC 3230, `SHA-256=<base64>`) header claims. Digests cover the content
+//! without transfer framing, so a chunked body is de-chunked first.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256, Sha512};
+
+/// Check every `sha-256`/`sha-512` digest in the `Content-Digest` and
+/// `Digest` headers of `headers` against `body`, the body as read off the
+/// wire. Other algorithms are skipped. `Ok` names the matching digests;
+/// `Err` says why the claim does not hold, including when no supported
+/// digest was sent.
+pub fn check<'a>(
+    headers: impl IntoIterator<Item = (&'a str, &'a str)> + Clone,
+    body: &[u8],
+) -> Result<String, String> {
+    let chunked = headers.clone().into_iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("transfer-encoding")
+            && value.to_ascii_lowercase().contains("chunked")
+    });
+    let dechunked;
+    let body = if chunked {
+        dechunked = dechunk(body).ok_or("chunked body is malformed")?;
+        &dechunked[..]
+    } else {
+        body
+    };
+    let mut checked = Vec::new();
+    let mut skipped = Vec::new();
+    for (name, value) in headers {
+        let structured = if name.eq_ignore_ascii_case("content-digest") {
+            true
+        } else if name.eq_ignore_ascii_case("digest") {
+            false
+        } else {
+            continue;
+        };
+        for member in value.split(',') {
+            let Some((algorithm, encoded)) = member.split_once('=') else {
+                continue;
+            };
+            let algorithm = algorithm.trim().to_ascii_lowercase();
+            let computed = match algorithm.as_str() {
+                "sha-256" => Sha256::digest(body).to_vec(),
+                "sha-512" => Sha512::digest(body).to_vec(),
+                _ => {
+                    skipped.push(algorithm);
+                    continue;
+                }
+            };
+            // Structured-field byte sequences are `:<base64>:`, with any
+            // parameters after a `;`.
+            let encoded = encoded.split(';').next().unwrap_or_default().trim();
+            let encoded = if structured {
+                encoded
+                    .strip_prefix(':')
+                    .and_then(|rest| rest.strip_suffix(':'))
+                    .ok_or_else(|| format!("{name} {algorithm} value is not a byte sequence"))?
+            } else {
+                encoded
+            };
+            let claimed = STANDARD
+                .decode(encoded)
+                .map_err(|_| format!("{name} {algorithm} value is not base64"))?;
+            if claimed != computed {
+                return Err(format!(
+                    "{name} {algorithm} mismatch: body hashes to {}",
+                    STANDARD.encode(computed)
+                ));
+            }
+            checked.push(format!("{} {algorithm}", name.to_ascii_lowercase()));
+        }
+    }
+    if checked.is_empty() {
+        return Err(if skipped.is_empty() {
+            "no Content-Digest or Digest header".to_string()
+        } else {
+            format!("no supported digest algorithm (saw {})", skipped.join(", "))
+        });
+    }
+    Ok(format!("{} match", checked.join(", ")))
+}
+
+/// The content of a `Transfer-Encoding: chunked` body; `None` when the
+/// framing is broken or ends before the last chunk.
+fn dechunk(mut framed: &[u8]) -> Option<Vec<u8>> {
+    let mut content = Vec::new();
+    loop {
+        let line_end = framed.windows(2).position(|pair| pair == b"\r\n")?;
+        let size_line = std::str::from_utf8(&framed[..line_end]).ok()?;
+        // Chunk extensions follow a `;`.
+        let size = size_line.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        framed = &framed[line_end + 2..];
+        if size == 0 {
+            return Some(content);
+        }
+        if framed.len() < size + 2 || &framed[size..size + 2] != b"\r\n" {
+            return None;
+        }
+        content.extend_from_slice(&framed[..size]);
+        framed = &framed[size + 2..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = b"{\"hello\": \"world\"}";
+    // sha-256 of BODY.
+    const SHA256: &str = "X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=";
+
+    #[test]
+    fn valid_content_digest_matches() {
+        let header = format!("sha-256=:{SHA256}:");
+        assert_eq!(
+            check([("Content-Digest", header.as_str())], BODY),
+            Ok("content-digest sha-256 match".to_string())
+        );
+        let legacy = format!("MD5=abc, SHA-256={SHA256}");
+        assert_eq!(
+            check([("Digest", legacy.as_str())], BODY),
+            Ok("digest sha-256 match".to_string())
+        );
+    }
+
+    #[test]
+    fn every_supported_algorithm_must_match() {
+        let sha512 = STANDARD.encode(Sha512::digest(BODY));
+        let header = format!("sha-512=:{sha512}:, sha-256=:{SHA256}:");
+        assert!(check([("content-digest", header.as_str())], BODY).is_ok());
+
+        let wrong = format!("sha-256=:{SHA256}:, sha-512=:{SHA256}:");
+        let err = check([("content-digest", wrong.as_str())], BODY).unwrap_err();
+        assert!(err.starts_with("content-digest sha-512 mismatch"), "{err}");
+    }
+
+    #[test]
+    fn mismatched_content_digest_fails() {
+        let header = format!("sha-256=:{SHA256}:");
+        let err = check([("Content-Digest", header.as_str())], b"tampered").unwrap_err();
+        assert!(err.starts_with("Content-Digest sha-256 mismatch"), "{err}");
+    }
+
+    #[test]
+    fn chunked_bodies_are_checked_without_framing() {
+        let header = format!("sha-256=:{SHA256}:");
+        let headers = [
+            ("Content-Digest", header.as_str()),
+            ("Transfer-Encoding", "chunked"),
+        ];
+        let framed = b"5\r\n{\"hel\r\nd;ext=1\r\nlo\": \"world\"}\r\n0\r\n\r\n";
+        assert!(check(headers, framed).is_ok());
+        assert_eq!(
+            check(headers, b"5\r\n{\"hel"),
+            Err("chunked body is malformed".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_or_unusable_headers_fail() {
+        assert_eq!(
+            check([("Content-Type", "application/json")], BODY),
+            Err("no Content-Digest or Digest header".to_string())
+        );
+        assert_eq!(
+            check([("Content-Digest", "md5=:abc:")], BODY),
+            Err("no supported digest algorithm (saw md5)".to_string())
+        );
+        let unwrapped = format!("sha-256={SHA256}");
+        assert!(check([("Content-Digest", unwrapped.as_str())], BODY).is_err());
+    }
+}
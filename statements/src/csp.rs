@@ -0,0 +1,101 @@
+//! Minimal Content-Security-Policy parsing for set-wise directive checks.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::DirectiveMode;
+
+/// Directive name -> allowed sources, merged across every policy.
+pub type CspDirectives = BTreeMap<String, BTreeSet<String>>;
+
+/// Parse one or more CSP header values. Within a policy only the first
+/// occurrence of a directive counts; across policies sources are unioned.
+pub fn parse_policies<'a>(values: impl IntoIterator<Item = &'a String>) -> CspDirectives {
+    let mut merged = CspDirectives::new();
+    for value in values {
+        let mut seen = BTreeSet::new();
+        for directive in value.split(';') {
+            let mut tokens = directive.split_ascii_whitespace();
+            let Some(name) = tokens.next() else {
+                continue;
+            };
+            let name = name.to_ascii_lowercase();
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            merged
+                .entry(name)
+                .or_default()
+                .extend(tokens.map(normalize_source));
+        }
+    }
+    merged
+}
+
+/// Sources compare case-insensitively, except nonces and hashes whose
+/// base64 payload is case-sensitive.
+pub fn normalize_source(source: &str) -> String {
+    let lower = source.to_ascii_lowercase();
+    if lower.starts_with("'nonce-") || lower.starts_with("'sha") {
+        source.to_string()
+    } else {
+        lower
+    }
+}
+
+/// Check `directive` against `expected` under `mode`; `Err` explains a miss.
+pub fn check(
+    policies: &CspDirectives,
+    directive: &str,
+    expected: &[String],
+    mode: DirectiveMode,
+) -> Result<(), String> {
+    let expected = expected
+        .iter()
+        .map(|source| normalize_source(source))
+        .collect::<BTreeSet<_>>();
+    let directive = directive.to_ascii_lowercase();
+    let actual = policies.get(&directive);
+    match (mode, actual) {
+        (DirectiveMode::Exact | DirectiveMode::Contains, None) => {
+            Err(format!("directive {directive} not present"))
+        }
+        (DirectiveMode::Exact, Some(actual)) if *actual != expected => {
+            Err(format!("{directive} sources are [{}]", join(actual.iter())))
+        }
+        (DirectiveMode::Contains, Some(actual)) if !expected.is_subset(actual) => Err(format!(
+            "{directive} lacks [{}]",
+            join(expected.difference(actual))
+        )),
+        (DirectiveMode::Absent, Some(_)) if expected.is_empty() => {
+            Err(format!("directive {directive} present"))
+        }
+        (DirectiveMode::Absent, Some(actual)) if !expected.is_disjoint(actual) => Err(format!(
+            "{directive} allows [{}]",
+            join(expected.intersection(actual))
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn join<'a>(sources: impl Iterator<Item = &'a String>) -> String {
+    sources.map(String::as_str).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_policies_and_keeps_first_duplicate_directive() {
+        let values = vec![
+            "default-src 'self'; script-src 'self'; script-src *".to_string(),
+            "Script-Src https://CDN.example 'nonce-AbC'".to_string(),
+        ];
+        let policies = parse_policies(&values);
+        let script = &policies["script-src"];
+        assert!(script.contains("'self'"));
+        assert!(script.contains("https://cdn.example"));
+        assert!(script.contains("'nonce-AbC'"));
+        assert!(!script.contains("*"));
+    }
+}
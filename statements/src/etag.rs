@@ -0,0 +1,105 @@
+//! Entity tags (RFC 7232 §2.3): an opaque quoted string, optionally marked
+//! weak with `W/`. Comparing raw header text gets `W/"a"` vs `"a"` wrong.
+
+/// A parsed `ETag` value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityTag {
+    pub weak: bool,
+    /// The tag between the quotes.
+    pub opaque: String,
+}
+
+impl EntityTag {
+    /// Parse a header value such as `"xyzzy"` or `W/"xyzzy"`. `None` for
+    /// values that are not a single entity tag.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (weak, quoted) = match value.strip_prefix("W/") {
+            Some(rest) => (true, rest),
+            None => (false, value),
+        };
+        let opaque = quoted.strip_prefix('"')?.strip_suffix('"')?;
+        // etagc = %x21 / %x23-7E / obs-text: no DQUOTE, space or controls.
+        if opaque
+            .bytes()
+            .any(|byte| byte == b'"' || byte <= b' ' || byte == 0x7f)
+        {
+            return None;
+        }
+        Some(Self {
+            weak,
+            opaque: opaque.to_string(),
+        })
+    }
+
+    /// Parse an expected tag given on the command line, where the quotes are
+    /// usually lost to shell or statement quoting: `xyzzy` and `W/xyzzy` are
+    /// accepted alongside the header forms.
+    pub fn parse_lenient(value: &str) -> Option<Self> {
+        let value = value.trim();
+        Self::parse(value).or_else(|| {
+            let (prefix, opaque) = match value.strip_prefix("W/") {
+                Some(rest) => ("W/", rest),
+                None => ("", value),
+            };
+            Self::parse(&format!("{prefix}\"{opaque}\""))
+        })
+    }
+
+    /// Strong comparison when `allow_weak` is false (both tags strong and
+    /// identical), weak comparison otherwise (opaque tags identical).
+    pub fn matches(&self, other: &EntityTag, allow_weak: bool) -> bool {
+        self.opaque == other.opaque && (allow_weak || (!self.weak && !other.weak))
+    }
+}
+
+impl std::fmt::Display for EntityTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.weak {
+            f.write_str("W/")?;
+        }
+        write!(f, "\"{}\"", self.opaque)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(value: &str) -> EntityTag {
+        EntityTag::parse(value).unwrap()
+    }
+
+    #[test]
+    fn compares_per_rfc_7232_table() {
+        // RFC 7232 §2.3.2 example table.
+        let cases = [
+            (r#"W/"1""#, r#"W/"1""#, false, true),
+            (r#"W/"1""#, r#"W/"2""#, false, false),
+            (r#"W/"1""#, r#""1""#, false, true),
+            (r#""1""#, r#""1""#, true, true),
+        ];
+        for (a, b, strong, weak) in cases {
+            assert_eq!(tag(a).matches(&tag(b), false), strong, "{a} vs {b}");
+            assert_eq!(tag(a).matches(&tag(b), true), weak, "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn opaque_tag_must_match_exactly() {
+        assert!(!tag(r#""abc""#).matches(&tag(r#""ABC""#), true));
+        assert!(!tag(r#""abc""#).matches(&tag(r#""abc-gzip""#), true));
+        assert_eq!(tag(r#" W/"a/b" "#).opaque, "a/b");
+    }
+
+    #[test]
+    fn rejects_malformed_values_and_accepts_bare_cli_tags() {
+        for value in ["abc", r#""a"b""#, r#""a b""#, r#"w/"a""#, r#""a"#] {
+            assert_eq!(EntityTag::parse(value), None, "{value}");
+        }
+        assert_eq!(EntityTag::parse_lenient("abc"), Some(tag(r#""abc""#)));
+        assert_eq!(EntityTag::parse_lenient("W/abc"), Some(tag(r#"W/"abc""#)));
+        assert_eq!(EntityTag::parse_lenient("a b"), None);
+        assert_eq!(tag(r#"W/"abc""#).to_string(), r#"W/"abc""#);
+    }
+}
@@ -0,0 +1,2803 @@
+//! Statement evaluation over an [`EvalRecord`], shared by the prover's live
+//! capture and the verifier's `--recompute-all`.
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use encoding_rs::{Encoding, UTF_8};
+use regex::bytes::{Regex as BytesRegex, RegexBuilder as BytesRegexBuilder};
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::link::{self, LinkRel};
+use crate::record::{EvalRecord, HeaderEntry, HeaderMap, HttpResponse};
+use crate::san::covering_name;
+use crate::{
+    cache, content_digest, csp, field, header_casing, http_version, json_schema, server,
+    split_list, transform, EntityTag, Glob, HashAlgorithm, NumOp, RegexScope, Statement,
+};
+
+/// Upper bound on matches counted for `regex:count`, so pathological bodies
+/// cannot stall evaluation.
+const MAX_COUNTED_MATCHES: usize = 10_000;
+
+#[derive(Debug, Serialize)]
+pub struct StatementEvaluation {
+    pub satisfied: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    /// Why the statement is unsatisfied, for callers that branch on it;
+    /// `details` stays the explanation for people. `None` when satisfied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<EvalReason>,
+}
+
+/// Machine-readable cause of an unsatisfied [`StatementEvaluation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvalReason {
+    /// The response was checked and does not say what the statement claims.
+    ValueMismatch,
+    /// A header the statement needs was not sent.
+    HeaderNotFound,
+    /// A header was sent but its value cannot be interpreted: not an
+    /// integer, a malformed ETag or Date, several values where one belongs.
+    MalformedValue,
+    /// The statement's regex or glob does not compile.
+    InvalidRegex,
+    /// Another operand of the statement is unusable, such as a digest,
+    /// base64 bytes, an entity tag or a JSON Schema.
+    InvalidOperand,
+    /// The body was cut off at the capture limit before the check could be
+    /// decided.
+    BodyTruncated,
+    /// The body was hashed as it streamed in, so its bytes are unavailable.
+    BodyStreamed,
+    /// The body is not valid UTF-8 where JSON was expected.
+    NonUtf8Body,
+    /// The body or canonical document is not JSON.
+    NotJson,
+    /// The capture holds nothing to check: a `--record-*` option was off,
+    /// or no certificate chain was served.
+    NotRecorded,
+    /// The record lacks a fact it cannot vouch for, such as an uncommitted
+    /// timing trace when rebuilt from a witness.
+    NotCommitted,
+    /// No member of an `any` composite was satisfied.
+    NoMemberSatisfied,
+}
+
+/// [`EvalReason::ValueMismatch`] unless `satisfied`.
+fn mismatch(satisfied: bool) -> Option<EvalReason> {
+    (!satisfied).then_some(EvalReason::ValueMismatch)
+}
+
+/// How `all`/`any` composites visit their members.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvalMode {
+    /// Stop at the first member that decides the result and report only it.
+    #[default]
+    ShortCircuit,
+    /// Evaluate every leaf and report each outcome, e.g. to list all failing
+    /// checks of a large baseline at once.
+    All,
+}
+
+/// [`evaluate`] with composites visited as `mode` asks. In
+/// [`EvalMode::All`] the details list every leaf as `satisfied: ...` or
+/// `unsatisfied: ...`, separated by `; `.
+pub fn evaluate_with(
+    statement: &Statement,
+    record: &EvalRecord,
+    mode: EvalMode,
+) -> StatementEvaluation {
+    if mode == EvalMode::ShortCircuit
+        || !matches!(statement, Statement::All { .. } | Statement::Any { .. })
+    {
+        return evaluate(statement, record);
+    }
+    let mut leaves = Vec::new();
+    let reason = evaluate_leaves(statement, record, &mut leaves);
+    StatementEvaluation {
+        satisfied: reason.is_none(),
+        details: Some(leaves.join("; ")),
+        reason,
+    }
+}
+
+/// Evaluate every leaf under `statement`, recording each outcome in `leaves`.
+/// Returns the reason `statement` is unsatisfied, as [`evaluate`] would
+/// report it, or `None` when it holds.
+fn evaluate_leaves(
+    statement: &Statement,
+    record: &EvalRecord,
+    leaves: &mut Vec<String>,
+) -> Option<EvalReason> {
+    match statement {
+        Statement::All { statements } | Statement::Any { statements } => {
+            // Collect first: `all`/`any` would stop visiting leaves early.
+            let results = statements
+                .iter()
+                .map(|member| evaluate_leaves(member, record, leaves))
+                .collect::<Vec<_>>();
+            if matches!(statement, Statement::All { .. }) {
+                results.into_iter().flatten().next()
+            } else {
+                results
+                    .iter()
+                    .all(Option::is_some)
+                    .then_some(EvalReason::NoMemberSatisfied)
+            }
+        }
+        leaf => {
+            let outcome = evaluate(leaf, record);
+            leaves.push(if outcome.satisfied {
+                format!("satisfied: {}", leaf.summary())
+            } else {
+                failed_leaf_details(leaf, &outcome)
+            });
+            (!outcome.satisfied).then(|| outcome.reason.unwrap_or(EvalReason::ValueMismatch))
+        }
+    }
+}
+
+pub fn evaluate(statement: &Statement, record: &EvalRecord) -> StatementEvaluation {
+    match statement {
+        Statement::HeaderCasingIs {
+            target,
+            expected_casing,
+        } => {
+            let headers = record
+                .response
+                .headers
+                .iter()
+                .map(|h| (h.name.as_str(), h.wire_name.as_deref()));
+            let outcome = header_casing::check(target, expected_casing, headers);
+            let reason = outcome.is_err().then(|| {
+                match record
+                    .response
+                    .headers
+                    .iter()
+                    .find(|h| h.name.eq_ignore_ascii_case(target))
+                {
+                    None => EvalReason::HeaderNotFound,
+                    Some(h) if h.wire_name.is_none() => EvalReason::NotRecorded,
+                    Some(_) => EvalReason::ValueMismatch,
+                }
+            });
+            StatementEvaluation {
+                satisfied: outcome.is_ok(),
+                details: outcome.err(),
+                reason,
+            }
+        }
+        Statement::HeaderPresent { target } => {
+            let key = target.to_ascii_lowercase();
+            let satisfied = record.headers.contains_key(&key);
+            StatementEvaluation {
+                satisfied,
+                details: None,
+                reason: (!satisfied).then_some(EvalReason::HeaderNotFound),
+            }
+        }
+        Statement::HeaderAbsent { target } => {
+            let key = target.to_ascii_lowercase();
+            let satisfied = !record.headers.contains_key(&key);
+            StatementEvaluation {
+                satisfied,
+                details: None,
+                reason: mismatch(satisfied),
+            }
+        }
+        Statement::HeadersAllAbsent { targets } => {
+            let present = targets
+                .iter()
+                .filter(|target| record.headers.contains_key(&target.to_ascii_lowercase()))
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            StatementEvaluation {
+                satisfied: present.is_empty(),
+                details: (!present.is_empty())
+                    .then(|| format!("present headers: {}", present.join(", "))),
+                reason: mismatch(present.is_empty()),
+            }
+        }
+        Statement::HeaderEquals {
+            target,
+            expected,
+            case_sensitive,
+        } => {
+            let key = target.to_ascii_lowercase();
+            let values = record.headers.get(&key);
+            let satisfied = values.is_some_and(|vals| {
+                vals.iter()
+                    .any(|val| compare_value(val, expected, *case_sensitive))
+            });
+            StatementEvaluation {
+                satisfied,
+                details: None,
+                reason: header_reason(values.is_some(), satisfied),
+            }
+        }
+        Statement::HeaderGlob {
+            target,
+            pattern,
+            case_sensitive,
+        } => match Glob::new(pattern, *case_sensitive) {
+            Ok(glob) => {
+                let values = record.headers.get(&target.to_ascii_lowercase());
+                let satisfied =
+                    values.is_some_and(|vals| vals.iter().any(|val| glob.is_match(val)));
+                StatementEvaluation {
+                    satisfied,
+                    details: None,
+                    reason: header_reason(values.is_some(), satisfied),
+                }
+            }
+            Err(err) => StatementEvaluation {
+                satisfied: false,
+                details: Some(format!("invalid glob: {err}")),
+                reason: Some(EvalReason::InvalidRegex),
+            },
+        },
+        Statement::ETagEquals {
+            expected,
+            allow_weak,
+        } => evaluate_etag(record, expected, *allow_weak),
+        Statement::HeaderSetEquals { target, expected } => {
+            evaluate_header_set(record, target, expected, true)
+        }
+        Statement::HeaderSetContains { target, expected } => {
+            evaluate_header_set(record, target, expected, false)
+        }
+        Statement::HeaderNumeric { target, op, value } => {
+            evaluate_header_numeric(record, target, *op, *value)
+        }
+        Statement::HeaderNameAbsentMatching { pattern } => match build_regex(pattern, false) {
+            Ok(re) => {
+                let offending = matching_header_names(&record.response.headers, &re);
+                StatementEvaluation {
+                    satisfied: offending.is_empty(),
+                    details: (!offending.is_empty())
+                        .then(|| format!("matching headers: {}", offending.join(", "))),
+                    reason: mismatch(offending.is_empty()),
+                }
+            }
+            Err(err) => invalid_regex(err),
+        },
+        Statement::AllHeadersValueMatch {
+            name_pattern,
+            value_pattern,
+        } => match (
+            build_regex(name_pattern, false),
+            build_regex(value_pattern, true),
+        ) {
+            (Ok(name_re), Ok(value_re)) => {
+                let mut checked = 0;
+                for header in &record.response.headers {
+                    if !name_re.is_match(&header.name) {
+                        continue;
+                    }
+                    checked += 1;
+                    if !value_re.is_match(&header.value) {
+                        return StatementEvaluation {
+                            satisfied: false,
+                            details: Some(format!(
+                                "{}: {:?} does not match value pattern",
+                                header.name, header.value
+                            )),
+                            reason: Some(EvalReason::ValueMismatch),
+                        };
+                    }
+                }
+                StatementEvaluation {
+                    satisfied: true,
+                    details: Some(format!("headers checked={checked}")),
+                    reason: None,
+                }
+            }
+            (Err(err), _) | (_, Err(err)) => invalid_regex(err),
+        },
+        Statement::CspDirective {
+            directive,
+            expected_sources,
+            mode,
+        } => {
+            let sent = record.headers.get("content-security-policy");
+            let policies = csp::parse_policies(sent.into_iter().flatten());
+            let outcome = csp::check(&policies, directive, expected_sources, *mode);
+            StatementEvaluation {
+                satisfied: outcome.is_ok(),
+                reason: header_reason(sent.is_some(), outcome.is_ok()),
+                details: outcome.err(),
+            }
+        }
+        Statement::HashEquals { algorithm, digest } => {
+            let calculated = match &record.response.body_digest {
+                Some(streamed) if streamed.algorithm != *algorithm => {
+                    return StatementEvaluation {
+                        satisfied: false,
+                        details: Some(format!(
+                            "body was streamed through {:?}; cannot check {:?}",
+                            streamed.algorithm, algorithm
+                        )),
+                        reason: Some(EvalReason::BodyStreamed),
+                    };
+                }
+                Some(streamed) => streamed.hex.to_ascii_lowercase(),
+                None if record.response.body_truncated => {
+                    return StatementEvaluation {
+                        satisfied: false,
+                        details: Some("response body truncated; hash unverifiable".into()),
+                        reason: Some(EvalReason::BodyTruncated),
+                    };
+                }
+                None => compute_hash(algorithm, &record.response.body),
+            };
+            match algorithm.decode_digest(digest) {
+                Ok(expected) => {
+                    let satisfied = hex_string(&expected) == calculated;
+                    StatementEvaluation {
+                        satisfied,
+                        details: Some(format!("calculated={calculated}")),
+                        reason: mismatch(satisfied),
+                    }
+                }
+                Err(err) => StatementEvaluation {
+                    satisfied: false,
+                    details: Some(err.to_string()),
+                    reason: Some(EvalReason::InvalidOperand),
+                },
+            }
+        }
+        Statement::JsonSchemaValid { schema } => {
+            let response = &record.response;
+            let outcome = body_unavailable(response, "cannot validate").and_then(|()| {
+                json_schema::check_body(schema, &response.body).map_err(|err| {
+                    let reason = if json_schema::compile(schema).is_err() {
+                        EvalReason::InvalidOperand
+                    } else if std::str::from_utf8(&response.body).is_err() {
+                        EvalReason::NonUtf8Body
+                    } else if serde_json::from_slice::<serde_json::Value>(&response.body).is_err() {
+                        EvalReason::NotJson
+                    } else {
+                        EvalReason::ValueMismatch
+                    };
+                    (reason, err)
+                })
+            });
+            from_outcome(outcome.map(|()| None))
+        }
+        Statement::BodyStartsWith { prefix_base64 } => {
+            evaluate_body_affix(prefix_base64, &record.response, BodyEnd::Start)
+        }
+        Statement::BodyEndsWith { suffix_base64 } => {
+            evaluate_body_affix(suffix_base64, &record.response, BodyEnd::End)
+        }
+        Statement::ContentDigestValid => {
+            let response = &record.response;
+            let outcome = body_unavailable(response, "digest unverifiable").and_then(|()| {
+                let headers = response
+                    .headers
+                    .iter()
+                    .map(|h| (h.name.as_str(), h.value.as_str()));
+                content_digest::check(headers, &response.body).map_err(|err| {
+                    let sent = ["content-digest", "digest"]
+                        .iter()
+                        .any(|name| record.headers.contains_key(*name));
+                    let reason = if sent {
+                        EvalReason::ValueMismatch
+                    } else {
+                        EvalReason::HeaderNotFound
+                    };
+                    (reason, err)
+                })
+            });
+            from_outcome(outcome.map(Some))
+        }
+        Statement::Regex {
+            pattern,
+            scope,
+            case_sensitive,
+            multi_line,
+            dot_all,
+        } => match build_bytes_regex(pattern, *case_sensitive, *multi_line, *dot_all) {
+            Ok(re) => {
+                let haystack = regex_scope_text(scope, &record.response);
+                let satisfied = re.is_match(&haystack);
+                StatementEvaluation {
+                    satisfied,
+                    details: None,
+                    reason: mismatch(satisfied),
+                }
+            }
+            Err(err) => invalid_regex(err),
+        },
+        Statement::RegexAll {
+            pattern,
+            scope,
+            case_sensitive,
+            min_matches,
+        } => match build_bytes_regex(pattern, *case_sensitive, false, false) {
+            Ok(re) => {
+                let haystack = regex_scope_text(scope, &record.response);
+                let count = re.find_iter(&haystack).take(MAX_COUNTED_MATCHES).count();
+                let satisfied = if *min_matches == 0 {
+                    count == 0
+                } else {
+                    count >= *min_matches
+                };
+                StatementEvaluation {
+                    satisfied,
+                    details: Some(format!("matches={count}")),
+                    reason: mismatch(satisfied),
+                }
+            }
+            Err(err) => invalid_regex(err),
+        },
+        Statement::HttpVersionIs { version } => {
+            let served = &record.response.http_version;
+            let satisfied = http_version::canonical(served)
+                .is_some_and(|served| Some(served) == http_version::canonical(version));
+            StatementEvaluation {
+                satisfied,
+                details: Some(format!("served over {served}")),
+                reason: mismatch(satisfied),
+            }
+        }
+        Statement::ReasonEquals {
+            expected,
+            case_sensitive,
+        } => {
+            let response = &record.response;
+            if response.reason.is_empty() && response.http_version.starts_with("HTTP/2") {
+                return StatementEvaluation {
+                    satisfied: false,
+                    details: Some(format!(
+                        "{} responses carry no reason phrase",
+                        response.http_version
+                    )),
+                    reason: Some(EvalReason::ValueMismatch),
+                };
+            }
+            let satisfied = compare_value(&response.reason, expected, Some(*case_sensitive));
+            StatementEvaluation {
+                satisfied,
+                details: Some(format!("reason={:?}", response.reason)),
+                reason: mismatch(satisfied),
+            }
+        }
+        Statement::CorsAllowsOrigin { origin } => {
+            let allowed = record
+                .headers
+                .get("access-control-allow-origin")
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let satisfied = match allowed {
+                [value] => {
+                    let value = value.trim();
+                    value == "*" || value.eq_ignore_ascii_case(origin.trim())
+                }
+                _ => false,
+            };
+            StatementEvaluation {
+                satisfied,
+                details: match allowed {
+                    [] => Some("no Access-Control-Allow-Origin header".into()),
+                    [value] => Some(format!("allow-origin={value}")),
+                    _ => Some("multiple Access-Control-Allow-Origin values".into()),
+                },
+                reason: match allowed {
+                    _ if satisfied => None,
+                    [] => Some(EvalReason::HeaderNotFound),
+                    [_] => Some(EvalReason::ValueMismatch),
+                    _ => Some(EvalReason::MalformedValue),
+                },
+            }
+        }
+        Statement::LinkRelPresent { rel } => {
+            let links = record_links(record);
+            let satisfied = links
+                .iter()
+                .any(|link| link.rel == rel.to_ascii_lowercase());
+            StatementEvaluation {
+                satisfied,
+                details: Some(format!("links={}", links.len())),
+                reason: mismatch(satisfied),
+            }
+        }
+        Statement::LinkRelUri { rel, uri_pattern } => match build_regex(uri_pattern, true) {
+            Ok(re) => {
+                let rel = rel.to_ascii_lowercase();
+                let uris = record_links(record)
+                    .into_iter()
+                    .filter(|link| link.rel == rel)
+                    .map(|link| link.uri)
+                    .collect::<Vec<_>>();
+                let satisfied = uris.iter().any(|uri| re.is_match(uri));
+                StatementEvaluation {
+                    satisfied,
+                    details: (!uris.is_empty())
+                        .then(|| format!("rel={rel} uris: {}", uris.join(", "))),
+                    reason: mismatch(satisfied),
+                }
+            }
+            Err(err) => invalid_regex(err),
+        },
+        Statement::ServerSoftwareAbsent => {
+            let disclosing = record
+                .headers
+                .get("server")
+                .into_iter()
+                .flatten()
+                .filter(|value| server::discloses_software(value))
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            StatementEvaluation {
+                satisfied: disclosing.is_empty(),
+                details: (!disclosing.is_empty())
+                    .then(|| format!("Server discloses: {}", disclosing.join(", "))),
+                reason: mismatch(disclosing.is_empty()),
+            }
+        }
+        Statement::CacheHit { expected, headers } => {
+            let lookup = |name: &str| {
+                record
+                    .headers
+                    .get(&name.to_ascii_lowercase())
+                    .and_then(|values| values.first())
+                    .map(String::as_str)
+            };
+            match cache::detect(headers, lookup) {
+                Some(verdict) => StatementEvaluation {
+                    satisfied: verdict.hit == *expected,
+                    details: Some(format!(
+                        "{} by {}: {}",
+                        if verdict.hit {
+                            "cache hit"
+                        } else {
+                            "cache miss"
+                        },
+                        verdict.header,
+                        verdict.value
+                    )),
+                    reason: mismatch(verdict.hit == *expected),
+                },
+                None => StatementEvaluation {
+                    satisfied: false,
+                    details: Some("no cache indicator header".into()),
+                    reason: Some(EvalReason::HeaderNotFound),
+                },
+            }
+        }
+        Statement::EarlyHintsPresent { target } => {
+            let hints = &record.response.early_hints;
+            let satisfied = match target {
+                Some(target) => hints
+                    .iter()
+                    .any(|entry| entry.name.eq_ignore_ascii_case(target)),
+                None => !hints.is_empty(),
+            };
+            StatementEvaluation {
+                satisfied,
+                details: None,
+                reason: (!satisfied).then_some(EvalReason::HeaderNotFound),
+            }
+        }
+        Statement::SpkiPinIs { pin } => {
+            if record.tls.spki_pins.is_empty() {
+                return StatementEvaluation {
+                    satisfied: false,
+                    details: Some("SPKI pins not recorded (use --record-spki)".into()),
+                    reason: Some(EvalReason::NotRecorded),
+                };
+            }
+            let satisfied = record.tls.spki_pins.contains(pin);
+            StatementEvaluation {
+                satisfied,
+                details: (!satisfied)
+                    .then(|| format!("served pins: {}", record.tls.spki_pins.join(", "))),
+                reason: mismatch(satisfied),
+            }
+        }
+        Statement::FieldPresent { path } => {
+            match field::document(record.canonical_handshake, record.canonical_app_data) {
+                Ok(document) => {
+                    let satisfied = field::is_present(&document, path);
+                    StatementEvaluation {
+                        satisfied,
+                        details: None,
+                        reason: mismatch(satisfied),
+                    }
+                }
+                Err(err) => StatementEvaluation {
+                    satisfied: false,
+                    details: Some(err),
+                    reason: Some(EvalReason::NotJson),
+                },
+            }
+        }
+        Statement::CertChainLengthAtMost { n } => {
+            let count = record.tls.served_certificates;
+            if count == 0 {
+                return no_chain_served();
+            }
+            StatementEvaluation {
+                satisfied: count <= *n,
+                details: Some(match record.tls.chain_bytes {
+                    Some(bytes) => format!("{count} certificates, {bytes} bytes"),
+                    None => format!("{count} certificates"),
+                }),
+                reason: mismatch(count <= *n),
+            }
+        }
+        Statement::SctPresent => {
+            if record.tls.served_certificates == 0 {
+                return no_chain_served();
+            }
+            let Some(count) = record.tls.embedded_scts else {
+                return not_committed("leaf certificate not at hand; SCTs cannot be counted");
+            };
+            StatementEvaluation {
+                satisfied: count > 0,
+                details: Some(if count > 0 {
+                    format!("{count} SCTs embedded in the leaf certificate")
+                } else {
+                    "leaf certificate embeds no SCTs".into()
+                }),
+                reason: mismatch(count > 0),
+            }
+        }
+        Statement::CertCoversHost { host } => {
+            if record.tls.served_certificates == 0 {
+                return no_chain_served();
+            }
+            let names = record.tls.subject_alt_names;
+            if names.is_empty() {
+                return StatementEvaluation {
+                    satisfied: false,
+                    details: Some("leaf certificate has no subject alt names".into()),
+                    reason: Some(EvalReason::ValueMismatch),
+                };
+            }
+            match covering_name(names, host) {
+                Some(name) => StatementEvaluation {
+                    satisfied: true,
+                    details: Some(format!("covered by {name}")),
+                    reason: None,
+                },
+                None => StatementEvaluation {
+                    satisfied: false,
+                    details: Some(format!("subject alt names: {}", names.join(", "))),
+                    reason: Some(EvalReason::ValueMismatch),
+                },
+            }
+        }
+        Statement::ResolvedIpIs { ip } => {
+            if record.resolved_ips.is_empty() {
+                return StatementEvaluation {
+                    satisfied: false,
+                    details: Some("DNS resolution not recorded (use --record-dns)".into()),
+                    reason: Some(EvalReason::NotRecorded),
+                };
+            }
+            let Ok(expected) = ip.parse::<IpAddr>() else {
+                return StatementEvaluation {
+                    satisfied: false,
+                    details: Some(format!("{ip} is not an IP address")),
+                    reason: Some(EvalReason::InvalidOperand),
+                };
+            };
+            let satisfied = record.resolved_ips.contains(&expected);
+            StatementEvaluation {
+                satisfied,
+                details: Some("prover-attested; DNS cannot be re-verified".into()),
+                reason: mismatch(satisfied),
+            }
+        }
+        Statement::ClockSkewUnder { millis } => {
+            let Some(captured_at) = record.captured_at else {
+                return not_committed("capture time not committed; skew cannot be measured");
+            };
+            match clock_skew(&record.headers, captured_at) {
+                Ok((_, skew_ms)) => StatementEvaluation {
+                    satisfied: skew_ms.unsigned_abs() < *millis,
+                    details: Some(format!("skew={skew_ms:+}ms (prover-attested)")),
+                    reason: mismatch(skew_ms.unsigned_abs() < *millis),
+                },
+                Err(err) => StatementEvaluation {
+                    satisfied: false,
+                    details: Some(err),
+                    reason: Some(if record.headers.contains_key("date") {
+                        EvalReason::MalformedValue
+                    } else {
+                        EvalReason::HeaderNotFound
+                    }),
+                },
+            }
+        }
+        Statement::ResponseTimeUnder { max_ms } => {
+            let Some((label, ttfb_ms)) = record.ttfb else {
+                return not_committed("timing trace not committed");
+            };
+            StatementEvaluation {
+                satisfied: ttfb_ms < *max_ms,
+                details: Some(format!("{label} ttfb={ttfb_ms}ms (prover-attested)")),
+                reason: mismatch(ttfb_ms < *max_ms),
+            }
+        }
+        Statement::RegexReplace {
+            pattern,
+            replacement,
+            statement,
+        } => {
+            let response = &record.response;
+            let body = body_unavailable(response, "cannot transform").and_then(|()| {
+                transform::replace_body(pattern, replacement, &response.body)
+                    .map_err(|err| (EvalReason::InvalidRegex, err))
+            });
+            let body = match body {
+                Ok(body) => body.into_owned(),
+                Err(failure) => return from_outcome(Err(failure)),
+            };
+            let mut transformed = record.clone();
+            transformed.response.to_mut().body = body;
+            evaluate(statement, &transformed)
+        }
+        Statement::All { statements } => {
+            for member in statements {
+                let outcome = evaluate(member, record);
+                if !outcome.satisfied {
+                    return StatementEvaluation {
+                        satisfied: false,
+                        details: Some(failed_leaf_details(member, &outcome)),
+                        reason: outcome.reason,
+                    };
+                }
+            }
+            StatementEvaluation {
+                satisfied: true,
+                details: None,
+                reason: None,
+            }
+        }
+        Statement::Any { statements } => {
+            if statements
+                .iter()
+                .any(|member| evaluate(member, record).satisfied)
+            {
+                return StatementEvaluation {
+                    satisfied: true,
+                    details: None,
+                    reason: None,
+                };
+            }
+            let tried = statements
+                .iter()
+                .map(Statement::summary)
+                .collect::<Vec<_>>()
+                .join("; ");
+            StatementEvaluation {
+                satisfied: false,
+                details: Some(format!("no member satisfied; tried: {tried}")),
+                reason: Some(EvalReason::NoMemberSatisfied),
+            }
+        }
+    }
+}
+
+fn no_chain_served() -> StatementEvaluation {
+    StatementEvaluation {
+        satisfied: false,
+        details: Some("no certificate chain was served".into()),
+        reason: Some(EvalReason::NotRecorded),
+    }
+}
+
+fn not_committed(details: &str) -> StatementEvaluation {
+    StatementEvaluation {
+        satisfied: false,
+        details: Some(details.into()),
+        reason: Some(EvalReason::NotCommitted),
+    }
+}
+
+/// Compare the union of every `target` line's comma-separated elements to
+/// `expected`, case-insensitively and ignoring order.
+fn evaluate_header_set(
+    record: &EvalRecord,
+    target: &str,
+    expected: &[String],
+    exact: bool,
+) -> StatementEvaluation {
+    let Some(values) = record.headers.get(&target.to_ascii_lowercase()) else {
+        return StatementEvaluation {
+            satisfied: false,
+            details: Some(format!("header {target} not present")),
+            reason: Some(EvalReason::HeaderNotFound),
+        };
+    };
+    let lower = |element: &String| element.to_ascii_lowercase();
+    let actual = values
+        .iter()
+        .flat_map(|value| split_list(value))
+        .map(|element| lower(&element))
+        .collect::<BTreeSet<_>>();
+    let expected = expected.iter().map(lower).collect::<BTreeSet<_>>();
+    let missing = expected.difference(&actual).cloned().collect::<Vec<_>>();
+    let extra = actual.difference(&expected).cloned().collect::<Vec<_>>();
+    let satisfied = missing.is_empty() && (!exact || extra.is_empty());
+    let details = (!satisfied).then(|| {
+        let mut parts = Vec::new();
+        if !missing.is_empty() {
+            parts.push(format!("missing: {}", missing.join(", ")));
+        }
+        if exact && !extra.is_empty() {
+            parts.push(format!("unexpected: {}", extra.join(", ")));
+        }
+        parts.join("; ")
+    });
+    StatementEvaluation {
+        satisfied,
+        details,
+        reason: mismatch(satisfied),
+    }
+}
+
+/// The server's `Date` header and its offset from `local` in milliseconds,
+/// positive when the server is ahead. `Err` says why there is no figure.
+pub fn clock_skew(
+    headers: &HeaderMap,
+    local: DateTime<Utc>,
+) -> Result<(DateTime<Utc>, i64), String> {
+    let value = headers
+        .get("date")
+        .and_then(|values| values.first())
+        .ok_or("Date header not present")?;
+    // IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`) is valid RFC 2822.
+    let server = DateTime::parse_from_rfc2822(value.trim())
+        .map_err(|_| format!("unparseable Date header {value:?}"))?
+        .with_timezone(&Utc);
+    Ok((server, (server - local).num_milliseconds()))
+}
+
+fn evaluate_etag(record: &EvalRecord, expected: &str, allow_weak: bool) -> StatementEvaluation {
+    let unsatisfied = |reason: EvalReason, details: String| StatementEvaluation {
+        satisfied: false,
+        details: Some(details),
+        reason: Some(reason),
+    };
+    let Some(expected) = EntityTag::parse_lenient(expected) else {
+        return unsatisfied(
+            EvalReason::InvalidOperand,
+            format!("invalid expected entity tag {expected}"),
+        );
+    };
+    let actual = match record.headers.get("etag").map(Vec::as_slice) {
+        None | Some([]) => {
+            return unsatisfied(EvalReason::HeaderNotFound, "ETag not present".into())
+        }
+        Some([value]) => value,
+        Some(_) => return unsatisfied(EvalReason::MalformedValue, "multiple ETag values".into()),
+    };
+    match EntityTag::parse(actual) {
+        Some(tag) if tag.matches(&expected, allow_weak) => StatementEvaluation {
+            satisfied: true,
+            details: None,
+            reason: None,
+        },
+        Some(tag) => unsatisfied(EvalReason::ValueMismatch, format!("ETag is {tag}")),
+        None => unsatisfied(
+            EvalReason::MalformedValue,
+            format!("malformed ETag {actual}"),
+        ),
+    }
+}
+
+fn evaluate_header_numeric(
+    record: &EvalRecord,
+    target: &str,
+    op: NumOp,
+    expected: i64,
+) -> StatementEvaluation {
+    let Some(values) = record.headers.get(&target.to_ascii_lowercase()) else {
+        return StatementEvaluation {
+            satisfied: false,
+            details: Some(format!("header {target} not present")),
+            reason: Some(EvalReason::HeaderNotFound),
+        };
+    };
+    for value in values {
+        let Ok(actual) = value.trim().parse::<i64>() else {
+            return StatementEvaluation {
+                satisfied: false,
+                details: Some(format!("{target}: {value:?} is not an integer")),
+                reason: Some(EvalReason::MalformedValue),
+            };
+        };
+        if !op.compare(actual, expected) {
+            return StatementEvaluation {
+                satisfied: false,
+                details: Some(format!(
+                    "{target}: {actual} is not {} {expected}",
+                    op.symbol()
+                )),
+                reason: Some(EvalReason::ValueMismatch),
+            };
+        }
+    }
+    StatementEvaluation {
+        satisfied: true,
+        details: None,
+        reason: None,
+    }
+}
+
+fn record_links(record: &EvalRecord) -> Vec<LinkRel> {
+    link::parse_links(record.headers.get("link").into_iter().flatten())
+}
+
+/// Outcome of a check on a header that may not have been sent.
+fn header_reason(sent: bool, satisfied: bool) -> Option<EvalReason> {
+    if sent {
+        mismatch(satisfied)
+    } else {
+        Some(EvalReason::HeaderNotFound)
+    }
+}
+
+fn invalid_regex(err: String) -> StatementEvaluation {
+    StatementEvaluation {
+        satisfied: false,
+        details: Some(err),
+        reason: Some(EvalReason::InvalidRegex),
+    }
+}
+
+/// `Err` when the body bytes a check needs were not kept: streamed into a
+/// digest, or truncated so that the check `consequence` follows.
+fn body_unavailable(
+    response: &HttpResponse,
+    consequence: &str,
+) -> Result<(), (EvalReason, String)> {
+    if response.body_digest.is_some() {
+        Err((
+            EvalReason::BodyStreamed,
+            "body was streamed into a digest; bytes unavailable".into(),
+        ))
+    } else if response.body_truncated {
+        Err((
+            EvalReason::BodyTruncated,
+            format!("response body truncated; {consequence}"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Evaluation of a check that either holds, with optional details, or fails
+/// for a reason.
+fn from_outcome(outcome: Result<Option<String>, (EvalReason, String)>) -> StatementEvaluation {
+    match outcome {
+        Ok(details) => StatementEvaluation {
+            satisfied: true,
+            details,
+            reason: None,
+        },
+        Err((reason, details)) => StatementEvaluation {
+            satisfied: false,
+            details: Some(details),
+            reason: Some(reason),
+        },
+    }
+}
+
+fn failed_leaf_details(member: &Statement, outcome: &StatementEvaluation) -> String {
+    match &outcome.details {
+        Some(details) => format!("unsatisfied: {} ({details})", member.summary()),
+        None => format!("unsatisfied: {}", member.summary()),
+    }
+}
+
+enum BodyEnd {
+    Start,
+    End,
+}
+
+fn evaluate_body_affix(
+    encoded: &str,
+    response: &HttpResponse,
+    end: BodyEnd,
+) -> StatementEvaluation {
+    let unsatisfied = |reason: EvalReason, details: &str| StatementEvaluation {
+        satisfied: false,
+        details: Some(details.to_string()),
+        reason: Some(reason),
+    };
+    let Ok(expected) = B64.decode(encoded) else {
+        return unsatisfied(
+            EvalReason::InvalidOperand,
+            "expected bytes are not valid base64",
+        );
+    };
+    if response.body_digest.is_some() {
+        return unsatisfied(
+            EvalReason::BodyStreamed,
+            "body was streamed into a digest; bytes unavailable",
+        );
+    }
+    let body = &response.body;
+    let satisfied = match end {
+        BodyEnd::Start => {
+            if response.body_truncated && body.len() < expected.len() {
+                return unsatisfied(
+                    EvalReason::BodyTruncated,
+                    "response body truncated before the prefix ends",
+                );
+            }
+            body.starts_with(&expected)
+        }
+        BodyEnd::End => {
+            if response.body_truncated {
+                return unsatisfied(
+                    EvalReason::BodyTruncated,
+                    "response body truncated; suffix unverifiable",
+                );
+            }
+            body.ends_with(&expected)
+        }
+    };
+    StatementEvaluation {
+        satisfied,
+        details: None,
+        reason: mismatch(satisfied),
+    }
+}
+
+fn matching_header_names(headers: &[HeaderEntry], re: &Regex) -> Vec<String> {
+    let mut names = headers
+        .iter()
+        .filter(|h| re.is_match(&h.name))
+        .map(|h| h.name.clone())
+        .collect::<Vec<_>>();
+    names.dedup();
+    names
+}
+
+fn compare_value(actual: &str, expected: &str, case_sensitive: Option<bool>) -> bool {
+    if case_sensitive.unwrap_or(false) {
+        actual.trim() == expected.trim()
+    } else {
+        actual.trim().eq_ignore_ascii_case(expected.trim())
+    }
+}
+
+fn compute_hash(algo: &HashAlgorithm, data: &[u8]) -> String {
+    match algo {
+        HashAlgorithm::Sha256 => {
+            let digest = Sha256::digest(data);
+            hex_string(digest.as_slice())
+        }
+        HashAlgorithm::Blake3 => {
+            let digest = blake3::hash(data);
+            hex_string(digest.as_bytes())
+        }
+    }
+}
+
+/// Incremental counterpart of [`compute_hash`] for bodies hashed as they are
+/// read off the wire.
+pub enum BodyHasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl BodyHasher {
+    pub fn new(algo: &HashAlgorithm) -> Self {
+        match algo {
+            HashAlgorithm::Sha256 => BodyHasher::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => BodyHasher::Blake3(Box::default()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            BodyHasher::Sha256(hasher) => hasher.update(data),
+            BodyHasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    pub fn finalize_hex(self) -> String {
+        match self {
+            BodyHasher::Sha256(hasher) => hex_string(hasher.finalize().as_slice()),
+            BodyHasher::Blake3(hasher) => hex_string(hasher.finalize().as_bytes()),
+        }
+    }
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn build_regex(pattern: &str, case_sensitive: bool) -> Result<regex::Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map_err(|err| format!("invalid regex: {err}"))
+}
+
+/// Like [`build_regex`], for haystacks that may borrow the raw body bytes.
+/// Unicode mode stays on, so patterns match as they would against a `&str`.
+fn build_bytes_regex(
+    pattern: &str,
+    case_sensitive: bool,
+    multi_line: bool,
+    dot_all: bool,
+) -> Result<BytesRegex, String> {
+    BytesRegexBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .multi_line(multi_line)
+        .dot_matches_new_line(dot_all)
+        .build()
+        .map_err(|err| format!("invalid regex: {err}"))
+}
+
+fn regex_scope_text<'a>(scope: &RegexScope, response: &'a HttpResponse) -> Cow<'a, [u8]> {
+    match scope {
+        RegexScope::Headers => Cow::Owned(headers_as_text(&response.headers).into_bytes()),
+        RegexScope::Body => body_as_text(response),
+        RegexScope::Any => {
+            let headers = headers_as_text(&response.headers);
+            let body = body_as_text(response);
+            let mut text = Vec::with_capacity(headers.len() + 2 + body.len());
+            text.extend_from_slice(headers.as_bytes());
+            text.extend_from_slice(b"\n\n");
+            text.extend_from_slice(&body);
+            Cow::Owned(text)
+        }
+        RegexScope::Status => {
+            Cow::Owned(format!("{} {}", response.status_code, response.reason).into_bytes())
+        }
+        RegexScope::StatusLine => Cow::Owned(
+            format!(
+                "{} {} {}",
+                response.http_version, response.status_code, response.reason
+            )
+            .into_bytes(),
+        ),
+        RegexScope::Cookies => Cow::Owned(
+            response
+                .headers
+                .iter()
+                .filter(|h| h.name.eq_ignore_ascii_case("set-cookie"))
+                .map(|h| h.value.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+                .into_bytes(),
+        ),
+    }
+}
+
+fn headers_as_text(headers: &[HeaderEntry]) -> String {
+    headers
+        .iter()
+        .map(|h| format!("{}: {}", h.name, h.value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Body as UTF-8 bytes. Valid UTF-8 bodies, and pure-ASCII bodies in an
+/// ASCII-compatible charset, are borrowed as-is (minus any BOM); only other
+/// content is transcoded into a fresh buffer.
+fn body_as_text(response: &HttpResponse) -> Cow<'_, [u8]> {
+    let (encoding, body) = match Encoding::for_bom(&response.body) {
+        Some((encoding, bom_len)) => (encoding, &response.body[bom_len..]),
+        None => (detect_charset(response), response.body.as_slice()),
+    };
+    match encoding.decode_without_bom_handling(body).0 {
+        Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+        Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+    }
+}
+
+/// Character encoding of the body: the Content-Type `charset` parameter, then
+/// an HTML `<meta charset>` declaration, falling back to UTF-8.
+pub fn detect_charset(response: &HttpResponse) -> &'static Encoding {
+    let content_type = response
+        .headers
+        .iter()
+        .find(|h| h.name == "content-type")
+        .map(|h| h.value.to_ascii_lowercase());
+    if let Some(encoding) = content_type.as_deref().and_then(content_type_charset) {
+        return encoding;
+    }
+    let is_html = content_type.as_deref().is_none_or(|ct| ct.contains("html"));
+    if is_html {
+        if let Some(encoding) = meta_charset(&response.body) {
+            return encoding;
+        }
+    }
+    UTF_8
+}
+
+fn content_type_charset(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if name.trim() != "charset" {
+            return None;
+        }
+        Encoding::for_label(value.trim().trim_matches('"').as_bytes())
+    })
+}
+
+fn meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    static META: OnceLock<Regex> = OnceLock::new();
+    let re = META.get_or_init(|| {
+        Regex::new(r#"(?i)<meta[^>]+charset\s*=\s*["']?([a-z0-9_:.\-]+)"#).expect("meta regex")
+    });
+    // Browsers only honor the declaration within the first 1024 bytes.
+    let head = String::from_utf8_lossy(&body[..body.len().min(1024)]);
+    let label = re.captures(&head)?.get(1)?.as_str().to_string();
+    Encoding::for_label(label.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::{header_map, TlsFacts};
+    use crate::witness::{AppDataWitness, HandshakeWitness, TimingTrace};
+    use crate::DirectiveMode;
+
+    fn base_response() -> HttpResponse {
+        HttpResponse {
+            http_version: "HTTP/1.1".into(),
+            status_code: 200,
+            reason: "OK".into(),
+            headers: vec![],
+            early_hints: vec![],
+            body: b"body".to_vec(),
+            body_truncated: false,
+            body_digest: None,
+        }
+    }
+
+    fn record(response: &HttpResponse) -> EvalRecord<'_> {
+        EvalRecord {
+            response: Cow::Borrowed(response),
+            headers: Cow::Owned(header_map(&response.headers)),
+            canonical_handshake: &[],
+            canonical_app_data: &[],
+            tls: TlsFacts::default(),
+            resolved_ips: &[],
+            captured_at: None,
+            ttfb: None,
+        }
+    }
+
+    fn header(name: &str, value: &str) -> HeaderEntry {
+        HeaderEntry {
+            name: name.into(),
+            value: value.into(),
+            wire_name: None,
+        }
+    }
+
+    /// Owned facts behind an [`EvalRecord`], filled in the way a live
+    /// capture fills them: the prover's clock and measured timing included.
+    #[derive(Clone)]
+    struct Captured {
+        response: HttpResponse,
+        headers: HeaderMap,
+        canonical_handshake: Vec<u8>,
+        canonical_app_data: Vec<u8>,
+        tls: CapturedTls,
+        resolved_ips: Vec<IpAddr>,
+        captured_at: DateTime<Utc>,
+        ttfb: (&'static str, u64),
+    }
+
+    #[derive(Clone, Default)]
+    struct CapturedTls {
+        spki_pins: Vec<String>,
+        subject_alt_names: Vec<String>,
+        served_certificates: usize,
+        chain_bytes: usize,
+        embedded_scts: usize,
+    }
+
+    impl Captured {
+        fn eval_record(&self) -> EvalRecord<'_> {
+            EvalRecord {
+                response: Cow::Borrowed(&self.response),
+                headers: Cow::Borrowed(&self.headers),
+                canonical_handshake: &self.canonical_handshake,
+                canonical_app_data: &self.canonical_app_data,
+                tls: TlsFacts {
+                    spki_pins: &self.tls.spki_pins,
+                    subject_alt_names: &self.tls.subject_alt_names,
+                    served_certificates: self.tls.served_certificates,
+                    chain_bytes: Some(self.tls.chain_bytes),
+                    embedded_scts: Some(self.tls.embedded_scts),
+                },
+                resolved_ips: &self.resolved_ips,
+                captured_at: Some(self.captured_at),
+                ttfb: Some(self.ttfb),
+            }
+        }
+    }
+
+    fn captured() -> Captured {
+        Captured {
+            response: base_response(),
+            headers: HeaderMap::new(),
+            canonical_handshake: vec![],
+            canonical_app_data: vec![],
+            tls: CapturedTls::default(),
+            resolved_ips: vec![],
+            captured_at: Utc::now(),
+            ttfb: ("cold", 180),
+        }
+    }
+
+    fn check(statement: &Statement, captured: &Captured) -> StatementEvaluation {
+        evaluate(statement, &captured.eval_record())
+    }
+
+    fn check_with(
+        statement: &Statement,
+        captured: &Captured,
+        mode: EvalMode,
+    ) -> StatementEvaluation {
+        evaluate_with(statement, &captured.eval_record(), mode)
+    }
+
+    /// The handshake witness a capture of `captured` commits, with every
+    /// served certificate fingerprinted as `leaf`.
+    fn canonical_handshake(captured: &Captured) -> Vec<u8> {
+        HandshakeWitness {
+            domain: "example.com".into(),
+            version: String::new(),
+            cipher: String::new(),
+            alpn: None,
+            cert_fingerprints: vec![
+                format!("sha256:{:x}", Sha256::digest(b"leaf"));
+                captured.tls.served_certificates
+            ],
+            spki_pins: captured.tls.spki_pins.clone(),
+            subject_alt_names: captured.tls.subject_alt_names.clone(),
+            resolved_ips: captured.resolved_ips.clone(),
+            source: None,
+        }
+        .encode()
+        .unwrap()
+    }
+
+    fn canonical_app_data(response: &HttpResponse, timing: Option<&TimingTrace>) -> Vec<u8> {
+        AppDataWitness {
+            http_version: Some(response.http_version.clone()),
+            status_code: response.status_code,
+            reason: response.reason.clone(),
+            headers: response.headers.clone(),
+            early_hints: response.early_hints.clone(),
+            body_base64: B64.encode(&response.body),
+            body_truncated: response.body_truncated,
+            body_digest: response.body_digest.clone(),
+            timing: timing.cloned(),
+        }
+        .encode()
+        .unwrap()
+    }
+
+    #[test]
+    fn streamed_digest_matches_one_shot_digest() {
+        let body = (0..3_000_000u32)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        for algo in [HashAlgorithm::Sha256, HashAlgorithm::Blake3] {
+            let mut hasher = BodyHasher::new(&algo);
+            for chunk in body.chunks(8192) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize_hex(), compute_hash(&algo, &body));
+        }
+    }
+
+    #[test]
+    fn byte_regex_agrees_with_string_regex_on_utf8() {
+        let mut response = base_response();
+        response.headers = vec![header("x-note", "Grüße")];
+        response.body = "naïve café\r\n日本語 <b>ok</b>\nend".as_bytes().to_vec();
+        let patterns = [
+            "café",
+            r"\w+語",
+            "^end$",
+            "(?m)^end$",
+            r"ï.e",
+            "CAFÉ",
+            r"[^\x00-\x7f]{3}",
+            "b>",
+        ];
+        for scope in [RegexScope::Body, RegexScope::Any, RegexScope::Headers] {
+            let text = String::from_utf8(regex_scope_text(&scope, &response).into_owned())
+                .expect("utf-8 haystack");
+            for pattern in patterns {
+                for case_sensitive in [true, false] {
+                    let expected = build_regex(pattern, case_sensitive)
+                        .unwrap()
+                        .find_iter(&text)
+                        .count();
+                    let stmt = Statement::RegexAll {
+                        pattern: pattern.into(),
+                        scope: scope.clone(),
+                        case_sensitive,
+                        min_matches: 1,
+                    };
+                    let eval = evaluate(&stmt, &record(&response));
+                    assert_eq!(
+                        eval.details,
+                        Some(format!("matches={expected}")),
+                        "{pattern} in {scope:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn utf8_body_is_matched_without_copying() {
+        let mut response = base_response();
+        response.body = "é".repeat(2 * 1024 * 1024).into_bytes();
+        response.body.extend_from_slice(b"needle");
+        assert!(matches!(
+            regex_scope_text(&RegexScope::Body, &response),
+            Cow::Borrowed(_)
+        ));
+        let stmt = Statement::Regex {
+            pattern: "é+needle$".into(),
+            scope: RegexScope::Body,
+            case_sensitive: true,
+            multi_line: false,
+            dot_all: false,
+        };
+        assert!(evaluate(&stmt, &record(&response)).satisfied);
+
+        let mut bom = base_response();
+        bom.body = b"\xef\xbb\xbfstart".to_vec();
+        let stmt = Statement::Regex {
+            pattern: "^start".into(),
+            scope: RegexScope::Body,
+            case_sensitive: true,
+            multi_line: false,
+            dot_all: false,
+        };
+        assert!(evaluate(&stmt, &record(&bom)).satisfied);
+    }
+
+    #[test]
+    fn detect_charset_reads_html_meta_declaration() {
+        let mut response = base_response();
+        response.headers = vec![header("content-type", "text/html")];
+        response.body = b"<html><head><meta charset=\"iso-8859-1\"></head>caf\xe9".to_vec();
+        assert_eq!(detect_charset(&response).name(), "windows-1252");
+        assert_eq!(detect_charset(&base_response()), UTF_8);
+    }
+
+    #[test]
+    fn facts_the_record_cannot_vouch_for_are_not_committed() {
+        let response = base_response();
+        for statement in [
+            Statement::ResponseTimeUnder { max_ms: 100 },
+            Statement::ClockSkewUnder { millis: 1000 },
+        ] {
+            let outcome = evaluate(&statement, &record(&response));
+            assert_eq!(
+                outcome.reason,
+                Some(EvalReason::NotCommitted),
+                "{statement:?}"
+            );
+        }
+
+        let mut served = record(&response);
+        served.tls.served_certificates = 1;
+        let outcome = evaluate(&Statement::SctPresent, &served);
+        assert_eq!(outcome.reason, Some(EvalReason::NotCommitted));
+        served.tls.embedded_scts = Some(1);
+        assert!(evaluate(&Statement::SctPresent, &served).satisfied);
+    }
+
+    #[test]
+    fn header_present_and_absent_evaluate_correctly() {
+        let mut record = captured();
+        record
+            .headers
+            .entry("server".into())
+            .or_default()
+            .push("Example".into());
+
+        let present = Statement::HeaderPresent {
+            target: "Server".into(),
+        };
+        assert!(check(&present, &record).satisfied);
+
+        let absent = Statement::HeaderAbsent {
+            target: "Strict-Transport-Security".into(),
+        };
+        assert!(check(&absent, &record).satisfied);
+    }
+
+    #[test]
+    fn clock_skew_compares_date_header_with_local_clock() {
+        let mut record = captured();
+        record.captured_at = DateTime::parse_from_rfc3339("2024-03-01T12:00:01.500Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let under = |millis| Statement::ClockSkewUnder { millis };
+        let missing = check(&under(5000), &record);
+        assert!(!missing.satisfied);
+        assert_eq!(missing.details.as_deref(), Some("Date header not present"));
+
+        record
+            .headers
+            .insert("date".into(), vec!["Fri, 01 Mar 2024 12:00:00 GMT".into()]);
+        let (server, skew) = clock_skew(&record.headers, record.captured_at).unwrap();
+        assert_eq!(server.to_rfc3339(), "2024-03-01T12:00:00+00:00");
+        assert_eq!(skew, -1500);
+        let outcome = check(&under(2000), &record);
+        assert!(outcome.satisfied);
+        assert_eq!(
+            outcome.details.as_deref(),
+            Some("skew=-1500ms (prover-attested)")
+        );
+        assert!(!check(&under(1500), &record).satisfied);
+
+        record
+            .headers
+            .insert("date".into(), vec!["yesterday".into()]);
+        let garbled = check(&under(2000), &record);
+        assert!(!garbled.satisfied);
+        assert_eq!(
+            garbled.details.as_deref(),
+            Some(r#"unparseable Date header "yesterday""#)
+        );
+    }
+
+    #[test]
+    fn header_glob_matches_whole_value() {
+        let mut record = captured();
+        let glob = |pattern: &str| Statement::HeaderGlob {
+            target: "Server".into(),
+            pattern: pattern.into(),
+            case_sensitive: false,
+        };
+        assert!(!check(&glob("nginx/*"), &record).satisfied);
+
+        record
+            .headers
+            .insert("server".into(), vec!["nginx/1.25.3".into()]);
+        assert!(check(&glob("nginx/*"), &record).satisfied);
+        assert!(check(&glob("NGINX/1.2?.*"), &record).satisfied);
+        assert!(!check(&glob("apache/*"), &record).satisfied);
+        assert!(!check(&glob("nginx"), &record).satisfied);
+    }
+
+    #[test]
+    fn etag_equals_distinguishes_weak_and_strong_tags() {
+        let mut record = captured();
+        let statement = |expected: &str, allow_weak| Statement::ETagEquals {
+            expected: expected.into(),
+            allow_weak,
+        };
+        let missing = check(&statement(r#""v1""#, true), &record);
+        assert!(!missing.satisfied);
+        assert_eq!(missing.details.as_deref(), Some("ETag not present"));
+
+        record
+            .headers
+            .insert("etag".into(), vec![r#"W/"v1""#.into()]);
+        assert!(check(&statement(r#""v1""#, true), &record).satisfied);
+        let strong = check(&statement(r#""v1""#, false), &record);
+        assert!(!strong.satisfied);
+        assert_eq!(strong.details.as_deref(), Some(r#"ETag is W/"v1""#));
+        assert!(!check(&statement(r#"W/"v1""#, false), &record).satisfied);
+        assert!(!check(&statement(r#""v2""#, true), &record).satisfied);
+
+        record.headers.insert("etag".into(), vec![r#""v1""#.into()]);
+        assert!(check(&statement(r#""v1""#, false), &record).satisfied);
+    }
+
+    #[test]
+    fn chain_length_limit_counts_served_certificates() {
+        let mut record = captured();
+        let limit = |n| Statement::CertChainLengthAtMost { n };
+        let plaintext = check(&limit(3), &record);
+        assert!(!plaintext.satisfied);
+        assert_eq!(
+            plaintext.details.as_deref(),
+            Some("no certificate chain was served")
+        );
+
+        record.tls.served_certificates = 3;
+        record.tls.chain_bytes = 3400;
+        let within = check(&limit(3), &record);
+        assert!(within.satisfied);
+        assert_eq!(
+            within.details.as_deref(),
+            Some("3 certificates, 3400 bytes")
+        );
+        assert!(!check(&limit(2), &record).satisfied);
+    }
+
+    #[test]
+    fn headers_all_absent_reports_every_present_header() {
+        let mut record = captured();
+        let baseline = Statement::HeadersAllAbsent {
+            targets: vec![
+                "X-Powered-By".into(),
+                "X-AspNet-Version".into(),
+                "X-AspNetMvc-Version".into(),
+            ],
+        };
+        assert!(check(&baseline, &record).satisfied);
+
+        for name in ["x-powered-by", "x-aspnetmvc-version"] {
+            record.headers.insert(name.into(), vec!["1".into()]);
+        }
+        let outcome = check(&baseline, &record);
+        assert!(!outcome.satisfied);
+        assert_eq!(
+            outcome.details.as_deref(),
+            Some("present headers: X-Powered-By, X-AspNetMvc-Version")
+        );
+    }
+
+    #[test]
+    fn header_name_patterns_evaluate_over_all_headers() {
+        let mut record = captured();
+        record.response.headers = vec![
+            header("x-frame-options", "DENY"),
+            header("x-content-type-options", "nosniff"),
+            header("x-powered-by", "PHP/8.1"),
+        ];
+
+        let absent = Statement::HeaderNameAbsentMatching {
+            pattern: "^X-Powered-By$".into(),
+        };
+        let outcome = check(&absent, &record);
+        assert!(!outcome.satisfied);
+        assert_eq!(
+            outcome.details.as_deref(),
+            Some("matching headers: x-powered-by")
+        );
+
+        let all_safe = Statement::AllHeadersValueMatch {
+            name_pattern: "^x-".into(),
+            value_pattern: "^[A-Za-z]+$".into(),
+        };
+        let outcome = check(&all_safe, &record);
+        assert!(!outcome.satisfied);
+        assert!(outcome.details.unwrap().starts_with("x-powered-by:"));
+
+        record.response.headers.pop();
+        assert!(check(&absent, &record).satisfied);
+        assert!(check(&all_safe, &record).satisfied);
+    }
+
+    #[test]
+    fn csp_directive_compares_sources_setwise() {
+        let mut record = captured();
+        record.headers.insert(
+            "content-security-policy".into(),
+            vec![
+                "script-src 'self'  'unsafe-inline';default-src 'self'".into(),
+                "img-src *".into(),
+            ],
+        );
+        let csp = |directive: &str, sources: &[&str], mode| Statement::CspDirective {
+            directive: directive.into(),
+            expected_sources: sources.iter().map(|s| s.to_string()).collect(),
+            mode,
+        };
+
+        let default_self = csp("default-src", &["'SELF'"], DirectiveMode::Exact);
+        assert!(check(&default_self, &record).satisfied);
+
+        let no_inline = csp("script-src", &["'unsafe-inline'"], DirectiveMode::Absent);
+        let outcome = check(&no_inline, &record);
+        assert!(!outcome.satisfied);
+        assert_eq!(
+            outcome.details.as_deref(),
+            Some("script-src allows ['unsafe-inline']")
+        );
+
+        assert!(check(&csp("img-src", &["*"], DirectiveMode::Contains), &record).satisfied);
+        assert!(check(&csp("object-src", &[], DirectiveMode::Absent), &record).satisfied);
+        assert!(!check(&csp("object-src", &[], DirectiveMode::Exact), &record).satisfied);
+    }
+
+    #[test]
+    fn reason_equals_compares_phrase() {
+        let mut record = captured();
+        record.response.reason = "I'm a teapot".into();
+        let stmt = |expected: &str, case_sensitive| Statement::ReasonEquals {
+            expected: expected.into(),
+            case_sensitive,
+        };
+        assert!(check(&stmt("i'm a TEAPOT", false), &record).satisfied);
+        assert!(!check(&stmt("i'm a TEAPOT", true), &record).satisfied);
+
+        record.response.http_version = "HTTP/2".into();
+        record.response.reason.clear();
+        let outcome = check(&stmt("OK", false), &record);
+        assert!(!outcome.satisfied);
+        assert_eq!(
+            outcome.details.as_deref(),
+            Some("HTTP/2 responses carry no reason phrase")
+        );
+    }
+
+    #[test]
+    fn header_sets_ignore_order_and_whitespace() {
+        let mut record = captured();
+        record
+            .headers
+            .insert("allow".into(), vec!["HEAD,  post".into(), "GET".into()]);
+        let list = |items: &[&str]| items.iter().map(|item| item.to_string()).collect();
+        let set_eq = |items: &[&str]| Statement::HeaderSetEquals {
+            target: "Allow".into(),
+            expected: list(items),
+        };
+        assert!(check(&set_eq(&["GET", "POST", "HEAD"]), &record).satisfied);
+        let outcome = check(&set_eq(&["GET", "POST"]), &record);
+        assert!(!outcome.satisfied);
+        assert_eq!(outcome.details.as_deref(), Some("unexpected: head"));
+
+        let contains = Statement::HeaderSetContains {
+            target: "Allow".into(),
+            expected: list(&["post", "get"]),
+        };
+        assert!(check(&contains, &record).satisfied);
+        let missing = Statement::HeaderSetContains {
+            target: "Allow".into(),
+            expected: list(&["DELETE"]),
+        };
+        assert_eq!(
+            check(&missing, &record).details.as_deref(),
+            Some("missing: delete")
+        );
+    }
+
+    #[test]
+    fn header_numeric_compares_integer_values() {
+        let mut record = captured();
+        record.headers.insert("age".into(), vec![" 42".into()]);
+        let age = |op, value| Statement::HeaderNumeric {
+            target: "Age".into(),
+            op,
+            value,
+        };
+        assert!(check(&age(NumOp::Lt, 60), &record).satisfied);
+        assert!(check(&age(NumOp::Gte, 42), &record).satisfied);
+        assert!(check(&age(NumOp::Eq, 42), &record).satisfied);
+        let outcome = check(&age(NumOp::Gt, 42), &record);
+        assert!(!outcome.satisfied);
+        assert_eq!(outcome.details.as_deref(), Some("Age: 42 is not > 42"));
+
+        record.headers.insert("age".into(), vec!["soon".into()]);
+        let outcome = check(&age(NumOp::Ne, 0), &record);
+        assert!(!outcome.satisfied);
+        assert_eq!(
+            outcome.details.as_deref(),
+            Some(r#"Age: "soon" is not an integer"#)
+        );
+
+        record.headers.clear();
+        assert_eq!(
+            check(&age(NumOp::Lt, 60), &record).details.as_deref(),
+            Some("header Age not present")
+        );
+    }
+
+    #[test]
+    fn cors_allows_listed_or_wildcard_origin() {
+        let statement = Statement::CorsAllowsOrigin {
+            origin: "https://app.example.com".into(),
+        };
+        let mut record = captured();
+        assert!(!check(&statement, &record).satisfied);
+
+        for (allowed, expected) in [
+            ("https://app.example.com", true),
+            ("*", true),
+            ("https://other.example.com", false),
+        ] {
+            record
+                .headers
+                .insert("access-control-allow-origin".into(), vec![allowed.into()]);
+            assert_eq!(check(&statement, &record).satisfied, expected, "{allowed}");
+        }
+
+        record.headers.insert(
+            "access-control-allow-origin".into(),
+            vec!["https://app.example.com".into(), "*".into()],
+        );
+        assert!(!check(&statement, &record).satisfied);
+    }
+
+    #[test]
+    fn link_relations_span_headers_and_values() {
+        let mut record = captured();
+        record.headers.insert(
+            "link".into(),
+            vec![
+                r#"<https://api.example.com/items?page=3>; rel="next", <https://api.example.com/items?page=1>; rel="prev""#.into(),
+                r#"<https://api.example.com/items?page=9>; rel="last""#.into(),
+            ],
+        );
+        let present = |rel: &str| Statement::LinkRelPresent { rel: rel.into() };
+        assert!(check(&present("next"), &record).satisfied);
+        assert!(check(&present("PREV"), &record).satisfied);
+        assert!(check(&present("last"), &record).satisfied);
+        assert!(!check(&present("first"), &record).satisfied);
+
+        let uri = |rel: &str, pattern: &str| Statement::LinkRelUri {
+            rel: rel.into(),
+            uri_pattern: pattern.into(),
+        };
+        assert!(check(&uri("next", r"page=3$"), &record).satisfied);
+        assert!(!check(&uri("prev", r"page=3$"), &record).satisfied);
+    }
+
+    #[test]
+    fn body_affixes_compare_raw_bytes() {
+        const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+        let mut record = captured();
+        record.response.body = [PNG_SIGNATURE, b"\x00\xffIEND"].concat();
+        let starts = Statement::BodyStartsWith {
+            prefix_base64: B64.encode(PNG_SIGNATURE),
+        };
+        let ends = Statement::BodyEndsWith {
+            suffix_base64: B64.encode(b"IEND"),
+        };
+        assert!(check(&starts, &record).satisfied);
+        assert!(check(&ends, &record).satisfied);
+
+        record.response.body_truncated = true;
+        assert!(check(&starts, &record).satisfied);
+        let outcome = check(&ends, &record);
+        assert!(!outcome.satisfied);
+        assert_eq!(
+            outcome.details.as_deref(),
+            Some("response body truncated; suffix unverifiable")
+        );
+    }
+
+    #[test]
+    fn header_casing_needs_the_recorded_spelling() {
+        let mut record = captured();
+        record.response.headers = vec![header("x-powered-by", "PHP")];
+        let casing = crate::parse_statement("header:casing:X-Powered-By").unwrap();
+        let outcome = check(&casing, &record);
+        assert!(!outcome.satisfied);
+        assert!(outcome.details.unwrap().contains("--header-case-preserve"));
+
+        record.response.headers[0].wire_name = Some("X-Powered-By".into());
+        assert!(check(&casing, &record).satisfied);
+        record.response.headers[0].wire_name = Some("x-powered-by".into());
+        let outcome = check(&casing, &record);
+        assert_eq!(outcome.details.as_deref(), Some("sent as x-powered-by"));
+    }
+
+    #[test]
+    fn content_digest_checks_the_servers_claim() {
+        let mut record = captured();
+        record.response.body = b"{\"hello\": \"world\"}".to_vec();
+        record.response.headers = vec![HeaderEntry {
+            name: "content-digest".into(),
+            value: "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:".into(),
+            wire_name: None,
+        }];
+        let outcome = check(&Statement::ContentDigestValid, &record);
+        assert!(outcome.satisfied, "{:?}", outcome.details);
+
+        record.response.body = b"{\"hello\": \"there\"}".to_vec();
+        let outcome = check(&Statement::ContentDigestValid, &record);
+        assert!(!outcome.satisfied);
+        assert!(outcome
+            .details
+            .unwrap()
+            .starts_with("content-digest sha-256 mismatch"));
+
+        record.response.headers.clear();
+        let outcome = check(&Statement::ContentDigestValid, &record);
+        assert_eq!(
+            outcome.details.as_deref(),
+            Some("no Content-Digest or Digest header")
+        );
+    }
+
+    #[test]
+    fn json_schema_validates_the_body() {
+        let stmt = crate::parse_statement(
+            r#"json:schema:{"type": "object", "properties": {"status": {"enum": ["ok"]}}}"#,
+        )
+        .unwrap();
+        let mut record = captured();
+        record.response.body = br#"{"status": "ok", "uptime": 12}"#.to_vec();
+        let conforming = check(&stmt, &record);
+        assert!(conforming.satisfied);
+        assert_eq!(conforming.details, None);
+
+        record.response.body = br#"{"status": "degraded"}"#.to_vec();
+        let violating = check(&stmt, &record);
+        assert!(!violating.satisfied);
+        assert!(violating
+            .details
+            .unwrap()
+            .starts_with("at /status: \"degraded\""));
+
+        record.response.body = b"OK".to_vec();
+        let not_json = check(&stmt, &record);
+        assert!(!not_json.satisfied);
+        assert!(not_json.details.unwrap().starts_with("body is not JSON"));
+    }
+
+    #[test]
+    fn field_present_resolves_canonical_pointers() {
+        let mut record = captured();
+        record.canonical_handshake = canonical_handshake(&record);
+        record.canonical_app_data = canonical_app_data(&record.response, None);
+        let present =
+            |path: &str| check(&Statement::FieldPresent { path: path.into() }, &record).satisfied;
+        assert!(present("/handshake/domain"));
+        assert!(!present("/handshake/cipher"));
+        assert!(present("/app_data/status_code"));
+        assert!(!present("/handshake/alpn"));
+        assert!(!present("/app_data/timing"));
+
+        record.canonical_app_data = b"not json".to_vec();
+        let broken = check(
+            &Statement::FieldPresent {
+                path: "/app_data/reason".into(),
+            },
+            &record,
+        );
+        assert!(!broken.satisfied);
+        assert!(broken.details.unwrap().starts_with("app data is not JSON"));
+    }
+
+    #[test]
+    fn regex_transform_strips_tokens_before_hash_check() {
+        let stripped = b"<form><input name=csrf value=REDACTED></form>";
+        let stmt = Statement::RegexReplace {
+            pattern: "value=[a-f0-9]+".into(),
+            replacement: "value=REDACTED".into(),
+            statement: Box::new(Statement::HashEquals {
+                algorithm: HashAlgorithm::Sha256,
+                digest: format!("{:x}", Sha256::digest(stripped)),
+            }),
+        };
+        let mut record = captured();
+        for token in ["9f2c01", "77ab"] {
+            record.response.body = format!("<form><input name=csrf value={token}></form>").into();
+            let outcome = check(&stmt, &record);
+            assert!(outcome.satisfied, "{:?}", outcome.details);
+        }
+
+        record.response.body = b"<form><input name=csrf value=9f2c01 extra></form>".to_vec();
+        assert!(!check(&stmt, &record).satisfied);
+
+        record.response.body = stripped.to_vec();
+        record.response.body_truncated = true;
+        let truncated = check(&stmt, &record);
+        assert!(!truncated.satisfied);
+        assert_eq!(
+            truncated.details.as_deref(),
+            Some("response body truncated; cannot transform")
+        );
+    }
+
+    #[test]
+    fn header_equals_respects_case_insensitive_compare() {
+        let mut record = captured();
+        record
+            .headers
+            .entry("server".into())
+            .or_default()
+            .push("Apache".into());
+        let stmt = Statement::HeaderEquals {
+            target: "Server".into(),
+            expected: "apache".into(),
+            case_sensitive: None,
+        };
+        assert!(check(&stmt, &record).satisfied);
+    }
+
+    #[test]
+    fn hash_equals_fails_when_truncated() {
+        let mut record = captured();
+        record.response.body_truncated = true;
+        let stmt = Statement::HashEquals {
+            algorithm: HashAlgorithm::Sha256,
+            digest: "deadbeef".into(),
+        };
+        let eval = check(&stmt, &record);
+        assert!(!eval.satisfied);
+        assert_eq!(eval.reason, Some(EvalReason::BodyTruncated));
+        assert!(eval.details.unwrap().contains("truncated"));
+    }
+
+    #[test]
+    fn unsatisfied_evaluations_carry_a_reason_code() {
+        let record = captured();
+        let missing = Statement::HeaderPresent {
+            target: "X-Missing".into(),
+        };
+        let outcome = check(&missing, &record);
+        assert_eq!(outcome.reason, Some(EvalReason::HeaderNotFound));
+        let json = serde_json::to_value(&outcome).unwrap();
+        assert_eq!(json["reason"], "header_not_found");
+        let etag = Statement::ETagEquals {
+            expected: "\"v1\"".into(),
+            allow_weak: false,
+        };
+        assert_eq!(
+            check(&etag, &record).reason,
+            Some(EvalReason::HeaderNotFound)
+        );
+
+        let invalid = Statement::Regex {
+            pattern: "(unclosed".into(),
+            scope: RegexScope::Body,
+            case_sensitive: true,
+            multi_line: false,
+            dot_all: false,
+        };
+        let outcome = check(&invalid, &record);
+        assert_eq!(outcome.reason, Some(EvalReason::InvalidRegex));
+        assert!(outcome.details.unwrap().starts_with("invalid regex"));
+
+        let mut truncated = captured();
+        truncated.response.body_truncated = true;
+        let suffix = Statement::BodyEndsWith {
+            suffix_base64: B64.encode("}"),
+        };
+        assert_eq!(
+            check(&suffix, &truncated).reason,
+            Some(EvalReason::BodyTruncated)
+        );
+
+        let satisfied = check(
+            &Statement::HeaderAbsent {
+                target: "X-Missing".into(),
+            },
+            &record,
+        );
+        assert!(satisfied.satisfied);
+        assert_eq!(satisfied.reason, None);
+        assert!(serde_json::to_value(&satisfied)
+            .unwrap()
+            .get("reason")
+            .is_none());
+    }
+
+    #[test]
+    fn composites_report_the_deciding_reason() {
+        let record = captured();
+        let all = Statement::All {
+            statements: vec![
+                Statement::HeaderAbsent {
+                    target: "X-Missing".into(),
+                },
+                Statement::HeaderPresent {
+                    target: "X-Missing".into(),
+                },
+            ],
+        };
+        for mode in [EvalMode::ShortCircuit, EvalMode::All] {
+            assert_eq!(
+                check_with(&all, &record, mode).reason,
+                Some(EvalReason::HeaderNotFound),
+                "{mode:?}"
+            );
+        }
+        let any = Statement::Any {
+            statements: vec![Statement::HeaderPresent {
+                target: "X-Missing".into(),
+            }],
+        };
+        for mode in [EvalMode::ShortCircuit, EvalMode::All] {
+            assert_eq!(
+                check_with(&any, &record, mode).reason,
+                Some(EvalReason::NoMemberSatisfied),
+                "{mode:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn hash_equals_accepts_hex_and_base64_digests() {
+        let mut record = captured();
+        record.response.body = b"hello".to_vec();
+        let stmt = |digest: &str| Statement::HashEquals {
+            algorithm: HashAlgorithm::Sha256,
+            digest: digest.into(),
+        };
+        let hex = "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824";
+        assert!(check(&stmt(hex), &record).satisfied);
+        assert!(
+            check(
+                &stmt("LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ="),
+                &record
+            )
+            .satisfied
+        );
+        assert!(
+            !check(
+                &stmt("LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCA="),
+                &record
+            )
+            .satisfied
+        );
+        let invalid = check(&stmt("not-a-digest"), &record);
+        assert!(!invalid.satisfied);
+        assert!(invalid.details.unwrap().contains("neither hex nor base64"));
+    }
+
+    #[test]
+    fn regex_scope_headers_matches() {
+        let mut record = captured();
+        record.response.headers = vec![HeaderEntry {
+            name: "set-cookie".into(),
+            value: "session=abc; HttpOnly".into(),
+            wire_name: None,
+        }];
+        let stmt = Statement::Regex {
+            pattern: "session=.*".into(),
+            scope: RegexScope::Headers,
+            case_sensitive: false,
+            multi_line: false,
+            dot_all: false,
+        };
+        assert!(check(&stmt, &record).satisfied);
+    }
+
+    #[test]
+    fn regex_line_flags_reach_across_and_within_lines() {
+        let mut record = captured();
+        record.response.body = b"<script>\nalert(1)\n</script>\nfooter".to_vec();
+        let stmt = |pattern: &str, multi_line, dot_all| Statement::Regex {
+            pattern: pattern.into(),
+            scope: RegexScope::Body,
+            case_sensitive: false,
+            multi_line,
+            dot_all,
+        };
+        assert!(!check(&stmt("<script>.*</script>", false, false), &record).satisfied);
+        assert!(check(&stmt("<script>.*</script>", false, true), &record).satisfied);
+        assert!(!check(&stmt("^alert", false, false), &record).satisfied);
+        assert!(check(&stmt("^alert\\(1\\)$", true, false), &record).satisfied);
+    }
+
+    #[test]
+    fn regex_decodes_latin1_body_from_content_type() {
+        let mut record = captured();
+        record.response.headers = vec![HeaderEntry {
+            name: "content-type".into(),
+            value: "text/plain; charset=ISO-8859-1".into(),
+            wire_name: None,
+        }];
+        record.response.body = b"caf\xe9 au lait".to_vec();
+        let stmt = Statement::Regex {
+            pattern: "café".into(),
+            scope: RegexScope::Body,
+            case_sensitive: true,
+            multi_line: false,
+            dot_all: false,
+        };
+        assert_eq!(detect_charset(&record.response).name(), "windows-1252");
+        assert!(check(&stmt, &record).satisfied);
+    }
+
+    #[test]
+    fn resolved_ip_matches_any_recorded_address() {
+        let mut record = captured();
+        let stmt = Statement::ResolvedIpIs {
+            ip: "203.0.113.5".into(),
+        };
+        assert!(!check(&stmt, &record).satisfied);
+
+        record.resolved_ips = vec![
+            "203.0.113.5".parse().unwrap(),
+            "2001:db8::1".parse().unwrap(),
+        ];
+        assert!(check(&stmt, &record).satisfied);
+    }
+
+    #[test]
+    fn spki_pin_matches_any_certificate_in_chain() {
+        let mut record = captured();
+        let stmt = Statement::SpkiPinIs {
+            pin: "bGVhZg==".into(),
+        };
+        assert_eq!(
+            check(&stmt, &record).details.as_deref(),
+            Some("SPKI pins not recorded (use --record-spki)")
+        );
+
+        record.tls.spki_pins = vec!["cm9vdA==".into(), "bGVhZg==".into()];
+        assert!(check(&stmt, &record).satisfied);
+        record.tls.spki_pins.pop();
+        let outcome = check(&stmt, &record);
+        assert!(!outcome.satisfied);
+        assert_eq!(outcome.details.as_deref(), Some("served pins: cm9vdA=="));
+    }
+
+    #[test]
+    fn ttfb_under_uses_labelled_warm_figure() {
+        let mut record = captured();
+        let stmt = Statement::ResponseTimeUnder { max_ms: 100 };
+        let cold = check(&stmt, &record);
+        assert!(!cold.satisfied);
+        assert_eq!(
+            cold.details.as_deref(),
+            Some("cold ttfb=180ms (prover-attested)")
+        );
+
+        record.ttfb = ("warm", 40);
+        let warm = check(&stmt, &record);
+        assert!(warm.satisfied);
+        assert!(warm.details.unwrap().starts_with("warm ttfb=40ms"));
+    }
+
+    #[test]
+    fn all_reports_first_failing_member() {
+        let mut record = captured();
+        record
+            .headers
+            .entry("server".into())
+            .or_default()
+            .push("Example".into());
+        let stmt = Statement::All {
+            statements: vec![
+                Statement::HeaderPresent {
+                    target: "Server".into(),
+                },
+                Statement::HeaderPresent {
+                    target: "Content-Security-Policy".into(),
+                },
+                Statement::HeaderPresent {
+                    target: "X-Frame-Options".into(),
+                },
+            ],
+        };
+        let eval = check(&stmt, &record);
+        assert!(!eval.satisfied);
+        assert_eq!(
+            eval.details.as_deref(),
+            Some("unsatisfied: header present: Content-Security-Policy")
+        );
+    }
+
+    #[test]
+    fn all_mode_reports_every_leaf() {
+        let mut record = captured();
+        record
+            .headers
+            .insert("server".into(), vec!["Example".into()]);
+        let present = |target: &str| Statement::HeaderPresent {
+            target: target.into(),
+        };
+        let stmt = Statement::All {
+            statements: vec![
+                present("Content-Security-Policy"),
+                Statement::Any {
+                    statements: vec![present("Server"), present("Via")],
+                },
+                present("X-Frame-Options"),
+            ],
+        };
+
+        let fast = check_with(&stmt, &record, EvalMode::ShortCircuit);
+        assert!(!fast.satisfied);
+        assert_eq!(
+            fast.details.as_deref(),
+            Some("unsatisfied: header present: Content-Security-Policy")
+        );
+
+        let full = check_with(&stmt, &record, EvalMode::All);
+        assert!(!full.satisfied);
+        assert_eq!(
+            full.details.as_deref(),
+            Some(
+                "unsatisfied: header present: Content-Security-Policy; \
+                 satisfied: header present: Server; \
+                 unsatisfied: header present: Via; \
+                 unsatisfied: header present: X-Frame-Options"
+            )
+        );
+
+        // Both modes agree on the overall result.
+        for target in ["content-security-policy", "x-frame-options"] {
+            record.headers.insert(target.into(), vec!["x".into()]);
+        }
+        assert!(check_with(&stmt, &record, EvalMode::ShortCircuit).satisfied);
+        let full = check_with(&stmt, &record, EvalMode::All);
+        assert!(full.satisfied);
+        assert_eq!(full.details.unwrap().matches("unsatisfied").count(), 1);
+    }
+
+    #[test]
+    fn any_lists_tried_members_when_none_match() {
+        let record = captured();
+        let stmt = Statement::Any {
+            statements: vec![
+                Statement::HeaderPresent {
+                    target: "Server".into(),
+                },
+                Statement::HeaderPresent {
+                    target: "Via".into(),
+                },
+            ],
+        };
+        let eval = check(&stmt, &record);
+        assert!(!eval.satisfied);
+        let details = eval.details.expect("details");
+        assert!(details.contains("header present: Server"));
+        assert!(details.contains("header present: Via"));
+    }
+
+    #[test]
+    fn regex_status_line_matches_reason_phrase() {
+        let mut record = captured();
+        record.response.status_code = 403;
+        record.response.reason = "Forbidden".into();
+        let stmt = |scope, pattern: &str| Statement::Regex {
+            pattern: pattern.into(),
+            scope,
+            case_sensitive: true,
+            multi_line: false,
+            dot_all: false,
+        };
+        assert!(
+            check(
+                &stmt(RegexScope::StatusLine, r"^HTTP/1\.1 403 Forbidden$"),
+                &record
+            )
+            .satisfied
+        );
+        assert!(check(&stmt(RegexScope::Status, "^403 Forbidden$"), &record).satisfied);
+        assert!(!check(&stmt(RegexScope::Body, "Forbidden"), &record).satisfied);
+    }
+
+    #[test]
+    fn cookie_scope_ignores_other_headers() {
+        let mut record = captured();
+        record.response.headers = vec![
+            header("Set-Cookie", "session=abc; Path=/; HttpOnly"),
+            header("X-Debug", "cookie=session; Secure"),
+        ];
+        let stmt = |scope, pattern: &str| Statement::Regex {
+            pattern: pattern.into(),
+            scope,
+            case_sensitive: false,
+            multi_line: false,
+            dot_all: false,
+        };
+        assert!(check(&stmt(RegexScope::Headers, "; secure"), &record).satisfied);
+        assert!(!check(&stmt(RegexScope::Cookies, "; secure"), &record).satisfied);
+        assert!(check(&stmt(RegexScope::Cookies, "^session=abc;"), &record).satisfied);
+        // The haystack is the values alone, not `Set-Cookie: ...` lines.
+        assert!(!check(&stmt(RegexScope::Cookies, "set-cookie"), &record).satisfied);
+    }
+
+    #[test]
+    fn regex_count_requires_minimum_matches() {
+        let mut record = captured();
+        record.response.body = b"<script></script><script></script>".to_vec();
+        let count = |min_matches| Statement::RegexAll {
+            pattern: "<script>".into(),
+            scope: RegexScope::Body,
+            case_sensitive: false,
+            min_matches,
+        };
+        assert!(check(&count(2), &record).satisfied);
+        let unmet = check(&count(3), &record);
+        assert!(!unmet.satisfied);
+        assert_eq!(unmet.details.as_deref(), Some("matches=2"));
+    }
+
+    #[test]
+    fn regex_count_zero_means_must_not_match() {
+        let mut record = captured();
+        let stmt = Statement::RegexAll {
+            pattern: "<script>".into(),
+            scope: RegexScope::Body,
+            case_sensitive: false,
+            min_matches: 0,
+        };
+        assert!(check(&stmt, &record).satisfied);
+        record.response.body = b"<script></script>".to_vec();
+        assert!(!check(&stmt, &record).satisfied);
+    }
+
+    #[test]
+    fn early_hints_present_checks_interim_headers() {
+        let mut record = captured();
+        let stmt = Statement::EarlyHintsPresent {
+            target: Some("Link".into()),
+        };
+        assert!(!check(&stmt, &record).satisfied);
+
+        record.response.early_hints = vec![HeaderEntry {
+            name: "link".into(),
+            value: "</style.css>; rel=preload".into(),
+            wire_name: None,
+        }];
+        assert!(check(&stmt, &record).satisfied);
+    }
+
+    #[test]
+    fn http_version_matches_any_spelling() {
+        let version = |version: &str| Statement::HttpVersionIs {
+            version: version.into(),
+        };
+        let mut record = captured();
+        assert!(check(&version("HTTP/1.1"), &record).satisfied);
+        let mismatch = check(&version("HTTP/2"), &record);
+        assert!(!mismatch.satisfied);
+        assert_eq!(mismatch.details.as_deref(), Some("served over HTTP/1.1"));
+
+        record.response.http_version = "HTTP/2.0".into();
+        assert!(check(&version("HTTP/2"), &record).satisfied);
+        assert!(check(&version("h2"), &record).satisfied);
+        assert!(!check(&version("HTTP/1.1"), &record).satisfied);
+    }
+
+    #[test]
+    fn sct_present_needs_embedded_timestamps() {
+        let mut record = captured();
+        let unserved = check(&Statement::SctPresent, &record);
+        assert!(!unserved.satisfied);
+        assert_eq!(
+            unserved.details.as_deref(),
+            Some("no certificate chain was served")
+        );
+        record.tls.served_certificates = 1;
+        let unlogged = check(&Statement::SctPresent, &record);
+        assert!(!unlogged.satisfied);
+        assert_eq!(
+            unlogged.details.as_deref(),
+            Some("leaf certificate embeds no SCTs")
+        );
+        record.tls.embedded_scts = 2;
+        assert!(check(&Statement::SctPresent, &record).satisfied);
+    }
+
+    #[test]
+    fn cert_covers_host_matches_exact_and_wildcard_names() {
+        let covers = |host: &str| Statement::CertCoversHost { host: host.into() };
+        let mut record = captured();
+        record.tls.served_certificates = 1;
+        record.tls.subject_alt_names = vec!["*.example.com".into(), "example.com".into()];
+
+        let exact = check(&covers("example.com"), &record);
+        assert!(exact.satisfied);
+        assert_eq!(exact.details.as_deref(), Some("covered by example.com"));
+        let wildcard = check(&covers("api.example.com"), &record);
+        assert!(wildcard.satisfied);
+        assert_eq!(
+            wildcard.details.as_deref(),
+            Some("covered by *.example.com")
+        );
+        let uncovered = check(&covers("a.b.example.com"), &record);
+        assert!(!uncovered.satisfied);
+        assert_eq!(
+            uncovered.details.as_deref(),
+            Some("subject alt names: *.example.com, example.com")
+        );
+
+        record.tls.served_certificates = 0;
+        assert!(!check(&covers("example.com"), &record).satisfied);
+    }
+
+    #[test]
+    fn server_software_absent_flags_versions() {
+        let mut record = captured();
+        assert!(check(&Statement::ServerSoftwareAbsent, &record).satisfied);
+        record.headers.insert("server".into(), vec!["nginx".into()]);
+        assert!(check(&Statement::ServerSoftwareAbsent, &record).satisfied);
+        record
+            .headers
+            .insert("server".into(), vec!["nginx 1.25.3".into()]);
+        assert!(!check(&Statement::ServerSoftwareAbsent, &record).satisfied);
+        record
+            .headers
+            .insert("server".into(), vec!["Apache/2.4.57 (Debian)".into()]);
+        let leaked = check(&Statement::ServerSoftwareAbsent, &record);
+        assert!(!leaked.satisfied);
+        assert_eq!(
+            leaked.details.as_deref(),
+            Some("Server discloses: Apache/2.4.57 (Debian)")
+        );
+    }
+
+    #[test]
+    fn cache_hit_reports_the_deciding_header() {
+        let hit = Statement::CacheHit {
+            expected: true,
+            headers: vec![],
+        };
+        let miss = Statement::CacheHit {
+            expected: false,
+            headers: vec![],
+        };
+        let mut record = captured();
+        let none = check(&hit, &record);
+        assert!(!none.satisfied);
+        assert_eq!(none.details.as_deref(), Some("no cache indicator header"));
+        assert!(!check(&miss, &record).satisfied);
+
+        record.headers.insert("age".into(), vec!["60".into()]);
+        let by_age = check(&hit, &record);
+        assert!(by_age.satisfied);
+        assert_eq!(by_age.details.as_deref(), Some("cache hit by age: 60"));
+
+        record.headers.insert("x-cache".into(), vec!["HIT".into()]);
+        let by_x_cache = check(&hit, &record);
+        assert!(by_x_cache.satisfied);
+        assert_eq!(
+            by_x_cache.details.as_deref(),
+            Some("cache hit by x-cache: HIT")
+        );
+        assert!(!check(&miss, &record).satisfied);
+    }
+
+    /// A capture exercising every statement, with its canonical transcripts
+    /// as the prover commits them.
+    fn committed_record() -> Captured {
+        let body = br#"{"ok":true}"#.to_vec();
+        let mut headers = [
+            ("access-control-allow-origin", "*"),
+            ("age", "42"),
+            (
+                "content-digest",
+                &format!("sha-256=:{}:", B64.encode(Sha256::digest(&body))),
+            ),
+            ("content-security-policy", "default-src 'self'"),
+            ("content-type", "application/json"),
+            ("date", "Fri, 01 Mar 2024 12:00:00 GMT"),
+            ("etag", "\"abc\""),
+            ("link", "</style.css>; rel=preload"),
+            ("server", "nginx/1.25.3"),
+            ("vary", "Accept, Origin"),
+            ("x-cache", "HIT"),
+        ]
+        .map(|(name, value)| header(name, value))
+        .to_vec();
+        headers[8].wire_name = Some("Server".into());
+        let mut record = captured();
+        record.captured_at = DateTime::parse_from_rfc3339("2024-03-01T12:00:00.400Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        record.headers = header_map(&headers);
+        record.response.headers = headers;
+        record.response.early_hints = vec![header("link", "</style.css>; rel=preload")];
+        record.response.body = body;
+        record.tls = CapturedTls {
+            spki_pins: vec!["pin-sha256:leaf".into()],
+            subject_alt_names: vec!["*.example.com".into()],
+            served_certificates: 1,
+            chain_bytes: 4,
+            embedded_scts: 1,
+        };
+        record.resolved_ips = vec!["203.0.113.5".parse().unwrap()];
+        let trace = TimingTrace {
+            ttfb_ms: record.ttfb.1,
+            ..TimingTrace::default()
+        };
+        record.canonical_handshake = canonical_handshake(&record);
+        record.canonical_app_data = canonical_app_data(&record.response, Some(&trace));
+        record
+    }
+
+    /// One statement of every type, most of them satisfied by
+    /// [`committed_record`].
+    fn every_statement() -> Vec<Statement> {
+        let body_regex = |pattern: &str| Statement::Regex {
+            pattern: pattern.into(),
+            scope: RegexScope::Body,
+            case_sensitive: true,
+            multi_line: false,
+            dot_all: false,
+        };
+        vec![
+            Statement::HeaderPresent {
+                target: "Server".into(),
+            },
+            Statement::HeaderAbsent {
+                target: "X-Powered-By".into(),
+            },
+            Statement::HeadersAllAbsent {
+                targets: vec!["x-powered-by".into(), "x-aspnet-version".into()],
+            },
+            Statement::HeaderEquals {
+                target: "content-type".into(),
+                expected: "application/json".into(),
+                case_sensitive: None,
+            },
+            Statement::HeaderCasingIs {
+                target: "server".into(),
+                expected_casing: "Server".into(),
+            },
+            Statement::HeaderGlob {
+                target: "server".into(),
+                pattern: "nginx/*".into(),
+                case_sensitive: false,
+            },
+            Statement::ETagEquals {
+                expected: "\"abc\"".into(),
+                allow_weak: false,
+            },
+            Statement::HeaderSetEquals {
+                target: "vary".into(),
+                expected: vec!["origin".into(), "accept".into()],
+            },
+            Statement::HeaderSetContains {
+                target: "vary".into(),
+                expected: vec!["accept".into()],
+            },
+            Statement::HeaderNumeric {
+                target: "age".into(),
+                op: NumOp::Lt,
+                value: 60,
+            },
+            Statement::HeaderNameAbsentMatching {
+                pattern: "^x-powered".into(),
+            },
+            Statement::AllHeadersValueMatch {
+                name_pattern: "^x-".into(),
+                value_pattern: "^HIT$".into(),
+            },
+            Statement::CspDirective {
+                directive: "default-src".into(),
+                expected_sources: vec!["'self'".into()],
+                mode: DirectiveMode::Exact,
+            },
+            Statement::HashEquals {
+                algorithm: HashAlgorithm::Sha256,
+                digest: format!("{:x}", Sha256::digest(br#"{"ok":true}"#)),
+            },
+            Statement::JsonSchemaValid {
+                schema: serde_json::json!({"type": "object", "required": ["ok"]}),
+            },
+            Statement::BodyStartsWith {
+                prefix_base64: B64.encode("{\"ok\""),
+            },
+            Statement::BodyEndsWith {
+                suffix_base64: B64.encode("}"),
+            },
+            Statement::ContentDigestValid,
+            body_regex("\"ok\":true"),
+            Statement::RegexAll {
+                pattern: "nginx|ok".into(),
+                scope: RegexScope::Any,
+                case_sensitive: false,
+                min_matches: 2,
+            },
+            Statement::ReasonEquals {
+                expected: "ok".into(),
+                case_sensitive: false,
+            },
+            Statement::HttpVersionIs {
+                version: "HTTP/1.1".into(),
+            },
+            Statement::CorsAllowsOrigin {
+                origin: "https://example.org".into(),
+            },
+            Statement::LinkRelPresent {
+                rel: "preload".into(),
+            },
+            Statement::LinkRelUri {
+                rel: "preload".into(),
+                uri_pattern: "\\.css$".into(),
+            },
+            Statement::ServerSoftwareAbsent,
+            Statement::CacheHit {
+                expected: true,
+                headers: vec![],
+            },
+            Statement::EarlyHintsPresent {
+                target: Some("Link".into()),
+            },
+            Statement::SpkiPinIs {
+                pin: "pin-sha256:leaf".into(),
+            },
+            Statement::CertChainLengthAtMost { n: 2 },
+            Statement::SctPresent,
+            Statement::CertCoversHost {
+                host: "api.example.com".into(),
+            },
+            Statement::ResolvedIpIs {
+                ip: "203.0.113.5".into(),
+            },
+            Statement::ResponseTimeUnder { max_ms: 500 },
+            Statement::ClockSkewUnder { millis: 1000 },
+            Statement::FieldPresent {
+                path: "/handshake/resolved_ips".into(),
+            },
+            Statement::RegexReplace {
+                pattern: "true".into(),
+                replacement: "false".into(),
+                statement: Box::new(body_regex("\"ok\":false")),
+            },
+            Statement::All {
+                statements: vec![Statement::ContentDigestValid, Statement::SctPresent],
+            },
+            Statement::Any {
+                statements: vec![Statement::ServerSoftwareAbsent, body_regex("^\\{")],
+            },
+        ]
+    }
+
+    #[test]
+    fn every_statement_evaluates_alike_from_the_capture_and_the_witness() {
+        let statements = every_statement();
+        let schema = serde_json::to_value(schemars::schema_for!(Statement)).unwrap();
+        let types = schema["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| variant["properties"]["type"]["enum"][0].to_string())
+            .collect::<BTreeSet<_>>();
+        let covered = statements
+            .iter()
+            .map(|statement| serde_json::to_value(statement).unwrap()["type"].to_string())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(covered, types, "every statement type needs a sample");
+
+        let capture = committed_record();
+        let handshake: HandshakeWitness =
+            serde_json::from_slice(&capture.canonical_handshake).unwrap();
+        let app: AppDataWitness = serde_json::from_slice(&capture.canonical_app_data).unwrap();
+        let mut witnessed = EvalRecord::from_witness(
+            &handshake,
+            &app,
+            &capture.canonical_handshake,
+            &capture.canonical_app_data,
+        )
+        .unwrap();
+        // The verifier takes these from the clock-skew annotation and the
+        // embedded leaf certificate.
+        witnessed.captured_at = Some(capture.captured_at);
+        witnessed.tls.embedded_scts = Some(capture.tls.embedded_scts);
+
+        for statement in &statements {
+            let proved = check(statement, &capture);
+            let verified = evaluate(statement, &witnessed);
+            assert_eq!(
+                (proved.satisfied, proved.reason),
+                (verified.satisfied, verified.reason),
+                "{statement:?}: prover {:?}, verifier {:?}",
+                proved.details,
+                verified.details
+            );
+        }
+        let satisfied = statements
+            .iter()
+            .filter(|statement| check(statement, &capture).satisfied)
+            .count();
+        assert_eq!(
+            satisfied,
+            statements.len() - 1,
+            "only the Server version leaks"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! `field:present`: a JSON pointer into the canonical transcripts, rooted at
+//! `/handshake` or `/app_data`, resolves to a value.
+
+use serde_json::Value;
+
+/// Pointer roots, one per canonical transcript.
+pub const ROOTS: [&str; 2] = ["/handshake", "/app_data"];
+
+/// `Ok` when `pointer` is a JSON pointer under one of [`ROOTS`].
+pub fn check_pointer(pointer: &str) -> Result<(), String> {
+    let rooted = ROOTS.iter().any(|root| {
+        pointer
+            .strip_prefix(root)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    });
+    if rooted {
+        Ok(())
+    } else {
+        Err(format!("'{pointer}' is not under {}", ROOTS.join(" or ")))
+    }
+}
+
+/// The canonical transcripts as one document, so a pointer's first segment
+/// picks the transcript.
+pub fn document(handshake: &[u8], app_data: &[u8]) -> Result<Value, String> {
+    let parse = |name: &str, bytes: &[u8]| {
+        serde_json::from_slice::<Value>(bytes).map_err(|err| format!("{name} is not JSON: {err}"))
+    };
+    Ok(serde_json::json!({
+        "handshake": parse("handshake", handshake)?,
+        "app_data": parse("app data", app_data)?,
+    }))
+}
+
+/// Whether `pointer` resolves in `document` to something other than `null`
+/// or an empty string, the forms the canonical encoding gives to absent
+/// values (no ALPN, no reason phrase).
+pub fn is_present(document: &Value, pointer: &str) -> bool {
+    match document.pointer(pointer) {
+        None | Some(Value::Null) => false,
+        Some(Value::String(text)) => !text.is_empty(),
+        Some(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Value {
+        document(
+            br#"{"domain":"example.com","alpn":null,"cert_fingerprints":["sha256:ab"]}"#,
+            br#"{"status_code":200,"reason":"OK","headers":[{"name":"server","value":"nginx"}]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn resolves_existing_fields() {
+        let doc = sample();
+        assert!(is_present(&doc, "/app_data/reason"));
+        assert!(is_present(&doc, "/app_data/headers/0/value"));
+        assert!(is_present(&doc, "/handshake/cert_fingerprints"));
+    }
+
+    #[test]
+    fn null_and_missing_fields_are_absent() {
+        let doc = sample();
+        assert!(!is_present(&doc, "/handshake/alpn"));
+        assert!(!is_present(&doc, "/app_data/headers/1"));
+        assert!(!is_present(&doc, "/app_data/timing"));
+        assert!(check_pointer("/app_data/reason").is_ok());
+        assert!(check_pointer("/app_database").is_err());
+        assert!(check_pointer("reason").is_err());
+    }
+}
@@ -0,0 +1,64 @@
+//! Shell-style wildcards for header values: `*` matches any run of
+//! characters (including none), `?` exactly one; everything else is literal.
+
+use regex::{Regex, RegexBuilder};
+
+/// A compiled glob, matched against the whole value.
+#[derive(Debug, Clone)]
+pub struct Glob(Regex);
+
+impl Glob {
+    pub fn new(pattern: &str, case_sensitive: bool) -> Result<Self, regex::Error> {
+        let mut anchored = String::from("^");
+        let mut literal = String::new();
+        for ch in pattern.chars() {
+            let wildcard = match ch {
+                '*' => "(?s:.*)",
+                '?' => "(?s:.)",
+                _ => {
+                    literal.push(ch);
+                    continue;
+                }
+            };
+            anchored.push_str(&regex::escape(&literal));
+            literal.clear();
+            anchored.push_str(wildcard);
+        }
+        anchored.push_str(&regex::escape(&literal));
+        anchored.push('$');
+        RegexBuilder::new(&anchored)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map(Glob)
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        self.0.is_match(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_span_the_whole_value() {
+        let glob = Glob::new("nginx/*", false).unwrap();
+        assert!(glob.is_match("nginx/1.25.3"));
+        assert!(glob.is_match("NGINX/"));
+        assert!(!glob.is_match("apache/2.4"));
+        assert!(!glob.is_match("openresty nginx/1.25"));
+
+        let one = Glob::new("v?", true).unwrap();
+        assert!(one.is_match("v2"));
+        assert!(!one.is_match("V2"));
+        assert!(!one.is_match("v10"));
+    }
+
+    #[test]
+    fn regex_metacharacters_are_literal() {
+        let glob = Glob::new("max-age=(31536000)+.", true).unwrap();
+        assert!(glob.is_match("max-age=(31536000)+."));
+        assert!(!glob.is_match("max-age=3153600000x"));
+    }
+}
@@ -0,0 +1,60 @@
+//! `header:casing`: a header name was sent with exact capitalization
+//! (`X-Powered-By` rather than `x-powered-by`), which HTTP fingerprinting
+//! keys on. Needs the on-the-wire names the prover records with
+//! `--header-case-preserve`.
+
+/// Check that every `target` header in `headers`, given as lowercased name
+/// and recorded wire spelling, was spelled `expected`. `Err` says why not:
+/// the header is absent, its spelling was not recorded, or it differs.
+pub fn check<'a>(
+    target: &str,
+    expected: &str,
+    headers: impl IntoIterator<Item = (&'a str, Option<&'a str>)>,
+) -> Result<(), String> {
+    let mut seen = false;
+    for (name, wire_name) in headers {
+        if !name.eq_ignore_ascii_case(target) {
+            continue;
+        }
+        seen = true;
+        match wire_name {
+            Some(wire_name) if wire_name == expected => {}
+            Some(wire_name) => return Err(format!("sent as {wire_name}")),
+            None => {
+                return Err(
+                    "header name casing not recorded; capture with --header-case-preserve".into(),
+                )
+            }
+        }
+    }
+    if seen {
+        Ok(())
+    } else {
+        Err(format!("{target} header absent"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_recorded_spelling_exactly() {
+        let headers = [
+            ("server", Some("Server")),
+            ("x-powered-by", Some("X-Powered-By")),
+        ];
+        assert_eq!(check("x-powered-by", "X-Powered-By", headers), Ok(()));
+        assert_eq!(
+            check("server", "SERVER", headers),
+            Err("sent as Server".to_string())
+        );
+        assert_eq!(
+            check("via", "Via", headers),
+            Err("via header absent".to_string())
+        );
+        assert!(check("server", "Server", [("server", None)])
+            .unwrap_err()
+            .contains("--header-case-preserve"));
+    }
+}
@@ -0,0 +1,36 @@
+//! `http:version`: the protocol version the response was served over. Status
+//! lines (`HTTP/2`), ALPN ids (`h2`) and bare numbers (`2.0`) all name the
+//! same version.
+
+/// The status-line form (`HTTP/1.1`, `HTTP/2`) of `version`, or `None` when
+/// it names no known HTTP version.
+pub fn canonical(version: &str) -> Option<&'static str> {
+    let version = version.trim().to_ascii_lowercase();
+    let number = version.strip_prefix("http/").unwrap_or(&version);
+    match number {
+        "0.9" => Some("HTTP/0.9"),
+        "1" | "1.0" => Some("HTTP/1.0"),
+        "1.1" => Some("HTTP/1.1"),
+        "2" | "2.0" | "h2" | "h2c" => Some("HTTP/2"),
+        "3" | "3.0" | "h3" => Some("HTTP/3"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spellings_of_one_version_agree() {
+        for spelling in ["HTTP/2", "http/2.0", "h2", "2", " H2 "] {
+            assert_eq!(canonical(spelling), Some("HTTP/2"), "{spelling}");
+        }
+        assert_eq!(canonical("HTTP/1.1"), Some("HTTP/1.1"));
+        assert_eq!(canonical("http/1.1"), Some("HTTP/1.1"));
+        assert_eq!(canonical("1.0"), Some("HTTP/1.0"));
+        assert_eq!(canonical("h3"), Some("HTTP/3"));
+        assert_eq!(canonical("HTTP/4"), None);
+        assert_eq!(canonical("spdy/3"), None);
+    }
+}
@@ -0,0 +1,61 @@
+//! `json:schema`: the response body parses as JSON and validates against a
+//! JSON Schema. Remote `$ref`s are never fetched, so a schema must be
+//! self-contained.
+
+use jsonschema::Validator;
+use serde_json::Value;
+
+/// Compile `schema`, or describe why it is not a usable JSON Schema.
+pub fn compile(schema: &Value) -> Result<Validator, String> {
+    jsonschema::validator_for(schema).map_err(|err| err.to_string())
+}
+
+/// `Ok` when `body` is JSON conforming to `schema`; otherwise the reason,
+/// naming the first validation error and where it occurred.
+pub fn check_body(schema: &Value, body: &[u8]) -> Result<(), String> {
+    let validator = compile(schema).map_err(|err| format!("invalid schema: {err}"))?;
+    let instance =
+        serde_json::from_slice::<Value>(body).map_err(|err| format!("body is not JSON: {err}"))?;
+    validator.validate(&instance).map_err(|err| {
+        let path = err.instance_path.to_string();
+        let path = if path.is_empty() { "/" } else { &path };
+        format!("at {path}: {err}")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["id", "tags"],
+            "properties": {
+                "id": {"type": "integer"},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            }
+        })
+    }
+
+    #[test]
+    fn accepts_conforming_body() {
+        assert_eq!(
+            check_body(&schema(), br#"{"id": 7, "tags": ["a"], "extra": null}"#),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn reports_first_violation_and_non_json_bodies() {
+        let err = check_body(&schema(), br#"{"id": 7, "tags": ["a", 2]}"#).unwrap_err();
+        assert!(err.starts_with("at /tags/1: "), "{err}");
+        assert!(err.contains("not of type \"string\""), "{err}");
+
+        let err = check_body(&schema(), b"<html></html>").unwrap_err();
+        assert!(err.starts_with("body is not JSON"), "{err}");
+        assert!(compile(&json!({"type": 12})).is_err());
+    }
+}
@@ -1,8 +1,34 @@
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
+pub mod cache;
+pub mod combine;
+pub mod content_digest;
+pub mod csp;
+pub mod etag;
+pub mod evaluate;
+pub mod field;
+pub mod glob;
+pub mod header_casing;
+pub mod http_version;
+pub mod json_schema;
+pub mod link;
+pub mod normalize;
 pub mod parser;
-pub use parser::{parse_statement, StatementParseError};
+pub mod record;
+pub mod san;
+pub mod server;
+pub mod transform;
+pub mod version;
+pub mod witness;
+pub use etag::EntityTag;
+pub use glob::Glob;
+pub use normalize::StatementError;
+pub use parser::{parse_statement, parse_statement_with_vars, split_list, StatementParseError};
+pub use version::{BASELINE_VERIFIER_VERSION, EXTENDED_VERIFIER_VERSION};
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(tag = "type")]
@@ -12,6 +38,9 @@ pub enum Statement {
     HeaderPresent { target: String },
     #[serde(rename = "header:absent")]
     HeaderAbsent { target: String },
+    /// None of `targets` is present, e.g. a baseline of deprecated headers.
+    #[serde(rename = "header:none")]
+    HeadersAllAbsent { targets: Vec<String> },
     #[serde(rename = "header:eq")]
     HeaderEquals {
         target: String,
@@ -19,11 +48,95 @@ pub enum Statement {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         case_sensitive: Option<bool>,
     },
+    /// Every `target` header was sent with the name spelled exactly
+    /// `expected_casing`. Needs a capture with `--header-case-preserve`.
+    #[serde(rename = "header:casing")]
+    HeaderCasingIs {
+        target: String,
+        expected_casing: String,
+    },
+    /// Some value of `target` matches the shell-style `pattern` (`*`, `?`)
+    /// in full, e.g. `nginx/*`. Case-insensitive unless `case_sensitive`.
+    #[serde(rename = "header:glob")]
+    HeaderGlob {
+        target: String,
+        pattern: String,
+        #[serde(default)]
+        case_sensitive: bool,
+    },
+    /// The `ETag` header matches `expected` under RFC 7232 comparison:
+    /// strong (both tags strong, same opaque tag) unless `allow_weak`, which
+    /// only compares the opaque tags. A missing or malformed `ETag` fails.
+    #[serde(rename = "etag:eq")]
+    ETagEquals {
+        expected: String,
+        #[serde(default)]
+        allow_weak: bool,
+    },
+    /// Treats the comma-separated header value (all lines of `target`
+    /// combined) as a set and compares it to `expected`, ignoring order,
+    /// whitespace and case.
+    #[serde(rename = "header:set-eq")]
+    HeaderSetEquals {
+        target: String,
+        expected: Vec<String>,
+    },
+    /// Like `header:set-eq`, but the header may list further elements.
+    #[serde(rename = "header:set-contains")]
+    HeaderSetContains {
+        target: String,
+        expected: Vec<String>,
+    },
+    /// Every value of `target` is an integer satisfying `op value`.
+    #[serde(rename = "header:num")]
+    HeaderNumeric {
+        target: String,
+        op: NumOp,
+        value: i64,
+    },
+    /// No response header name matches `pattern` (case-insensitive regex).
+    #[serde(rename = "header:none-match")]
+    HeaderNameAbsentMatching { pattern: String },
+    /// Every header whose name matches `name_pattern` (case-insensitive) has
+    /// a value matching `value_pattern` (case-sensitive). Holds vacuously
+    /// when no header name matches.
+    #[serde(rename = "header:all-match")]
+    AllHeadersValueMatch {
+        name_pattern: String,
+        value_pattern: String,
+    },
+    /// Compares a Content-Security-Policy directive's source list set-wise.
+    /// Multiple CSP headers are merged, unioning each directive's sources.
+    #[serde(rename = "csp")]
+    CspDirective {
+        directive: String,
+        #[serde(default)]
+        expected_sources: Vec<String>,
+        mode: DirectiveMode,
+    },
     #[serde(rename = "hash:eq")]
     HashEquals {
         algorithm: HashAlgorithm,
         digest: String,
     },
+    /// The body parses as JSON and validates against `schema`. Non-JSON and
+    /// truncated bodies fail.
+    #[serde(rename = "json:schema")]
+    JsonSchemaValid { schema: serde_json::Value },
+    /// Raw body bytes begin with the base64-encoded prefix.
+    #[serde(rename = "body:starts")]
+    BodyStartsWith { prefix_base64: String },
+    /// Raw body bytes end with the base64-encoded suffix; unverifiable when
+    /// the body was truncated.
+    #[serde(rename = "body:ends")]
+    BodyEndsWith { suffix_base64: String },
+    /// The body hashes to every `sha-256`/`sha-512` digest in the server's
+    /// `Content-Digest` or legacy `Digest` header. Fails when neither header
+    /// carries a supported digest, and when the body was truncated.
+    #[serde(rename = "body:content-digest")]
+    ContentDigestValid,
+    /// `multi_line` makes `^`/`$` match at line breaks and `dot_all` lets
+    /// `.` match `\n`; both are off by default.
     #[serde(rename = "regex")]
     Regex {
         pattern: String,
@@ -31,7 +144,110 @@ pub enum Statement {
         scope: RegexScope,
         #[serde(default)]
         case_sensitive: bool,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        multi_line: bool,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        dot_all: bool,
+    },
+    /// Requires at least `min_matches` non-overlapping matches; a minimum of
+    /// zero means the pattern must not match at all.
+    #[serde(rename = "regex:count")]
+    RegexAll {
+        pattern: String,
+        #[serde(default)]
+        scope: RegexScope,
+        #[serde(default)]
+        case_sensitive: bool,
+        min_matches: usize,
+    },
+    /// Compares the status line's reason phrase (`Not Found` in
+    /// `HTTP/1.1 404 Not Found`). HTTP/2 responses have none.
+    #[serde(rename = "reason:eq")]
+    ReasonEquals {
+        expected: String,
+        #[serde(default)]
+        case_sensitive: bool,
     },
+    /// The response was served over `version`, stored in status-line form
+    /// (`HTTP/1.1`, `HTTP/2`); `h2` and `2.0` are accepted when parsing.
+    #[serde(rename = "http:version")]
+    HttpVersionIs { version: String },
+    /// `Access-Control-Allow-Origin` admits `origin`, either verbatim or via
+    /// `*`. Pair with `--method options --origin <origin>` for preflights.
+    #[serde(rename = "cors:allows-origin")]
+    CorsAllowsOrigin { origin: String },
+    /// Some `Link` header carries relation `rel` (case-insensitive).
+    #[serde(rename = "link:rel")]
+    LinkRelPresent { rel: String },
+    /// Some `Link` with relation `rel` targets a URI matching `uri_pattern`.
+    #[serde(rename = "link:uri")]
+    LinkRelUri { rel: String, uri_pattern: String },
+    /// No `Server` header leaks software: neither a version (`nginx/1.25.3`,
+    /// `nginx 1.25.3`) nor a platform (`(Ubuntu)`). A bare product token or
+    /// no header passes.
+    #[serde(rename = "server:software-absent")]
+    ServerSoftwareAbsent,
+    /// Whether the response was (`expected`) or was not served from a
+    /// cache, judged by the first of `headers` present (default `X-Cache`,
+    /// `CF-Cache-Status`, `Age`). Fails when none is.
+    #[serde(rename = "cache:hit")]
+    CacheHit {
+        expected: bool,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        headers: Vec<String>,
+    },
+    #[serde(rename = "early-hints:present")]
+    EarlyHintsPresent {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target: Option<String>,
+    },
+    /// Some certificate in the served chain has SubjectPublicKeyInfo SHA-256
+    /// `pin` (base64, as in HPKP `pin-sha256`).
+    #[serde(rename = "tls:spki-pin")]
+    SpkiPinIs { pin: String },
+    /// The server sent a certificate chain of at most `n` certificates.
+    /// Plaintext captures, which have no chain, fail.
+    #[serde(rename = "tls:chain-length-at-most")]
+    CertChainLengthAtMost { n: usize },
+    /// The leaf certificate embeds at least one signed certificate timestamp,
+    /// i.e. it was logged for Certificate Transparency.
+    #[serde(rename = "tls:sct-present")]
+    SctPresent,
+    /// The leaf certificate's SubjectAltName covers `host`, exactly or
+    /// through a wildcard (RFC 6125), independent of trust-store validation.
+    #[serde(rename = "tls:cert-covers")]
+    CertCoversHost { host: String },
+    /// Prover-attested: the host resolved to `ip` at capture time.
+    #[serde(rename = "dns:resolved")]
+    ResolvedIpIs { ip: String },
+    /// Prover-attested: time to first byte stayed below `max_ms`. Evaluated
+    /// against the warm figure when captured with `--warm`.
+    #[serde(rename = "timing:ttfb-under")]
+    ResponseTimeUnder { max_ms: u64 },
+    /// Prover-attested: the server's `Date` header was within `millis` of
+    /// the prover's clock. `Date` has one-second resolution.
+    #[serde(rename = "timing:clock-skew-under")]
+    ClockSkewUnder { millis: u64 },
+    /// JSON pointer `path` into the canonical transcripts, under
+    /// `/handshake` or `/app_data`, resolves to a value other than `null` or
+    /// an empty string.
+    #[serde(rename = "field:present")]
+    FieldPresent { path: String },
+    /// `statement` evaluated against the body with every match of `pattern`
+    /// replaced by `replacement`, e.g. to blank out per-request tokens
+    /// before a `hash:eq`. Streamed-digest and truncated bodies fail.
+    #[serde(rename = "transform:regex")]
+    RegexReplace {
+        pattern: String,
+        replacement: String,
+        statement: Box<Statement>,
+    },
+    /// Conjunction: satisfied when every member is.
+    #[serde(rename = "all")]
+    All { statements: Vec<Statement> },
+    /// Disjunction: satisfied when at least one member is.
+    #[serde(rename = "any")]
+    Any { statements: Vec<Statement> },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
@@ -41,6 +257,91 @@ pub enum HashAlgorithm {
     Blake3,
 }
 
+impl HashAlgorithm {
+    /// Digest length in bytes.
+    pub fn digest_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 | HashAlgorithm::Blake3 => 32,
+        }
+    }
+
+    /// Raw bytes of a `hash:eq` digest written as hex or base64 (standard or
+    /// URL-safe alphabet, padding optional). The encoding is picked by which
+    /// one yields a digest of [`Self::digest_len`] bytes; input that does so
+    /// under both, or neither, is rejected.
+    pub fn decode_digest(&self, digest: &str) -> Result<Vec<u8>, StatementParseError> {
+        let digest = digest.trim();
+        let len = self.digest_len();
+        let hex = decode_hex(digest).filter(|bytes| bytes.len() == len);
+        let base64 = [&STANDARD, &STANDARD_NO_PAD, &URL_SAFE, &URL_SAFE_NO_PAD]
+            .into_iter()
+            .find_map(|engine| engine.decode(digest).ok())
+            .filter(|bytes| bytes.len() == len);
+        match (hex, base64) {
+            (Some(bytes), None) | (None, Some(bytes)) => Ok(bytes),
+            (Some(_), Some(_)) => Err(StatementParseError::AmbiguousDigest(digest.into())),
+            (None, None) => Err(StatementParseError::InvalidDigest(digest.into(), len)),
+        }
+    }
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&text[idx..idx + 2], 16).ok())
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NumOp {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+impl NumOp {
+    pub fn compare(self, lhs: i64, rhs: i64) -> bool {
+        match self {
+            NumOp::Eq => lhs == rhs,
+            NumOp::Ne => lhs != rhs,
+            NumOp::Lt => lhs < rhs,
+            NumOp::Lte => lhs <= rhs,
+            NumOp::Gt => lhs > rhs,
+            NumOp::Gte => lhs >= rhs,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            NumOp::Eq => "==",
+            NumOp::Ne => "!=",
+            NumOp::Lt => "<",
+            NumOp::Lte => "<=",
+            NumOp::Gt => ">",
+            NumOp::Gte => ">=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DirectiveMode {
+    /// The directive is present with exactly the expected sources.
+    Exact,
+    /// The directive is present and allows every expected source.
+    Contains,
+    /// None of the expected sources is allowed; with no expected sources,
+    /// the directive itself must be missing.
+    Absent,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum RegexScope {
@@ -48,26 +349,207 @@ pub enum RegexScope {
     Body,
     #[default]
     Any,
+    /// Status code and reason phrase, e.g. `200 OK`.
+    Status,
+    /// Full status line, e.g. `HTTP/1.1 200 OK`.
+    StatusLine,
+    /// `Set-Cookie` values only, one per line, without the header name.
+    Cookies,
 }
 
 impl Statement {
+    /// Compact JSON encoding: fields in declaration order, object keys of
+    /// embedded JSON (e.g. a schema) sorted. What fingerprints and statement
+    /// commitments hash.
+    pub fn canonical_json(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("statements serialize to JSON")
+    }
+
+    /// Stable identifier for allow-lists: `sha256:<hex>` over the statement's
+    /// [canonical JSON](Self::canonical_json).
+    pub fn fingerprint(&self) -> String {
+        let digest = Sha256::digest(self.canonical_json());
+        let hex = digest
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        format!("sha256:{hex}")
+    }
+
+    /// Whether the statement can only be decided from the response body, so
+    /// a bodiless response (`HEAD`) always fails it. `all` needs any member
+    /// to read the body, `any` every member.
+    pub fn reads_body(&self) -> bool {
+        match self {
+            Statement::HashEquals { .. }
+            | Statement::JsonSchemaValid { .. }
+            | Statement::BodyStartsWith { .. }
+            | Statement::BodyEndsWith { .. }
+            | Statement::ContentDigestValid
+            | Statement::RegexReplace { .. }
+            | Statement::Regex {
+                scope: RegexScope::Body,
+                ..
+            }
+            | Statement::RegexAll {
+                scope: RegexScope::Body,
+                ..
+            } => true,
+            Statement::All { statements } => statements.iter().any(Statement::reads_body),
+            Statement::Any { statements } => {
+                !statements.is_empty() && statements.iter().all(Statement::reads_body)
+            }
+            _ => false,
+        }
+    }
+
     pub fn summary(&self) -> String {
         match self {
             Statement::HeaderPresent { target } => format!("header present: {}", target),
             Statement::HeaderAbsent { target } => format!("header absent: {}", target),
+            Statement::HeaderCasingIs {
+                expected_casing, ..
+            } => format!("header sent as {}", expected_casing),
+            Statement::HeadersAllAbsent { targets } => {
+                format!("headers absent: {}", targets.join(", "))
+            }
             Statement::HeaderEquals {
                 target, expected, ..
             } => format!("header {} equals {}", target, expected),
+            Statement::HeaderGlob {
+                target, pattern, ..
+            } => format!("header {} matches {}", target, pattern),
+            Statement::ETagEquals {
+                expected,
+                allow_weak,
+            } => format!(
+                "etag {} {}",
+                if *allow_weak {
+                    "weakly matches"
+                } else {
+                    "equals"
+                },
+                expected
+            ),
+            Statement::HeaderNumeric { target, op, value } => {
+                format!("header {} {} {}", target, op.symbol(), value)
+            }
+            Statement::HeaderNameAbsentMatching { pattern } => {
+                format!("no header name matches {}", pattern)
+            }
+            Statement::AllHeadersValueMatch {
+                name_pattern,
+                value_pattern,
+            } => format!(
+                "headers matching {} have values matching {}",
+                name_pattern, value_pattern
+            ),
+            Statement::CspDirective {
+                directive,
+                expected_sources,
+                mode,
+            } => format!(
+                "csp {} {:?} [{}]",
+                directive,
+                mode,
+                expected_sources.join(" ")
+            ),
             Statement::HashEquals { algorithm, .. } => {
                 format!("hash equals via {:?}", algorithm)
             }
+            Statement::JsonSchemaValid { schema } => {
+                match schema.get("title").and_then(|title| title.as_str()) {
+                    Some(title) => format!("body matches JSON schema \"{}\"", title),
+                    None => "body matches JSON schema".to_string(),
+                }
+            }
+            Statement::BodyStartsWith { prefix_base64 } => {
+                format!("body starts with base64 {}", prefix_base64)
+            }
+            Statement::BodyEndsWith { suffix_base64 } => {
+                format!("body ends with base64 {}", suffix_base64)
+            }
+            Statement::ContentDigestValid => "body matches its Content-Digest header".to_string(),
             Statement::Regex { pattern, scope, .. } => {
                 format!("regex {:?}: {}", scope, pattern)
             }
+            Statement::RegexAll {
+                pattern,
+                scope,
+                min_matches: 0,
+                ..
+            } => format!("regex {:?} never matches: {}", scope, pattern),
+            Statement::RegexAll {
+                pattern,
+                scope,
+                min_matches,
+                ..
+            } => format!(
+                "regex {:?} matches at least {}x: {}",
+                scope, min_matches, pattern
+            ),
+            Statement::HttpVersionIs { version } => format!("served over {}", version),
+            Statement::ReasonEquals { expected, .. } => {
+                format!("reason phrase equals {}", expected)
+            }
+            Statement::CorsAllowsOrigin { origin } => format!("CORS allows origin {}", origin),
+            Statement::LinkRelPresent { rel } => format!("link rel={} present", rel),
+            Statement::LinkRelUri { rel, uri_pattern } => {
+                format!("link rel={} uri matches /{}/", rel, uri_pattern)
+            }
+            Statement::HeaderSetEquals { target, expected } => {
+                format!("header {} set equals {{{}}}", target, expected.join(", "))
+            }
+            Statement::HeaderSetContains { target, expected } => {
+                format!("header {} set contains {{{}}}", target, expected.join(", "))
+            }
+            Statement::ServerSoftwareAbsent => {
+                "server header names no software version".to_string()
+            }
+            Statement::CacheHit { expected, .. } => {
+                format!(
+                    "served {} cache",
+                    if *expected { "from" } else { "bypassing" }
+                )
+            }
+            Statement::EarlyHintsPresent { target: None } => "early hints present".to_string(),
+            Statement::EarlyHintsPresent {
+                target: Some(target),
+            } => format!("early hints present: {}", target),
+            Statement::SpkiPinIs { pin } => format!("spki pin-sha256 is {}", pin),
+            Statement::CertChainLengthAtMost { n } => {
+                format!("certificate chain has at most {} certificates", n)
+            }
+            Statement::SctPresent => "leaf certificate embeds SCTs".to_string(),
+            Statement::CertCoversHost { host } => format!("leaf certificate covers {}", host),
+            Statement::ResolvedIpIs { ip } => format!("resolved ip is {} (prover-attested)", ip),
+            Statement::ResponseTimeUnder { max_ms } => format!("ttfb under {}ms", max_ms),
+            Statement::ClockSkewUnder { millis } => format!("clock skew under {}ms", millis),
+            Statement::FieldPresent { path } => format!("field {} present", path),
+            Statement::RegexReplace {
+                pattern,
+                replacement,
+                statement,
+            } => format!(
+                "after replacing /{}/ with \"{}\": {}",
+                pattern,
+                replacement,
+                statement.summary()
+            ),
+            Statement::All { statements } => format!("all of ({})", join_summaries(statements)),
+            Statement::Any { statements } => format!("any of ({})", join_summaries(statements)),
         }
     }
 }
 
+fn join_summaries(statements: &[Statement]) -> String {
+    statements
+        .iter()
+        .map(Statement::summary)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -81,4 +563,31 @@ mod tests {
         let back: Statement = serde_json::from_str(&json).expect("deserialize");
         assert_eq!(statement, back);
     }
+
+    #[test]
+    fn fingerprint_is_stable_and_distinguishes_statements() {
+        let absent = parse_statement("header:absent:Server").unwrap();
+        let present = parse_statement("header:present:Server").unwrap();
+        assert_eq!(absent.fingerprint(), absent.clone().fingerprint());
+        assert_ne!(absent.fingerprint(), present.fingerprint());
+        assert!(absent.fingerprint().starts_with("sha256:"));
+        assert_eq!(absent.fingerprint().len(), "sha256:".len() + 64);
+    }
+
+    #[test]
+    fn reads_body_covers_body_statements_and_composites() {
+        let reads = |expr: &str| parse_statement(expr).unwrap().reads_body();
+        let digest = "0".repeat(64);
+        assert!(reads(&format!("hash:eq:sha256:{digest}")));
+        assert!(reads("regex:body:x"));
+        assert!(!reads("regex:headers:x"));
+        assert!(!reads("regex:x"));
+        assert!(!reads("header:present:Server"));
+        assert!(reads(&format!(
+            "all:(header:present:Server,hash:eq:sha256:{digest})"
+        )));
+        assert!(!reads(&format!(
+            "any:(header:present:Server,hash:eq:sha256:{digest})"
+        )));
+    }
 }
@@ -0,0 +1,78 @@
+//! Minimal `Link` header parsing (RFC 8288) into `(uri, rel)` pairs.
+
+/// One target/relation pair. A link with `rel="next last"` yields two.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkRel {
+    pub uri: String,
+    /// Lower-cased relation type.
+    pub rel: String,
+}
+
+/// Parse every `Link` header value, each possibly holding comma-separated
+/// links. Links without a `rel` parameter are skipped.
+pub fn parse_links<'a>(values: impl IntoIterator<Item = &'a String>) -> Vec<LinkRel> {
+    let mut links = Vec::new();
+    for value in values {
+        let mut rest = value.as_str();
+        while let Some(start) = rest.find('<') {
+            let Some(end) = rest[start..].find('>') else {
+                break;
+            };
+            let uri = rest[start + 1..start + end].trim().to_string();
+            let (params, next) = split_params(&rest[start + end + 1..]);
+            rest = next;
+            if let Some(rels) = rel_param(params) {
+                links.extend(rels.split_ascii_whitespace().map(|rel| LinkRel {
+                    uri: uri.clone(),
+                    rel: rel.to_ascii_lowercase(),
+                }));
+            }
+        }
+    }
+    links
+}
+
+/// Split the parameters of one link from the remaining links at the first
+/// comma outside a quoted string.
+fn split_params(input: &str) -> (&str, &str) {
+    let mut quoted = false;
+    for (idx, ch) in input.char_indices() {
+        match ch {
+            '"' => quoted = !quoted,
+            ',' if !quoted => return (&input[..idx], &input[idx + 1..]),
+            _ => {}
+        }
+    }
+    (input, "")
+}
+
+fn rel_param(params: &str) -> Option<&str> {
+    params.split(';').find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("rel")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_multiple_links_and_headers() {
+        let values = [
+            r#"<https://api.example.com/items?page=3>; rel="next", <https://api.example.com/items?page=1>; rel="prev""#.to_string(),
+            r#"<https://api.example.com/items?page=9>; title="a, b"; rel="last nofollow""#
+                .to_string(),
+        ];
+        let links = parse_links(&values);
+        let rels = links
+            .iter()
+            .map(|link| link.rel.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(rels, ["next", "prev", "last", "nofollow"]);
+        assert_eq!(links[1].uri, "https://api.example.com/items?page=1");
+        assert_eq!(links[2].uri, "https://api.example.com/items?page=9");
+    }
+}
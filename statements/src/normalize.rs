@@ -0,0 +1,146 @@
+use thiserror::Error;
+
+use crate::Statement;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum StatementError {
+    #[error("contradictory statements: '{0}' and '{1}' cannot both hold")]
+    Contradiction(String, String),
+}
+
+impl Statement {
+    /// Flatten nested `all`/`any`, drop duplicate members, and unwrap
+    /// single-member composites. Fails when an `all` requires a header to be
+    /// both absent and present (or equal to a value).
+    pub fn normalize(self) -> Result<Statement, StatementError> {
+        match self {
+            Statement::All { statements } => {
+                let members = normalize_members(statements, true)?;
+                for (idx, member) in members.iter().enumerate() {
+                    for other in &members[idx + 1..] {
+                        if contradicts(member, other) {
+                            return Err(StatementError::Contradiction(
+                                member.summary(),
+                                other.summary(),
+                            ));
+                        }
+                    }
+                }
+                Ok(collapse(members, true))
+            }
+            Statement::Any { statements } => {
+                Ok(collapse(normalize_members(statements, false)?, false))
+            }
+            Statement::RegexReplace {
+                pattern,
+                replacement,
+                statement,
+            } => Ok(Statement::RegexReplace {
+                pattern,
+                replacement,
+                statement: Box::new(statement.normalize()?),
+            }),
+            other => Ok(other),
+        }
+    }
+}
+
+fn normalize_members(
+    statements: Vec<Statement>,
+    conjunction: bool,
+) -> Result<Vec<Statement>, StatementError> {
+    let mut members = Vec::new();
+    for member in statements {
+        let nested = match member.normalize()? {
+            Statement::All { statements } if conjunction => statements,
+            Statement::Any { statements } if !conjunction => statements,
+            other => vec![other],
+        };
+        for statement in nested {
+            if !members.contains(&statement) {
+                members.push(statement);
+            }
+        }
+    }
+    Ok(members)
+}
+
+fn collapse(mut statements: Vec<Statement>, conjunction: bool) -> Statement {
+    if statements.len() == 1 {
+        return statements.remove(0);
+    }
+    if conjunction {
+        Statement::All { statements }
+    } else {
+        Statement::Any { statements }
+    }
+}
+
+fn contradicts(a: &Statement, b: &Statement) -> bool {
+    let requires_header = |statement: &Statement| match statement {
+        Statement::HeaderPresent { target }
+        | Statement::HeaderEquals { target, .. }
+        | Statement::HeaderGlob { target, .. }
+        | Statement::HeaderNumeric { target, .. }
+        | Statement::HeaderSetEquals { target, .. }
+        | Statement::HeaderSetContains { target, .. } => Some(target.to_ascii_lowercase()),
+        _ => None,
+    };
+    let forbids_header = |statement: &Statement, name: &str| match statement {
+        Statement::HeaderAbsent { target } => target.eq_ignore_ascii_case(name),
+        Statement::HeadersAllAbsent { targets } => targets
+            .iter()
+            .any(|target| target.eq_ignore_ascii_case(name)),
+        _ => false,
+    };
+    let clash = |x: &Statement, y: &Statement| {
+        requires_header(x).is_some_and(|required| forbids_header(y, &required))
+    };
+    clash(a, b) || clash(b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_statement;
+
+    #[test]
+    fn flattens_nested_composites_and_drops_duplicates() {
+        let statement = parse_statement(
+            "all:(header:present:Server, all:(header:present:Server, any:(any:(header:absent:X-A), header:absent:X-A)))",
+        )
+        .unwrap();
+        assert_eq!(
+            statement.normalize().unwrap(),
+            Statement::All {
+                statements: vec![
+                    Statement::HeaderPresent {
+                        target: "Server".into()
+                    },
+                    Statement::HeaderAbsent {
+                        target: "X-A".into()
+                    },
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn detects_present_and_absent_of_same_header() {
+        let statement =
+            parse_statement("all:(header:present:X-Frame-Options, header:absent:x-frame-options)")
+                .unwrap();
+        assert!(matches!(
+            statement.normalize(),
+            Err(StatementError::Contradiction(_, _))
+        ));
+
+        let listed =
+            parse_statement(r#"all:(header:present:Server, header:none:"X-Powered-By,server")"#)
+                .unwrap();
+        assert!(listed.normalize().is_err());
+
+        let either = parse_statement("any:(header:present:X, header:absent:X)").unwrap();
+        assert!(either.normalize().is_ok());
+    }
+}
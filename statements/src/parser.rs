@@ -1,24 +1,274 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::str::FromStr;
 
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use thiserror::Error;
 
-use crate::{HashAlgorithm, RegexScope, Statement};
+use crate::{
+    field, http_version, json_schema, transform, DirectiveMode, EntityTag, Glob, HashAlgorithm,
+    NumOp, RegexScope, Statement,
+};
 
 /// Parse a CLI-friendly statement expression into a strongly typed [`Statement`].
+///
+/// Composites take the form `all:(<statement>, ...)` or `any:(<statement>, ...)`
+/// and may nest; quote member values that contain commas or parentheses.
 pub fn parse_statement(input: &str) -> Result<Statement, StatementParseError> {
+    parse(input, None)
+}
+
+/// [`parse_statement`] with every `${NAME}` in a value replaced by
+/// `vars[NAME]`, so one template serves many targets
+/// (`header:eq:Host:${DOMAIN}`). Substitution happens after the expression
+/// is split, so a value containing `:` or `)` cannot change its structure.
+/// Statement kinds and `json:schema` text are taken literally.
+pub fn parse_statement_with_vars(
+    input: &str,
+    vars: &HashMap<String, String>,
+) -> Result<Statement, StatementParseError> {
+    parse(input, Some(vars))
+}
+
+type Vars<'a> = Option<&'a HashMap<String, String>>;
+
+fn parse(input: &str, vars: Vars) -> Result<Statement, StatementParseError> {
+    if let Some(composite) = parse_composite(input, vars) {
+        return composite;
+    }
+    if let Some(transform) = parse_transform(input, vars) {
+        return transform;
+    }
+    if let Some(schema) = parse_json_schema(input) {
+        return schema;
+    }
     let mut parts = tokenize(input)?;
     if parts.is_empty() {
         return Err(StatementParseError::EmptyExpression);
     }
     let kind = parts.remove(0).to_ascii_lowercase();
+    if let Some(vars) = vars {
+        for part in &mut parts {
+            *part = interpolate(part, vars)?;
+        }
+    }
     match kind.as_str() {
         "header" => parse_header(parts),
         "hash" => parse_hash(parts),
+        "etag" => parse_etag(parts),
         "regex" => parse_regex(parts),
+        "early-hints" => parse_early_hints(parts),
+        "dns" => parse_dns(parts),
+        "tls" => parse_tls(parts),
+        "timing" => parse_timing(parts),
+        "csp" => parse_csp(parts),
+        "reason" => parse_reason(parts),
+        "http" => parse_http(parts),
+        "body" => parse_body(parts),
+        "link" => parse_link(parts),
+        "cors" => parse_cors(parts),
+        "cache" => parse_cache(parts),
+        "server" => parse_server(parts),
+        "field" => parse_field(parts),
         _ => Err(StatementParseError::UnknownKind(kind)),
     }
 }
 
+fn parse_composite(input: &str, vars: Vars) -> Option<Result<Statement, StatementParseError>> {
+    let (kind, rest) = input.trim().split_once(':')?;
+    let kind = kind.trim().to_ascii_lowercase();
+    if kind != "all" && kind != "any" {
+        return None;
+    }
+    let build = || {
+        let inner = rest
+            .trim()
+            .strip_prefix('(')
+            .and_then(|inner| inner.strip_suffix(')'))
+            .ok_or(StatementParseError::ExpectedFormat(
+                "all|any:(<statement>, <statement>, ...)",
+            ))?;
+        let members = split_members(inner)?;
+        if members.is_empty() {
+            return Err(StatementParseError::MissingValue("composite members"));
+        }
+        let statements = members
+            .into_iter()
+            .map(|member| parse(member, vars))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(if kind == "all" {
+            Statement::All { statements }
+        } else {
+            Statement::Any { statements }
+        })
+    };
+    Some(build())
+}
+
+/// `transform:regex:"<pattern>":"<replacement>":(<statement>)`. An empty
+/// quoted replacement (`""`) deletes every match.
+fn parse_transform(input: &str, vars: Vars) -> Option<Result<Statement, StatementParseError>> {
+    const FORMAT: &str = "transform:regex:\"<pattern>\":\"<replacement>\":(<statement>)";
+    let (kind, rest) = input.trim().split_once(':')?;
+    if !kind.trim().eq_ignore_ascii_case("transform") {
+        return None;
+    }
+    let build = || {
+        let rest = rest.trim();
+        let open = trailing_group(rest)?.ok_or(StatementParseError::ExpectedFormat(FORMAT))?;
+        let head = rest[..open]
+            .trim_end()
+            .strip_suffix(':')
+            .ok_or(StatementParseError::ExpectedFormat(FORMAT))?;
+        let inner = &rest[open + 1..rest.len() - 1];
+        let fields = split_fields(head)?;
+        if fields
+            .iter()
+            .any(|(field, quoted)| field.is_empty() && !quoted)
+        {
+            return Err(StatementParseError::EmptyToken);
+        }
+        let [(op, _), (pattern, _), (replacement, _)] =
+            <[_; 3]>::try_from(fields).map_err(|_| StatementParseError::ExpectedFormat(FORMAT))?;
+        if !op.eq_ignore_ascii_case("regex") {
+            return Err(StatementParseError::ExpectedFormat(FORMAT));
+        }
+        let (pattern, replacement) = match vars {
+            Some(vars) => (
+                interpolate(&pattern, vars)?,
+                interpolate(&replacement, vars)?,
+            ),
+            None => (pattern, replacement),
+        };
+        if pattern.is_empty() {
+            return Err(StatementParseError::MissingValue("transform pattern"));
+        }
+        transform::compile(&pattern).map_err(StatementParseError::InvalidRegex)?;
+        Ok(Statement::RegexReplace {
+            pattern,
+            replacement,
+            statement: Box::new(parse(inner, vars)?),
+        })
+    };
+    Some(build())
+}
+
+/// Byte offset of the `(` opening a parenthesized group that closes at the
+/// very end of `input`; `None` when `input` does not end in such a group.
+fn trailing_group(input: &str) -> Result<Option<usize>, StatementParseError> {
+    let mut depth = 0usize;
+    let mut in_quotes = false;
+    let mut escaping = false;
+    let mut open = None;
+    for (idx, ch) in input.char_indices() {
+        if escaping {
+            escaping = false;
+            continue;
+        }
+        match ch {
+            '\\' if in_quotes => escaping = true,
+            '"' => in_quotes = !in_quotes,
+            '(' if !in_quotes => {
+                if depth == 0 {
+                    open = Some(idx);
+                }
+                depth += 1;
+            }
+            ')' if !in_quotes => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or(StatementParseError::UnbalancedParentheses)?;
+                if depth == 0 && idx + 1 < input.len() {
+                    open = None;
+                }
+            }
+            _ => {}
+        }
+    }
+    if in_quotes {
+        return Err(StatementParseError::UnbalancedQuotes);
+    }
+    if depth != 0 {
+        return Err(StatementParseError::UnbalancedParentheses);
+    }
+    Ok(open.filter(|_| input.ends_with(')')))
+}
+
+/// `json:schema:<schema>`. Everything after the prefix is the schema's JSON
+/// text, taken verbatim rather than tokenized.
+fn parse_json_schema(input: &str) -> Option<Result<Statement, StatementParseError>> {
+    const PREFIX: &str = "json:schema";
+    let input = input.trim();
+    if !input
+        .get(..PREFIX.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(PREFIX))
+    {
+        return None;
+    }
+    let build = || {
+        let text = match &input[PREFIX.len()..] {
+            "" => return Err(StatementParseError::MissingValue("JSON schema")),
+            rest => rest
+                .strip_prefix(':')
+                .ok_or(StatementParseError::ExpectedFormat("json:schema:<schema>"))?
+                .trim(),
+        };
+        if text.is_empty() {
+            return Err(StatementParseError::MissingValue("JSON schema"));
+        }
+        let schema = serde_json::from_str(text)
+            .map_err(|err| StatementParseError::InvalidJsonSchema(err.to_string()))?;
+        json_schema::compile(&schema).map_err(StatementParseError::InvalidJsonSchema)?;
+        Ok(Statement::JsonSchemaValid { schema })
+    };
+    Some(build())
+}
+
+/// Split a composite body on top-level commas, honoring quotes and nesting.
+fn split_members(inner: &str) -> Result<Vec<&str>, StatementParseError> {
+    if inner.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut members = Vec::new();
+    let mut depth = 0usize;
+    let mut in_quotes = false;
+    let mut escaping = false;
+    let mut start = 0;
+    for (idx, ch) in inner.char_indices() {
+        if escaping {
+            escaping = false;
+            continue;
+        }
+        match ch {
+            '\\' if in_quotes => escaping = true,
+            '"' => in_quotes = !in_quotes,
+            '(' if !in_quotes => depth += 1,
+            ')' if !in_quotes => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or(StatementParseError::UnbalancedParentheses)?;
+            }
+            ',' if !in_quotes && depth == 0 => {
+                members.push(inner[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    if in_quotes {
+        return Err(StatementParseError::UnbalancedQuotes);
+    }
+    if depth != 0 {
+        return Err(StatementParseError::UnbalancedParentheses);
+    }
+    members.push(inner[start..].trim());
+    if members.iter().any(|member| member.is_empty()) {
+        return Err(StatementParseError::EmptyToken);
+    }
+    Ok(members)
+}
+
 fn parse_header(parts: Vec<String>) -> Result<Statement, StatementParseError> {
     if parts.is_empty() {
         return Err(StatementParseError::MissingValue("header action"));
@@ -45,6 +295,18 @@ fn parse_header(parts: Vec<String>) -> Result<Statement, StatementParseError> {
                 target: require_value(&parts[1], "header name")?,
             })
         }
+        "none" => {
+            if parts.len() != 2 {
+                return Err(StatementParseError::ExpectedFormat(
+                    "header:none:<header-name>,<header-name>,...",
+                ));
+            }
+            let targets = split_list(&parts[1]);
+            if targets.is_empty() {
+                return Err(StatementParseError::MissingValue("header names"));
+            }
+            Ok(Statement::HeadersAllAbsent { targets })
+        }
         "eq" => {
             if parts.len() != 3 {
                 return Err(StatementParseError::ExpectedFormat(
@@ -57,10 +319,130 @@ fn parse_header(parts: Vec<String>) -> Result<Statement, StatementParseError> {
                 case_sensitive: None,
             })
         }
+        "casing" => {
+            if parts.len() != 2 {
+                return Err(StatementParseError::ExpectedFormat(
+                    "header:casing:<Header-Name as sent>",
+                ));
+            }
+            let expected_casing = require_value(&parts[1], "header name")?.trim().to_string();
+            Ok(Statement::HeaderCasingIs {
+                target: expected_casing.to_ascii_lowercase(),
+                expected_casing,
+            })
+        }
+        "glob" => {
+            const FORMAT: &str = "header:glob:<header-name>:[case_sensitive=<bool>:]<pattern>";
+            let (case_sensitive, pattern) = match &parts[1..] {
+                [_, pattern] => (false, pattern),
+                [_, flag, pattern] => match flag.strip_prefix("case_sensitive=") {
+                    Some(value) => (parse_bool(value)?, pattern),
+                    None => return Err(StatementParseError::UnexpectedSegments(FORMAT)),
+                },
+                _ => return Err(StatementParseError::ExpectedFormat(FORMAT)),
+            };
+            let pattern = require_value(pattern, "glob pattern")?;
+            Glob::new(&pattern, case_sensitive)
+                .map_err(|_| StatementParseError::InvalidGlob(pattern.clone()))?;
+            Ok(Statement::HeaderGlob {
+                target: require_value(&parts[1], "header name")?,
+                pattern,
+                case_sensitive,
+            })
+        }
+        "set-eq" | "set-contains" => {
+            if parts.len() != 3 {
+                return Err(StatementParseError::ExpectedFormat(
+                    "header:<set-eq|set-contains>:<header-name>:<a, b, ...>",
+                ));
+            }
+            let target = require_value(&parts[1], "header name")?;
+            let expected = split_list(&parts[2]);
+            if expected.is_empty() {
+                return Err(StatementParseError::MissingValue("expected list elements"));
+            }
+            Ok(if action == "set-eq" {
+                Statement::HeaderSetEquals { target, expected }
+            } else {
+                Statement::HeaderSetContains { target, expected }
+            })
+        }
+        "num" => {
+            if parts.len() != 4 {
+                return Err(StatementParseError::ExpectedFormat(
+                    "header:num:<header-name>:<eq|ne|lt|lte|gt|gte>:<integer>",
+                ));
+            }
+            Ok(Statement::HeaderNumeric {
+                target: require_value(&parts[1], "header name")?,
+                op: NumOp::from_str(&parts[2])
+                    .map_err(|_| StatementParseError::UnknownNumericOperator(parts[2].clone()))?,
+                value: parts[3]
+                    .trim()
+                    .parse::<i64>()
+                    .map_err(|_| StatementParseError::InvalidCount(parts[3].clone()))?,
+            })
+        }
+        "none-match" => {
+            if parts.len() != 2 {
+                return Err(StatementParseError::ExpectedFormat(
+                    "header:none-match:<name-pattern>",
+                ));
+            }
+            Ok(Statement::HeaderNameAbsentMatching {
+                pattern: require_value(&parts[1], "header name pattern")?,
+            })
+        }
+        "all-match" => {
+            if parts.len() != 3 {
+                return Err(StatementParseError::ExpectedFormat(
+                    "header:all-match:<name-pattern>:<value-pattern>",
+                ));
+            }
+            Ok(Statement::AllHeadersValueMatch {
+                name_pattern: require_value(&parts[1], "header name pattern")?,
+                value_pattern: require_value(&parts[2], "header value pattern")?,
+            })
+        }
         other => Err(StatementParseError::UnknownHeaderAction(other.to_string())),
     }
 }
 
+/// Comma-separated list elements, trimmed, empty ones dropped.
+pub fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|element| !element.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// `csp:<exact|contains|absent>:<directive>[:<sources>]`, sources separated
+/// by whitespace. Quote sources containing `:` such as scheme-qualified hosts.
+fn parse_csp(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    if parts.len() < 2 || parts.len() > 3 {
+        return Err(StatementParseError::ExpectedFormat(
+            "csp:<exact|contains|absent>:<directive>[:<sources>]",
+        ));
+    }
+    let mode = match parts[0].to_ascii_lowercase().as_str() {
+        "exact" => DirectiveMode::Exact,
+        "contains" => DirectiveMode::Contains,
+        "absent" => DirectiveMode::Absent,
+        other => return Err(StatementParseError::UnknownCspMode(other.to_string())),
+    };
+    let expected_sources = parts
+        .get(2)
+        .map(|sources| sources.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    Ok(Statement::CspDirective {
+        directive: require_value(&parts[1], "CSP directive")?.to_ascii_lowercase(),
+        expected_sources,
+        mode,
+    })
+}
+
 fn parse_hash(parts: Vec<String>) -> Result<Statement, StatementParseError> {
     if parts.len() != 3 {
         return Err(StatementParseError::ExpectedFormat(
@@ -74,18 +456,62 @@ fn parse_hash(parts: Vec<String>) -> Result<Statement, StatementParseError> {
     }
     let algorithm = HashAlgorithm::from_str(&parts[1])
         .map_err(|_| StatementParseError::UnsupportedHashAlgorithm(parts[1].clone()))?;
-    Ok(Statement::HashEquals {
-        algorithm,
-        digest: require_value(&parts[2], "digest")?,
+    let digest = require_value(&parts[2], "digest")?.trim().to_string();
+    algorithm.decode_digest(&digest)?;
+    Ok(Statement::HashEquals { algorithm, digest })
+}
+
+fn parse_regex(mut parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    if parts.len() >= 3 && parts[0].eq_ignore_ascii_case("count") {
+        let min_matches = parts[1]
+            .parse::<usize>()
+            .map_err(|_| StatementParseError::InvalidCount(parts[1].clone()))?;
+        let body = parse_regex_body(parts.split_off(2))?;
+        if body.multi_line || body.dot_all {
+            return Err(StatementParseError::ExpectedFormat(
+                "regex:count:<n>:[scope=<scope>:][case_sensitive=<bool>:]<pattern>",
+            ));
+        }
+        let RegexBody {
+            pattern,
+            scope,
+            case_sensitive,
+            ..
+        } = body;
+        return Ok(Statement::RegexAll {
+            pattern,
+            scope,
+            case_sensitive,
+            min_matches,
+        });
+    }
+    let body = parse_regex_body(parts)?;
+    Ok(Statement::Regex {
+        pattern: body.pattern,
+        scope: body.scope,
+        case_sensitive: body.case_sensitive,
+        multi_line: body.multi_line,
+        dot_all: body.dot_all,
     })
 }
 
-fn parse_regex(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+/// A regex pattern with its leading parameters.
+struct RegexBody {
+    pattern: String,
+    scope: RegexScope,
+    case_sensitive: bool,
+    multi_line: bool,
+    dot_all: bool,
+}
+
+fn parse_regex_body(parts: Vec<String>) -> Result<RegexBody, StatementParseError> {
     if parts.is_empty() {
         return Err(StatementParseError::MissingValue("regex pattern"));
     }
     let mut scope = RegexScope::Any;
     let mut case_sensitive = false;
+    let mut multi_line = false;
+    let mut dot_all = false;
 
     let mut idx = 0;
     while idx < parts.len() - 1 {
@@ -100,6 +526,16 @@ fn parse_regex(parts: Vec<String>) -> Result<Statement, StatementParseError> {
             idx += 1;
             continue;
         }
+        if let Some(value) = token.strip_prefix("multiline=") {
+            multi_line = parse_bool(value)?;
+            idx += 1;
+            continue;
+        }
+        if let Some(value) = token.strip_prefix("dotall=") {
+            dot_all = parse_bool(value)?;
+            idx += 1;
+            continue;
+        }
         if matches_scope_name(token) {
             scope = parse_scope(token)?;
             idx += 1;
@@ -115,17 +551,283 @@ fn parse_regex(parts: Vec<String>) -> Result<Statement, StatementParseError> {
 
     if idx != parts.len() - 1 {
         return Err(StatementParseError::UnexpectedSegments(
-            "regex:<pattern> (optional leading scope/case parameters)",
+            "regex[:count:<n>]:<pattern> (optional leading scope/case/multiline/dotall parameters)",
         ));
     }
 
-    Ok(Statement::Regex {
+    Ok(RegexBody {
         pattern,
         scope,
         case_sensitive,
+        multi_line,
+        dot_all,
+    })
+}
+
+/// `reason:eq:[case_sensitive=<bool>:]<phrase>`
+fn parse_reason(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    const FORMAT: &str = "reason:eq:[case_sensitive=<bool>:]<phrase>";
+    if !parts
+        .first()
+        .is_some_and(|op| op.eq_ignore_ascii_case("eq"))
+    {
+        return Err(StatementParseError::ExpectedFormat(FORMAT));
+    }
+    let (case_sensitive, expected) = match &parts[1..] {
+        [expected] => (false, expected),
+        [flag, expected] => match flag.strip_prefix("case_sensitive=") {
+            Some(value) => (parse_bool(value)?, expected),
+            None => return Err(StatementParseError::UnexpectedSegments(FORMAT)),
+        },
+        [] => return Err(StatementParseError::MissingValue("reason phrase")),
+        _ => return Err(StatementParseError::UnexpectedSegments(FORMAT)),
+    };
+    Ok(Statement::ReasonEquals {
+        expected: require_value(expected, "reason phrase")?,
+        case_sensitive,
     })
 }
 
+/// `etag:eq:[allow_weak=<bool>:]<etag>`; the quotes around the tag may be
+/// left out (`etag:eq:W/xyzzy`). A weak expected tag only ever matches
+/// weakly, so it implies `allow_weak=true` and rejects `allow_weak=false`.
+fn parse_etag(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    const FORMAT: &str = "etag:eq:[allow_weak=<bool>:]<etag>";
+    if !parts
+        .first()
+        .is_some_and(|op| op.eq_ignore_ascii_case("eq"))
+    {
+        return Err(StatementParseError::ExpectedFormat(FORMAT));
+    }
+    let (allow_weak, expected) = match &parts[1..] {
+        [expected] => (None, expected),
+        [flag, expected] => match flag.strip_prefix("allow_weak=") {
+            Some(value) => (Some(parse_bool(value)?), expected),
+            None => return Err(StatementParseError::UnexpectedSegments(FORMAT)),
+        },
+        [] => return Err(StatementParseError::MissingValue("entity tag")),
+        _ => return Err(StatementParseError::UnexpectedSegments(FORMAT)),
+    };
+    let expected = require_value(expected, "entity tag")?;
+    let tag = EntityTag::parse_lenient(&expected)
+        .ok_or_else(|| StatementParseError::InvalidETag(expected.clone()))?;
+    let allow_weak = match allow_weak {
+        Some(false) if tag.weak => {
+            return Err(StatementParseError::WeakETagStrongCompare(tag.to_string()))
+        }
+        Some(allow_weak) => allow_weak,
+        None => tag.weak,
+    };
+    Ok(Statement::ETagEquals {
+        expected: tag.to_string(),
+        allow_weak,
+    })
+}
+
+/// `cors:allows-origin:<origin>`; quote the origin, it contains `:`.
+fn parse_cors(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    match &parts[..] {
+        [op, origin] if op.eq_ignore_ascii_case("allows-origin") => {
+            Ok(Statement::CorsAllowsOrigin {
+                origin: require_value(origin, "origin")?,
+            })
+        }
+        _ => Err(StatementParseError::ExpectedFormat(
+            "cors:allows-origin:\"<origin>\"",
+        )),
+    }
+}
+
+fn parse_server(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    match &parts[..] {
+        [op] if op.eq_ignore_ascii_case("software-absent") => Ok(Statement::ServerSoftwareAbsent),
+        _ => Err(StatementParseError::ExpectedFormat(
+            "server:software-absent",
+        )),
+    }
+}
+
+/// `cache:hit[:<true|false>[:<header,header,...>]]`.
+fn parse_cache(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    if parts.is_empty() || parts.len() > 3 || !parts[0].eq_ignore_ascii_case("hit") {
+        return Err(StatementParseError::ExpectedFormat(
+            "cache:hit[:<true|false>[:<header,header,...>]]",
+        ));
+    }
+    let expected = match parts.get(1) {
+        Some(value) => parse_bool(value)?,
+        None => true,
+    };
+    let headers = match parts.get(2) {
+        Some(list) => {
+            let headers = split_list(list);
+            if headers.is_empty() {
+                return Err(StatementParseError::MissingValue("cache headers"));
+            }
+            headers
+        }
+        None => Vec::new(),
+    };
+    Ok(Statement::CacheHit { expected, headers })
+}
+
+/// `link:rel:<rel>` or `link:uri:<rel>:<uri regex>`; quote patterns
+/// containing `:`.
+fn parse_link(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    const FORMAT: &str = "link:rel:<rel> | link:uri:<rel>:<uri regex>";
+    let op = parts.first().map(|op| op.to_ascii_lowercase());
+    match (op.as_deref(), &parts[..]) {
+        (Some("rel"), [_, rel]) => Ok(Statement::LinkRelPresent {
+            rel: require_value(rel, "link relation")?.to_ascii_lowercase(),
+        }),
+        (Some("uri"), [_, rel, pattern]) => Ok(Statement::LinkRelUri {
+            rel: require_value(rel, "link relation")?.to_ascii_lowercase(),
+            uri_pattern: require_value(pattern, "URI pattern")?,
+        }),
+        _ => Err(StatementParseError::ExpectedFormat(FORMAT)),
+    }
+}
+
+/// `body:starts:<base64>`, `body:ends:<base64>` or `body:content-digest`
+fn parse_body(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    if parts.len() == 1 && parts[0].eq_ignore_ascii_case("content-digest") {
+        return Ok(Statement::ContentDigestValid);
+    }
+    if parts.len() != 2 {
+        return Err(StatementParseError::ExpectedFormat(
+            "body:<starts|ends>:<base64 bytes> | body:content-digest",
+        ));
+    }
+    let encoded = require_value(&parts[1], "base64 bytes")?;
+    match STANDARD.decode(&encoded) {
+        Ok(bytes) if !bytes.is_empty() => {}
+        _ => return Err(StatementParseError::InvalidBase64(encoded)),
+    }
+    match parts[0].to_ascii_lowercase().as_str() {
+        "starts" => Ok(Statement::BodyStartsWith {
+            prefix_base64: encoded,
+        }),
+        "ends" => Ok(Statement::BodyEndsWith {
+            suffix_base64: encoded,
+        }),
+        _ => Err(StatementParseError::ExpectedFormat(
+            "body:<starts|ends>:<base64 bytes> | body:content-digest",
+        )),
+    }
+}
+
+fn parse_early_hints(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    if parts.is_empty() || parts.len() > 2 || !parts[0].eq_ignore_ascii_case("present") {
+        return Err(StatementParseError::ExpectedFormat(
+            "early-hints:present[:<header-name>]",
+        ));
+    }
+    let target = match parts.get(1) {
+        Some(value) => Some(require_value(value, "header name")?),
+        None => None,
+    };
+    Ok(Statement::EarlyHintsPresent { target })
+}
+
+fn parse_dns(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    if parts.len() != 2 || !parts[0].eq_ignore_ascii_case("resolved") {
+        return Err(StatementParseError::ExpectedFormat("dns:resolved:<ip>"));
+    }
+    let ip = require_value(&parts[1], "ip address")?;
+    IpAddr::from_str(&ip).map_err(|_| StatementParseError::InvalidIpAddress(ip.clone()))?;
+    Ok(Statement::ResolvedIpIs { ip })
+}
+
+fn parse_tls(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    if parts.len() == 2 && parts[0].eq_ignore_ascii_case("chain-length-at-most") {
+        let n = parts[1]
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| StatementParseError::InvalidCount(parts[1].clone()))?;
+        return Ok(Statement::CertChainLengthAtMost { n });
+    }
+    if parts.len() == 1 && parts[0].eq_ignore_ascii_case("sct-present") {
+        return Ok(Statement::SctPresent);
+    }
+    if parts.len() == 2 && parts[0].eq_ignore_ascii_case("cert-covers") {
+        let host = require_value(&parts[1], "host")?.trim().to_string();
+        if !is_host(&host) {
+            return Err(StatementParseError::InvalidHost(host));
+        }
+        return Ok(Statement::CertCoversHost { host });
+    }
+    if parts.len() != 2 || !parts[0].eq_ignore_ascii_case("spki-pin") {
+        return Err(StatementParseError::ExpectedFormat(
+            "tls:spki-pin:<base64 sha256> | tls:chain-length-at-most:<n> | tls:sct-present | tls:cert-covers:<host>",
+        ));
+    }
+    let pin = require_value(&parts[1], "SPKI pin")?.trim().to_string();
+    match STANDARD.decode(&pin) {
+        Ok(digest) if digest.len() == 32 => Ok(Statement::SpkiPinIs { pin }),
+        _ => Err(StatementParseError::InvalidBase64(pin)),
+    }
+}
+
+/// A concrete DNS name or IP address; wildcards belong in certificates, not
+/// in the host being checked.
+fn is_host(host: &str) -> bool {
+    IpAddr::from_str(host).is_ok()
+        || host.trim_end_matches('.').split('.').all(|label| {
+            !label.is_empty()
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+fn parse_timing(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    let op = parts.first().map(|op| op.to_ascii_lowercase());
+    if parts.len() != 2 || !matches!(op.as_deref(), Some("ttfb-under" | "clock-skew-under")) {
+        return Err(StatementParseError::ExpectedFormat(
+            "timing:<ttfb-under|clock-skew-under>:<milliseconds>",
+        ));
+    }
+    let millis = parts[1]
+        .trim_end_matches("ms")
+        .parse::<u64>()
+        .map_err(|_| StatementParseError::InvalidCount(parts[1].clone()))?;
+    Ok(if op.as_deref() == Some("ttfb-under") {
+        Statement::ResponseTimeUnder { max_ms: millis }
+    } else {
+        Statement::ClockSkewUnder { millis }
+    })
+}
+
+/// `field:present:<pointer>`; quote pointers containing `:`.
+/// `http:version:<version>`, e.g. `HTTP/2`, `h2` or `1.1`.
+fn parse_http(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    if parts.len() != 2 || !parts[0].eq_ignore_ascii_case("version") {
+        return Err(StatementParseError::ExpectedFormat(
+            "http:version:<version>",
+        ));
+    }
+    let version = require_value(&parts[1], "HTTP version")?;
+    match http_version::canonical(&version) {
+        Some(version) => Ok(Statement::HttpVersionIs {
+            version: version.to_string(),
+        }),
+        None => Err(StatementParseError::UnknownHttpVersion(version)),
+    }
+}
+
+fn parse_field(parts: Vec<String>) -> Result<Statement, StatementParseError> {
+    if parts.len() != 2 || !parts[0].eq_ignore_ascii_case("present") {
+        return Err(StatementParseError::ExpectedFormat(
+            "field:present:</handshake/...|/app_data/...>",
+        ));
+    }
+    let path = require_value(&parts[1], "field pointer")?
+        .trim()
+        .to_string();
+    field::check_pointer(&path).map_err(StatementParseError::InvalidFieldPointer)?;
+    Ok(Statement::FieldPresent { path })
+}
+
 fn require_value(value: &str, label: &'static str) -> Result<String, StatementParseError> {
     if value.trim().is_empty() {
         Err(StatementParseError::MissingValue(label))
@@ -139,6 +841,9 @@ fn parse_scope(value: &str) -> Result<RegexScope, StatementParseError> {
         "headers" => Ok(RegexScope::Headers),
         "body" => Ok(RegexScope::Body),
         "any" => Ok(RegexScope::Any),
+        "status" => Ok(RegexScope::Status),
+        "statusline" => Ok(RegexScope::StatusLine),
+        "cookies" => Ok(RegexScope::Cookies),
         other => Err(StatementParseError::InvalidScope(other.to_string())),
     }
 }
@@ -154,17 +859,49 @@ fn parse_bool(value: &str) -> Result<bool, StatementParseError> {
 fn matches_scope_name(token: &str) -> bool {
     matches!(
         token.to_ascii_lowercase().as_str(),
-        "headers" | "body" | "any"
+        "headers" | "body" | "any" | "status" | "statusline" | "cookies"
     )
 }
 
+/// Replace each `${NAME}` in `value` with `vars[NAME]`.
+fn interpolate(value: &str, vars: &HashMap<String, String>) -> Result<String, StatementParseError> {
+    let mut interpolated = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        interpolated.push_str(&rest[..start]);
+        let reference = &rest[start + 2..];
+        let end = reference
+            .find('}')
+            .ok_or_else(|| StatementParseError::UnterminatedVariable(value.to_string()))?;
+        let name = &reference[..end];
+        let substitute = vars
+            .get(name)
+            .ok_or_else(|| StatementParseError::UndefinedVariable(name.to_string()))?;
+        interpolated.push_str(substitute);
+        rest = &reference[end + 1..];
+    }
+    interpolated.push_str(rest);
+    Ok(interpolated)
+}
+
 fn tokenize(input: &str) -> Result<Vec<String>, StatementParseError> {
+    let fields = split_fields(input)?;
+    if fields.iter().any(|(field, _)| field.is_empty()) {
+        return Err(StatementParseError::EmptyToken);
+    }
+    Ok(fields.into_iter().map(|(field, _)| field).collect())
+}
+
+/// Split on unquoted `:`, unquoting and trimming each field. Each field is
+/// paired with whether it contained quotes, so callers can accept `""`.
+fn split_fields(input: &str) -> Result<Vec<(String, bool)>, StatementParseError> {
     let trimmed = input.trim();
     if trimmed.is_empty() {
         return Ok(Vec::new());
     }
     let mut fields = Vec::new();
     let mut buf = String::new();
+    let mut quoted = false;
     let mut in_quotes = false;
     let mut escaping = false;
 
@@ -180,10 +917,12 @@ fn tokenize(input: &str) -> Result<Vec<String>, StatementParseError> {
             }
             '"' => {
                 in_quotes = !in_quotes;
+                quoted = true;
             }
             ':' if !in_quotes => {
-                fields.push(buf.trim().to_string());
+                fields.push((buf.trim().to_string(), quoted));
                 buf.clear();
+                quoted = false;
             }
             _ => buf.push(ch),
         }
@@ -195,12 +934,8 @@ fn tokenize(input: &str) -> Result<Vec<String>, StatementParseError> {
     if escaping {
         return Err(StatementParseError::DanglingEscape);
     }
-    fields.push(buf.trim().to_string());
-    if fields.iter().any(|field| field.is_empty()) {
-        Err(StatementParseError::EmptyToken)
-    } else {
-        Ok(fields)
-    }
+    fields.push((buf.trim().to_string(), quoted));
+    Ok(fields)
 }
 
 #[derive(Debug, Error, PartialEq, Eq)]
@@ -213,6 +948,8 @@ pub enum StatementParseError {
     UnbalancedQuotes,
     #[error("dangling escape sequence in statement")]
     DanglingEscape,
+    #[error("unbalanced parentheses in composite statement")]
+    UnbalancedParentheses,
     #[error("unknown statement kind '{0}'")]
     UnknownKind(String),
     #[error("unknown header action '{0}'")]
@@ -223,8 +960,42 @@ pub enum StatementParseError {
     UnsupportedHashOperation(String),
     #[error("unsupported hash algorithm '{0}'")]
     UnsupportedHashAlgorithm(String),
+    #[error("digest '{0}' is neither hex nor base64 of {1} bytes")]
+    InvalidDigest(String, usize),
+    #[error("digest '{0}' is ambiguous: it decodes as both hex and base64")]
+    AmbiguousDigest(String),
+    #[error("unknown CSP mode '{0}'")]
+    UnknownCspMode(String),
+    #[error("unknown numeric operator '{0}'")]
+    UnknownNumericOperator(String),
     #[error("invalid regex scope '{0}'")]
     InvalidScope(String),
+    #[error("invalid number '{0}'")]
+    InvalidCount(String),
+    #[error("invalid IP address '{0}'")]
+    InvalidIpAddress(String),
+    #[error("invalid host '{0}'")]
+    InvalidHost(String),
+    #[error("unknown HTTP version '{0}'")]
+    UnknownHttpVersion(String),
+    #[error("invalid or empty base64 '{0}'")]
+    InvalidBase64(String),
+    #[error("invalid glob pattern '{0}'")]
+    InvalidGlob(String),
+    #[error("invalid entity tag '{0}'")]
+    InvalidETag(String),
+    #[error("weak entity tag '{0}' never matches under allow_weak=false")]
+    WeakETagStrongCompare(String),
+    #[error("invalid JSON schema: {0}")]
+    InvalidJsonSchema(String),
+    #[error("invalid regex: {0}")]
+    InvalidRegex(String),
+    #[error("invalid field pointer: {0}")]
+    InvalidFieldPointer(String),
+    #[error("undefined variable '{0}'")]
+    UndefinedVariable(String),
+    #[error("unterminated variable reference in '{0}'")]
+    UnterminatedVariable(String),
     #[error("invalid boolean value '{0}'")]
     InvalidBoolean(String),
     #[error("expected format: {0}")]
@@ -233,6 +1004,22 @@ pub enum StatementParseError {
     UnexpectedSegments(&'static str),
 }
 
+impl FromStr for NumOp {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "eq" => Ok(NumOp::Eq),
+            "ne" => Ok(NumOp::Ne),
+            "lt" => Ok(NumOp::Lt),
+            "lte" => Ok(NumOp::Lte),
+            "gt" => Ok(NumOp::Gt),
+            "gte" => Ok(NumOp::Gte),
+            _ => Err(()),
+        }
+    }
+}
+
 impl FromStr for HashAlgorithm {
     type Err = ();
 
@@ -261,43 +1048,743 @@ mod tests {
     }
 
     #[test]
-    fn parses_header_eq_with_quotes() {
-        let stmt = parse_statement(r#"header:eq:Server:"Apache/2.4.49 (Unix)""#).expect("parsed");
+    fn parses_header_none_list() {
         assert_eq!(
-            stmt,
-            Statement::HeaderEquals {
-                target: "Server".into(),
-                expected: "Apache/2.4.49 (Unix)".into(),
-                case_sensitive: None
+            parse_statement("header:none:X-Powered-By, X-AspNet-Version").unwrap(),
+            Statement::HeadersAllAbsent {
+                targets: vec!["X-Powered-By".into(), "X-AspNet-Version".into()]
             }
         );
+        assert_eq!(
+            parse_statement("header:none: , "),
+            Err(StatementParseError::MissingValue("header names"))
+        );
     }
 
     #[test]
-    fn parses_hash_eq() {
-        let stmt = parse_statement("hash:eq:sha256:deadbeef").expect("parsed hash statement");
+    fn parses_header_name_pattern_statements() {
         assert_eq!(
-            stmt,
-            Statement::HashEquals {
-                algorithm: HashAlgorithm::Sha256,
-                digest: "deadbeef".into()
+            parse_statement("header:none-match:^x-powered-by$").unwrap(),
+            Statement::HeaderNameAbsentMatching {
+                pattern: "^x-powered-by$".into()
+            }
+        );
+        assert_eq!(
+            parse_statement(r#"header:all-match:"^x-":"^[a-z0-9=;, -]*$""#).unwrap(),
+            Statement::AllHeadersValueMatch {
+                name_pattern: "^x-".into(),
+                value_pattern: "^[a-z0-9=;, -]*$".into(),
             }
         );
+        assert!(matches!(
+            parse_statement("header:all-match:^x-"),
+            Err(StatementParseError::ExpectedFormat(_))
+        ));
     }
 
     #[test]
-    fn parses_regex_with_scope_and_case() {
-        let stmt =
-            parse_statement("regex:scope=headers:case_sensitive=true:\"Set-Cookie: session=.*\"")
-                .expect("parsed regex");
+    fn parses_csp_directives() {
         assert_eq!(
-            stmt,
-            Statement::Regex {
-                pattern: "Set-Cookie: session=.*".into(),
-                scope: RegexScope::Headers,
-                case_sensitive: true
+            parse_statement("csp:exact:Default-Src:'self'").unwrap(),
+            Statement::CspDirective {
+                directive: "default-src".into(),
+                expected_sources: vec!["'self'".into()],
+                mode: DirectiveMode::Exact,
+            }
+        );
+        assert_eq!(
+            parse_statement(r#"csp:contains:script-src:"'self' https://cdn.example""#).unwrap(),
+            Statement::CspDirective {
+                directive: "script-src".into(),
+                expected_sources: vec!["'self'".into(), "https://cdn.example".into()],
+                mode: DirectiveMode::Contains,
+            }
+        );
+        assert_eq!(
+            parse_statement("csp:maybe:script-src"),
+            Err(StatementParseError::UnknownCspMode("maybe".into()))
+        );
+    }
+
+    #[test]
+    fn parses_reason_eq() {
+        assert_eq!(
+            parse_statement(r#"reason:eq:"Not Found""#).unwrap(),
+            Statement::ReasonEquals {
+                expected: "Not Found".into(),
+                case_sensitive: false,
+            }
+        );
+        assert_eq!(
+            parse_statement("reason:eq:case_sensitive=true:Teapot").unwrap(),
+            Statement::ReasonEquals {
+                expected: "Teapot".into(),
+                case_sensitive: true,
             }
         );
+        assert!(matches!(
+            parse_statement("reason:ne:OK"),
+            Err(StatementParseError::ExpectedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn parses_header_glob() {
+        assert_eq!(
+            parse_statement("header:glob:Server:nginx/*").unwrap(),
+            Statement::HeaderGlob {
+                target: "Server".into(),
+                pattern: "nginx/*".into(),
+                case_sensitive: false,
+            }
+        );
+        assert_eq!(
+            parse_statement("header:glob:Server:case_sensitive=yes:Apache/2.?").unwrap(),
+            Statement::HeaderGlob {
+                target: "Server".into(),
+                pattern: "Apache/2.?".into(),
+                case_sensitive: true,
+            }
+        );
+        assert!(matches!(
+            parse_statement("header:glob:Server"),
+            Err(StatementParseError::ExpectedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn parses_etag_eq() {
+        assert_eq!(
+            parse_statement("etag:eq:W/xyzzy").unwrap(),
+            Statement::ETagEquals {
+                expected: r#"W/"xyzzy""#.into(),
+                allow_weak: true,
+            }
+        );
+        assert_eq!(
+            parse_statement("etag:eq:xyzzy").unwrap(),
+            Statement::ETagEquals {
+                expected: r#""xyzzy""#.into(),
+                allow_weak: false,
+            }
+        );
+        assert_eq!(
+            parse_statement("etag:eq:allow_weak=false:W/xyzzy"),
+            Err(StatementParseError::WeakETagStrongCompare(
+                r#"W/"xyzzy""#.into()
+            ))
+        );
+        assert_eq!(
+            parse_statement(r#"etag:eq:allow_weak=true:"\"33a64df5\"""#).unwrap(),
+            Statement::ETagEquals {
+                expected: r#""33a64df5""#.into(),
+                allow_weak: true,
+            }
+        );
+        assert_eq!(
+            parse_statement(r#"etag:eq:"two words""#),
+            Err(StatementParseError::InvalidETag("two words".into()))
+        );
+    }
+
+    #[test]
+    fn parses_inline_json_schema_verbatim() {
+        assert_eq!(
+            parse_statement(r#"json:schema:{"type": "object", "required": ["id"]}"#).unwrap(),
+            Statement::JsonSchemaValid {
+                schema: serde_json::json!({"type": "object", "required": ["id"]}),
+            }
+        );
+        assert_eq!(
+            parse_statement("json:schema"),
+            Err(StatementParseError::MissingValue("JSON schema"))
+        );
+        assert!(matches!(
+            parse_statement("json:schema:{not json"),
+            Err(StatementParseError::InvalidJsonSchema(_))
+        ));
+        assert!(matches!(
+            parse_statement(r#"json:schema:{"type": 12}"#),
+            Err(StatementParseError::InvalidJsonSchema(_))
+        ));
+    }
+
+    #[test]
+    fn parses_header_set_statements() {
+        assert_eq!(
+            parse_statement("header:set-eq:Allow:GET, POST ,HEAD").unwrap(),
+            Statement::HeaderSetEquals {
+                target: "Allow".into(),
+                expected: vec!["GET".into(), "POST".into(), "HEAD".into()],
+            }
+        );
+        assert_eq!(
+            parse_statement("header:set-contains:Vary:Accept-Encoding").unwrap(),
+            Statement::HeaderSetContains {
+                target: "Vary".into(),
+                expected: vec!["Accept-Encoding".into()],
+            }
+        );
+        assert_eq!(
+            parse_statement("header:set-eq:Allow: , "),
+            Err(StatementParseError::MissingValue("expected list elements"))
+        );
+    }
+
+    #[test]
+    fn parses_header_numeric_operators() {
+        for (token, op) in [
+            ("eq", NumOp::Eq),
+            ("ne", NumOp::Ne),
+            ("lt", NumOp::Lt),
+            ("lte", NumOp::Lte),
+            ("gt", NumOp::Gt),
+            ("GTE", NumOp::Gte),
+        ] {
+            assert_eq!(
+                parse_statement(&format!("header:num:Content-Length:{token}:1")).unwrap(),
+                Statement::HeaderNumeric {
+                    target: "Content-Length".into(),
+                    op,
+                    value: 1,
+                }
+            );
+        }
+        assert_eq!(
+            parse_statement("header:num:Age:gt:-5").unwrap(),
+            Statement::HeaderNumeric {
+                target: "Age".into(),
+                op: NumOp::Gt,
+                value: -5,
+            }
+        );
+        assert_eq!(
+            parse_statement("header:num:Age:about:60"),
+            Err(StatementParseError::UnknownNumericOperator("about".into()))
+        );
+        assert_eq!(
+            parse_statement("header:num:Age:lt:soon"),
+            Err(StatementParseError::InvalidCount("soon".into()))
+        );
+        assert!(matches!(
+            parse_statement("header:num:Age:lt"),
+            Err(StatementParseError::ExpectedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn parses_spki_pin() {
+        assert_eq!(
+            parse_statement("tls:spki-pin:3brxwiwOeOoOswfHaD84AQrf+aMWL9q3wuuwpVUnBTM=").unwrap(),
+            Statement::SpkiPinIs {
+                pin: "3brxwiwOeOoOswfHaD84AQrf+aMWL9q3wuuwpVUnBTM=".into()
+            }
+        );
+        assert_eq!(
+            parse_statement("tls:spki-pin:c2hvcnQ="),
+            Err(StatementParseError::InvalidBase64("c2hvcnQ=".into()))
+        );
+    }
+
+    #[test]
+    fn parses_chain_length_limit() {
+        assert_eq!(
+            parse_statement("tls:chain-length-at-most:3").unwrap(),
+            Statement::CertChainLengthAtMost { n: 3 }
+        );
+        assert_eq!(
+            parse_statement("tls:chain-length-at-most:three"),
+            Err(StatementParseError::InvalidCount("three".into()))
+        );
+    }
+
+    #[test]
+    fn parses_sct_present() {
+        assert_eq!(
+            parse_statement("tls:sct-present").unwrap(),
+            Statement::SctPresent
+        );
+        assert!(matches!(
+            parse_statement("tls:sct-present:2"),
+            Err(StatementParseError::ExpectedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn parses_cert_covers() {
+        assert_eq!(
+            parse_statement("tls:cert-covers:api.example.com").unwrap(),
+            Statement::CertCoversHost {
+                host: "api.example.com".into()
+            }
+        );
+        assert_eq!(
+            parse_statement(r#"tls:cert-covers:"2001:db8::1""#).unwrap(),
+            Statement::CertCoversHost {
+                host: "2001:db8::1".into()
+            }
+        );
+        assert_eq!(
+            parse_statement("tls:cert-covers:*.example.com"),
+            Err(StatementParseError::InvalidHost("*.example.com".into()))
+        );
+        assert_eq!(
+            parse_statement("tls:cert-covers:"),
+            Err(StatementParseError::EmptyToken)
+        );
+    }
+
+    #[test]
+    fn parses_http_version_in_any_spelling() {
+        for spelling in ["HTTP/2", "h2", "2.0"] {
+            assert_eq!(
+                parse_statement(&format!("http:version:{spelling}")).unwrap(),
+                Statement::HttpVersionIs {
+                    version: "HTTP/2".into()
+                }
+            );
+        }
+        assert_eq!(
+            parse_statement("http:version:1.1").unwrap(),
+            Statement::HttpVersionIs {
+                version: "HTTP/1.1".into()
+            }
+        );
+        assert_eq!(
+            parse_statement("http:version:HTTP/4"),
+            Err(StatementParseError::UnknownHttpVersion("HTTP/4".into()))
+        );
+        assert!(matches!(
+            parse_statement("http:2"),
+            Err(StatementParseError::ExpectedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn parses_cors_statement() {
+        assert_eq!(
+            parse_statement(r#"cors:allows-origin:"https://app.example.com""#).unwrap(),
+            Statement::CorsAllowsOrigin {
+                origin: "https://app.example.com".into()
+            }
+        );
+        assert!(parse_statement("cors:allows-origin:https://app.example.com").is_err());
+    }
+
+    #[test]
+    fn parses_link_statements() {
+        assert_eq!(
+            parse_statement("link:rel:Next").unwrap(),
+            Statement::LinkRelPresent { rel: "next".into() }
+        );
+        assert_eq!(
+            parse_statement(r#"link:uri:prev:"^https://api\\.example\\.com/""#).unwrap(),
+            Statement::LinkRelUri {
+                rel: "prev".into(),
+                uri_pattern: r"^https://api\.example\.com/".into()
+            }
+        );
+        assert!(parse_statement("link:uri:next").is_err());
+    }
+
+    #[test]
+    fn parses_body_byte_checks() {
+        assert_eq!(
+            parse_statement("body:starts:iVBORw0KGgo=").unwrap(),
+            Statement::BodyStartsWith {
+                prefix_base64: "iVBORw0KGgo=".into()
+            }
+        );
+        assert_eq!(
+            parse_statement("body:ends:SUVORK5CYII=").unwrap(),
+            Statement::BodyEndsWith {
+                suffix_base64: "SUVORK5CYII=".into()
+            }
+        );
+        assert_eq!(
+            parse_statement("body:starts:not*base64"),
+            Err(StatementParseError::InvalidBase64("not*base64".into()))
+        );
+        assert_eq!(
+            parse_statement("body:Content-Digest").unwrap(),
+            Statement::ContentDigestValid
+        );
+        assert!(parse_statement("body:content-digest:sha-256").is_err());
+    }
+
+    #[test]
+    fn parses_header_casing() {
+        assert_eq!(
+            parse_statement("header:casing:X-Powered-By").unwrap(),
+            Statement::HeaderCasingIs {
+                target: "x-powered-by".into(),
+                expected_casing: "X-Powered-By".into(),
+            }
+        );
+        assert!(parse_statement("header:casing:").is_err());
+        assert!(parse_statement("header:casing:Server:SERVER").is_err());
+    }
+
+    #[test]
+    fn parses_header_eq_with_quotes() {
+        let stmt = parse_statement(r#"header:eq:Server:"Apache/2.4.49 (Unix)""#).expect("parsed");
+        assert_eq!(
+            stmt,
+            Statement::HeaderEquals {
+                target: "Server".into(),
+                expected: "Apache/2.4.49 (Unix)".into(),
+                case_sensitive: None
+            }
+        );
+    }
+
+    #[test]
+    fn parses_hash_eq() {
+        let hex = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let stmt = parse_statement(&format!("hash:eq:sha256:{hex}")).expect("parsed hash");
+        assert_eq!(
+            stmt,
+            Statement::HashEquals {
+                algorithm: HashAlgorithm::Sha256,
+                digest: hex.into()
+            }
+        );
+    }
+
+    #[test]
+    fn hash_eq_accepts_hex_and_base64_of_same_digest() {
+        let hex = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let expected = HashAlgorithm::Sha256.decode_digest(hex).expect("hex");
+        for encoded in [
+            "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=",
+            "47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU",
+        ] {
+            let stmt = parse_statement(&format!("hash:eq:sha256:{encoded}")).expect("base64");
+            let Statement::HashEquals { algorithm, digest } = stmt else {
+                panic!("expected hash statement");
+            };
+            assert_eq!(algorithm.decode_digest(&digest).unwrap(), expected);
+        }
+        assert_eq!(
+            parse_statement("hash:eq:sha256:deadbeef"),
+            Err(StatementParseError::InvalidDigest("deadbeef".into(), 32))
+        );
+    }
+
+    #[test]
+    fn parses_regex_with_scope_and_case() {
+        let stmt =
+            parse_statement("regex:scope=headers:case_sensitive=true:\"Set-Cookie: session=.*\"")
+                .expect("parsed regex");
+        assert_eq!(
+            stmt,
+            Statement::Regex {
+                pattern: "Set-Cookie: session=.*".into(),
+                scope: RegexScope::Headers,
+                case_sensitive: true,
+                multi_line: false,
+                dot_all: false,
+            }
+        );
+    }
+
+    #[test]
+    fn parses_regex_line_flags() {
+        let stmt = parse_statement("regex:body:multiline=true:dotall=yes:\"^<div>.*</div>$\"")
+            .expect("parsed regex");
+        assert_eq!(
+            stmt,
+            Statement::Regex {
+                pattern: "^<div>.*</div>$".into(),
+                scope: RegexScope::Body,
+                case_sensitive: false,
+                multi_line: true,
+                dot_all: true,
+            }
+        );
+        let json = serde_json::to_value(&stmt).unwrap();
+        assert_eq!(json["multi_line"], true);
+        let plain = parse_statement("regex:body:dotall=false:x").expect("parsed regex");
+        assert_eq!(
+            serde_json::to_value(&plain).unwrap().get("dot_all"),
+            None,
+            "unset flags are omitted so existing fingerprints stay stable"
+        );
+        assert!(matches!(
+            parse_statement("regex:count:2:dotall=true:x"),
+            Err(StatementParseError::ExpectedFormat(_))
+        ));
+        assert!(matches!(
+            parse_statement("regex:multiline=maybe:x"),
+            Err(StatementParseError::InvalidBoolean(_))
+        ));
+    }
+
+    #[test]
+    fn parses_early_hints_present() {
+        let stmt = parse_statement("early-hints:present").expect("parsed");
+        assert_eq!(stmt, Statement::EarlyHintsPresent { target: None });
+        let stmt = parse_statement("early-hints:present:Link").expect("parsed");
+        assert_eq!(
+            stmt,
+            Statement::EarlyHintsPresent {
+                target: Some("Link".into())
+            }
+        );
+    }
+
+    #[test]
+    fn parses_nested_composites() {
+        let stmt = parse_statement(
+            r#"all:(header:present:Server, any:(header:absent:X-Frame-Options, header:eq:Cache-Control:"no-cache, no-store"))"#,
+        )
+        .expect("parsed composite");
+        assert_eq!(
+            stmt,
+            Statement::All {
+                statements: vec![
+                    Statement::HeaderPresent {
+                        target: "Server".into()
+                    },
+                    Statement::Any {
+                        statements: vec![
+                            Statement::HeaderAbsent {
+                                target: "X-Frame-Options".into()
+                            },
+                            Statement::HeaderEquals {
+                                target: "Cache-Control".into(),
+                                expected: "no-cache, no-store".into(),
+                                case_sensitive: None
+                            },
+                        ]
+                    },
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn parses_regex_transform_around_inner_statement() {
+        let stmt = parse_statement(
+            r#"transform:regex:"token=[a-f0-9]+":"token=REDACTED":(all:(hash:eq:sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08, body:starts:PGh0bWw+))"#,
+        )
+        .unwrap();
+        let Statement::RegexReplace {
+            pattern,
+            replacement,
+            statement,
+        } = stmt
+        else {
+            panic!("expected transform, got {stmt:?}");
+        };
+        assert_eq!(pattern, "token=[a-f0-9]+");
+        assert_eq!(replacement, "token=REDACTED");
+        assert!(matches!(*statement, Statement::All { ref statements } if statements.len() == 2));
+
+        let stripped =
+            parse_statement(r#"transform:regex:"nonce=\\d+":"":(body:ends:PC9odG1sPg==)"#).unwrap();
+        assert!(matches!(
+            stripped,
+            Statement::RegexReplace { ref pattern, ref replacement, .. }
+                if pattern == r"nonce=\d+" && replacement.is_empty()
+        ));
+
+        assert!(matches!(
+            parse_statement(r#"transform:regex:"(":"x":(header:present:Server)"#),
+            Err(StatementParseError::InvalidRegex(_))
+        ));
+        assert_eq!(
+            parse_statement(r#"transform:regex:"a":"b":(header:present:Server):x"#).unwrap_err(),
+            StatementParseError::ExpectedFormat(
+                "transform:regex:\"<pattern>\":\"<replacement>\":(<statement>)"
+            )
+        );
+        assert_eq!(
+            parse_statement(r#"transform:regex:"a":"b":(header:present:Server))"#).unwrap_err(),
+            StatementParseError::UnbalancedParentheses
+        );
+    }
+
+    #[test]
+    fn errors_on_unbalanced_composite() {
+        let err = parse_statement("all:(header:present:Server))").unwrap_err();
+        assert_eq!(err, StatementParseError::UnbalancedParentheses);
+        let err = parse_statement("any:()").unwrap_err();
+        assert_eq!(err, StatementParseError::MissingValue("composite members"));
+    }
+
+    #[test]
+    fn parses_dns_resolved_with_quoted_ipv6() {
+        let stmt = parse_statement(r#"dns:resolved:"2001:db8::1""#).expect("parsed");
+        assert_eq!(
+            stmt,
+            Statement::ResolvedIpIs {
+                ip: "2001:db8::1".into()
+            }
+        );
+        let err = parse_statement("dns:resolved:example.com").unwrap_err();
+        assert_eq!(
+            err,
+            StatementParseError::InvalidIpAddress("example.com".into())
+        );
+    }
+
+    #[test]
+    fn parses_status_scopes() {
+        let stmt = parse_statement("regex:scope=statusline:\"HTTP/1.1 200\"").expect("parsed");
+        assert_eq!(
+            stmt,
+            Statement::Regex {
+                pattern: "HTTP/1.1 200".into(),
+                scope: RegexScope::StatusLine,
+                case_sensitive: false,
+                multi_line: false,
+                dot_all: false,
+            }
+        );
+        let stmt = parse_statement("regex:status:Forbidden").expect("parsed");
+        assert!(matches!(
+            stmt,
+            Statement::Regex {
+                scope: RegexScope::Status,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parses_cookie_scope() {
+        let stmt = parse_statement(r#"regex:scope=cookies:"; *Secure""#).expect("parsed");
+        assert_eq!(
+            stmt,
+            Statement::Regex {
+                pattern: "; *Secure".into(),
+                scope: RegexScope::Cookies,
+                case_sensitive: false,
+                multi_line: false,
+                dot_all: false,
+            }
+        );
+        let stmt = parse_statement("regex:count:0:cookies:SameSite=None").expect("parsed");
+        assert!(matches!(
+            stmt,
+            Statement::RegexAll {
+                scope: RegexScope::Cookies,
+                min_matches: 0,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parses_regex_count() {
+        let stmt = parse_statement("regex:count:3:scope=body:\"<script\"").expect("parsed");
+        assert_eq!(
+            stmt,
+            Statement::RegexAll {
+                pattern: "<script".into(),
+                scope: RegexScope::Body,
+                case_sensitive: false,
+                min_matches: 3
+            }
+        );
+        let err = parse_statement("regex:count:many:foo").unwrap_err();
+        assert_eq!(err, StatementParseError::InvalidCount("many".into()));
+    }
+
+    #[test]
+    fn parses_timing_ttfb_under() {
+        let stmt = parse_statement("timing:ttfb-under:250ms").expect("parsed");
+        assert_eq!(stmt, Statement::ResponseTimeUnder { max_ms: 250 });
+        let stmt = parse_statement("timing:clock-skew-under:2000").expect("parsed");
+        assert_eq!(stmt, Statement::ClockSkewUnder { millis: 2000 });
+    }
+
+    #[test]
+    fn interpolates_variables_in_values() {
+        let vars = HashMap::from([
+            ("DOMAIN".to_string(), "staging.example.com".to_string()),
+            ("TIER".to_string(), "a:b)".to_string()),
+        ]);
+        let stmt = parse_statement_with_vars("header:eq:Host:${DOMAIN}", &vars).unwrap();
+        assert_eq!(
+            stmt,
+            Statement::HeaderEquals {
+                target: "Host".into(),
+                expected: "staging.example.com".into(),
+                case_sensitive: None,
+            }
+        );
+        let stmt = parse_statement_with_vars(
+            "all:(header:present:Server, header:eq:X-Tier:tier-${TIER})",
+            &vars,
+        )
+        .unwrap();
+        let Statement::All { statements } = stmt else {
+            panic!("expected a composite");
+        };
+        assert_eq!(
+            statements[1],
+            Statement::HeaderEquals {
+                target: "X-Tier".into(),
+                expected: "tier-a:b)".into(),
+                case_sensitive: None,
+            }
+        );
+        // Without variables the reference stays literal.
+        let stmt = parse_statement("header:eq:Host:${DOMAIN}").unwrap();
+        assert!(
+            matches!(stmt, Statement::HeaderEquals { expected, .. } if expected == "${DOMAIN}")
+        );
+    }
+
+    #[test]
+    fn rejects_undefined_variables() {
+        let vars = HashMap::from([("DOMAIN".to_string(), "example.com".to_string())]);
+        assert_eq!(
+            parse_statement_with_vars("header:eq:Host:${HOST}", &vars).unwrap_err(),
+            StatementParseError::UndefinedVariable("HOST".into())
+        );
+        assert_eq!(
+            parse_statement_with_vars("header:eq:Host:${DOMAIN", &vars).unwrap_err(),
+            StatementParseError::UnterminatedVariable("${DOMAIN".into())
+        );
+    }
+
+    #[test]
+    fn parses_field_present() {
+        let stmt = parse_statement("field:present:/handshake/alpn").expect("parsed");
+        assert_eq!(
+            stmt,
+            Statement::FieldPresent {
+                path: "/handshake/alpn".into()
+            }
+        );
+        let err = parse_statement("field:present:/body").unwrap_err();
+        assert!(matches!(err, StatementParseError::InvalidFieldPointer(_)));
+    }
+
+    #[test]
+    fn parses_cache_hit() {
+        assert_eq!(
+            parse_statement("cache:hit").unwrap(),
+            Statement::CacheHit {
+                expected: true,
+                headers: vec![],
+            }
+        );
+        assert_eq!(
+            parse_statement("cache:hit:false:X-Proxy-Cache,Age").unwrap(),
+            Statement::CacheHit {
+                expected: false,
+                headers: vec!["X-Proxy-Cache".into(), "Age".into()],
+            }
+        );
+        assert!(parse_statement("cache:hit:maybe").is_err());
+        assert_eq!(
+            parse_statement("server:software-absent").unwrap(),
+            Statement::ServerSoftwareAbsent
+        );
     }
 
     #[test]
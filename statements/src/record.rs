@@ -0,0 +1,97 @@
+//! The response and connection facts statements are evaluated against. The
+//! prover fills an [`EvalRecord`] from its live capture; the verifier
+//! rebuilds one from the committed witness (see [`crate::witness`]), so both
+//! run the same checks over the same shape.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::HashAlgorithm;
+
+/// Header values by lowercased name, in wire order.
+pub type HeaderMap = BTreeMap<String, Vec<String>>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpResponse {
+    pub http_version: String,
+    pub status_code: u16,
+    pub reason: String,
+    pub headers: Vec<HeaderEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub early_hints: Vec<HeaderEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub body: Vec<u8>,
+    pub body_truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_digest: Option<BodyDigest>,
+}
+
+/// Digest of a body that was hashed while streaming and not retained.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyDigest {
+    pub algorithm: HashAlgorithm,
+    pub hex: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeaderEntry {
+    /// Lowercased; evaluation matches on this.
+    pub name: String,
+    pub value: String,
+    /// The name as the server spelled it, kept only when the capture
+    /// preserved header case.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wire_name: Option<String>,
+}
+
+/// Group `headers` by name, keeping repeated values in order.
+pub fn header_map(headers: &[HeaderEntry]) -> HeaderMap {
+    let mut map = HeaderMap::default();
+    for entry in headers {
+        map.entry(entry.name.clone())
+            .or_default()
+            .push(entry.value.clone());
+    }
+    map
+}
+
+/// Everything a statement can be checked against. Facts a source cannot
+/// vouch for are `None`, and statements needing them are unsatisfied with
+/// [`crate::evaluate::EvalReason::NotCommitted`].
+#[derive(Debug, Clone)]
+pub struct EvalRecord<'a> {
+    pub response: Cow<'a, HttpResponse>,
+    /// `response.headers` as a [`HeaderMap`].
+    pub headers: Cow<'a, HeaderMap>,
+    /// Canonical transcripts, for `field:present` pointers.
+    pub canonical_handshake: &'a [u8],
+    pub canonical_app_data: &'a [u8],
+    pub tls: TlsFacts<'a>,
+    /// Addresses the host resolved to; empty unless DNS was recorded.
+    pub resolved_ips: &'a [IpAddr],
+    /// The prover's clock when the response arrived.
+    pub captured_at: Option<DateTime<Utc>>,
+    /// Time to first byte in milliseconds, labelled with the connection it
+    /// was measured on.
+    pub ttfb: Option<(&'static str, u64)>,
+}
+
+/// What the handshake says about the served certificates.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TlsFacts<'a> {
+    /// `pin-sha256` per certificate; empty unless SPKI pins were recorded.
+    pub spki_pins: &'a [String],
+    /// Names in the leaf certificate's SubjectAltName.
+    pub subject_alt_names: &'a [String],
+    /// Certificates the server sent; zero without TLS.
+    pub served_certificates: usize,
+    /// Combined DER size of the chain, when known.
+    pub chain_bytes: Option<usize>,
+    /// Signed certificate timestamps embedded in the leaf certificate;
+    /// `None` when the leaf itself is not at hand.
+    pub embedded_scts: Option<usize>,
+}
@@ -0,0 +1,35 @@
+//! Hostname matching against a certificate's subject alternative names,
+//! following RFC 6125 §6.4 as browsers apply it to wildcard certificates.
+
+use std::net::IpAddr;
+
+/// The first of `names` that covers `host`, if any.
+pub fn covering_name<'a>(names: &'a [String], host: &str) -> Option<&'a str> {
+    names
+        .iter()
+        .map(String::as_str)
+        .find(|name| name_covers(name, host))
+}
+
+/// Whether one SAN entry covers `host`: IP addresses and plain names match
+/// exactly (ignoring case and a trailing dot); `*.rest` matches exactly one
+/// non-empty leftmost label, and only when `rest` has at least two labels.
+pub fn name_covers(name: &str, host: &str) -> bool {
+    let name = name.trim_end_matches('.');
+    let host = host.trim_end_matches('.');
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return name.parse::<IpAddr>() == Ok(ip);
+    }
+    match name.strip_prefix("*.") {
+        Some(suffix) => {
+            let Some((label, rest)) = host.split_once('.') else {
+                return false;
+            };
+            !label.is_empty()
+                && suffix.contains('.')
+                && !suffix.contains('*')
+                && rest.eq_ignore_ascii_case(suffix)
+        }
+        None => !name.contains('*') && name.eq_ignore_ascii_case(host),
+    }
+}
@@ -0,0 +1,57 @@
+//! `server:software-absent`: the `Server` header, if sent, leaks no software
+//! version or platform. A bare product token (`nginx`, `cloudflare`) is the
+//! usual hardened banner and passes.
+
+/// Whether a `Server` value discloses a software version, however it is
+/// written (`nginx/1.25.3`, `nginx 1.25.3`, `Apache-2.4`, a bare `1.0`), or
+/// a platform comment (`Apache (Ubuntu)`).
+pub fn discloses_software(value: &str) -> bool {
+    value.contains('(')
+        || value
+            .split(|c: char| c.is_whitespace() || matches!(c, '/' | '-' | '_'))
+            .any(is_version)
+}
+
+/// A version segment: digits, optionally after a `v`, that end the segment
+/// or run into a `.`, so `1`, `10.0`, `v2.4.57` and `1.25.3rc1` count while
+/// product names such as `3scale` do not.
+fn is_version(segment: &str) -> bool {
+    let segment = segment
+        .strip_prefix(['v', 'V'])
+        .filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or(segment);
+    let digits = segment.len()
+        - segment
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .len();
+    digits > 0 && matches!(segment[digits..].chars().next(), None | Some('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_and_platforms_are_disclosures() {
+        assert!(discloses_software("nginx/1.25.3"));
+        assert!(discloses_software("Apache/2.4.57 (Debian) OpenSSL/3.0.11"));
+        assert!(discloses_software("Microsoft-IIS/10.0"));
+        assert!(discloses_software("Apache (Ubuntu)"));
+        assert!(!discloses_software("nginx"));
+        assert!(!discloses_software("cloudflare"));
+        assert!(!discloses_software("AmazonS3"));
+        assert!(!discloses_software("gws/edge"));
+    }
+
+    #[test]
+    fn versions_need_not_follow_a_slash() {
+        assert!(discloses_software("nginx 1.25.3"));
+        assert!(discloses_software("Apache-2.4"));
+        assert!(discloses_software("1.0"));
+        assert!(discloses_software("Caddy v2.7.6"));
+        assert!(discloses_software("Jetty_9.4.z-SNAPSHOT"));
+        assert!(!discloses_software("Apache-Coyote"));
+        assert!(!discloses_software("3scale"));
+        assert!(!discloses_software("h2o"));
+    }
+}
@@ -0,0 +1,46 @@
+//! `transform:regex`: rewrite the body with a regex substitution before
+//! evaluating an inner statement, so per-request values (CSRF tokens,
+//! nonces) do not break content proofs. Prover and verifier both rewrite
+//! through [`replace_body`], so the substitution is identical on each side.
+
+use std::borrow::Cow;
+
+use regex::bytes::Regex;
+
+/// Compile `pattern` (case-sensitive; use `(?i)` to ignore case).
+pub fn compile(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|err| err.to_string())
+}
+
+/// `body` with every match of `pattern` replaced by `replacement`, in which
+/// `$1`/`${name}` expand to capture groups. Borrowed when nothing matched.
+pub fn replace_body<'a>(
+    pattern: &str,
+    replacement: &str,
+    body: &'a [u8],
+) -> Result<Cow<'a, [u8]>, String> {
+    Ok(compile(pattern)?.replace_all(body, replacement.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_every_match_and_expands_groups() {
+        let body = b"<input name=csrf value=9f2c><a href=?token=ab12&x=token=cd34>";
+        let replaced = replace_body("token=[a-f0-9]+", "token=REDACTED", body).unwrap();
+        assert_eq!(
+            replaced.as_ref(),
+            b"<input name=csrf value=9f2c><a href=?token=REDACTED&x=token=REDACTED>"
+        );
+        let replaced = replace_body("value=([a-f0-9]+)", "value=<$1>", body).unwrap();
+        assert!(replaced.starts_with(b"<input name=csrf value=<9f2c>>"));
+
+        assert!(matches!(
+            replace_body("nonce=\\d+", "", body).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert!(replace_body("(unclosed", "", body).is_err());
+    }
+}
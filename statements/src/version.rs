@@ -0,0 +1,159 @@
+//! Which verifier release a statement needs. The prover stamps the answer
+//! into the artifact so an older verifier refuses a statement it cannot
+//! decide instead of reporting it unsatisfied.
+
+use std::cmp::Ordering;
+
+use crate::{RegexScope, Statement};
+
+/// Release of the statement types RedProof started with: `header:present`,
+/// `header:absent`, `header:eq`, `hash:eq`, and `regex` over headers, body
+/// or both without the `multi_line`/`dot_all` flags.
+pub const BASELINE_VERIFIER_VERSION: &str = "0.0.1";
+
+/// Release of every statement type added since the baseline, composites
+/// included.
+pub const EXTENDED_VERIFIER_VERSION: &str = "0.1.0";
+
+impl Statement {
+    /// Oldest verifier release that decides this statement. Every variant
+    /// names the release that introduced it, so a new variant does not
+    /// compile without one; composites also need the newest of their
+    /// members.
+    pub fn min_verifier_version(&self) -> &'static str {
+        match self {
+            Statement::HeaderPresent { .. }
+            | Statement::HeaderAbsent { .. }
+            | Statement::HeaderEquals { .. }
+            | Statement::HashEquals { .. } => BASELINE_VERIFIER_VERSION,
+            // Only the scopes and flags the baseline knew.
+            Statement::Regex {
+                scope: RegexScope::Headers | RegexScope::Body | RegexScope::Any,
+                multi_line: false,
+                dot_all: false,
+                ..
+            } => BASELINE_VERIFIER_VERSION,
+            Statement::Regex { .. } => EXTENDED_VERIFIER_VERSION,
+            Statement::HeadersAllAbsent { .. }
+            | Statement::HeaderCasingIs { .. }
+            | Statement::HeaderGlob { .. }
+            | Statement::ETagEquals { .. }
+            | Statement::HeaderNumeric { .. }
+            | Statement::JsonSchemaValid { .. }
+            | Statement::BodyStartsWith { .. }
+            | Statement::BodyEndsWith { .. }
+            | Statement::ContentDigestValid
+            | Statement::ReasonEquals { .. }
+            | Statement::HttpVersionIs { .. }
+            | Statement::ServerSoftwareAbsent
+            | Statement::CacheHit { .. }
+            | Statement::EarlyHintsPresent { .. }
+            | Statement::SpkiPinIs { .. }
+            | Statement::CertChainLengthAtMost { .. }
+            | Statement::CertCoversHost { .. }
+            | Statement::ResolvedIpIs { .. }
+            | Statement::ResponseTimeUnder { .. }
+            | Statement::FieldPresent { .. }
+            | Statement::HeaderSetEquals { .. }
+            | Statement::HeaderSetContains { .. }
+            | Statement::HeaderNameAbsentMatching { .. }
+            | Statement::AllHeadersValueMatch { .. }
+            | Statement::CspDirective { .. }
+            | Statement::RegexAll { .. }
+            | Statement::CorsAllowsOrigin { .. }
+            | Statement::LinkRelPresent { .. }
+            | Statement::LinkRelUri { .. }
+            | Statement::SctPresent
+            | Statement::ClockSkewUnder { .. } => EXTENDED_VERIFIER_VERSION,
+            Statement::RegexReplace { statement, .. } => {
+                newest(EXTENDED_VERIFIER_VERSION, statement.min_verifier_version())
+            }
+            Statement::All { statements } | Statement::Any { statements } => statements
+                .iter()
+                .map(Statement::min_verifier_version)
+                .fold(EXTENDED_VERIFIER_VERSION, newest),
+        }
+    }
+}
+
+fn newest<'a>(a: &'a str, b: &'a str) -> &'a str {
+    match compare(a, b) {
+        Some(Ordering::Less) => b,
+        _ => a,
+    }
+}
+
+/// Order two dotted release numbers (`0.2.10` after `0.2.9`; missing
+/// components count as zero). `None` when either is not purely numeric.
+pub fn compare(a: &str, b: &str) -> Option<Ordering> {
+    let parse = |version: &str| {
+        version
+            .trim()
+            .strip_prefix('v')
+            .unwrap_or(version.trim())
+            .split('.')
+            .map(|part| part.parse::<u64>().ok())
+            .collect::<Option<Vec<_>>>()
+    };
+    let (mut a, mut b) = (parse(a)?, parse(b)?);
+    let len = a.len().max(b.len());
+    a.resize(len, 0);
+    b.resize(len, 0);
+    Some(a.cmp(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_statement;
+
+    #[test]
+    fn compares_release_numbers() {
+        assert_eq!(compare("0.2.10", "0.2.9"), Some(Ordering::Greater));
+        assert_eq!(compare("1.0", "1.0.0"), Some(Ordering::Equal));
+        assert_eq!(compare("v0.0.1", "0.1"), Some(Ordering::Less));
+        assert_eq!(compare("0.1.0-beta", "0.1.0"), None);
+    }
+
+    #[test]
+    fn statements_need_the_release_that_decides_them() {
+        for (expr, release) in [
+            ("header:present:Server", BASELINE_VERIFIER_VERSION),
+            ("regex:body:^ok", BASELINE_VERIFIER_VERSION),
+            ("regex:body:dotall=true:^ok", EXTENDED_VERIFIER_VERSION),
+            ("regex:cookies:session", EXTENDED_VERIFIER_VERSION),
+            ("body:content-digest", EXTENDED_VERIFIER_VERSION),
+            ("http:version:h2", EXTENDED_VERIFIER_VERSION),
+            ("tls:sct-present", EXTENDED_VERIFIER_VERSION),
+            (
+                "all:(header:present:Server,header:absent:Via)",
+                EXTENDED_VERIFIER_VERSION,
+            ),
+            (
+                r#"any:(header:present:Server,cors:allows-origin:"https://a.test")"#,
+                EXTENDED_VERIFIER_VERSION,
+            ),
+        ] {
+            assert_eq!(
+                parse_statement(expr).unwrap().min_verifier_version(),
+                release,
+                "{expr}"
+            );
+        }
+        let empty = Statement::Any {
+            statements: Vec::new(),
+        };
+        assert_eq!(empty.min_verifier_version(), EXTENDED_VERIFIER_VERSION);
+    }
+
+    #[test]
+    fn this_release_decides_every_stamp() {
+        for release in [BASELINE_VERIFIER_VERSION, EXTENDED_VERIFIER_VERSION] {
+            assert_ne!(
+                compare(release, env!("CARGO_PKG_VERSION")),
+                Some(Ordering::Greater),
+                "{release}"
+            );
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Canonical handshake and response transcripts as the prover commits them,
+//! decoded back into an [`EvalRecord`] so a verifier can re-evaluate the
+//! statement from the witness alone. The `encode` methods are the one
+//! encoder for both `json/v1` and `json/v2`: fields only `json/v2` commits
+//! are left out while empty.
+
+use std::borrow::Cow;
+use std::net::IpAddr;
+
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::record::{header_map, BodyDigest, EvalRecord, HeaderEntry, HttpResponse, TlsFacts};
+
+/// Canonical handshake as committed by the prover.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HandshakeWitness {
+    pub domain: String,
+    pub version: String,
+    pub cipher: String,
+    pub alpn: Option<String>,
+    pub cert_fingerprints: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spki_pins: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subject_alt_names: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolved_ips: Vec<IpAddr>,
+    /// `imported` when the response was captured by another tool.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl HandshakeWitness {
+    /// The canonical bytes committed for this handshake.
+    pub fn encode(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec(self)
+    }
+
+    /// Certificates the server sent; plaintext and certificate-less captures
+    /// only carry a `domain-only:` placeholder.
+    pub fn served_certificates(&self) -> usize {
+        self.cert_fingerprints
+            .iter()
+            .filter(|fingerprint| fingerprint.starts_with("sha256:"))
+            .count()
+    }
+}
+
+/// Canonical response as committed by the prover.
+#[derive(Debug, Deserialize)]
+pub struct AppDataWitness {
+    /// Committed since `json/v2`.
+    #[serde(default)]
+    pub http_version: Option<String>,
+    pub status_code: u16,
+    pub reason: String,
+    pub headers: Vec<HeaderEntry>,
+    #[serde(default)]
+    pub early_hints: Vec<HeaderEntry>,
+    pub body_base64: String,
+    pub body_truncated: bool,
+    pub body_digest: Option<BodyDigest>,
+    /// Present when the prover committed its timing trace.
+    #[serde(default)]
+    pub timing: Option<TimingTrace>,
+}
+
+impl AppDataWitness {
+    /// The canonical bytes committed for this response. Header names are
+    /// trimmed and lowercased, values trimmed, and headers sorted by name;
+    /// the sort is stable, so repeated headers keep their wire order. Early
+    /// hints stay in wire order.
+    pub fn encode(&self) -> serde_json::Result<Vec<u8>> {
+        #[derive(Serialize)]
+        struct Encoded<'a> {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            http_version: Option<&'a str>,
+            status_code: u16,
+            reason: &'a str,
+            headers: Vec<HeaderEntry>,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            early_hints: Vec<HeaderEntry>,
+            body_base64: &'a str,
+            body_truncated: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            body_digest: Option<&'a BodyDigest>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            timing: Option<&'a TimingTrace>,
+        }
+
+        let normalized = |headers: &[HeaderEntry]| {
+            headers
+                .iter()
+                .map(|header| HeaderEntry {
+                    name: header.name.trim().to_ascii_lowercase(),
+                    value: header.value.trim().to_string(),
+                    wire_name: header.wire_name.clone(),
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut headers = normalized(&self.headers);
+        headers.sort_by(|a, b| a.name.cmp(&b.name));
+        serde_json::to_vec(&Encoded {
+            http_version: self.http_version.as_deref().filter(|v| !v.is_empty()),
+            status_code: self.status_code,
+            reason: &self.reason,
+            headers,
+            early_hints: normalized(&self.early_hints),
+            body_base64: &self.body_base64,
+            body_truncated: self.body_truncated,
+            body_digest: self.body_digest.as_ref(),
+            timing: self.timing.as_ref(),
+        })
+    }
+}
+
+/// Durations of one connection's phases, in milliseconds from the start of
+/// the TCP connect. Still measured by the prover; committing it only makes
+/// later edits detectable.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct TimingTrace {
+    /// TCP connect.
+    pub connect_ms: u64,
+    /// TLS handshake after the connect; zero over plaintext HTTP.
+    pub handshake_ms: u64,
+    /// From sending the request to the first response byte.
+    pub ttfb_ms: u64,
+    /// Connect through the end of the response.
+    pub total_ms: u64,
+}
+
+impl<'a> EvalRecord<'a> {
+    /// The record a witness commits to. `canonical_handshake` and
+    /// `canonical_app_data` are the bytes `handshake` and `app` were decoded
+    /// from. The capture time and the leaf's SCTs are not committed and stay
+    /// `None` for the caller to fill in from whatever vouches for them.
+    pub fn from_witness(
+        handshake: &'a HandshakeWitness,
+        app: &AppDataWitness,
+        canonical_handshake: &'a [u8],
+        canonical_app_data: &'a [u8],
+    ) -> Result<Self, String> {
+        let lowercased = |headers: &[HeaderEntry]| {
+            headers
+                .iter()
+                .map(|header| HeaderEntry {
+                    name: header.name.to_ascii_lowercase(),
+                    ..header.clone()
+                })
+                .collect::<Vec<_>>()
+        };
+        let headers = lowercased(&app.headers);
+        let body = B64
+            .decode(&app.body_base64)
+            .map_err(|_| "app-data witness body is not base64".to_string())?;
+        Ok(EvalRecord {
+            headers: Cow::Owned(header_map(&headers)),
+            response: Cow::Owned(HttpResponse {
+                http_version: app.http_version.clone().unwrap_or_default(),
+                status_code: app.status_code,
+                reason: app.reason.clone(),
+                headers,
+                early_hints: lowercased(&app.early_hints),
+                body,
+                body_truncated: app.body_truncated,
+                body_digest: app.body_digest.clone(),
+            }),
+            canonical_handshake,
+            canonical_app_data,
+            tls: TlsFacts {
+                spki_pins: &handshake.spki_pins,
+                subject_alt_names: &handshake.subject_alt_names,
+                served_certificates: handshake.served_certificates(),
+                chain_bytes: None,
+                embedded_scts: None,
+            },
+            resolved_ips: &handshake.resolved_ips,
+            captured_at: None,
+            ttfb: app
+                .timing
+                .as_ref()
+                .map(|trace| ("committed", trace.ttfb_ms)),
+        })
+    }
+}
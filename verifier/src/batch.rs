@@ -0,0 +1,357 @@
+//! Directory mode: `redproof-verify DIR` verifies every artifact whose file
+//! name matches `--glob`, optionally skipping those captured before
+//! `--since`, and ends with a one-line summary.
+
+use std::fs;
+use std::io::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use redproof_artifact::RedProofArtifact;
+use redproof_statements::Glob;
+
+/// Which files in the directory are verified.
+pub struct BatchFilter {
+    /// Matched against file names; subdirectories are not entered.
+    pub glob: Glob,
+    /// Artifacts whose `time_utc` is earlier are skipped, not verified.
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub valid: usize,
+    pub invalid: usize,
+    /// Files that could not be read or parsed, or whose verification panicked.
+    pub errored: usize,
+    pub skipped: usize,
+}
+
+/// What happened to one file.
+pub enum Outcome {
+    Valid,
+    Invalid(anyhow::Error),
+    Errored(anyhow::Error),
+    Skipped(DateTime<Utc>),
+}
+
+/// Receives each file's outcome, then the summary. Any writer reports one
+/// `VALID`/`INVALID`/`ERRORED`/`SKIPPED` line per file.
+pub trait BatchReport {
+    /// `artifact` is `None` when the file could not be loaded.
+    fn file(
+        &mut self,
+        path: &Path,
+        artifact: Option<&RedProofArtifact>,
+        outcome: &Outcome,
+    ) -> Result<()>;
+
+    fn summary(&mut self, summary: &BatchSummary) -> Result<()>;
+}
+
+impl<W: Write> BatchReport for W {
+    fn file(&mut self, path: &Path, _: Option<&RedProofArtifact>, outcome: &Outcome) -> Result<()> {
+        match outcome {
+            Outcome::Valid => writeln!(self, "VALID {}", path.display())?,
+            Outcome::Skipped(captured) => writeln!(
+                self,
+                "SKIPPED {}: captured {captured}, before --since",
+                path.display()
+            )?,
+            Outcome::Invalid(err) => writeln!(self, "INVALID {}: {err:#}", path.display())?,
+            Outcome::Errored(err) => writeln!(self, "ERRORED {}: {err:#}", path.display())?,
+        }
+        Ok(())
+    }
+
+    fn summary(&mut self, summary: &BatchSummary) -> Result<()> {
+        writeln!(
+            self,
+            "Summary: {} valid, {} invalid, {} errored, {} skipped (captured before --since)",
+            summary.valid, summary.invalid, summary.errored, summary.skipped
+        )?;
+        Ok(())
+    }
+}
+
+/// Verify the matching artifacts in `dir` in file-name order, reporting each
+/// file and then the summary to `report`. A file that fails to load, or
+/// whose verification panics, is errored and the run moves on; with
+/// `fail_fast` it stops there instead, after the summary, with an error.
+pub fn run(
+    dir: &Path,
+    filter: &BatchFilter,
+    fail_fast: bool,
+    load: impl Fn(&Path) -> Result<RedProofArtifact>,
+    mut verify: impl FnMut(&Path, &RedProofArtifact) -> Result<()>,
+    report: &mut impl BatchReport,
+) -> Result<BatchSummary> {
+    let mut summary = BatchSummary::default();
+    let mut stopped_at = None;
+    for path in artifact_paths(dir, &filter.glob)? {
+        let (artifact, outcome) = match load(&path) {
+            Err(err) => (None, Outcome::Errored(err)),
+            Ok(artifact) if filter.since.is_some_and(|since| artifact.time_utc < since) => {
+                let captured = artifact.time_utc;
+                (Some(artifact), Outcome::Skipped(captured))
+            }
+            Ok(artifact) => {
+                let outcome =
+                    match panic::catch_unwind(AssertUnwindSafe(|| verify(&path, &artifact))) {
+                        Ok(Ok(())) => Outcome::Valid,
+                        Ok(Err(err)) => Outcome::Invalid(err),
+                        Err(payload) => Outcome::Errored(anyhow!(
+                            "verification panicked: {}",
+                            panic_message(payload.as_ref())
+                        )),
+                    };
+                (Some(artifact), outcome)
+            }
+        };
+        report.file(&path, artifact.as_ref(), &outcome)?;
+        match outcome {
+            Outcome::Valid => summary.valid += 1,
+            Outcome::Skipped(_) => summary.skipped += 1,
+            Outcome::Invalid(_) => summary.invalid += 1,
+            Outcome::Errored(_) => {
+                summary.errored += 1;
+                if fail_fast {
+                    stopped_at = Some(path);
+                    break;
+                }
+            }
+        }
+    }
+    report.summary(&summary)?;
+    if let Some(path) = stopped_at {
+        bail!(
+            "stopped at {} (--fail-fast); the remaining artifacts were not verified",
+            path.display()
+        );
+    }
+    Ok(summary)
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
+}
+
+/// Regular files directly in `dir` whose name matches `glob`, sorted.
+fn artifact_paths(dir: &Path, glob: &Glob) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && glob.is_match(&entry.file_name().to_string_lossy()) {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Parse a `--since` cutoff: an RFC 3339 timestamp, or an age such as `90m`,
+/// `24h`, `7d` or `2w` counted back from `now`.
+pub fn parse_since(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .filter(|split| *split > 0)
+        .ok_or_else(|| format!("'{value}' is neither an RFC 3339 timestamp nor an age like 7d"))?;
+    let (amount, unit) = value.split_at(split);
+    let amount = amount
+        .parse::<i64>()
+        .map_err(|_| format!("invalid amount in '{value}'"))?;
+    let age = match unit {
+        "s" => chrono::Duration::try_seconds(amount),
+        "m" => chrono::Duration::try_minutes(amount),
+        "h" => chrono::Duration::try_hours(amount),
+        "d" => chrono::Duration::try_days(amount),
+        "w" => chrono::Duration::try_weeks(amount),
+        other => return Err(format!("unknown unit '{other}' (use s, m, h, d or w)")),
+    };
+    age.and_then(|age| now.checked_sub_signed(age))
+        .ok_or_else(|| format!("age '{value}' out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use redproof_artifact::{
+        ArtifactMeta, CommitmentAlgorithm, CommitmentSet, EncodedBlob, TlsProofContext,
+        CANONICALIZATION_JSON_V2,
+    };
+    use redproof_statements::Statement;
+
+    use super::*;
+
+    fn artifact_at(time_utc: DateTime<Utc>) -> RedProofArtifact {
+        RedProofArtifact {
+            version: "1.0".into(),
+            domain: "example.test".into(),
+            time_utc,
+            tls: TlsProofContext {
+                version: "TLS1.3".into(),
+                cipher: "TLS13_AES_128_GCM_SHA256".into(),
+                cert_fingerprints: vec![format!("sha256:{}", "ab".repeat(32))],
+                alpn: None,
+                cert_chain: vec![],
+                spki_pins: vec![],
+                subject_alt_names: vec![],
+                chain_size: None,
+            },
+            statement: Statement::HeaderPresent {
+                target: "Server".into(),
+            },
+            commitments: CommitmentSet {
+                algorithm: CommitmentAlgorithm::Sha256,
+                canonicalization: CANONICALIZATION_JSON_V2.into(),
+                salt: None,
+                handshake: EncodedBlob::from_bytes(b"handshake"),
+                app_data: EncodedBlob::from_bytes(b"app"),
+                alternates: Default::default(),
+                headers: None,
+                witness: None,
+                statement: None,
+                witness_digest: None,
+            },
+            proof: EncodedBlob::from_bytes(b"proof"),
+            expires_at: None,
+            meta: ArtifactMeta::default(),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("redproof-batch-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn load(path: &Path) -> Result<RedProofArtifact> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    #[test]
+    fn skips_artifacts_captured_before_cutoff() {
+        let dir = temp_dir("since");
+        let day = |d| Utc.with_ymd_and_hms(2030, 1, d, 0, 0, 0).unwrap();
+        for (name, captured) in [("a-old.red", 1), ("b-new.red", 20), ("c-older.red", 2)] {
+            let json = serde_json::to_vec(&artifact_at(day(captured))).unwrap();
+            fs::write(dir.join(name), json).unwrap();
+        }
+        fs::write(dir.join("d-broken.red"), b"{").unwrap();
+        fs::write(dir.join("notes.txt"), b"not an artifact").unwrap();
+        let filter = BatchFilter {
+            glob: Glob::new("*.red", true).unwrap(),
+            since: Some(parse_since("2030-01-10T00:00:00Z", Utc::now()).unwrap()),
+        };
+
+        let mut verified = Vec::new();
+        let mut out = Vec::new();
+        let summary = run(
+            &dir,
+            &filter,
+            false,
+            load,
+            |path, _| {
+                verified.push(path.file_name().unwrap().to_owned());
+                Ok(())
+            },
+            &mut out,
+        )
+        .unwrap();
+
+        assert_eq!(
+            summary,
+            BatchSummary {
+                valid: 1,
+                invalid: 0,
+                errored: 1,
+                skipped: 2,
+            }
+        );
+        assert_eq!(verified, ["b-new.red"]);
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert!(lines[0].starts_with("SKIPPED "), "{out}");
+        assert!(lines[0].ends_with("captured 2030-01-01 00:00:00 UTC, before --since"));
+        assert!(lines[1].starts_with("VALID "), "{out}");
+        assert!(lines[3].starts_with("ERRORED "), "{out}");
+        assert_eq!(
+            lines[4],
+            "Summary: 1 valid, 0 invalid, 1 errored, 2 skipped (captured before --since)"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn errored_files_do_not_block_the_rest() {
+        let dir = temp_dir("errors");
+        let json = serde_json::to_vec(&artifact_at(Utc::now())).unwrap();
+        for name in ["a.red", "c-panics.red", "e.red"] {
+            fs::write(dir.join(name), &json).unwrap();
+        }
+        fs::write(dir.join("b-truncated.red"), &json[..json.len() / 2]).unwrap();
+        fs::write(dir.join("d-garbage.red"), [0xff, 0x00, 0x13, 0x37]).unwrap();
+        let filter = BatchFilter {
+            glob: Glob::new("*.red", true).unwrap(),
+            since: None,
+        };
+        let verify = |path: &Path, _: &RedProofArtifact| {
+            if path.ends_with("c-panics.red") {
+                panic!("verifier bug");
+            }
+            Ok(())
+        };
+
+        let mut out = Vec::new();
+        let summary = run(&dir, &filter, false, load, verify, &mut out).unwrap();
+        assert_eq!(
+            summary,
+            BatchSummary {
+                valid: 2,
+                invalid: 0,
+                errored: 3,
+                skipped: 0,
+            }
+        );
+        let out = String::from_utf8(out).unwrap();
+        assert!(
+            out.contains("c-panics.red: verification panicked: verifier bug"),
+            "{out}"
+        );
+        assert!(out.lines().nth(4).unwrap().starts_with("VALID "), "{out}");
+
+        let mut out = Vec::new();
+        let err = run(&dir, &filter, true, load, verify, &mut out).unwrap_err();
+        assert!(err.to_string().contains("b-truncated.red"), "{err}");
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 3, "{out}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parses_timestamps_and_ages() {
+        let now = Utc.with_ymd_and_hms(2030, 1, 10, 12, 0, 0).unwrap();
+        assert_eq!(
+            parse_since("7d", now),
+            Ok(Utc.with_ymd_and_hms(2030, 1, 3, 12, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_since("2030-01-01T01:00:00+01:00", now),
+            Ok(Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap())
+        );
+        assert!(parse_since("7y", now).is_err());
+        assert!(parse_since("yesterday", now).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+//! On-disk memo of integrity-check results, so re-verifying an unchanged
+//! artifact (e.g. a dashboard polling the same files) skips the cryptography.
+//! Entries are plain JSON that anyone able to write the cache directory can
+//! forge, so the cache is only used with `--cache`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+/// Last outcome for one artifact. It only applies while the file content and
+/// the verification context both still match.
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    content_sha256: String,
+    context: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Cache slot for one artifact: where its entry lives and what it must match.
+pub struct VerifyCache {
+    path: PathBuf,
+    content_sha256: String,
+    context: String,
+}
+
+impl VerifyCache {
+    /// Slot in `dir` for the artifact `id` with bytes `content`. `context`
+    /// covers everything else the outcome depends on.
+    pub fn new(dir: &Path, id: &str, content: &[u8], context: String) -> Self {
+        Self {
+            path: dir.join(format!("{}.json", hex_digest(id.as_bytes()))),
+            content_sha256: hex_digest(content),
+            context,
+        }
+    }
+
+    /// `$XDG_CACHE_HOME/redproof`, else `$HOME/.cache/redproof`.
+    pub fn default_dir() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+        Some(base.join("redproof"))
+    }
+
+    /// Outcome of `check`. A stored result is reused while the content hash
+    /// and context are unchanged; otherwise `check` runs and its result
+    /// replaces the entry.
+    pub fn verify(&self, check: impl FnOnce() -> Result<()>) -> Result<()> {
+        if let Some(entry) = read_entry(&self.path) {
+            if entry.content_sha256 == self.content_sha256 && entry.context == self.context {
+                debug!(entry = %self.path.display(), "verification cache hit");
+                return match entry.error {
+                    Some(error) => Err(anyhow!(error)),
+                    None => Ok(()),
+                };
+            }
+        }
+        let outcome = check();
+        let entry = CacheEntry {
+            content_sha256: self.content_sha256.clone(),
+            context: self.context.clone(),
+            error: outcome.as_ref().err().map(|err| err.to_string()),
+        };
+        if let Err(err) = write_entry(&self.path, &entry) {
+            warn!(path = %self.path.display(), "cannot update verification cache: {err:#}");
+        }
+        outcome
+    }
+}
+
+fn read_entry(path: &Path) -> Option<CacheEntry> {
+    let data = fs::read(path).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+fn write_entry(path: &Path, entry: &CacheEntry) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Write then rename so a concurrent reader never sees a partial entry.
+    let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+    fs::write(&tmp, serde_json::to_vec(entry)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn hex_digest(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("redproof-cache-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn hit_skips_recomputation_until_content_changes() {
+        let dir = temp_dir("hit");
+        let runs = Cell::new(0);
+        let check = || {
+            runs.set(runs.get() + 1);
+            Ok(())
+        };
+        let slot =
+            |id, content: &[u8], context: &str| VerifyCache::new(&dir, id, content, context.into());
+        slot("a.red", b"v1", "ctx")
+            .verify(check)
+            .expect("first run");
+        slot("a.red", b"v1", "ctx").verify(check).expect("cached");
+        assert_eq!(runs.get(), 1);
+
+        slot("a.red", b"v2", "ctx")
+            .verify(check)
+            .expect("content changed");
+        assert_eq!(runs.get(), 2);
+        slot("a.red", b"v2", "other")
+            .verify(check)
+            .expect("context changed");
+        assert_eq!(runs.get(), 3);
+        slot("b.red", b"v2", "other")
+            .verify(check)
+            .expect("other artifact");
+        assert_eq!(runs.get(), 4);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failures_are_cached_with_their_reason() {
+        let dir = temp_dir("failure");
+        let runs = Cell::new(0);
+        let check = || {
+            runs.set(runs.get() + 1);
+            Err(anyhow!("handshake digest mismatch"))
+        };
+        let slot = VerifyCache::new(&dir, "a.red", b"v1", "ctx".into());
+        let first = slot.verify(check).unwrap_err();
+        let second = slot.verify(check).unwrap_err();
+        assert_eq!(first.to_string(), second.to_string());
+        assert_eq!(runs.get(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,10 +1,38 @@
+mod batch;
+mod cache;
+mod recompute;
+mod sarif;
+mod schema_check;
+
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{bail, Context, Result};
-use clap::{Parser, ValueEnum};
-use redproof_artifact::{CommitmentAlgorithm, EncodedBlob, RedProofArtifact};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use redproof_artifact::{
+    check_verifier_version, spki_pin, subject_alt_names, ArtifactFormat, CommitmentAlgorithm,
+    CommitmentSet, CommitmentWitness, RedProofArtifact, UnknownArtifactFormat,
+};
+use redproof_logging::LogFormat;
+use redproof_statements::{parse_statement, Glob, Statement};
+use rustls::client::danger::ServerCertVerifier;
+use rustls::client::WebPkiServerVerifier;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::RootCertStore;
+use serde::Deserialize;
 use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::cache::VerifyCache;
+use crate::recompute::WitnessField;
+use crate::sarif::SarifLog;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -12,107 +40,1359 @@ use sha2::{Digest, Sha256};
     about = "Verify RedProof artifacts (JSON or CBOR)."
 )]
 struct Cli {
-    /// Path to the artifact file (.red)
+    /// Path to the artifact file (.red), or a directory whose matching
+    /// artifacts are all verified
     artifact: PathBuf,
 
-    #[arg(long, default_value_t = InputFormat::Auto)]
+    /// File names verified when given a directory (`*` and `?` wildcards).
+    #[arg(long, default_value = "*.red")]
+    glob: String,
+
+    /// With a directory, skip artifacts captured before this RFC 3339
+    /// timestamp or age (`24h`, `7d`); they are counted as skipped.
+    #[arg(long, value_name = "TIME", value_parser = parse_since)]
+    since: Option<DateTime<Utc>>,
+
+    /// With a directory, keep going past files that cannot be read or
+    /// parsed (or whose verification panics), counting them as errored.
+    /// This is the default; the flag is accepted for existing scripts.
+    #[arg(long, conflicts_with = "fail_fast")]
+    continue_on_error: bool,
+
+    /// With a directory, stop at the first file that cannot be read or
+    /// parsed (or whose verification panics) instead of recording it and
+    /// moving on.
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Artifact encoding; `auto` treats files whose first non-whitespace
+    /// byte is `{` as JSON and anything else as CBOR.
+    #[arg(long, visible_alias = "input-format", default_value_t = InputFormat::Auto)]
     format: InputFormat,
+
+    /// Report format on stdout: `text`, or a SARIF 2.1.0 log with one result
+    /// per artifact for code-scanning dashboards.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        conflicts_with = "print_witness"
+    )]
+    format_out: OutputFormat,
+
+    /// Witness sidecar written by `redproof-prove --output-witness-separate`;
+    /// it must match the digest the artifact records.
+    #[arg(long, value_name = "FILE")]
+    witness: Option<PathBuf>,
+
+    /// Reject blobs that are not canonical base64 (missing or extra padding,
+    /// non-zero trailing bits), naming the field. `--strict-base64=false`
+    /// accepts them for interop with sloppy producers.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        default_value_t = true,
+        default_missing_value = "true",
+        action = clap::ArgAction::Set
+    )]
+    strict_base64: bool,
+
+    /// Validate the raw artifact against the published JSON Schema before
+    /// verifying it; violations are reported apart from integrity failures.
+    #[arg(long)]
+    schema_check: bool,
+
+    /// PEM trust anchors; re-validates the embedded certificate chain as of
+    /// the capture time.
+    #[arg(long)]
+    trust_store: Option<PathBuf>,
+
+    /// Detached Ed25519 signature over the artifact's canonical CBOR
+    /// encoding: 64 raw bytes or base64 text.
+    #[arg(long, value_name = "FILE", requires = "pubkey")]
+    signature: Option<PathBuf>,
+
+    /// Ed25519 public key for `--signature`: 32 raw bytes, base64, or a PEM
+    /// `PUBLIC KEY`.
+    #[arg(long, value_name = "FILE", requires = "signature")]
+    pubkey: Option<PathBuf>,
+
+    /// Accept artifacts past their prover-declared `expires_at`.
+    #[arg(long)]
+    allow_expired: bool,
+
+    /// Accept artifacts captured over plaintext HTTP (`tls.version =
+    /// "none"`), which bind nothing to the domain.
+    #[arg(long)]
+    allow_plaintext: bool,
+
+    /// Only accept artifacts whose statement is listed in this file: one
+    /// statement fingerprint (`sha256:...`) or expression per line, `#` at
+    /// line start or after whitespace starts a comment.
+    #[arg(long, value_name = "FILE")]
+    allowed_statements: Option<PathBuf>,
+
+    /// Ignore stored digests: rebuild every field from the witness,
+    /// re-evaluate the statement and report each disagreement.
+    #[arg(long)]
+    recompute_all: bool,
+
+    /// After successful verification, write this part of the witness to
+    /// stdout (replacing the report) or to `--witness-out`.
+    #[arg(long, value_enum, value_name = "FIELD")]
+    print_witness: Option<WitnessField>,
+
+    /// File for `--print-witness` output.
+    #[arg(long, value_name = "FILE", requires = "print_witness")]
+    witness_out: Option<PathBuf>,
+
+    /// Reuse the cached result for an unchanged artifact instead of
+    /// re-running the cryptographic checks. Cache entries are not
+    /// authenticated: only enable this when nobody else can write to the
+    /// cache directory.
+    #[arg(long, conflicts_with = "no_cache")]
+    cache: bool,
+
+    /// Always run the full cryptographic checks. This is the default; the
+    /// flag is accepted for existing scripts.
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Directory for cached verification results, with `--cache` [default:
+    /// $XDG_CACHE_HOME/redproof or ~/.cache/redproof]
+    #[arg(long, value_name = "DIR", requires = "cache")]
+    cache_dir: Option<PathBuf>,
+
+    /// Format of diagnostic logs on stderr.
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Log more (repeatable: -v info, -vv debug, -vvv trace).
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log less (repeatable: -q errors only, -qq silent).
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "verbose")]
+    quiet: u8,
 }
 
-#[derive(Copy, Clone, Debug, ValueEnum)]
+/// This release; artifacts stamped with a newer `meta.min_verifier_version`
+/// are refused.
+const VERIFIER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Exit status for an artifact that fails verification.
+const EXIT_INVALID: i32 = 2;
+
+/// Exit status for an artifact that violates the schema (`--schema-check`).
+const EXIT_SCHEMA_VIOLATION: i32 = 3;
+
+/// `auto` or any [`ArtifactFormat`]; parsing defers to the shared enum so new
+/// formats are picked up without changes here.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum InputFormat {
     Auto,
-    Json,
-    Cbor,
+    Explicit(ArtifactFormat),
+}
+
+impl FromStr for InputFormat {
+    type Err = UnknownArtifactFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("auto") {
+            Ok(InputFormat::Auto)
+        } else {
+            s.parse().map(InputFormat::Explicit)
+        }
+    }
+}
+
+/// `--format-out`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    Text,
+    Sarif,
+}
+
+fn parse_since(value: &str) -> Result<DateTime<Utc>, String> {
+    batch::parse_since(value, Utc::now())
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    redproof_logging::init(cli.log_format, cli.verbose, cli.quiet);
+    let (mut options, cache_context) = verify_options(&cli)?;
+    if cli.artifact.is_dir() {
+        return run_batch(&cli, &mut options, &cache_context);
+    }
+    if cli.since.is_some() || cli.continue_on_error || cli.fail_fast {
+        bail!("--since, --continue-on-error and --fail-fast apply to a directory of artifacts");
+    }
     let data = fs::read(&cli.artifact)
         .with_context(|| format!("failed to read {}", cli.artifact.display()))?;
+    if cli.format_out == OutputFormat::Sarif {
+        return run_sarif(&cli, &mut options, &cache_context, &data);
+    }
+    if cli.schema_check {
+        let violations = schema_violations(&data, cli.format)?;
+        if !violations.is_empty() {
+            for violation in &violations {
+                println!("SCHEMA VIOLATION: {violation}");
+            }
+            std::process::exit(EXIT_SCHEMA_VIOLATION);
+        }
+    }
     let artifact = load_artifact(&data, cli.format)?;
-    match verify_artifact(&artifact) {
+    options.cache = verify_cache(&cli, &cache_context, &cli.artifact, &data);
+    match verify_artifact(&artifact, &options) {
         Ok(()) => {
+            if let Some(field) = cli.print_witness {
+                let artifact = with_sidecar(&artifact, &options)?;
+                return write_witness(&artifact, field, cli.witness_out.as_deref());
+            }
             println!("VALID");
             println!("Domain: {}", artifact.domain);
+            let witnessed = with_sidecar(&artifact, &options)?;
+            if let Some(source) = recompute::committed_source(&witnessed)? {
+                println!("Source: {source} (not a live capture)");
+            }
             println!("Statement: {}", artifact.statement.summary());
             println!(
-                "Commitments: {:?} (witness={})",
-                artifact.commitments.algorithm,
-                artifact.commitments.witness.is_some()
+                "Statement fingerprint: {}",
+                artifact.statement.fingerprint()
+            );
+            println!("Claim ID: {}", artifact.claim_id());
+            if options.detached_signature.is_some() {
+                println!("Detached signature: valid");
+            }
+            let algorithms = artifact
+                .commitments
+                .algorithms()
+                .iter()
+                .map(|alg| format!("{:?}", alg))
+                .collect::<Vec<_>>();
+            println!(
+                "Commitments: {} (witness={})",
+                algorithms.join(", "),
+                artifact.commitments.witness.is_some() || options.witness.is_some()
             );
+            let provenance = artifact.provenance();
+            if !provenance.is_empty() {
+                let actions = provenance
+                    .iter()
+                    .map(|entry| entry.action.as_str())
+                    .collect::<Vec<_>>();
+                println!(
+                    "Provenance: {} step(s) ({})",
+                    provenance.len(),
+                    actions.join(" -> ")
+                );
+            }
+            if let Some(headers) = &artifact.commitments.headers {
+                for header in &headers.revealed {
+                    println!("Disclosed header: {}: {}", header.name, header.value);
+                }
+            }
         }
         Err(err) => {
             println!("INVALID: {err}");
+            std::process::exit(EXIT_INVALID);
+        }
+    }
+    Ok(())
+}
+
+/// `--format-out sarif` for a single artifact: one result, with the exit
+/// status the text report would have had.
+fn run_sarif(
+    cli: &Cli,
+    options: &mut VerifyOptions,
+    cache_context: &str,
+    data: &[u8],
+) -> Result<()> {
+    let mut exit = None;
+    let (artifact, outcome) = match load_artifact(data, cli.format) {
+        Err(err) => {
+            exit = Some(EXIT_INVALID);
+            (None, batch::Outcome::Errored(err))
         }
+        Ok(artifact) => {
+            let violations = if cli.schema_check {
+                schema_violations(data, cli.format)?
+            } else {
+                Vec::new()
+            };
+            let outcome = if !violations.is_empty() {
+                exit = Some(EXIT_SCHEMA_VIOLATION);
+                batch::Outcome::Invalid(anyhow!("schema violation: {}", violations.join("; ")))
+            } else {
+                options.cache = verify_cache(cli, cache_context, &cli.artifact, data);
+                match verify_artifact(&artifact, options) {
+                    Ok(()) => batch::Outcome::Valid,
+                    Err(err) => {
+                        exit = Some(EXIT_INVALID);
+                        batch::Outcome::Invalid(err)
+                    }
+                }
+            };
+            (Some(artifact), outcome)
+        }
+    };
+    let mut log = SarifLog::default();
+    batch::BatchReport::file(&mut log, &cli.artifact, artifact.as_ref(), &outcome)?;
+    print_sarif(&log)?;
+    if let Some(code) = exit {
+        std::process::exit(code);
     }
     Ok(())
 }
 
+fn print_sarif(log: &SarifLog) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    serde_json::to_writer_pretty(&mut out, &log.to_json())?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Options shared by every artifact of a run, without a cache, plus the
+/// cache context they imply.
+fn verify_options(cli: &Cli) -> Result<(VerifyOptions, String)> {
+    let mut trust_store_digest = None;
+    let trust_roots = match &cli.trust_store {
+        Some(path) => {
+            let pem =
+                fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+            trust_store_digest = Some(format!("{:x}", Sha256::digest(&pem)));
+            Some(load_trust_store(&pem)?)
+        }
+        None => None,
+    };
+    let detached_signature = match (&cli.signature, &cli.pubkey) {
+        (Some(signature), Some(pubkey)) => Some(DetachedSignature {
+            signature: fs::read(signature)
+                .with_context(|| format!("failed to read {}", signature.display()))?,
+            public_key: fs::read(pubkey)
+                .with_context(|| format!("failed to read {}", pubkey.display()))?,
+        }),
+        _ => None,
+    };
+    let mut witness_digest = None;
+    let witness = match &cli.witness {
+        Some(path) => {
+            let data =
+                fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+            witness_digest = Some(format!("{:x}", Sha256::digest(&data)));
+            Some(
+                serde_json::from_slice::<CommitmentWitness>(&data)
+                    .with_context(|| format!("unable to parse witness {}", path.display()))?,
+            )
+        }
+        None => None,
+    };
+    let allowed_statements = match &cli.allowed_statements {
+        Some(path) => {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            Some(StatementAllowList::parse(&text)?)
+        }
+        None => None,
+    };
+    // Everything besides the artifact bytes that the integrity outcome
+    // depends on; a change here invalidates cached results.
+    let cache_context = format!(
+        "{} recompute_all={} strict_base64={} trust_store={} witness={} signature={}",
+        env!("CARGO_PKG_VERSION"),
+        cli.recompute_all,
+        cli.strict_base64,
+        trust_store_digest.as_deref().unwrap_or("none"),
+        witness_digest.as_deref().unwrap_or("none"),
+        detached_signature
+            .as_ref()
+            .map_or("none".to_string(), DetachedSignature::digest)
+    );
+    let options = VerifyOptions {
+        trust_roots,
+        allow_expired: cli.allow_expired,
+        allow_plaintext: cli.allow_plaintext,
+        now: Utc::now(),
+        allowed_statements,
+        recompute_all: cli.recompute_all,
+        strict_base64: cli.strict_base64,
+        detached_signature,
+        witness,
+        cache: None,
+    };
+    Ok((options, cache_context))
+}
+
+/// The result cache for the artifact at `path`, with `--cache`.
+fn verify_cache(cli: &Cli, context: &str, path: &Path, data: &[u8]) -> Option<VerifyCache> {
+    if !cli.cache {
+        return None;
+    }
+    let dir = cli.cache_dir.clone().or_else(VerifyCache::default_dir)?;
+    let id = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    Some(VerifyCache::new(
+        &dir,
+        &id.to_string_lossy(),
+        data,
+        context.to_string(),
+    ))
+}
+
+/// Verify every artifact in the `cli.artifact` directory; exits with
+/// [`EXIT_INVALID`] when any fails.
+fn run_batch(cli: &Cli, options: &mut VerifyOptions, cache_context: &str) -> Result<()> {
+    if options.detached_signature.is_some() || cli.print_witness.is_some() || cli.witness.is_some()
+    {
+        bail!("--signature, --witness and --print-witness apply to a single artifact");
+    }
+    let filter = batch::BatchFilter {
+        glob: Glob::new(&cli.glob, true).context("invalid --glob pattern")?,
+        since: cli.since,
+    };
+    let load = |path: &Path| {
+        let data = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        load_artifact(&data, cli.format)
+    };
+    let verify = |path: &Path, artifact: &RedProofArtifact| {
+        let data = fs::read(path)?;
+        if cli.schema_check {
+            let violations = schema_violations(&data, cli.format)?;
+            if !violations.is_empty() {
+                bail!("schema violation: {}", violations.join("; "));
+            }
+        }
+        options.cache = verify_cache(cli, cache_context, path, &data);
+        verify_artifact(artifact, options)
+    };
+    let summary = match cli.format_out {
+        OutputFormat::Text => batch::run(
+            &cli.artifact,
+            &filter,
+            cli.fail_fast,
+            load,
+            verify,
+            &mut std::io::stdout().lock(),
+        )?,
+        OutputFormat::Sarif => {
+            // Print what was verified even when the run stops early.
+            let mut log = SarifLog::default();
+            let summary = batch::run(
+                &cli.artifact,
+                &filter,
+                cli.fail_fast,
+                load,
+                verify,
+                &mut log,
+            );
+            print_sarif(&log)?;
+            summary?
+        }
+    };
+    if summary.invalid > 0 || summary.errored > 0 {
+        std::process::exit(EXIT_INVALID);
+    }
+    Ok(())
+}
+
+/// Extract `field` from the witness to `out`, or stdout.
+fn write_witness(
+    artifact: &RedProofArtifact,
+    field: WitnessField,
+    out: Option<&Path>,
+) -> Result<()> {
+    let data = recompute::extract(artifact, field)?;
+    match out {
+        Some(path) => {
+            fs::write(path, &data).with_context(|| format!("failed to write {}", path.display()))
+        }
+        None => Ok(std::io::stdout().write_all(&data)?),
+    }
+}
+
 fn load_artifact(data: &[u8], format: InputFormat) -> Result<RedProofArtifact> {
+    ensure_supported(data, format, VERIFIER_VERSION)?;
+    match artifact_format(data, format) {
+        ArtifactFormat::Json => {
+            serde_json::from_slice(json_text(data)).context("unable to parse artifact as JSON")
+        }
+        ArtifactFormat::Cbor => {
+            serde_cbor::from_slice(data).context("unable to parse artifact as CBOR")
+        }
+    }
+}
+
+/// The part of an artifact read before anything else.
+#[derive(Deserialize)]
+struct Requirements {
+    #[serde(default)]
+    meta: MetaRequirements,
+}
+
+#[derive(Default, Deserialize)]
+struct MetaRequirements {
+    min_verifier_version: Option<String>,
+}
+
+/// Refuse `data` when its `meta.min_verifier_version` is newer than
+/// `running`. This runs before the statement is parsed, which an older
+/// release may not be able to do at all; data that does not even parse this
+/// far is left for the full load to report.
+fn ensure_supported(data: &[u8], format: InputFormat, running: &str) -> Result<()> {
+    let requirements = match artifact_format(data, format) {
+        ArtifactFormat::Json => serde_json::from_slice::<Requirements>(json_text(data)).ok(),
+        ArtifactFormat::Cbor => serde_cbor::from_slice::<Requirements>(data).ok(),
+    };
+    if let Some(required) = requirements.and_then(|r| r.meta.min_verifier_version) {
+        check_verifier_version(&required, running)?;
+    }
+    Ok(())
+}
+
+/// `--schema-check` findings for `data`; see [`schema_check::violations`].
+fn schema_violations(data: &[u8], format: InputFormat) -> Result<Vec<String>> {
+    ensure_supported(data, format, VERIFIER_VERSION)?;
+    match artifact_format(data, format) {
+        ArtifactFormat::Json => schema_check::violations(json_text(data), ArtifactFormat::Json),
+        ArtifactFormat::Cbor => schema_check::violations(data, ArtifactFormat::Cbor),
+    }
+}
+
+/// The encoding of `data` under `format`.
+fn artifact_format(data: &[u8], format: InputFormat) -> ArtifactFormat {
     match format {
-        InputFormat::Json => Ok(serde_json::from_slice(data)?),
-        InputFormat::Cbor => Ok(serde_cbor::from_slice(data)?),
-        InputFormat::Auto => serde_json::from_slice(data)
-            .or_else(|_| serde_cbor::from_slice(data))
-            .context("unable to parse artifact as JSON or CBOR"),
-    }
-}
-
-fn verify_artifact(artifact: &RedProofArtifact) -> Result<()> {
-    artifact.validate()?;
-    if let Some(witness) = &artifact.commitments.witness {
-        let handshake = witness.handshake.decode()?;
-        let app_data = witness.app_data.decode()?;
-        ensure_digest(
-            &artifact.commitments.algorithm,
-            &handshake,
-            &artifact.commitments.handshake,
-            "handshake",
-        )?;
-        ensure_digest(
-            &artifact.commitments.algorithm,
-            &app_data,
-            &artifact.commitments.app_data,
-            "app-data",
+        InputFormat::Explicit(format) => format,
+        InputFormat::Auto if json_text(data).starts_with(b"{") => ArtifactFormat::Json,
+        InputFormat::Auto => ArtifactFormat::Cbor,
+    }
+}
+
+/// `data` without a leading UTF-8 byte-order mark and whitespace.
+fn json_text(data: &[u8]) -> &[u8] {
+    let data = data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(data);
+    let start = data
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(data.len());
+    &data[start..]
+}
+
+struct VerifyOptions {
+    trust_roots: Option<RootCertStore>,
+    allow_expired: bool,
+    allow_plaintext: bool,
+    /// Reference time for expiry checks.
+    now: DateTime<Utc>,
+    allowed_statements: Option<StatementAllowList>,
+    recompute_all: bool,
+    /// Refuse non-canonical base64 rather than rewriting it canonically.
+    strict_base64: bool,
+    /// Sidecar signature the artifact must carry.
+    detached_signature: Option<DetachedSignature>,
+    /// Witness shipped apart from the artifact.
+    witness: Option<CommitmentWitness>,
+    /// Reuse the integrity outcome of an earlier run on the same artifact.
+    cache: Option<VerifyCache>,
+}
+
+/// Contents of the `--signature` and `--pubkey` files.
+struct DetachedSignature {
+    signature: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+impl DetachedSignature {
+    /// Identifies the sidecar pair in the cache context.
+    fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.public_key);
+        hasher.update(&self.signature);
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// Statements an operator accepts, by fingerprint.
+struct StatementAllowList {
+    fingerprints: HashSet<String>,
+}
+
+impl StatementAllowList {
+    /// One fingerprint or expression per line. `#` starts a comment at the
+    /// beginning of a line or after whitespace, so expressions containing
+    /// `#` (`header:eq:Link:"</#top>"`) survive. Expressions are parsed and
+    /// normalized, so equivalent spellings share a fingerprint.
+    fn parse(text: &str) -> Result<Self> {
+        let mut fingerprints = HashSet::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let context = || format!("allow-list line {}", idx + 1);
+            let fingerprint = if let Some(hex) = line.strip_prefix("sha256:") {
+                if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err(anyhow!("malformed statement fingerprint '{line}'"))
+                        .with_context(context);
+                }
+                format!("sha256:{}", hex.to_ascii_lowercase())
+            } else {
+                parse_statement(line)
+                    .with_context(context)?
+                    .normalize()
+                    .with_context(context)?
+                    .fingerprint()
+            };
+            fingerprints.insert(fingerprint);
+        }
+        Ok(Self { fingerprints })
+    }
+
+    /// Accepts a statement listed as-is or in normalized form.
+    fn check(&self, statement: &Statement) -> Result<()> {
+        let fingerprint = statement.fingerprint();
+        let normalized = statement
+            .clone()
+            .normalize()
+            .map(|statement| statement.fingerprint());
+        let listed = |fingerprint: &String| self.fingerprints.contains(fingerprint);
+        if !listed(&fingerprint) && !normalized.as_ref().is_ok_and(listed) {
+            bail!(
+                "statement not on the allow-list: {} ({fingerprint})",
+                statement.summary()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// `line` up to a `#` that opens it or follows whitespace.
+fn strip_comment(line: &str) -> &str {
+    let mut previous = None;
+    for (idx, c) in line.char_indices() {
+        if c == '#' && previous.is_none_or(char::is_whitespace) {
+            return &line[..idx];
+        }
+        previous = Some(c);
+    }
+    line
+}
+
+fn verify_artifact(artifact: &RedProofArtifact, options: &VerifyOptions) -> Result<()> {
+    check_acceptance(artifact, options)?;
+    match &options.cache {
+        Some(cache) => cache.verify(|| verify_integrity(artifact, options)),
+        None => verify_integrity(artifact, options),
+    }
+}
+
+/// `artifact` with the `--witness` sidecar attached, which must match the
+/// recorded digest and reproduce the commitments.
+fn with_sidecar<'a>(
+    artifact: &'a RedProofArtifact,
+    options: &VerifyOptions,
+) -> Result<Cow<'a, RedProofArtifact>> {
+    Ok(match &options.witness {
+        Some(witness) => Cow::Owned(artifact.clone().attach_witness(witness.clone())?),
+        None => Cow::Borrowed(artifact),
+    })
+}
+
+/// `artifact` itself, or under `--strict-base64=false` a copy with its
+/// non-canonical blobs rewritten canonically.
+fn canonical_base64<'a>(
+    artifact: &'a RedProofArtifact,
+    options: &VerifyOptions,
+) -> Cow<'a, RedProofArtifact> {
+    if options.strict_base64 {
+        return Cow::Borrowed(artifact);
+    }
+    let mut canonical = artifact.clone();
+    let rewritten = canonical.canonicalize_base64();
+    if rewritten == 0 {
+        return Cow::Borrowed(artifact);
+    }
+    warn!(
+        rewritten,
+        "accepted non-canonical base64 blobs via --strict-base64=false"
+    );
+    Cow::Owned(canonical)
+}
+
+/// Checks that depend on the operator's policy or the clock rather than the
+/// artifact alone. They are cheap and never cached.
+fn check_acceptance(artifact: &RedProofArtifact, options: &VerifyOptions) -> Result<()> {
+    if artifact.tls.is_plaintext() {
+        if !options.allow_plaintext {
+            bail!(
+                "artifact was captured over plaintext HTTP and binds nothing to the domain; \
+                 pass --allow-plaintext to accept it"
+            );
+        }
+        warn!("plaintext artifact accepted via --allow-plaintext; nothing binds it to the domain");
+    }
+    if artifact.commitments.statement.is_none() {
+        warn!("no statement commitment; the statement is not bound to the captured data");
+    }
+    if let Some(allowed) = &options.allowed_statements {
+        allowed.check(&artifact.statement)?;
+    }
+    verify_expiry(artifact, options)
+}
+
+/// Structural and cryptographic checks. The outcome depends only on the
+/// artifact, the trust store and `recompute_all`, so it may be cached.
+fn verify_integrity(artifact: &RedProofArtifact, options: &VerifyOptions) -> Result<()> {
+    let canonical = canonical_base64(artifact, options);
+    canonical.validate()?;
+    recompute::Canonicalizer::select(&artifact.commitments.canonicalization)?;
+    artifact.verify_provenance()?;
+    if let Some(detached) = &options.detached_signature {
+        artifact.verify_detached(&detached.public_key, &detached.signature)?;
+    }
+    // Signatures and provenance cover the artifact as distributed; the
+    // remaining checks need canonical blobs and the witness.
+    let artifact = with_sidecar(&canonical, options)?;
+    let artifact = artifact.as_ref();
+    verify_commitments(&artifact.commitments, &CommitmentAlgorithm::registered())?;
+    if let Some(headers) = &artifact.commitments.headers {
+        headers.verify(
+            artifact.commitments.algorithm,
+            &artifact.commitments.salt_bytes()?,
         )?;
-    } else {
-        println!("warning: no witness included; commitment verification skipped");
+    }
+    if let Some(roots) = &options.trust_roots {
+        verify_cert_chain(artifact, roots.clone())?;
+    }
+    if options.recompute_all {
+        let found = recompute::discrepancies(artifact)?;
+        if !found.is_empty() {
+            bail!(
+                "{} discrepancies against the witness: {}",
+                found.len(),
+                found.join("; ")
+            );
+        }
     }
     Ok(())
 }
 
-fn ensure_digest(
-    algorithm: &CommitmentAlgorithm,
-    data: &[u8],
-    expected: &EncodedBlob,
-    label: &str,
-) -> Result<()> {
-    let actual = hash_bytes(algorithm, data);
-    if actual.0 != expected.0 {
-        bail!("{label} digest mismatch");
+fn verify_expiry(artifact: &RedProofArtifact, options: &VerifyOptions) -> Result<()> {
+    let Some(expires_at) = artifact.expires_at else {
+        return Ok(());
+    };
+    if options.now < expires_at {
+        return Ok(());
     }
-    Ok(())
+    if options.allow_expired {
+        warn!(%expires_at, "artifact expired; accepted via --allow-expired");
+        return Ok(());
+    }
+    bail!("artifact expired at {expires_at}");
+}
+
+fn load_trust_store(pem: &[u8]) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut &pem[..]) {
+        let cert = cert.context("invalid PEM in trust store")?;
+        roots
+            .add(cert)
+            .map_err(|err| anyhow!("unusable trust anchor: {err}"))?;
+    }
+    if roots.is_empty() {
+        bail!("trust store contains no certificates");
+    }
+    Ok(roots)
 }
 
-fn hash_bytes(algo: &CommitmentAlgorithm, data: &[u8]) -> EncodedBlob {
-    match algo {
-        CommitmentAlgorithm::Blake3 => {
-            let digest = blake3::hash(data);
-            EncodedBlob::from_bytes(digest.as_bytes())
+/// Check the embedded chain matches the recorded fingerprints, then that it
+/// builds to one of `roots` for the artifact domain at capture time.
+fn verify_cert_chain(artifact: &RedProofArtifact, roots: RootCertStore) -> Result<()> {
+    let tls = &artifact.tls;
+    if tls.cert_chain.is_empty() {
+        bail!("artifact has no embedded certificate chain");
+    }
+    let chain = tls
+        .cert_chain
+        .iter()
+        .map(|cert| cert.decode().map(CertificateDer::from))
+        .collect::<Result<Vec<_>, _>>()?;
+    if chain.len() != tls.cert_fingerprints.len() {
+        bail!("embedded certificate chain does not match recorded fingerprints");
+    }
+    for (cert, fingerprint) in chain.iter().zip(&tls.cert_fingerprints) {
+        if format!("sha256:{:x}", Sha256::digest(cert.as_ref())) != *fingerprint {
+            bail!("embedded certificate does not match fingerprint {fingerprint}");
         }
-        CommitmentAlgorithm::Sha256 => {
-            let digest = Sha256::digest(data);
-            EncodedBlob::from_bytes(&digest)
+    }
+    if !tls.spki_pins.is_empty() {
+        let pins = chain
+            .iter()
+            .map(|cert| spki_pin(cert.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if pins != tls.spki_pins {
+            bail!("embedded certificate chain does not match recorded SPKI pins");
         }
     }
+    if !tls.subject_alt_names.is_empty()
+        && subject_alt_names(chain[0].as_ref())? != tls.subject_alt_names
+    {
+        bail!("embedded leaf certificate does not match recorded subject alt names");
+    }
+
+    let verifier = WebPkiServerVerifier::builder_with_provider(
+        Arc::new(roots),
+        Arc::new(rustls::crypto::ring::default_provider()),
+    )
+    .build()?;
+    let server_name = ServerName::try_from(artifact.domain.clone())
+        .map_err(|_| anyhow!("invalid DNS name {}", artifact.domain))?;
+    let captured_at = u64::try_from(artifact.time_utc.timestamp()).unwrap_or_default();
+    verifier
+        .verify_server_cert(
+            &chain[0],
+            &chain[1..],
+            &server_name,
+            &[],
+            UnixTime::since_unix_epoch(Duration::from_secs(captured_at)),
+        )
+        .map_err(|err| anyhow!("certificate chain not trusted: {err}"))?;
+    Ok(())
+}
+
+/// Recompute every commitment whose algorithm is in `supported`; at least one
+/// must be recognized.
+fn verify_commitments(
+    commitments: &CommitmentSet,
+    supported: &[CommitmentAlgorithm],
+) -> Result<()> {
+    let recognized = commitments
+        .algorithms()
+        .into_iter()
+        .filter(|alg| supported.contains(alg))
+        .collect::<Vec<_>>();
+    if recognized.is_empty() {
+        bail!(
+            "no supported commitment algorithm (artifact has {:?})",
+            commitments.algorithms()
+        );
+    }
+    if commitments.witness.is_none() {
+        warn!("no witness included; commitment verification skipped");
+        return Ok(());
+    }
+    for algorithm in recognized {
+        commitments.verify_witness(algorithm)?;
+    }
+    Ok(())
 }
+
 impl std::fmt::Display for InputFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self {
-            InputFormat::Auto => "auto",
-            InputFormat::Json => "json",
-            InputFormat::Cbor => "cbor",
-        })
+        match self {
+            InputFormat::Auto => f.write_str("auto"),
+            InputFormat::Explicit(format) => format.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use base64::engine::general_purpose::STANDARD as B64;
+    use base64::Engine;
+    use chrono::{TimeZone, Utc};
+    use redproof_artifact::{
+        ArtifactMeta, CommitmentDigests, CommitmentWitness, EncodedBlob, TlsProofContext,
+        CANONICALIZATION_JSON_V1, CANONICALIZATION_JSON_V2,
+    };
+    use redproof_statements::Statement;
+    use ring::signature::KeyPair;
+
+    use super::*;
+
+    fn hash_bytes(algo: &CommitmentAlgorithm, data: &[u8]) -> EncodedBlob {
+        EncodedBlob::from_bytes(&algo.digest(data))
+    }
+
+    const ROOT_PEM: &[u8] = include_bytes!("../testdata/root.pem");
+    const OTHER_ROOT_PEM: &[u8] = include_bytes!("../testdata/other-root.pem");
+    const LEAF_PEM: &[u8] = include_bytes!("../testdata/leaf.pem");
+
+    fn artifact_with_chain() -> RedProofArtifact {
+        let leaf = rustls_pemfile::certs(&mut &LEAF_PEM[..])
+            .next()
+            .expect("leaf cert")
+            .expect("valid pem");
+        let mut artifact = RedProofArtifact {
+            version: "1.0".into(),
+            domain: "example.test".into(),
+            time_utc: Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap(),
+            tls: TlsProofContext {
+                version: "TLS1.3".into(),
+                cipher: "TLS13_AES_128_GCM_SHA256".into(),
+                cert_fingerprints: vec![format!("sha256:{:x}", Sha256::digest(leaf.as_ref()))],
+                alpn: None,
+                cert_chain: vec![EncodedBlob::from_bytes(leaf.as_ref())],
+                spki_pins: vec![],
+                subject_alt_names: vec![],
+                chain_size: None,
+            },
+            statement: Statement::HeaderPresent {
+                target: "Server".into(),
+            },
+            commitments: dual_commitments(),
+            proof: EncodedBlob::from_bytes(b"proof"),
+            expires_at: None,
+            meta: ArtifactMeta::default(),
+        };
+        artifact.bind_statement().expect("statement binds");
+        artifact
+    }
+
+    fn options_at(now: DateTime<Utc>) -> VerifyOptions {
+        VerifyOptions {
+            trust_roots: None,
+            allow_expired: false,
+            allow_plaintext: false,
+            now,
+            allowed_statements: None,
+            recompute_all: false,
+            strict_base64: true,
+            detached_signature: None,
+            witness: None,
+            cache: None,
+        }
+    }
+
+    #[test]
+    fn strict_base64_names_the_non_canonical_blob() {
+        let mut artifact = artifact_with_chain();
+        verify_artifact(&artifact, &options_at(Utc::now())).expect("canonical artifact");
+        // `proof` is `cHJvb2Y=`; drop the padding.
+        artifact.proof = EncodedBlob("cHJvb2Y".into());
+        let err = verify_artifact(&artifact, &options_at(Utc::now())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "proof is not canonical base64 (padding or trailing bits)"
+        );
+        let lenient = VerifyOptions {
+            strict_base64: false,
+            ..options_at(Utc::now())
+        };
+        verify_artifact(&artifact, &lenient).expect("accepted leniently");
+
+        artifact.proof = EncodedBlob("not base64!".into());
+        let err = verify_artifact(&artifact, &lenient).unwrap_err();
+        assert_eq!(err.to_string(), "proof is not valid base64 data");
+    }
+
+    #[test]
+    fn allow_list_accepts_listed_statements_only() {
+        let artifact = artifact_with_chain();
+        let listed = format!(
+            "# approved claims\nheader:absent:X-Powered-By\n{}  # sample\n",
+            artifact.statement.fingerprint()
+        );
+        let options = VerifyOptions {
+            allowed_statements: Some(StatementAllowList::parse(&listed).expect("allow-list")),
+            ..options_at(Utc::now())
+        };
+        verify_artifact(&artifact, &options).expect("fingerprint listed");
+
+        let by_expression = VerifyOptions {
+            allowed_statements: Some(
+                StatementAllowList::parse("header:absent:X-Powered-By").expect("allow-list"),
+            ),
+            ..options_at(Utc::now())
+        };
+        let mut other = artifact.clone();
+        other.statement = parse_statement("header:absent:X-Powered-By").unwrap();
+        other.bind_statement().unwrap();
+        verify_artifact(&other, &by_expression).expect("expression listed");
+
+        let err = verify_artifact(&artifact, &by_expression).unwrap_err();
+        assert!(err.to_string().contains("not on the allow-list"));
+    }
+
+    #[test]
+    fn allow_list_keeps_hashes_inside_expressions_and_normalizes() {
+        let list = StatementAllowList::parse(
+            "header:eq:Link:\"</#top>\"  # anchors\n\
+             all:(header:present:Server,all:(header:present:Server,header:absent:Via))\n",
+        )
+        .expect("allow-list");
+        let link = parse_statement("header:eq:Link:\"</#top>\"").unwrap();
+        list.check(&link).expect("expression with # listed");
+
+        // Listed in a redundant spelling; the artifact carries the flat one.
+        let flat = parse_statement("all:(header:present:Server,header:absent:Via)").unwrap();
+        list.check(&flat).expect("normalized spelling listed");
+
+        assert!(StatementAllowList::parse("sha256:abc").is_err());
+    }
+
+    fn dual_commitments() -> CommitmentSet {
+        let handshake = b"handshake".to_vec();
+        let app_data = b"app".to_vec();
+        let mut alternates = BTreeMap::new();
+        alternates.insert(
+            CommitmentAlgorithm::Sha256,
+            CommitmentDigests {
+                handshake: hash_bytes(&CommitmentAlgorithm::Sha256, &handshake),
+                app_data: hash_bytes(&CommitmentAlgorithm::Sha256, &app_data),
+            },
+        );
+        CommitmentSet {
+            algorithm: CommitmentAlgorithm::Blake3,
+            canonicalization: CANONICALIZATION_JSON_V2.into(),
+            salt: None,
+            handshake: hash_bytes(&CommitmentAlgorithm::Blake3, &handshake),
+            app_data: hash_bytes(&CommitmentAlgorithm::Blake3, &app_data),
+            alternates,
+            headers: None,
+            witness: Some(CommitmentWitness {
+                handshake: EncodedBlob::from_bytes(&handshake),
+                app_data: EncodedBlob::from_bytes(&app_data),
+                header_nonces: Vec::new(),
+            }),
+            statement: None,
+            witness_digest: None,
+        }
+    }
+
+    #[test]
+    fn rejects_statement_swapped_after_binding() {
+        let mut artifact = artifact_with_chain();
+        verify_artifact(&artifact, &options_at(Utc::now())).expect("bound statement");
+        artifact.statement = parse_statement("header:absent:Server").unwrap();
+        let err = verify_artifact(&artifact, &options_at(Utc::now())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "statement does not match the statement commitment"
+        );
+    }
+
+    #[test]
+    fn rebinding_a_swapped_statement_is_only_caught_by_a_signature() {
+        let artifact = artifact_with_chain();
+        let options = signed_options(&artifact);
+        let mut forged = artifact;
+        forged.statement = parse_statement("header:absent:Server").unwrap();
+        forged.bind_statement().unwrap();
+        // The unkeyed binding cannot tell the attacker's digest from the
+        // prover's.
+        verify_artifact(&forged, &options_at(Utc::now())).expect("rebound statement");
+        let err = verify_artifact(&forged, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "detached signature does not match the artifact"
+        );
+    }
+
+    #[test]
+    fn requires_statement_commitment_from_json_v2() {
+        let mut artifact = artifact_with_chain();
+        artifact.commitments.statement = None;
+        let err = verify_artifact(&artifact, &options_at(Utc::now())).unwrap_err();
+        assert_eq!(err.to_string(), "artifact has no statement commitment");
+
+        artifact.commitments.canonicalization = CANONICALIZATION_JSON_V1.into();
+        verify_artifact(&artifact, &options_at(Utc::now())).expect("older artifact");
+    }
+
+    #[test]
+    fn split_artifact_verifies_with_its_own_sidecar_only() {
+        let mut artifact = artifact_with_chain();
+        let witness = artifact.detach_witness().unwrap().expect("witness");
+        let with_sidecar = |witness: CommitmentWitness| VerifyOptions {
+            witness: Some(witness),
+            ..options_at(Utc::now())
+        };
+        verify_artifact(&artifact, &with_sidecar(witness.clone())).expect("matching sidecar");
+
+        let mut other = artifact_with_chain();
+        other.commitments.witness.as_mut().unwrap().app_data = EncodedBlob::from_bytes(b"other");
+        let swapped = other.detach_witness().unwrap().expect("witness");
+        let err = verify_artifact(&artifact, &with_sidecar(swapped)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "witness does not match the recorded sidecar digest"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_canonicalization() {
+        let mut artifact = artifact_with_chain();
+        artifact.commitments.canonicalization = "cbor/v9".into();
+        let err = verify_artifact(&artifact, &options_at(Utc::now())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown commitment canonicalization 'cbor/v9'"
+        );
+    }
+
+    #[test]
+    fn rejects_artifact_modified_after_provenance() {
+        let mut artifact = artifact_with_chain();
+        let prior = artifact.digest();
+        artifact.meta.tool_version = "enriched".into();
+        let key_pair = ring::signature::Ed25519KeyPair::from_seed_unchecked(&[7; 32]).unwrap();
+        artifact.sign_enrichment(prior, "retag", &key_pair, Utc::now());
+        verify_artifact(&artifact, &options_at(Utc::now())).expect("signed enrichment");
+
+        artifact.domain = "attacker.example".into();
+        let err = verify_artifact(&artifact, &options_at(Utc::now())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "artifact was modified after the last provenance entry"
+        );
+    }
+
+    fn signed_options(artifact: &RedProofArtifact) -> VerifyOptions {
+        let key_pair = ring::signature::Ed25519KeyPair::from_seed_unchecked(&[5; 32]).unwrap();
+        VerifyOptions {
+            detached_signature: Some(DetachedSignature {
+                signature: B64.encode(artifact.sign_detached(&key_pair)).into_bytes(),
+                public_key: B64.encode(key_pair.public_key()).into_bytes(),
+            }),
+            ..options_at(Utc::now())
+        }
+    }
+
+    #[test]
+    fn detached_signature_survives_json_reformatting() {
+        let artifact = artifact_with_chain();
+        let options = signed_options(&artifact);
+        let pretty = serde_json::to_vec_pretty(&artifact).unwrap();
+        let reloaded = load_artifact(&pretty, InputFormat::Auto).unwrap();
+        verify_artifact(&reloaded, &options).expect("detached signature");
+    }
+
+    #[test]
+    fn rejects_tampered_artifact_with_valid_looking_sidecar() {
+        let artifact = artifact_with_chain();
+        let options = signed_options(&artifact);
+        let mut tampered = artifact;
+        tampered.meta.tool_version = "9.9.9".into();
+        let err = verify_artifact(&tampered, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "detached signature does not match the artifact"
+        );
+    }
+
+    #[test]
+    fn dual_algorithm_artifact_verifies_with_sha256_only() {
+        let mut commitments = dual_commitments();
+        verify_commitments(&commitments, &[CommitmentAlgorithm::Sha256]).expect("sha256 path");
+
+        // A broken blake3 digest is invisible to a sha256-only verifier.
+        commitments.handshake = EncodedBlob::from_bytes(b"tampered");
+        verify_commitments(&commitments, &[CommitmentAlgorithm::Sha256]).expect("sha256 path");
+        assert!(verify_commitments(&commitments, &CommitmentAlgorithm::registered()).is_err());
+    }
+
+    #[test]
+    fn reports_witness_committed_under_another_algorithm() {
+        let blake3 = dual_commitments();
+        let relabelled = CommitmentSet {
+            algorithm: CommitmentAlgorithm::Sha256,
+            alternates: BTreeMap::new(),
+            ..blake3
+        };
+        let err = verify_commitments(&relabelled, &CommitmentAlgorithm::registered()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "algorithm mismatch: claimed sha256, witness matches blake3"
+        );
+    }
+
+    #[test]
+    fn cert_chain_validates_under_fixture_root() {
+        let options = VerifyOptions {
+            trust_roots: Some(load_trust_store(ROOT_PEM).expect("trust store")),
+            ..options_at(Utc::now())
+        };
+        verify_artifact(&artifact_with_chain(), &options).expect("trusted chain");
+    }
+
+    #[test]
+    fn cert_chain_rejected_under_unrelated_root() {
+        let options = VerifyOptions {
+            trust_roots: Some(load_trust_store(OTHER_ROOT_PEM).expect("trust store")),
+            ..options_at(Utc::now())
+        };
+        let err = verify_artifact(&artifact_with_chain(), &options).unwrap_err();
+        assert!(err.to_string().contains("not trusted"));
+    }
+
+    #[test]
+    fn cert_chain_must_match_recorded_spki_pins() {
+        let options = VerifyOptions {
+            trust_roots: Some(load_trust_store(ROOT_PEM).expect("trust store")),
+            ..options_at(Utc::now())
+        };
+        let mut artifact = artifact_with_chain();
+        artifact.tls.spki_pins = vec!["3brxwiwOeOoOswfHaD84AQrf+aMWL9q3wuuwpVUnBTM=".into()];
+        verify_artifact(&artifact, &options).expect("matching pin");
+
+        artifact.tls.spki_pins = vec!["aCVfYY0/lJgdgd3UtVY3sBUVXQh9UYatCC8F16of2QA=".into()];
+        let err = verify_artifact(&artifact, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "embedded certificate chain does not match recorded SPKI pins"
+        );
+    }
+
+    #[test]
+    fn cert_chain_must_match_recorded_subject_alt_names() {
+        let options = VerifyOptions {
+            trust_roots: Some(load_trust_store(ROOT_PEM).expect("trust store")),
+            ..options_at(Utc::now())
+        };
+        let mut artifact = artifact_with_chain();
+        artifact.tls.subject_alt_names = vec!["example.test".into()];
+        verify_artifact(&artifact, &options).expect("matching names");
+
+        artifact.tls.subject_alt_names = vec!["*.example.test".into()];
+        let err = verify_artifact(&artifact, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "embedded leaf certificate does not match recorded subject alt names"
+        );
+    }
+
+    #[test]
+    fn accepts_artifact_before_expiry() {
+        let mut artifact = artifact_with_chain();
+        artifact.expires_at = Some(artifact.time_utc + chrono::Duration::days(30));
+        let now = artifact.time_utc + chrono::Duration::days(29);
+        verify_artifact(&artifact, &options_at(now)).expect("unexpired");
+    }
+
+    #[test]
+    fn rejects_expired_artifact_unless_allowed() {
+        let mut artifact = artifact_with_chain();
+        artifact.expires_at = Some(artifact.time_utc + chrono::Duration::days(30));
+        let now = artifact.time_utc + chrono::Duration::days(31);
+        let err = verify_artifact(&artifact, &options_at(now)).unwrap_err();
+        assert!(err.to_string().contains("expired"));
+
+        let options = VerifyOptions {
+            allow_expired: true,
+            ..options_at(now)
+        };
+        verify_artifact(&artifact, &options).expect("expired but allowed");
+    }
+
+    #[test]
+    fn rejects_plaintext_artifact_unless_allowed() {
+        let mut artifact = artifact_with_chain();
+        artifact.tls.version = TlsProofContext::PLAINTEXT.into();
+        artifact.tls.cipher = TlsProofContext::PLAINTEXT.into();
+        artifact.tls.cert_fingerprints.clear();
+        artifact.tls.cert_chain.clear();
+        let err = verify_artifact(&artifact, &options_at(Utc::now())).unwrap_err();
+        assert!(err.to_string().contains("--allow-plaintext"), "{err}");
+
+        let options = VerifyOptions {
+            allow_plaintext: true,
+            ..options_at(Utc::now())
+        };
+        verify_artifact(&artifact, &options).expect("plaintext but allowed");
+    }
+
+    #[test]
+    fn input_format_parses_auto_and_shared_formats() {
+        assert_eq!("auto".parse::<InputFormat>(), Ok(InputFormat::Auto));
+        for format in ArtifactFormat::ALL {
+            let parsed = format.to_string().parse::<InputFormat>();
+            assert_eq!(parsed, Ok(InputFormat::Explicit(format)));
+            assert_eq!(parsed.unwrap().to_string(), format.to_string());
+        }
+        assert!("yaml".parse::<InputFormat>().is_err());
+    }
+
+    #[test]
+    fn loads_canonical_and_plain_cbor_alike() {
+        let artifact = artifact_with_chain();
+        let canonical = artifact.to_canonical_cbor().expect("canonical cbor");
+        let plain = serde_cbor::to_vec(&artifact).expect("cbor");
+        for format in [
+            InputFormat::Auto,
+            InputFormat::Explicit(ArtifactFormat::Cbor),
+        ] {
+            for bytes in [&canonical, &plain] {
+                assert_eq!(load_artifact(bytes, format).expect("loads"), artifact);
+            }
+        }
+    }
+
+    #[test]
+    fn auto_format_sniffs_json_past_bom_and_whitespace() {
+        let artifact = artifact_with_chain();
+        let mut data = b"\xef\xbb\xbf\r\n  ".to_vec();
+        data.extend(serde_json::to_vec_pretty(&artifact).unwrap());
+        assert_eq!(load_artifact(&data, InputFormat::Auto).unwrap(), artifact);
+        let explicit = InputFormat::Explicit(ArtifactFormat::Json);
+        assert_eq!(load_artifact(&data, explicit).unwrap(), artifact);
+
+        // Broken JSON is reported as JSON rather than retried as CBOR.
+        data.truncate(data.len() - 1);
+        let err = load_artifact(&data, InputFormat::Auto).unwrap_err();
+        assert_eq!(err.to_string(), "unable to parse artifact as JSON");
+    }
+
+    #[test]
+    fn refuses_artifacts_stamped_for_newer_verifiers() {
+        let mut artifact = artifact_with_chain();
+        artifact.meta.min_verifier_version = Some("0.0.2".into());
+        let json = serde_json::to_vec(&artifact).unwrap();
+        let cbor = artifact.to_canonical_cbor().unwrap();
+        for data in [&json, &cbor] {
+            // An older release refuses; the one it was stamped for loads it.
+            let err = ensure_supported(data, InputFormat::Auto, "0.0.1").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "artifact requires newer verifier: its statement needs 0.0.2, this is 0.0.1"
+            );
+            assert!(ensure_supported(data, InputFormat::Auto, "0.0.2").is_ok());
+        }
+
+        // A statement type this release does not know is refused for what it
+        // is rather than reported as a parse failure.
+        let mut future = serde_json::to_value(&artifact).unwrap();
+        future["statement"] = serde_json::json!({"type": "future:statement", "n": 1});
+        future["meta"]["min_verifier_version"] = "999.0.0".into();
+        let data = serde_json::to_vec(&future).unwrap();
+        let err = load_artifact(&data, InputFormat::Auto).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("artifact requires newer verifier"),
+            "{err}"
+        );
+        assert!(schema_violations(&data, InputFormat::Auto).is_err());
+    }
+
+    #[test]
+    fn auto_format_reads_cbor_and_names_it_on_failure() {
+        let artifact = artifact_with_chain();
+        let cbor = artifact.to_canonical_cbor().unwrap();
+        assert_eq!(load_artifact(&cbor, InputFormat::Auto).unwrap(), artifact);
+
+        let err = load_artifact(&cbor[..cbor.len() / 2], InputFormat::Auto).unwrap_err();
+        assert_eq!(err.to_string(), "unable to parse artifact as CBOR");
+    }
+
+    #[test]
+    fn rejects_artifact_without_supported_algorithm() {
+        let mut commitments = dual_commitments();
+        commitments.alternates.clear();
+        let err = verify_commitments(&commitments, &[CommitmentAlgorithm::Sha256]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("no supported commitment algorithm"));
     }
 }
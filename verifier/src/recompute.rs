@@ -0,0 +1,859 @@
+//! `--recompute-all`: ignore the stored digests, rebuild everything from the
+//! witness, re-canonicalize it and compare the independent result against
+//! every stored field.
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use redproof_artifact::{
+    header_leaf, RedProofArtifact, CANONICALIZATION_JSON_V1, CANONICALIZATION_JSON_V2,
+};
+use redproof_statements::evaluate::{evaluate, EvalReason};
+use redproof_statements::version;
+use redproof_statements::witness::{AppDataWitness, HandshakeWitness};
+use redproof_statements::Statement;
+
+/// Decoder for a `commitments.canonicalization` scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Canonicalizer {
+    JsonV1,
+    JsonV2,
+}
+
+impl Canonicalizer {
+    /// Pick the decoder for `tag`; unknown schemes are an error rather than a
+    /// silent miscomputation.
+    pub fn select(tag: &str) -> Result<Self> {
+        match tag {
+            CANONICALIZATION_JSON_V1 => Ok(Canonicalizer::JsonV1),
+            CANONICALIZATION_JSON_V2 => Ok(Canonicalizer::JsonV2),
+            other => bail!("unknown commitment canonicalization '{other}'"),
+        }
+    }
+
+    fn handshake(self, bytes: &[u8]) -> Result<HandshakeWitness> {
+        let handshake: HandshakeWitness =
+            serde_json::from_slice(bytes).context("handshake witness is not canonical")?;
+        if self == Canonicalizer::JsonV1
+            && !(handshake.spki_pins.is_empty()
+                && handshake.subject_alt_names.is_empty()
+                && handshake.resolved_ips.is_empty()
+                && handshake.source.is_none())
+        {
+            bail!("handshake witness carries json/v2 fields under json/v1");
+        }
+        Ok(handshake)
+    }
+
+    fn app_data(self, bytes: &[u8]) -> Result<AppDataWitness> {
+        let app: AppDataWitness =
+            serde_json::from_slice(bytes).context("app-data witness is not canonical")?;
+        if self == Canonicalizer::JsonV1
+            && !(app.http_version.is_none()
+                && app.early_hints.is_empty()
+                && app.body_digest.is_none()
+                && app.timing.is_none()
+                && app.headers.iter().all(|header| header.wire_name.is_none()))
+        {
+            bail!("app-data witness carries json/v2 fields under json/v1");
+        }
+        Ok(app)
+    }
+}
+
+/// Part of the witness `--print-witness` extracts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum WitnessField {
+    /// Canonical handshake bytes.
+    Handshake,
+    /// Canonical response bytes.
+    AppData,
+    /// Response body decoded from the app-data witness.
+    Body,
+}
+
+/// `source` committed in the handshake witness: `imported` for artifacts
+/// minted from another tool's capture, `None` for live captures and
+/// artifacts without a witness.
+pub fn committed_source(artifact: &RedProofArtifact) -> Result<Option<String>> {
+    let Some(witness) = &artifact.commitments.witness else {
+        return Ok(None);
+    };
+    let canonicalizer = Canonicalizer::select(&artifact.commitments.canonicalization)?;
+    Ok(canonicalizer
+        .handshake(&witness.handshake.decode()?)?
+        .source)
+}
+
+/// Decode `field` from the embedded witness.
+pub fn extract(artifact: &RedProofArtifact, field: WitnessField) -> Result<Vec<u8>> {
+    let Some(witness) = &artifact.commitments.witness else {
+        bail!("artifact carries no witness to extract");
+    };
+    match field {
+        WitnessField::Handshake => Ok(witness.handshake.decode()?),
+        WitnessField::AppData => Ok(witness.app_data.decode()?),
+        WitnessField::Body => {
+            let canonicalizer = Canonicalizer::select(&artifact.commitments.canonicalization)?;
+            let app = canonicalizer.app_data(&witness.app_data.decode()?)?;
+            if app.body_digest.is_some() {
+                bail!("body was streamed into a digest; the witness holds no body bytes");
+            }
+            B64.decode(&app.body_base64)
+                .context("app-data witness body is not base64")
+        }
+    }
+}
+
+/// Refuse a statement the `running` release cannot decide, whatever the
+/// artifact's `meta.min_verifier_version` claims; reporting it unsatisfied
+/// would blame the capture for the verifier's gap.
+fn ensure_decidable(statement: &Statement, running: &str) -> Result<()> {
+    let required = statement.min_verifier_version();
+    if version::compare(required, running) != Some(std::cmp::Ordering::Greater) {
+        return Ok(());
+    }
+    let kind = serde_json::to_value(statement)?["type"].clone();
+    bail!("unsupported statement {kind}: it needs verifier {required}, this is {running}")
+}
+
+/// Every disagreement between the witness and the stored artifact. Fails
+/// outright when there is no witness or it cannot be decoded.
+pub fn discrepancies(artifact: &RedProofArtifact) -> Result<Vec<String>> {
+    ensure_decidable(&artifact.statement, crate::VERIFIER_VERSION)?;
+    let canonicalizer = Canonicalizer::select(&artifact.commitments.canonicalization)?;
+    let Some(witness) = &artifact.commitments.witness else {
+        bail!("--recompute-all requires an embedded witness");
+    };
+    let handshake_bytes = witness.handshake.decode()?;
+    let app_data_bytes = witness.app_data.decode()?;
+    let salt = artifact.commitments.salt_bytes()?;
+    let mut found = Vec::new();
+
+    for algorithm in artifact.commitments.algorithms() {
+        let Some(stored) = artifact.commitments.digests_for(algorithm) else {
+            continue;
+        };
+        if algorithm.digest_salted(&salt, &handshake_bytes) != stored.handshake.decode()? {
+            found.push(format!(
+                "{algorithm} handshake digest does not match witness"
+            ));
+        }
+        if algorithm.digest_salted(&salt, &app_data_bytes) != stored.app_data.decode()? {
+            found.push(format!(
+                "{algorithm} app-data digest does not match witness"
+            ));
+        }
+    }
+
+    let handshake = canonicalizer.handshake(&handshake_bytes)?;
+    let app = canonicalizer.app_data(&app_data_bytes)?;
+    // Decoding is lenient (unknown fields, key order, header case); the
+    // prover's encoder is not.
+    if handshake.encode()? != handshake_bytes {
+        found.push("handshake witness is not canonical: re-encoding it changes the bytes".into());
+    }
+    if app.encode()? != app_data_bytes {
+        found.push("app-data witness is not canonical: re-encoding it changes the bytes".into());
+    }
+    let record = artifact
+        .witness_record(&handshake, &app, &handshake_bytes, &app_data_bytes)
+        .map_err(anyhow::Error::msg)?;
+
+    compare_handshake(artifact, &handshake, &mut found);
+    compare_app_data(artifact, &app, &mut found);
+    let evaluation = evaluate(&artifact.statement, &record);
+    if evaluation.reason == Some(EvalReason::NotCommitted) {
+        found.push(format!(
+            "statement cannot be re-evaluated from the witness: {}",
+            evaluation.details.unwrap_or_default()
+        ));
+    } else if !evaluation.satisfied {
+        found.push(format!(
+            "statement not satisfied by the witness: {}",
+            artifact.statement.summary()
+        ));
+    }
+    Ok(found)
+}
+
+fn mismatch<T: std::fmt::Debug + PartialEq>(
+    found: &mut Vec<String>,
+    field: &str,
+    stored: T,
+    witness: T,
+) {
+    if stored != witness {
+        found.push(format!("{field}: stored {stored:?}, witness {witness:?}"));
+    }
+}
+
+fn compare_handshake(
+    artifact: &RedProofArtifact,
+    handshake: &HandshakeWitness,
+    found: &mut Vec<String>,
+) {
+    let tls = &artifact.tls;
+    mismatch(found, "domain", &artifact.domain, &handshake.domain);
+    mismatch(found, "tls.version", &tls.version, &handshake.version);
+    mismatch(found, "tls.cipher", &tls.cipher, &handshake.cipher);
+    mismatch(found, "tls.alpn", &tls.alpn, &handshake.alpn);
+    mismatch(
+        found,
+        "tls.cert_fingerprints",
+        &tls.cert_fingerprints,
+        &handshake.cert_fingerprints,
+    );
+    mismatch(found, "tls.spki_pins", &tls.spki_pins, &handshake.spki_pins);
+    mismatch(
+        found,
+        "tls.subject_alt_names",
+        &tls.subject_alt_names,
+        &handshake.subject_alt_names,
+    );
+    if let Some(size) = tls.chain_size {
+        mismatch(
+            found,
+            "tls.chain_size.count",
+            size.count,
+            handshake.served_certificates(),
+        );
+    }
+    if let Some(resolved) = &artifact.meta.annotations.resolved_ips {
+        mismatch(
+            found,
+            "annotations.resolved_ips",
+            &resolved.addresses,
+            &handshake.resolved_ips,
+        );
+    }
+}
+
+fn compare_app_data(artifact: &RedProofArtifact, app: &AppDataWitness, found: &mut Vec<String>) {
+    let annotations = &artifact.meta.annotations;
+    if let Some(status_code) = annotations.status_code {
+        mismatch(
+            found,
+            "annotations.status_code",
+            status_code,
+            app.status_code,
+        );
+    }
+    if let Some(header_count) = annotations.header_count {
+        mismatch(
+            found,
+            "annotations.header_count",
+            header_count,
+            app.headers.len(),
+        );
+    }
+    if let Some(body_truncated) = annotations.body_truncated {
+        mismatch(
+            found,
+            "annotations.body_truncated",
+            body_truncated,
+            app.body_truncated,
+        );
+    }
+    let stored_trace = annotations
+        .timing
+        .as_ref()
+        .and_then(|timing| timing.trace.as_ref());
+    if stored_trace.is_some() || app.timing.is_some() {
+        mismatch(
+            found,
+            "annotations.timing.trace",
+            stored_trace,
+            app.timing.as_ref(),
+        );
+    }
+    if let Some(skew) = &annotations.clock_skew {
+        // The skew itself depends on the prover's clock; only the server
+        // side is committed.
+        let witnessed = app
+            .headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case("date"))
+            .and_then(|header| DateTime::parse_from_rfc2822(header.value.trim()).ok())
+            .map(|date| date.with_timezone(&Utc));
+        mismatch(
+            found,
+            "annotations.clock_skew.server_date",
+            Some(skew.server_date),
+            witnessed,
+        );
+    }
+    if let Some(headers) = &artifact.commitments.headers {
+        for disclosed in &headers.revealed {
+            let present = app.headers.iter().any(|header| {
+                header.name.eq_ignore_ascii_case(&disclosed.name) && header.value == disclosed.value
+            });
+            if !present {
+                found.push(format!(
+                    "disclosed header {}: {:?} not in witness",
+                    disclosed.name, disclosed.value
+                ));
+            }
+        }
+        if let Some(rebuilt) = rebuilt_header_leaves(artifact, app) {
+            let stored = headers
+                .leaves
+                .iter()
+                .map(|leaf| leaf.decode().ok())
+                .collect::<Option<Vec<_>>>();
+            if stored.as_ref() != Some(&rebuilt) {
+                found.push("header commitment leaves do not match the witness".into());
+            }
+        }
+    }
+}
+
+/// Sorted header commitment leaves rebuilt from the witness headers and
+/// their nonces; `None` when the witness holds no (usable) nonces.
+fn rebuilt_header_leaves(
+    artifact: &RedProofArtifact,
+    app: &AppDataWitness,
+) -> Option<Vec<Vec<u8>>> {
+    let nonces = &artifact.commitments.witness.as_ref()?.header_nonces;
+    if nonces.is_empty() {
+        return None;
+    }
+    let salted = artifact.commitments.headers.as_ref()?.salted;
+    let salt = if salted {
+        artifact.commitments.salt_bytes().ok()?
+    } else {
+        Vec::new()
+    };
+    let mut leaves = app
+        .headers
+        .iter()
+        .zip(nonces)
+        .map(|(header, nonce)| {
+            let nonce = nonce.decode().ok()?;
+            Some(header_leaf(
+                artifact.commitments.algorithm,
+                &salt,
+                &nonce,
+                &header.name,
+                &header.value,
+            ))
+        })
+        .collect::<Option<Vec<_>>>()
+        .unwrap_or_default();
+    if leaves.len() != app.headers.len() || nonces.len() != app.headers.len() {
+        leaves.clear();
+    }
+    leaves.sort();
+    Some(leaves)
+}
+
+#[cfg(test)]
+mod tests {
+    use redproof_artifact::{
+        ArtifactMeta, CertChainSize, ClockSkewAnnotation, CommitmentAlgorithm, CommitmentDigests,
+        CommitmentSet, CommitmentWitness, EncodedBlob, HeaderCommitment, TimingAnnotation,
+        TimingTrace, TlsProofContext, HEADER_NONCE_BYTES,
+    };
+    use redproof_statements::{HashAlgorithm, Statement};
+    use serde_json::json;
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+
+    fn commitments_for(handshake: &[u8], app_data: &[u8]) -> CommitmentSet {
+        let digests = |algorithm: CommitmentAlgorithm| CommitmentDigests {
+            handshake: EncodedBlob::from_bytes(&algorithm.digest(handshake)),
+            app_data: EncodedBlob::from_bytes(&algorithm.digest(app_data)),
+        };
+        let primary = digests(CommitmentAlgorithm::Blake3);
+        CommitmentSet {
+            algorithm: CommitmentAlgorithm::Blake3,
+            canonicalization: CANONICALIZATION_JSON_V2.into(),
+            salt: None,
+            handshake: primary.handshake,
+            app_data: primary.app_data,
+            alternates: [(
+                CommitmentAlgorithm::Sha256,
+                digests(CommitmentAlgorithm::Sha256),
+            )]
+            .into(),
+            headers: None,
+            witness: Some(CommitmentWitness {
+                handshake: EncodedBlob::from_bytes(handshake),
+                app_data: EncodedBlob::from_bytes(app_data),
+                header_nonces: Vec::new(),
+            }),
+            statement: None,
+            witness_digest: None,
+        }
+    }
+
+    /// `handshake` as the prover's encoder writes it.
+    fn handshake_bytes(handshake: &serde_json::Value) -> Vec<u8> {
+        serde_json::from_value::<HandshakeWitness>(handshake.clone())
+            .unwrap()
+            .encode()
+            .unwrap()
+    }
+
+    /// `app_data` as the prover's encoder writes it.
+    fn app_data_bytes(app_data: &serde_json::Value) -> Vec<u8> {
+        serde_json::from_value::<AppDataWitness>(app_data.clone())
+            .unwrap()
+            .encode()
+            .unwrap()
+    }
+
+    fn consistent_artifact() -> RedProofArtifact {
+        let handshake = json!({
+            "domain": "example.test",
+            "version": "TLS1.3",
+            "cipher": "TLS13_AES_128_GCM_SHA256",
+            "alpn": null,
+            "cert_fingerprints": ["sha256:aa"],
+        });
+        let app_data = json!({
+            "status_code": 200,
+            "reason": "OK",
+            "headers": [{"name": "server", "value": "nginx"}],
+            "body_base64": B64.encode(b"hello"),
+            "body_truncated": false,
+        });
+        let mut meta = ArtifactMeta::default();
+        meta.annotations.status_code = Some(200);
+        meta.annotations.header_count = Some(1);
+        RedProofArtifact {
+            version: "1.0".into(),
+            domain: "example.test".into(),
+            time_utc: chrono::Utc::now(),
+            tls: TlsProofContext {
+                version: "TLS1.3".into(),
+                cipher: "TLS13_AES_128_GCM_SHA256".into(),
+                cert_fingerprints: vec!["sha256:aa".into()],
+                alpn: None,
+                cert_chain: vec![],
+                spki_pins: vec![],
+                subject_alt_names: vec![],
+                chain_size: None,
+            },
+            statement: Statement::HeaderEquals {
+                target: "server".into(),
+                expected: "nginx".into(),
+                case_sensitive: None,
+            },
+            commitments: commitments_for(&handshake_bytes(&handshake), &app_data_bytes(&app_data)),
+            proof: EncodedBlob::from_bytes(b"proof"),
+            expires_at: None,
+            meta,
+        }
+    }
+
+    #[test]
+    fn consistent_artifact_has_no_discrepancies() {
+        let artifact = consistent_artifact();
+        assert_eq!(discrepancies(&artifact).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_every_inconsistent_stored_field() {
+        let mut artifact = consistent_artifact();
+        // Digests and witness still agree; only the stored claims drifted.
+        artifact.tls.cipher = "TLS13_AES_256_GCM_SHA384".into();
+        artifact.meta.annotations.status_code = Some(204);
+        artifact.statement = Statement::HeaderEquals {
+            target: "server".into(),
+            expected: "apache".into(),
+            case_sensitive: None,
+        };
+        let found = discrepancies(&artifact).unwrap();
+        assert_eq!(found.len(), 3, "{found:?}");
+        assert!(found[0].starts_with("tls.cipher"));
+        assert!(found[1].starts_with("annotations.status_code"));
+        assert!(found[2].starts_with("statement not satisfied"));
+    }
+
+    #[test]
+    fn reports_non_canonical_witnesses() {
+        let artifact = consistent_artifact();
+        let witness = artifact.commitments.witness.clone().unwrap();
+        let app_data: serde_json::Value =
+            serde_json::from_slice(&witness.app_data.decode().unwrap()).unwrap();
+        let handshake: serde_json::Value =
+            serde_json::from_slice(&witness.handshake.decode().unwrap()).unwrap();
+        let recommitted = |handshake: Vec<u8>, app_data: Vec<u8>| {
+            let mut artifact = artifact.clone();
+            artifact.commitments = commitments_for(&handshake, &app_data);
+            discrepancies(&artifact).unwrap()
+        };
+        let not_canonical = "app-data witness is not canonical: re-encoding it changes the bytes";
+
+        // `json!` sorts keys alphabetically; the encoder keeps field order.
+        let reordered = serde_json::to_vec(&app_data).unwrap();
+        assert_eq!(
+            recommitted(handshake_bytes(&handshake), reordered),
+            [not_canonical]
+        );
+
+        let mut extra = app_data_bytes(&app_data);
+        extra.pop();
+        extra.extend_from_slice(br#","note":"unsigned"}"#);
+        assert_eq!(
+            recommitted(handshake_bytes(&handshake), extra),
+            [not_canonical]
+        );
+
+        let canonical_headers = serde_json::to_string(&app_data["headers"]).unwrap();
+        for headers in [
+            json!([{"name": "Server", "value": "nginx"}]),
+            json!([{"name": "server", "value": " nginx"}]),
+            json!([{"name": "x-b", "value": "1"}, {"name": "server", "value": "nginx"}]),
+        ] {
+            let bytes = String::from_utf8(app_data_bytes(&app_data))
+                .unwrap()
+                .replace(&canonical_headers, &headers.to_string());
+            let found = recommitted(handshake_bytes(&handshake), bytes.into_bytes());
+            assert!(
+                found.contains(&not_canonical.to_string()),
+                "{headers}: {found:?}"
+            );
+        }
+
+        let mut spaced = handshake_bytes(&handshake);
+        spaced.insert(1, b' ');
+        assert_eq!(
+            recommitted(spaced, app_data_bytes(&app_data)),
+            ["handshake witness is not canonical: re-encoding it changes the bytes"]
+        );
+    }
+
+    #[test]
+    fn reports_stale_alternate_digest() {
+        let mut artifact = consistent_artifact();
+        let sha = artifact
+            .commitments
+            .alternates
+            .get_mut(&CommitmentAlgorithm::Sha256)
+            .unwrap();
+        sha.app_data = EncodedBlob::from_bytes(b"stale");
+        let found = discrepancies(&artifact).unwrap();
+        assert_eq!(found, vec!["sha256 app-data digest does not match witness"]);
+    }
+
+    #[test]
+    fn undecidable_statement_is_a_discrepancy() {
+        let mut artifact = consistent_artifact();
+        artifact.statement = Statement::ResponseTimeUnder { max_ms: 100 };
+        let found = discrepancies(&artifact).unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].starts_with("statement cannot be re-evaluated"));
+    }
+
+    #[test]
+    fn statement_newer_than_the_verifier_is_refused() {
+        let mut artifact = consistent_artifact();
+        artifact.statement = Statement::SctPresent;
+        let err = ensure_decidable(&artifact.statement, "0.0.1").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unsupported statement \"tls:sct-present\": it needs verifier 0.1.0, this is 0.0.1"
+        );
+        assert!(ensure_decidable(&artifact.statement, crate::VERIFIER_VERSION).is_ok());
+        artifact.statement = Statement::HeaderPresent {
+            target: "server".into(),
+        };
+        assert!(ensure_decidable(&artifact.statement, "0.0.1").is_ok());
+    }
+
+    #[test]
+    fn chain_length_is_counted_from_committed_fingerprints() {
+        let mut artifact = consistent_artifact();
+        artifact.statement = Statement::CertChainLengthAtMost { n: 1 };
+        artifact.tls.chain_size = Some(CertChainSize {
+            count: 1,
+            total_bytes: 1200,
+        });
+        assert_eq!(discrepancies(&artifact).unwrap(), Vec::<String>::new());
+
+        artifact.tls.chain_size = Some(CertChainSize {
+            count: 2,
+            total_bytes: 2400,
+        });
+        let found = discrepancies(&artifact).unwrap();
+        assert_eq!(found, vec!["tls.chain_size.count: stored 2, witness 1"]);
+    }
+
+    #[test]
+    fn field_pointers_resolve_in_the_witness() {
+        let mut artifact = consistent_artifact();
+        artifact.statement = Statement::FieldPresent {
+            path: "/app_data/headers/0/name".into(),
+        };
+        assert_eq!(discrepancies(&artifact).unwrap(), Vec::<String>::new());
+
+        artifact.statement = Statement::FieldPresent {
+            path: "/handshake/resolved_ips".into(),
+        };
+        let found = discrepancies(&artifact).unwrap();
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert!(found[0].starts_with("statement not satisfied"));
+    }
+
+    #[test]
+    fn header_casing_is_read_from_the_committed_witness() {
+        let mut artifact = consistent_artifact();
+        artifact.statement = Statement::HeaderCasingIs {
+            target: "server".into(),
+            expected_casing: "Server".into(),
+        };
+        let found = discrepancies(&artifact).unwrap();
+        assert!(found[0].starts_with("statement not satisfied"), "{found:?}");
+
+        let witness = artifact.commitments.witness.clone().unwrap();
+        let app_data = json!({
+            "status_code": 200,
+            "reason": "OK",
+            "headers": [{"name": "server", "value": "nginx", "wire_name": "Server"}],
+            "body_base64": B64.encode(b"hello"),
+            "body_truncated": false,
+        });
+        artifact.commitments = commitments_for(
+            &witness.handshake.decode().unwrap(),
+            &app_data_bytes(&app_data),
+        );
+        assert_eq!(discrepancies(&artifact).unwrap(), Vec::<String>::new());
+
+        artifact.commitments.canonicalization = CANONICALIZATION_JSON_V1.into();
+        let err = discrepancies(&artifact).unwrap_err();
+        assert!(err.to_string().contains("json/v2 fields"), "{err}");
+    }
+
+    #[test]
+    fn http_version_is_read_from_the_committed_witness() {
+        let mut artifact = consistent_artifact();
+        artifact.meta.annotations.http_version = Some("HTTP/1.1".into());
+        artifact.statement = Statement::HttpVersionIs {
+            version: "1.1".into(),
+        };
+        let found = discrepancies(&artifact).unwrap();
+        assert!(found[0].starts_with("statement not satisfied"), "{found:?}");
+
+        let witness = artifact.commitments.witness.clone().unwrap();
+        let app_data = json!({
+            "http_version": "HTTP/1.1",
+            "status_code": 200,
+            "reason": "OK",
+            "headers": [{"name": "server", "value": "nginx"}],
+            "body_base64": B64.encode(b"hello"),
+            "body_truncated": false,
+        });
+        artifact.commitments = commitments_for(
+            &witness.handshake.decode().unwrap(),
+            &app_data_bytes(&app_data),
+        );
+        assert_eq!(discrepancies(&artifact).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn regex_transform_is_reapplied_before_rechecking() {
+        let mut artifact = consistent_artifact();
+        let digest = |body: &[u8]| {
+            CommitmentAlgorithm::Sha256
+                .digest(body)
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+        };
+        let transform = |digest: String| Statement::RegexReplace {
+            pattern: "l+".into(),
+            replacement: "L".into(),
+            statement: Box::new(Statement::HashEquals {
+                algorithm: HashAlgorithm::Sha256,
+                digest,
+            }),
+        };
+        artifact.statement = transform(digest(b"heLo"));
+        assert_eq!(discrepancies(&artifact).unwrap(), Vec::<String>::new());
+
+        artifact.statement = transform(digest(b"hello"));
+        let found = discrepancies(&artifact).unwrap();
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert!(found[0].starts_with("statement not satisfied"));
+    }
+
+    #[test]
+    fn committed_timing_is_checked_against_witness() {
+        let trace = TimingTrace {
+            connect_ms: 3,
+            handshake_ms: 9,
+            ttfb_ms: 40,
+            total_ms: 55,
+        };
+        let mut artifact = consistent_artifact();
+        let witness = artifact.commitments.witness.clone().unwrap();
+        let mut app_data: serde_json::Value =
+            serde_json::from_slice(&witness.app_data.decode().unwrap()).unwrap();
+        app_data["timing"] = serde_json::to_value(&trace).unwrap();
+        artifact.commitments = commitments_for(
+            &witness.handshake.decode().unwrap(),
+            &app_data_bytes(&app_data),
+        );
+        artifact.statement = Statement::ResponseTimeUnder { max_ms: 50 };
+        artifact.meta.annotations.timing = Some(TimingAnnotation {
+            cold_ttfb_ms: 40,
+            warm_ttfb_ms: None,
+            statement_uses: "cold".into(),
+            attested_by: "prover".into(),
+            trace: Some(trace.clone()),
+        });
+        assert_eq!(discrepancies(&artifact).unwrap(), Vec::<String>::new());
+
+        let mut edited = artifact.clone();
+        edited.meta.annotations.timing.as_mut().unwrap().trace = Some(TimingTrace {
+            ttfb_ms: 4,
+            ..trace
+        });
+        let found = discrepancies(&edited).unwrap();
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert!(found[0].starts_with("annotations.timing.trace"));
+
+        let mut tampered = artifact;
+        app_data["timing"]["ttfb_ms"] = 4.into();
+        tampered.commitments.witness.as_mut().unwrap().app_data =
+            EncodedBlob::from_bytes(&app_data_bytes(&app_data));
+        let found = discrepancies(&tampered).unwrap();
+        assert!(found.contains(&"blake3 app-data digest does not match witness".to_string()));
+    }
+
+    #[test]
+    fn sct_count_is_read_from_the_embedded_committed_leaf() {
+        const LEAF_SCT_DER: &[u8] = include_bytes!("../../artifact/testdata/leaf-sct.der");
+        let mut artifact = consistent_artifact();
+        artifact.statement = Statement::SctPresent;
+        let found = discrepancies(&artifact).unwrap();
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert!(found[0].starts_with("statement cannot be re-evaluated"));
+
+        let fingerprint = format!("sha256:{:x}", Sha256::digest(LEAF_SCT_DER));
+        let witness = artifact.commitments.witness.clone().unwrap();
+        let mut handshake: serde_json::Value =
+            serde_json::from_slice(&witness.handshake.decode().unwrap()).unwrap();
+        handshake["cert_fingerprints"] = json!([fingerprint]);
+        artifact.commitments = commitments_for(
+            &handshake_bytes(&handshake),
+            &witness.app_data.decode().unwrap(),
+        );
+        artifact.tls.cert_fingerprints = vec![fingerprint];
+        artifact.tls.cert_chain = vec![EncodedBlob::from_bytes(LEAF_SCT_DER)];
+        assert_eq!(discrepancies(&artifact).unwrap(), Vec::<String>::new());
+
+        // A leaf the handshake does not commit is not taken at its word.
+        artifact.tls.cert_chain = vec![EncodedBlob::from_bytes(b"other leaf")];
+        let found = discrepancies(&artifact).unwrap();
+        assert!(
+            found[0].starts_with("statement cannot be re-evaluated"),
+            "{found:?}"
+        );
+    }
+
+    #[test]
+    fn clock_skew_is_measured_from_the_annotated_capture_time() {
+        let mut artifact = consistent_artifact();
+        let witness = artifact.commitments.witness.clone().unwrap();
+        let app_data = json!({
+            "status_code": 200,
+            "reason": "OK",
+            "headers": [
+                {"name": "date", "value": "Fri, 01 Mar 2024 12:00:00 GMT"},
+                {"name": "server", "value": "nginx"}
+            ],
+            "body_base64": B64.encode(b"hello"),
+            "body_truncated": false,
+        });
+        artifact.commitments = commitments_for(
+            &witness.handshake.decode().unwrap(),
+            &app_data_bytes(&app_data),
+        );
+        artifact.meta.annotations.header_count = Some(2);
+        artifact.statement = Statement::ClockSkewUnder { millis: 2000 };
+        let found = discrepancies(&artifact).unwrap();
+        assert!(
+            found[0].starts_with("statement cannot be re-evaluated"),
+            "{found:?}"
+        );
+
+        artifact.meta.annotations.clock_skew = Some(ClockSkewAnnotation {
+            server_date: "2024-03-01T12:00:00Z".parse().unwrap(),
+            skew_ms: -1500,
+            attested_by: "prover".into(),
+        });
+        assert_eq!(discrepancies(&artifact).unwrap(), Vec::<String>::new());
+
+        artifact.statement = Statement::ClockSkewUnder { millis: 1000 };
+        let found = discrepancies(&artifact).unwrap();
+        assert!(found[0].starts_with("statement not satisfied"), "{found:?}");
+    }
+
+    #[test]
+    fn extracts_witnessed_body() {
+        let artifact = consistent_artifact();
+        assert_eq!(extract(&artifact, WitnessField::Body).unwrap(), b"hello");
+        let app_data = extract(&artifact, WitnessField::AppData).unwrap();
+        assert!(app_data.starts_with(b"{"));
+
+        let mut witnessless = artifact;
+        witnessless.commitments.witness = None;
+        let err = extract(&witnessless, WitnessField::Body).unwrap_err();
+        assert_eq!(err.to_string(), "artifact carries no witness to extract");
+    }
+
+    #[test]
+    fn reads_committed_source() {
+        let mut artifact = consistent_artifact();
+        assert_eq!(committed_source(&artifact).unwrap(), None);
+
+        let witness = artifact.commitments.witness.clone().unwrap();
+        let mut handshake: serde_json::Value =
+            serde_json::from_slice(&witness.handshake.decode().unwrap()).unwrap();
+        handshake["source"] = "imported".into();
+        artifact.commitments = commitments_for(
+            &handshake_bytes(&handshake),
+            &witness.app_data.decode().unwrap(),
+        );
+        assert_eq!(
+            committed_source(&artifact).unwrap().as_deref(),
+            Some("imported")
+        );
+        assert_eq!(discrepancies(&artifact).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn header_leaves_are_rebuilt_from_the_witnessed_nonces() {
+        let mut artifact = consistent_artifact();
+        let nonce = vec![7; HEADER_NONCE_BYTES];
+        artifact.commitments.headers = Some(HeaderCommitment::build(
+            artifact.commitments.algorithm,
+            &[],
+            &[("Server".into(), "nginx".into())],
+            std::slice::from_ref(&nonce),
+            &[],
+        ));
+        let witness = artifact.commitments.witness.as_mut().unwrap();
+        witness.header_nonces = vec![EncodedBlob::from_bytes(&nonce)];
+        assert_eq!(discrepancies(&artifact).unwrap(), Vec::<String>::new());
+
+        let witness = artifact.commitments.witness.as_mut().unwrap();
+        witness.header_nonces = vec![EncodedBlob::from_bytes(&[8; HEADER_NONCE_BYTES])];
+        assert_eq!(
+            discrepancies(&artifact).unwrap(),
+            ["header commitment leaves do not match the witness"]
+        );
+    }
+
+    #[test]
+    fn requires_witness() {
+        let mut artifact = consistent_artifact();
+        artifact.commitments.witness = None;
+        assert!(discrepancies(&artifact).is_err());
+    }
+}
@@ -0,0 +1,281 @@
+//! `--format-out sarif`: a SARIF 2.1.0 log for code-scanning dashboards. Each
+//! artifact is one result: its statement is the rule, its domain the
+//! location and the verdict the result kind.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+use redproof_artifact::RedProofArtifact;
+use serde_json::{json, Value};
+
+use crate::batch::{BatchReport, BatchSummary, Outcome};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+/// Rule for files that are not a loadable artifact, so have no statement.
+const UNREADABLE_RULE: &str = "redproof/unreadable-artifact";
+
+/// One run of `redproof-verify`, collected in verification order.
+#[derive(Default)]
+pub struct SarifLog {
+    rules: BTreeMap<String, Value>,
+    results: Vec<Value>,
+}
+
+impl SarifLog {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "redproof-verify",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": self.rules.values().collect::<Vec<_>>(),
+                    }
+                },
+                "results": self.results,
+            }]
+        })
+    }
+}
+
+impl BatchReport for SarifLog {
+    fn file(
+        &mut self,
+        path: &Path,
+        artifact: Option<&RedProofArtifact>,
+        outcome: &Outcome,
+    ) -> Result<()> {
+        let (kind, level, text) = match outcome {
+            Outcome::Valid => ("pass", "none", "VALID".to_string()),
+            Outcome::Invalid(err) => ("fail", "error", format!("INVALID: {err:#}")),
+            Outcome::Errored(err) => ("fail", "error", format!("ERRORED: {err:#}")),
+            Outcome::Skipped(captured) => (
+                "notApplicable",
+                "none",
+                format!("SKIPPED: captured {captured}, before --since"),
+            ),
+        };
+        let rule_id = match artifact {
+            Some(artifact) => {
+                let id = artifact.statement.fingerprint();
+                self.rules.entry(id.clone()).or_insert_with(|| {
+                    json!({
+                        "id": id,
+                        "name": "statement",
+                        "shortDescription": { "text": artifact.statement.summary() },
+                    })
+                });
+                id
+            }
+            None => {
+                self.rules
+                    .entry(UNREADABLE_RULE.to_string())
+                    .or_insert_with(|| {
+                        json!({
+                            "id": UNREADABLE_RULE,
+                            "shortDescription": { "text": "file is not a readable artifact" },
+                        })
+                    });
+                UNREADABLE_RULE.to_string()
+            }
+        };
+        let mut location = json!({
+            "physicalLocation": {
+                "artifactLocation": { "uri": path.to_string_lossy() }
+            }
+        });
+        if let Some(artifact) = artifact {
+            location["logicalLocations"] = json!([{
+                "name": artifact.domain,
+                "kind": "resource",
+            }]);
+        }
+        self.results.push(json!({
+            "ruleId": rule_id,
+            "kind": kind,
+            "level": level,
+            "message": { "text": text },
+            "locations": [location],
+        }));
+        Ok(())
+    }
+
+    fn summary(&mut self, _: &BatchSummary) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use chrono::Utc;
+    use redproof_artifact::{
+        ArtifactMeta, CommitmentAlgorithm, CommitmentSet, EncodedBlob, TlsProofContext,
+        CANONICALIZATION_JSON_V2,
+    };
+    use redproof_statements::Statement;
+
+    use super::*;
+
+    /// The parts of the SARIF 2.1.0 schema a consumer relies on: the
+    /// required properties and the enumerations this module emits.
+    fn sarif_required_fields() -> Value {
+        json!({
+            "type": "object",
+            "required": ["version", "runs"],
+            "properties": {
+                "version": { "const": "2.1.0" },
+                "runs": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["tool"],
+                        "properties": {
+                            "tool": {
+                                "type": "object",
+                                "required": ["driver"],
+                                "properties": {
+                                    "driver": {
+                                        "type": "object",
+                                        "required": ["name"],
+                                        "properties": {
+                                            "name": { "type": "string" },
+                                            "rules": {
+                                                "type": "array",
+                                                "items": {
+                                                    "type": "object",
+                                                    "required": ["id"]
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            },
+                            "results": {
+                                "type": "array",
+                                "items": {
+                                    "type": "object",
+                                    "required": ["message"],
+                                    "properties": {
+                                        "message": {
+                                            "type": "object",
+                                            "required": ["text"]
+                                        },
+                                        "kind": {
+                                            "enum": ["notApplicable", "pass", "fail", "review", "open", "informational"]
+                                        },
+                                        "level": {
+                                            "enum": ["none", "note", "warning", "error"]
+                                        },
+                                        "locations": {
+                                            "type": "array",
+                                            "items": {
+                                                "type": "object",
+                                                "properties": {
+                                                    "logicalLocations": {
+                                                        "type": "array",
+                                                        "items": { "type": "object" }
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn artifact(domain: &str, target: &str) -> RedProofArtifact {
+        RedProofArtifact {
+            version: "1.0".into(),
+            domain: domain.into(),
+            time_utc: Utc::now(),
+            tls: TlsProofContext {
+                version: "TLS1.3".into(),
+                cipher: "TLS13_AES_128_GCM_SHA256".into(),
+                cert_fingerprints: vec![format!("sha256:{}", "ab".repeat(32))],
+                alpn: None,
+                cert_chain: vec![],
+                spki_pins: vec![],
+                subject_alt_names: vec![],
+                chain_size: None,
+            },
+            statement: Statement::HeaderPresent {
+                target: target.into(),
+            },
+            commitments: CommitmentSet {
+                algorithm: CommitmentAlgorithm::Sha256,
+                canonicalization: CANONICALIZATION_JSON_V2.into(),
+                salt: None,
+                handshake: EncodedBlob::from_bytes(b"handshake"),
+                app_data: EncodedBlob::from_bytes(b"app"),
+                alternates: Default::default(),
+                headers: None,
+                witness: None,
+                statement: None,
+                witness_digest: None,
+            },
+            proof: EncodedBlob::from_bytes(b"proof"),
+            expires_at: None,
+            meta: ArtifactMeta::default(),
+        }
+    }
+
+    #[test]
+    fn batch_log_is_one_run_of_schema_conforming_results() {
+        let hsts = artifact("a.example", "Strict-Transport-Security");
+        let mut log = SarifLog::default();
+        log.file(Path::new("a.red"), Some(&hsts), &Outcome::Valid)
+            .unwrap();
+        log.file(
+            Path::new("b.red"),
+            Some(&artifact("b.example", "Strict-Transport-Security")),
+            &Outcome::Invalid(anyhow!("handshake digest mismatch")),
+        )
+        .unwrap();
+        log.file(
+            Path::new("c.red"),
+            None,
+            &Outcome::Errored(anyhow!("unable to parse artifact as JSON")),
+        )
+        .unwrap();
+        let sarif = log.to_json();
+
+        let schema = jsonschema::validator_for(&sarif_required_fields()).unwrap();
+        let errors = schema
+            .iter_errors(&sarif)
+            .map(|err| err.to_string())
+            .collect::<Vec<_>>();
+        assert!(errors.is_empty(), "{errors:?}");
+
+        let runs = sarif["runs"].as_array().unwrap();
+        assert_eq!(runs.len(), 1);
+        let results = runs[0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["kind"], "pass");
+        assert_eq!(results[0]["ruleId"], hsts.statement.fingerprint());
+        assert_eq!(
+            results[0]["locations"][0]["logicalLocations"][0]["name"],
+            "a.example"
+        );
+        assert_eq!(results[1]["kind"], "fail");
+        assert_eq!(results[1]["level"], "error");
+        assert_eq!(
+            results[1]["message"]["text"],
+            "INVALID: handshake digest mismatch"
+        );
+        assert_eq!(results[2]["ruleId"], UNREADABLE_RULE);
+        assert_eq!(
+            runs[0]["tool"]["driver"]["rules"].as_array().unwrap().len(),
+            2,
+            "one rule per distinct statement"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! `--schema-check`: validate the raw artifact document against the published
+//! JSON Schema before it is deserialized, catching artifacts that serde
+//! accepts loosely but the schema forbids.
+
+use anyhow::{Context, Result};
+use redproof_artifact::schema::artifact_schema;
+use redproof_artifact::ArtifactFormat;
+use serde_json::Value;
+
+/// Every way `data` violates the artifact schema, as `<pointer>: <reason>`;
+/// empty when it conforms. Errors only when `data` is not a JSON or CBOR
+/// document at all.
+pub fn violations(data: &[u8], format: ArtifactFormat) -> Result<Vec<String>> {
+    let document: Value = match format {
+        ArtifactFormat::Json => serde_json::from_slice(data).context("artifact is not JSON")?,
+        ArtifactFormat::Cbor => serde_cbor::from_slice(data).context("artifact is not CBOR")?,
+    };
+    let validator = jsonschema::validator_for(&artifact_schema())
+        .map_err(|err| anyhow::anyhow!("artifact schema does not compile: {err}"))?;
+    let violations = validator
+        .iter_errors(&document)
+        .map(|err| {
+            let path = err.instance_path.to_string();
+            let path = if path.is_empty() { "/" } else { &path };
+            format!("{path}: {err}")
+        })
+        .collect();
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn artifact_json(fingerprints: Value, version: &str) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "version": "1.0",
+            "domain": "example.test",
+            "time_utc": "2030-01-01T00:00:00Z",
+            "tls": {
+                "version": version,
+                "cipher": "TLS13_AES_128_GCM_SHA256",
+                "cert_fingerprints": fingerprints
+            },
+            "statement": { "type": "header:present", "target": "Server" },
+            "commitments": {
+                "algorithm": "sha256",
+                "canonicalization": "json/v1",
+                "handshake": "aGFuZHNoYWtl",
+                "app_data": "YXBw"
+            },
+            "proof": "cHJvb2Y=",
+            "meta": { "tool_version": "0.0.1" }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn conforming_artifact_passes() {
+        let fingerprint = format!("sha256:{}", "ab".repeat(32));
+        let data = artifact_json(json!([fingerprint]), "TLS1.3");
+        serde_json::from_slice::<redproof_artifact::RedProofArtifact>(&data).expect("parses");
+        assert_eq!(
+            violations(&data, ArtifactFormat::Json).unwrap(),
+            Vec::<String>::new()
+        );
+        let plaintext = artifact_json(json!([]), "none");
+        assert!(violations(&plaintext, ArtifactFormat::Json)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn flags_artifacts_that_parse_but_violate_the_schema() {
+        let data = artifact_json(json!([]), "TLS1.3");
+        serde_json::from_slice::<redproof_artifact::RedProofArtifact>(&data)
+            .expect("serde accepts an empty fingerprint list");
+        let found = violations(&data, ArtifactFormat::Json).unwrap();
+        assert!(
+            found
+                .iter()
+                .any(|v| v.starts_with("/tls/cert_fingerprints:")),
+            "{found:?}"
+        );
+
+        let data = artifact_json(json!(["sha256:DEADBEEF"]), "TLS1.3");
+        let found = violations(&data, ArtifactFormat::Json).unwrap();
+        assert!(
+            found
+                .iter()
+                .any(|v| v.starts_with("/tls/cert_fingerprints/0:")),
+            "{found:?}"
+        );
+    }
+}